@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use clap::{arg, command, value_parser};
 
-use rewsat::dimacs;
+use rewsat::{config::SolverConfig, dimacs};
 
 fn main() {
   let matches = command!()
@@ -12,6 +12,17 @@ fn main() {
         .value_parser(value_parser!(PathBuf))
         .required(true),
     )
+    .arg(
+      arg!(-j --threads <NUM> "number of solver threads")
+        .value_parser(value_parser!(usize))
+        .default_value("1"),
+    )
+    .arg(arg!(-p --preset <NAME> "configuration preset (sat, unsat, plain, fast-small)"))
+    .arg(
+      arg!(-s --seed <SEED> "random seed")
+        .value_parser(value_parser!(u64))
+        .default_value("0"),
+    )
     .get_matches();
 
   let dimacs_file = matches.get_one::<PathBuf>("dimacs_file").unwrap();
@@ -22,11 +33,21 @@ fn main() {
   let mut dimacs = dimacs::Dimacs::from(&dimacs_file)
     .unwrap_or_else(|_| panic!("failed to parse dimacs file: {:?}", dimacs_file));
 
-  if let Some(solution) = dimacs.solve() {
-    println!("SAT");
-    solution.iter().for_each(|e| print!("{} ", e));
-    println!("");
-  } else {
-    println!("UNSAT");
+  let config = match matches.get_one::<String>("preset") {
+    Some(name) => SolverConfig::preset_by_name(name).unwrap_or_else(|error| panic!("{}", error)),
+    None => SolverConfig::new(),
+  };
+  let config = config
+    .with_num_threads(*matches.get_one::<usize>("threads").unwrap())
+    .with_seed(*matches.get_one::<u64>("seed").unwrap());
+
+  match dimacs.solve_with(|solver| solver.set_config(config)) {
+    Some(Some(solution)) => {
+      println!("SAT");
+      solution.iter().for_each(|e| print!("{} ", e));
+      println!();
+    }
+    Some(None) => println!("UNSAT"),
+    None => println!("UNKNOWN"),
   }
 }
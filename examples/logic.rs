@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use clap::{arg, command, value_parser};
+
+use rewsat::{config::SolverConfig, encodings::formula::FormulaEncoding, logic};
+
+fn main() {
+  let matches = command!()
+    .about("propositional logic solver")
+    .arg(
+      arg!([logic_file]  "file with one formula such as `(a | !b) & (c -> d)` per line")
+        .value_parser(value_parser!(PathBuf))
+        .required(true),
+    )
+    .arg(
+      arg!(-e --encoding <NAME> "formula encoding (tseitin, plaisted-greenbaum)")
+        .default_value("plaisted-greenbaum"),
+    )
+    .arg(arg!(-p --preset <NAME> "configuration preset (sat, unsat, plain, fast-small)"))
+    .arg(
+      arg!(-s --seed <SEED> "random seed")
+        .value_parser(value_parser!(u64))
+        .default_value("0"),
+    )
+    .get_matches();
+
+  let logic_file = matches.get_one::<PathBuf>("logic_file").unwrap();
+  let logic_file = logic_file
+    .canonicalize()
+    .unwrap_or_else(|_| panic!("not found: {:?}", logic_file));
+
+  let logic = logic::Logic::from(&logic_file).unwrap_or_else(|error| panic!("{}", error));
+
+  let encoding = matches
+    .get_one::<String>("encoding")
+    .unwrap()
+    .parse::<FormulaEncoding>()
+    .unwrap_or_else(|error| panic!("{}", error));
+  let config = match matches.get_one::<String>("preset") {
+    Some(name) => SolverConfig::preset_by_name(name).unwrap_or_else(|error| panic!("{}", error)),
+    None => SolverConfig::new(),
+  };
+  let config = config.with_seed(*matches.get_one::<u64>("seed").unwrap());
+
+  let solution = logic.solve_with(|solver| {
+    solver.set_config(config);
+    solver.set_formula_encoding(encoding);
+  });
+  match solution {
+    Some(Some(solution)) => {
+      println!("SAT");
+      for (name, value) in solution {
+        println!("{}: {}", name, value);
+      }
+    }
+    Some(None) => println!("UNSAT"),
+    None => println!("UNKNOWN"),
+  }
+}
@@ -0,0 +1,93 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::{arg, command, value_parser};
+
+use rewsat::{
+  config::SolverConfig,
+  maxsat::{algorithm::MaxSatAlgorithm, options::MaxSatOptions, wcnf},
+  pb::{encoding::PbEncoding, optimize::MinimizeResult},
+};
+
+fn main() {
+  let matches = command!()
+    .about("maxsat solver")
+    .arg(
+      arg!([wcnf_file]  "wcnf file")
+        .value_parser(value_parser!(PathBuf))
+        .required(true),
+    )
+    .arg(arg!(-a --algorithm <NAME> "maxsat algorithm (oll, linear)").default_value("oll"))
+    .arg(
+      arg!(-e --encoding <NAME> "pseudo-Boolean encoding for linear search (bdd, adder, generalized-totalizer, watchdog; large weighted objectives fall back to adder)")
+        .default_value("bdd"),
+    )
+    .arg(
+      arg!(-t --"time-limit" <SECONDS> "print the best solution found within the time limit")
+        .value_parser(value_parser!(f64)),
+    )
+    .arg(arg!(-p --preset <NAME> "configuration preset (sat, unsat, plain, fast-small)"))
+    .arg(
+      arg!(-s --seed <SEED> "random seed")
+        .value_parser(value_parser!(u64))
+        .default_value("0"),
+    )
+    .get_matches();
+
+  let wcnf_file = matches.get_one::<PathBuf>("wcnf_file").unwrap();
+  let wcnf_file = wcnf_file
+    .canonicalize()
+    .unwrap_or_else(|_| panic!("not found: {:?}", wcnf_file));
+
+  let mut wcnf = wcnf::Wcnf::from(&wcnf_file).unwrap_or_else(|error| panic!("{}", error));
+
+  let algorithm = matches
+    .get_one::<String>("algorithm")
+    .unwrap()
+    .parse::<MaxSatAlgorithm>()
+    .unwrap_or_else(|error| panic!("{}", error));
+  let encoding = matches
+    .get_one::<String>("encoding")
+    .unwrap()
+    .parse::<PbEncoding>()
+    .unwrap_or_else(|error| panic!("{}", error));
+  let config = match matches.get_one::<String>("preset") {
+    Some(name) => SolverConfig::preset_by_name(name).unwrap_or_else(|error| panic!("{}", error)),
+    None => SolverConfig::new(),
+  };
+  let config = config.with_seed(*matches.get_one::<u64>("seed").unwrap());
+
+  let options = MaxSatOptions {
+    time_limit: matches
+      .get_one::<f64>("time-limit")
+      .map(|&seconds| Duration::from_secs_f64(seconds)),
+    ..MaxSatOptions::default()
+  };
+
+  // MaxSAT Evaluationの出力形式
+  let optimum = wcnf.solve_with(
+    |solver| {
+      solver.set_config(config);
+      solver.set_maxsat_algorithm(algorithm);
+      solver.set_maxsat_options(options);
+      solver.set_pb_encoding(encoding);
+    },
+    |cost| println!("o {}", cost),
+  );
+  match optimum {
+    MinimizeResult::Found(optimum) => {
+      if optimum.optimal {
+        println!("s OPTIMUM FOUND");
+      } else {
+        println!("s SATISFIABLE");
+      }
+      let values = optimum
+        .model
+        .iter()
+        .map(|&e| if e > 0 { '1' } else { '0' })
+        .collect::<String>();
+      println!("v {}", values);
+    }
+    MinimizeResult::Unsatisfiable => println!("s UNSATISFIABLE"),
+    MinimizeResult::Unknown => println!("s UNKNOWN"),
+  }
+}
@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+
+use clap::{arg, command, value_parser};
+
+use rewsat::{
+  config::SolverConfig,
+  pb::{
+    encoding::PbEncoding,
+    opb,
+    optimize::{MinimizeResult, Search},
+  },
+  sat_solver::SATSolver,
+};
+
+fn main() {
+  let matches = command!()
+    .about("opb solver")
+    .arg(
+      arg!([opb_file]  "opb file")
+        .value_parser(value_parser!(PathBuf))
+        .required(true),
+    )
+    .arg(
+      arg!(-e --encoding <NAME> "pseudo-Boolean encoding (bdd, adder, generalized-totalizer, watchdog)")
+        .default_value("bdd"),
+    )
+    .arg(
+      arg!(--search <NAME> "how to search the objective bound (linear, binary)")
+        .default_value("linear"),
+    )
+    .arg(arg!(-p --preset <NAME> "configuration preset (sat, unsat, plain, fast-small)"))
+    .arg(
+      arg!(-s --seed <SEED> "random seed")
+        .value_parser(value_parser!(u64))
+        .default_value("0"),
+    )
+    .get_matches();
+
+  let opb_file = matches.get_one::<PathBuf>("opb_file").unwrap();
+  let opb_file = opb_file
+    .canonicalize()
+    .unwrap_or_else(|_| panic!("not found: {:?}", opb_file));
+
+  let mut opb = opb::Opb::from(&opb_file).unwrap_or_else(|error| panic!("{}", error));
+
+  let encoding = matches
+    .get_one::<String>("encoding")
+    .unwrap()
+    .parse::<PbEncoding>()
+    .unwrap_or_else(|error| panic!("{}", error));
+  let search = matches
+    .get_one::<String>("search")
+    .unwrap()
+    .parse::<Search>()
+    .unwrap_or_else(|error| panic!("{}", error));
+  let config = match matches.get_one::<String>("preset") {
+    Some(name) => SolverConfig::preset_by_name(name).unwrap_or_else(|error| panic!("{}", error)),
+    None => SolverConfig::new(),
+  };
+  let config = config.with_seed(*matches.get_one::<u64>("seed").unwrap());
+
+  let configure = |solver: &mut SATSolver<usize>| {
+    solver.set_config(config);
+    solver.set_pb_encoding(encoding);
+    solver.set_search(search);
+  };
+
+  if opb.objective().is_some() {
+    // 改善解を見つけるたびにコストを出力する
+    match opb.minimize_with(configure, |cost| println!("o {}", cost)) {
+      MinimizeResult::Found(optimum) => {
+        println!("{}", if optimum.optimal { "OPTIMUM" } else { "SAT" });
+        optimum.model.iter().for_each(|e| print!("{} ", e));
+        println!();
+      }
+      MinimizeResult::Unsatisfiable => println!("UNSAT"),
+      MinimizeResult::Unknown => println!("UNKNOWN"),
+    }
+    return;
+  }
+
+  match opb.solve_with(configure) {
+    Some(Some(solution)) => {
+      println!("SAT");
+      solution.iter().for_each(|e| print!("{} ", e));
+      println!();
+    }
+    Some(None) => println!("UNSAT"),
+    None => println!("UNKNOWN"),
+  }
+}
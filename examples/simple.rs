@@ -24,7 +24,7 @@ fn main() {
       println!(
         "{}: {}",
         solver.get_variable_name(v).unwrap(),
-        solver.get_model_value(&v).unwrap()
+        solver.get_model_value(v).unwrap()
       );
     }
   } else {
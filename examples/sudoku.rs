@@ -8,7 +8,7 @@ use std::{
 
 use clap::{arg, command, value_parser};
 
-use rewsat::{sat_solver::*, *};
+use rewsat::{config::SolverConfig, encodings::cardinality::CardinalityEncoding, sat_solver::*, *};
 
 fn main() {
   let matches = command!()
@@ -18,6 +18,10 @@ fn main() {
         .value_parser(value_parser!(PathBuf))
         .required(true),
     )
+    .arg(arg!(-p --preset <NAME> "configuration preset (sat, unsat, plain, fast-small)"))
+    .arg(arg!(-e --encoding <NAME> "encoding of the exactly-one constraints (pairwise, sequential, commander, ...)"))
+    .arg(arg!(-a --all "print all answers"))
+    .arg(arg!(-c --count "print the number of answers"))
     .get_matches();
 
   println!("sudoku solver started.");
@@ -35,41 +39,104 @@ fn main() {
   println!("problem:");
   print_sudoku(&problem);
 
+  let config = match matches.get_one::<String>("preset") {
+    Some(name) => SolverConfig::preset_by_name(name).unwrap_or_else(|error| panic!("{}", error)),
+    None => SolverConfig::new(),
+  };
+
+  let encoding = match matches.get_one::<String>("encoding") {
+    Some(name) => name
+      .parse::<CardinalityEncoding>()
+      .unwrap_or_else(|error| panic!("{}", error)),
+    None => CardinalityEncoding::default(),
+  };
+
   println!("solving...");
 
-  if let Some(answer) = solve_sudoku(&problem) {
-    println!("SOLVED");
-    println!("answer:");
-    print_sudoku(&answer);
-  } else {
-    println!("UNSOLVABLE");
+  if matches.get_flag("count") {
+    let mut solver = sudoku_solver(&problem, config, encoding);
+    println!("{} answer(s)", solver.count_models());
+    return;
+  }
+
+  if matches.get_flag("all") {
+    let mut count = 0;
+    for answer in all_sudoku_answers(&problem, config, encoding) {
+      count += 1;
+      println!("answer {}:", count);
+      print_sudoku(&answer);
+    }
+    println!("{} answer(s)", count);
+    return;
+  }
+
+  match solve_sudoku(&problem, config, encoding) {
+    Some(Some(answer)) => {
+      println!("SOLVED");
+      println!("answer:");
+      print_sudoku(&answer);
+    }
+    Some(None) => println!("UNSOLVABLE"),
+    None => println!("UNKNOWN"),
   }
 }
 
 type Sudoku = Vec<Vec<u8>>;
 
 // 4x4 or 9x9
-fn solve_sudoku(problem: &Sudoku) -> Option<Sudoku> {
-  assert!(problem.len() == 4 || problem.len() == 9);
+// 解けなければSome(None)，設定した上限に達して分からなければNone
+fn solve_sudoku(
+  problem: &Sudoku,
+  config: SolverConfig,
+  encoding: CardinalityEncoding,
+) -> Option<Option<Sudoku>> {
+  let mut solver = sudoku_solver(problem, config, encoding);
+  if !solver.solve_limited()? {
+    return Some(None);
+  }
+  Some(Some(answer(problem.len() as u8, |name| {
+    solver.get_model_value_from_name(name).unwrap()
+  })))
+}
 
-  // variablesのうちどれかひとつのvariableだけがtrueであるような制約を追加する
-  fn add_only_one_constraints(solver: &mut SATSolver<(u8, u8, u8)>, variables: &[Variable]) {
-    // At Most One
-    // (!x1 || !x2) && (!x2 || !x3) && ... && (!x8 || !x9)
-    for i in 0..variables.len() - 1 {
-      for j in i + 1..variables.len() {
-        solver.add_clause(&[!variables[i], !variables[j]])
-      }
-    }
+// 各マスの数字(r, c, n)に射影して全ての解を求める
+fn all_sudoku_answers(
+  problem: &Sudoku,
+  config: SolverConfig,
+  encoding: CardinalityEncoding,
+) -> impl Iterator<Item = Sudoku> {
+  let sudoku_size = problem.len() as u8;
+  let mut solver = sudoku_solver(problem, config, encoding);
+  let cells = (0..sudoku_size)
+    .flat_map(|r| (0..sudoku_size).flat_map(move |c| (1..=sudoku_size).map(move |n| (r, c, n))))
+    .map(|name| solver.variable(name))
+    .collect::<Vec<_>>();
+  solver
+    .projected_models(&cells)
+    .map(move |model| answer(sudoku_size, |name| model[name]))
+}
 
-    // At Least One
-    // x1 || x2 || ... || x9
-    solver.add_clause(variables);
-  }
+fn answer(sudoku_size: u8, value: impl Fn(&(u8, u8, u8)) -> bool) -> Sudoku {
+  (0..sudoku_size)
+    .map(|r| {
+      (0..sudoku_size)
+        .map(|c| (1..=sudoku_size).find(|&n| value(&(r, c, n))).unwrap())
+        .collect()
+    })
+    .collect()
+}
+
+fn sudoku_solver(
+  problem: &Sudoku,
+  config: SolverConfig,
+  encoding: CardinalityEncoding,
+) -> SATSolver<(u8, u8, u8)> {
+  assert!(problem.len() == 4 || problem.len() == 9);
 
   let sudoku_size = problem.len() as u8;
 
-  let mut solver = SATSolver::new();
+  let mut solver = SATSolver::with_config(config);
+  solver.set_cardinality_encoding(encoding);
 
   // solverに変数を設定(x001-x889)
   for n in 1..=sudoku_size {
@@ -86,7 +153,7 @@ fn solve_sudoku(problem: &Sudoku) -> Option<Sudoku> {
       let variables = (1..=sudoku_size)
         .map(|n| solver.variable((r, c, n)))
         .collect::<Vec<_>>();
-      add_only_one_constraints(&mut solver, &variables);
+      solver.exactly_one(&variables);
     }
   }
 
@@ -96,7 +163,7 @@ fn solve_sudoku(problem: &Sudoku) -> Option<Sudoku> {
       let variables = (0..sudoku_size)
         .map(|c| solver.variable((r, c, n)))
         .collect::<Vec<_>>();
-      add_only_one_constraints(&mut solver, &variables);
+      solver.exactly_one(&variables);
     }
   }
 
@@ -106,7 +173,7 @@ fn solve_sudoku(problem: &Sudoku) -> Option<Sudoku> {
       let variables = (0..sudoku_size)
         .map(|r| solver.variable((r, c, n)))
         .collect::<Vec<_>>();
-      add_only_one_constraints(&mut solver, &variables);
+      solver.exactly_one(&variables);
     }
   }
 
@@ -117,14 +184,13 @@ fn solve_sudoku(problem: &Sudoku) -> Option<Sudoku> {
       for block_r in 0..block_size {
         for block_c in 0..block_size {
           let variables = (0..block_size)
-            .map(|r| {
+            .flat_map(|r| {
               (0..block_size)
                 .map(|c| solver.variable((block_size * block_r + r, block_size * block_c + c, n)))
                 .collect::<Vec<_>>()
             })
-            .flatten()
             .collect::<Vec<_>>();
-          add_only_one_constraints(&mut solver, &variables);
+          solver.exactly_one(&variables);
         }
       }
     }
@@ -147,23 +213,7 @@ fn solve_sudoku(problem: &Sudoku) -> Option<Sudoku> {
     }
   }
 
-  if !solver.solve() {
-    return None;
-  }
-
-  let answer = (0..sudoku_size)
-    .map(|r| {
-      (0..sudoku_size)
-        .map(|c| {
-          (1..=sudoku_size)
-            .find(|&n| solver.get_model_value_from_name(&(r, c, n)).unwrap())
-            .unwrap()
-        })
-        .collect()
-    })
-    .collect();
-
-  Some(answer)
+  solver
 }
 
 fn parse_sudoku<P: AsRef<Path>>(sudoku_file: P) -> Result<Sudoku, Box<dyn Error>> {
@@ -182,8 +232,8 @@ fn parse_sudoku<P: AsRef<Path>>(sudoku_file: P) -> Result<Sudoku, Box<dyn Error>
       return Err(Box::new(ParseSudokuError));
     }
 
-    for c in 0..sudoku_size {
-      sudoku[r][c] = match line.chars().nth(c).unwrap().to_digit(10) {
+    for (c, ch) in line.chars().enumerate() {
+      sudoku[r][c] = match ch.to_digit(10) {
         Some(n) => n as u8,
         None => return Err(Box::new(ParseSudokuError)),
       };
@@ -196,7 +246,7 @@ fn parse_sudoku<P: AsRef<Path>>(sudoku_file: P) -> Result<Sudoku, Box<dyn Error>
 fn print_sudoku(sudoku: &Sudoku) {
   sudoku.iter().for_each(|l| {
     l.iter().for_each(|n| print!("{}", n));
-    println!("");
+    println!();
   });
 }
 
@@ -205,7 +255,7 @@ struct ParseSudokuError;
 
 impl fmt::Display for ParseSudokuError {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    write!(f, "{}", self)
+    write!(f, "failed to parse sudoku file")
   }
 }
 
@@ -0,0 +1,251 @@
+use crate::expressions::literal::Literal;
+
+/// 節の参照(アリーナ内での節の先頭位置)
+pub(super) type ClauseRef = u32;
+
+/// 節の先頭に置くヘッダの語数(長さ，フラグ，LBD)
+const HEADER_SIZE: usize = 3;
+const LEARNT: u32 = 1;
+const DELETED: u32 = 1 << 1;
+const USED: u32 = 1 << 2;
+const VIVIFIED: u32 = 1 << 3;
+/// これより無駄な語が少なければ詰め直さない
+const MIN_GARBAGE: usize = 1 << 16;
+
+/// 全ての節を1つの32ビットの語の配列に並べて格納する
+/// リテラルとして読むのはliterals・literals_mutで節の本体を取り出すときだけ
+#[derive(Clone, Debug, Default)]
+pub(super) struct ClauseArena {
+  memory: Vec<u32>,
+  refs: Vec<ClauseRef>,
+  /// 削除や短縮で使われなくなった語の数
+  wasted: usize,
+}
+
+impl ClauseArena {
+  pub(super) fn alloc(&mut self, literals: &[Literal], learnt: bool, lbd: usize) -> ClauseRef {
+    let cref = self.memory.len() as ClauseRef;
+    let flags = if learnt { LEARNT } else { 0 };
+    self.memory.push(literals.len() as u32);
+    self.memory.push(flags);
+    self.memory.push(lbd as u32);
+    self
+      .memory
+      .extend(literals.iter().map(|literal| literal.index() as u32));
+    self.refs.push(cref);
+    cref
+  }
+
+  /// 削除したものも含む全ての節
+  pub(super) fn refs(&self) -> &[ClauseRef] {
+    &self.refs
+  }
+
+  pub(super) fn literals(&self, cref: ClauseRef) -> &[Literal] {
+    let start = cref as usize + HEADER_SIZE;
+    let words = &self.memory[start..start + self.len(cref)];
+    // SAFETY: Literalはu32をそのまま包んだrepr(transparent)の型なので，同じ配置で読める
+    unsafe { std::slice::from_raw_parts(words.as_ptr().cast::<Literal>(), words.len()) }
+  }
+
+  pub(super) fn literals_mut(&mut self, cref: ClauseRef) -> &mut [Literal] {
+    let start = cref as usize + HEADER_SIZE;
+    let end = start + self.len(cref);
+    let words = &mut self.memory[start..end];
+    // SAFETY: literalsと同じ(どのu32もLiteralとして有効)
+    unsafe { std::slice::from_raw_parts_mut(words.as_mut_ptr().cast::<Literal>(), words.len()) }
+  }
+
+  pub(super) fn len(&self, cref: ClauseRef) -> usize {
+    self.memory[cref as usize] as usize
+  }
+
+  /// 節をliteralsに置き換える(元の節より長くしてはいけない)
+  pub(super) fn shrink(&mut self, cref: ClauseRef, literals: &[Literal]) {
+    let len = self.len(cref);
+    debug_assert!(literals.len() <= len);
+    self.wasted += len - literals.len();
+    self.memory[cref as usize] = literals.len() as u32;
+    self.literals_mut(cref).copy_from_slice(literals);
+  }
+
+  /// 節を削除する(見張りや理由からの参照は詰め直すときに取り除く)
+  pub(super) fn delete(&mut self, cref: ClauseRef) {
+    if self.is_deleted(cref) {
+      return;
+    }
+    self.wasted += HEADER_SIZE + self.len(cref);
+    self.set_flag(cref, DELETED, true);
+  }
+
+  pub(super) fn is_deleted(&self, cref: ClauseRef) -> bool {
+    self.flag(cref, DELETED)
+  }
+
+  pub(super) fn is_learnt(&self, cref: ClauseRef) -> bool {
+    self.flag(cref, LEARNT)
+  }
+
+  pub(super) fn set_learnt(&mut self, cref: ClauseRef, learnt: bool) {
+    self.set_flag(cref, LEARNT, learnt);
+  }
+
+  /// 前回の削減以降に衝突解析で使われたか
+  pub(super) fn is_used(&self, cref: ClauseRef) -> bool {
+    self.flag(cref, USED)
+  }
+
+  pub(super) fn set_used(&mut self, cref: ClauseRef, used: bool) {
+    self.set_flag(cref, USED, used);
+  }
+
+  /// 既に節の強化を試みたか
+  pub(super) fn is_vivified(&self, cref: ClauseRef) -> bool {
+    self.flag(cref, VIVIFIED)
+  }
+
+  pub(super) fn set_vivified(&mut self, cref: ClauseRef, vivified: bool) {
+    self.set_flag(cref, VIVIFIED, vivified);
+  }
+
+  /// 節に含まれるリテラルの決定レベルの種類数
+  pub(super) fn lbd(&self, cref: ClauseRef) -> usize {
+    self.memory[cref as usize + 2] as usize
+  }
+
+  /// 無駄な語が全体の半分を超えたか
+  pub(super) fn needs_collection(&self) -> bool {
+    self.wasted >= MIN_GARBAGE && 2 * self.wasted > self.memory.len()
+  }
+
+  /// 削除されていない節を新しい配列に詰め直し，古い配列を返す
+  /// 古い配列のforwardで移動先の参照が得られる
+  pub(super) fn collect(&mut self) -> ClauseArena {
+    let mut arena = ClauseArena {
+      memory: Vec::with_capacity(self.memory.len() - self.wasted),
+      refs: Vec::with_capacity(self.refs.len()),
+      wasted: 0,
+    };
+
+    for k in 0..self.refs.len() {
+      let cref = self.refs[k];
+      if self.is_deleted(cref) {
+        continue;
+      }
+      let start = cref as usize;
+      let end = start + HEADER_SIZE + self.len(cref);
+      let moved = arena.memory.len() as ClauseRef;
+      arena.memory.extend_from_slice(&self.memory[start..end]);
+      arena.refs.push(moved);
+      // LBDの位置に移動先を書いておく
+      self.memory[start + 2] = moved;
+    }
+
+    std::mem::replace(self, arena)
+  }
+
+  /// collectで移動した節の新しい参照(削除した節ならNone)
+  pub(super) fn forward(&self, cref: ClauseRef) -> Option<ClauseRef> {
+    if self.is_deleted(cref) {
+      None
+    } else {
+      Some(self.memory[cref as usize + 2])
+    }
+  }
+
+  fn flag(&self, cref: ClauseRef, flag: u32) -> bool {
+    self.memory[cref as usize + 1] & flag != 0
+  }
+
+  fn set_flag(&mut self, cref: ClauseRef, flag: u32, value: bool) {
+    let flags = &mut self.memory[cref as usize + 1];
+    if value {
+      *flags |= flag;
+    } else {
+      *flags &= !flag;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn literals(variables: &[usize]) -> Vec<Literal> {
+    variables
+      .iter()
+      .map(|&variable| Literal::new(variable, variable % 2 == 0))
+      .collect()
+  }
+
+  #[test]
+  fn moves_live_clauses_when_collecting() {
+    let mut arena = ClauseArena::default();
+    let first = arena.alloc(&literals(&[0, 1, 2]), false, 0);
+    let deleted = arena.alloc(&literals(&[3, 4]), true, 2);
+    let shrunk = arena.alloc(&literals(&[5, 6, 7, 8]), true, 4);
+    arena.delete(deleted);
+    arena.shrink(shrunk, &literals(&[8, 5]));
+    arena.set_used(shrunk, true);
+    assert!(arena.is_deleted(deleted));
+    assert_eq!(arena.literals(shrunk), literals(&[8, 5]));
+    // 無駄な語が少なければ詰め直さない
+    assert!(!arena.needs_collection());
+
+    let old = arena.collect();
+    assert_eq!(old.forward(deleted), None);
+    let (first, shrunk) = (old.forward(first).unwrap(), old.forward(shrunk).unwrap());
+    assert_eq!(arena.refs(), [first, shrunk]);
+    assert_eq!(arena.literals(first), literals(&[0, 1, 2]));
+    assert!(!arena.is_learnt(first));
+    assert_eq!(arena.literals(shrunk), literals(&[8, 5]));
+    assert!(arena.is_learnt(shrunk) && arena.is_used(shrunk));
+    assert_eq!(arena.lbd(shrunk), 4);
+    assert_eq!(arena.wasted, 0);
+  }
+
+  #[test]
+  fn needs_collection_when_half_is_wasted() {
+    let mut arena = ClauseArena::default();
+    let clause = literals(&[0, 1, 2, 3, 4]);
+    let refs = (0..MIN_GARBAGE / 4)
+      .map(|_| arena.alloc(&clause, true, 3))
+      .collect::<Vec<_>>();
+    for &cref in &refs[..refs.len() / 3] {
+      arena.delete(cref);
+    }
+    assert!(!arena.needs_collection());
+    for &cref in &refs[..refs.len() * 2 / 3] {
+      arena.delete(cref);
+    }
+    assert!(arena.needs_collection());
+    arena.collect();
+    assert_eq!(arena.refs().len(), refs.len() - refs.len() * 2 / 3);
+    assert!(!arena.needs_collection());
+  }
+
+  #[test]
+  fn reads_words_as_literals() {
+    let mut arena = ClauseArena::default();
+    // ヘッダと同じ値の語も，大きな番号の変数もリテラルとして読める
+    let clause = vec![
+      Literal::new(0, false),
+      Literal::new(1, true),
+      Literal::new((u32::MAX / 2 - 1) as usize, true),
+    ];
+    let cref = arena.alloc(&clause, true, 3);
+    assert_eq!(arena.len(cref), 3);
+    assert_eq!(arena.literals(cref), clause);
+    arena.literals_mut(cref).swap(0, 2);
+    assert_eq!(arena.literals(cref), [clause[2], clause[1], clause[0]]);
+
+    // フラグは互いに独立に切り替わる
+    arena.set_vivified(cref, true);
+    arena.set_used(cref, true);
+    arena.set_learnt(cref, false);
+    assert!(arena.is_vivified(cref) && arena.is_used(cref) && !arena.is_learnt(cref));
+    arena.set_used(cref, false);
+    assert!(arena.is_vivified(cref) && !arena.is_used(cref) && !arena.is_deleted(cref));
+    assert_eq!(arena.lbd(cref), 3);
+  }
+}
@@ -0,0 +1,322 @@
+use std::mem;
+
+use crate::{
+  cdcl::solver::{Cdcl, Reason},
+  expressions::literal::Literal,
+};
+
+/// 真になるリテラルの個数がbound以下になる制約
+#[derive(Clone, Debug)]
+struct Constraint {
+  literals: Vec<Literal>,
+  bound: usize,
+  /// 今の割り当てで真のリテラルの個数
+  count: usize,
+}
+
+/// 個数制約の集まり
+/// 割り当てのたびに真のリテラルを数え，上限に達した制約の残りのリテラルを偽にする
+/// 導いたリテラルの説明の節は衝突の解析で必要になったときに求める
+#[derive(Clone, Debug, Default)]
+pub(super) struct Cardinalities {
+  constraints: Vec<Constraint>,
+  /// リテラルを含む制約(リテラルの添字ごと)
+  occurrences: Vec<Vec<usize>>,
+  /// 真のリテラルの個数が上限に達したので調べる制約
+  queue: Vec<usize>,
+  queued: Vec<bool>,
+  /// 変数を導いた制約
+  sources: Vec<usize>,
+}
+
+impl Cardinalities {
+  /// リテラルが真になったことを記録する
+  pub(super) fn assigned(&mut self, literal: Literal) {
+    let Some(occurrences) = self.occurrences.get(literal.index()) else {
+      return;
+    };
+    for &index in occurrences {
+      let constraint = &mut self.constraints[index];
+      constraint.count += 1;
+      if constraint.count >= constraint.bound && !self.queued[index] {
+        self.queued[index] = true;
+        self.queue.push(index);
+      }
+    }
+  }
+
+  /// 真だったリテラルの割り当てが取り消されたことを記録する
+  /// 未割り当てになった変数を含む制約が上限に達したままなら，その変数を導き直す
+  pub(super) fn unassigned(&mut self, literal: Literal) {
+    if self.occurrences.is_empty() {
+      return;
+    }
+    for &index in &self.occurrences[literal.index()] {
+      self.constraints[index].count -= 1;
+    }
+    for literal in [literal, !literal] {
+      for &index in &self.occurrences[literal.index()] {
+        let constraint = &self.constraints[index];
+        if constraint.count >= constraint.bound && !self.queued[index] {
+          self.queued[index] = true;
+          self.queue.push(index);
+        }
+      }
+    }
+  }
+
+  /// 変数の数を増やす
+  pub(super) fn grow(&mut self, num_variables: usize) {
+    if !self.occurrences.is_empty() {
+      self.occurrences.resize(2 * num_variables, vec![]);
+      self.sources.resize(num_variables, 0);
+    }
+  }
+
+  /// 調べる制約が残っていないか
+  pub(super) fn is_idle(&self) -> bool {
+    self.queue.is_empty()
+  }
+}
+
+impl Cdcl {
+  /// literalsのうち真になるものがbound個以下になる制約を追加する
+  /// 節に変換せず，上限に達したら残りのリテラルを偽にする
+  /// 追加した時点で充足不能になればfalseを返す
+  pub fn add_at_most(&mut self, literals: &[Literal], bound: usize) -> bool {
+    if self.inconsistent {
+      return false;
+    }
+    self.backtrack(0);
+
+    // 値の決まったリテラルと，互いに否定のリテラルの組(どちらか一方だけが真)を除く
+    let mut bound = bound as isize;
+    let mut remaining: Vec<Literal> = vec![];
+    for &literal in literals {
+      match self.value(literal) {
+        Some(true) => bound -= 1,
+        Some(false) => {}
+        None => match remaining.iter().position(|&other| other == !literal) {
+          Some(k) => {
+            remaining.swap_remove(k);
+            bound -= 1;
+          }
+          None => remaining.push(literal),
+        },
+      }
+    }
+    if bound < 0 {
+      self.inconsistent = true;
+      return false;
+    }
+    let bound = bound as usize;
+    if bound >= remaining.len() {
+      return true;
+    }
+    if bound == 0 {
+      for &literal in &remaining {
+        if !self.add_clause(&[!literal]) {
+          return false;
+        }
+      }
+      return true;
+    }
+
+    let cardinalities = &mut self.cardinalities;
+    if cardinalities.occurrences.is_empty() {
+      cardinalities.occurrences = vec![vec![]; 2 * self.num_variables];
+      cardinalities.sources = vec![0; self.num_variables];
+      // 変数ごとの説明と，衝突の説明の分
+      self.cardinality_reasons = vec![vec![]; self.num_variables + 1];
+    }
+    let index = cardinalities.constraints.len();
+    for literal in &remaining {
+      cardinalities.occurrences[literal.index()].push(index);
+    }
+    cardinalities.constraints.push(Constraint {
+      literals: remaining,
+      bound,
+      count: 0,
+    });
+    cardinalities.queued.push(false);
+    true
+  }
+
+  /// 上限に達した制約の未割り当てのリテラルを偽にする
+  /// 上限を超えた制約があれば，その真のリテラルからなる説明を衝突として返す
+  pub(super) fn propagate_cardinalities(&mut self) -> Option<Reason> {
+    while let Some(index) = self.cardinalities.queue.pop() {
+      self.cardinalities.queued[index] = false;
+      let constraint = &self.cardinalities.constraints[index];
+      if constraint.count < constraint.bound {
+        continue;
+      }
+      self.statistics.ticks += 1;
+
+      if constraint.count > constraint.bound {
+        let explanation = constraint
+          .literals
+          .iter()
+          .filter(|&&literal| self.value(literal) == Some(true))
+          .take(constraint.bound + 1)
+          .map(|&literal| !literal)
+          .collect();
+        self.cardinality_reasons[self.num_variables] = explanation;
+        self.statistics.cardinality_conflicts += 1;
+        return Some(Reason::Cardinality(self.num_variables));
+      }
+
+      for k in 0..self.cardinalities.constraints[index].literals.len() {
+        let literal = self.cardinalities.constraints[index].literals[k];
+        if self.value(literal).is_some() {
+          continue;
+        }
+        let variable = literal.variable();
+        self.cardinalities.sources[variable] = index;
+        self.cardinality_reasons[variable].clear();
+        self.statistics.cardinality_propagations += 1;
+        self.assign(!literal, Some(Reason::Cardinality(variable)));
+      }
+    }
+    None
+  }
+
+  /// 個数制約で導いた変数の説明の節を求める(求め済みなら何もしない)
+  /// 導いたリテラルと，それより前に真になった制約のリテラルの否定からなる
+  pub(super) fn explain_cardinality(&mut self, slot: usize) {
+    if !self.cardinality_reasons[slot].is_empty() {
+      return;
+    }
+    let position = self.positions[slot];
+    let mut explanation = mem::take(&mut self.cardinality_reasons[slot]);
+    explanation.push(Literal::new(slot, self.values[slot].unwrap()));
+    let constraint = &self.cardinalities.constraints[self.cardinalities.sources[slot]];
+    for &literal in &constraint.literals {
+      if self.value(literal) == Some(true) && self.positions[literal.variable()] < position {
+        explanation.push(!literal);
+      }
+    }
+    self.cardinality_reasons[slot] = explanation;
+  }
+
+  /// 個数制約で導いた変数の理由のリテラルの最大のレベル
+  /// 説明を求める前なら，導いた時点で真の制約のリテラルから求める
+  pub(super) fn cardinality_reason_level(&self, slot: usize) -> usize {
+    if !self.cardinality_reasons[slot].is_empty() {
+      return self.cardinality_reasons[slot][1..]
+        .iter()
+        .map(|literal| self.levels[literal.variable()])
+        .max()
+        .unwrap_or(0);
+    }
+    let constraint = &self.cardinalities.constraints[self.cardinalities.sources[slot]];
+    constraint
+      .literals
+      .iter()
+      .filter(|&&literal| self.value(literal) == Some(true))
+      .map(|literal| self.levels[literal.variable()])
+      .max()
+      .unwrap_or(0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    cdcl::{
+      options::CdclOptions,
+      solver::{Cdcl, SolveResult},
+    },
+    expressions::literal::Literal,
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  #[test]
+  fn agrees_with_brute_force() {
+    let mut random = Random::new(13);
+    let mut unsatisfiable = 0;
+    for _ in 0..100 {
+      let num_variables = 6 + random.below(7);
+      let clauses = random_clauses(&mut random, num_variables, 2 * num_variables, 3);
+      let constraints = (0..1 + random.below(4))
+        .map(|_| {
+          let mut variables = (0..num_variables).collect::<Vec<_>>();
+          let literals = (0..2 + random.below(num_variables - 1))
+            .map(|_| {
+              let variable = variables.swap_remove(random.below(variables.len()));
+              Literal::new(variable, random.below(2) == 0)
+            })
+            .collect::<Vec<_>>();
+          let bound = random.below(literals.len());
+          (literals, bound)
+        })
+        .collect::<Vec<_>>();
+      let expected = testing::models(num_variables, &clauses)
+        .into_iter()
+        .filter(|&assignment| {
+          constraints.iter().all(|(literals, bound)| {
+            literals
+              .iter()
+              .filter(|&&literal| testing::holds(assignment, literal))
+              .count()
+              <= *bound
+          })
+        })
+        .collect::<Vec<_>>();
+      if expected.is_empty() {
+        unsatisfiable += 1;
+      }
+
+      let mut solver = Cdcl::new(num_variables);
+      let consistent = clauses.iter().all(|clause| solver.add_clause(clause))
+        && constraints
+          .iter()
+          .all(|(literals, bound)| solver.add_at_most(literals, *bound));
+      if !consistent {
+        assert!(expected.is_empty());
+        continue;
+      }
+      match solver.solve() {
+        SolveResult::Satisfiable(model) => {
+          assert!(expected.contains(&testing::assignment(&model, num_variables)));
+        }
+        SolveResult::Unsatisfiable => assert!(expected.is_empty()),
+        SolveResult::Unknown => panic!("no limit is set"),
+      }
+    }
+    assert!(unsatisfiable > 0);
+  }
+
+  /// 鳩の巣原理: 各穴に1羽以下を個数制約で表すと，説明の節を学習して充足不能を示せる
+  #[test]
+  fn refutes_pigeonhole_with_lazy_explanations() {
+    let (pigeons, holes) = (6, 5);
+    let variable = |pigeon: usize, hole: usize| pigeon * holes + hole;
+    for chronological_backtracking in [false, true] {
+      let mut solver = Cdcl::new(pigeons * holes);
+      solver.set_options(CdclOptions {
+        chronological_backtracking,
+        chronological_levels: 0,
+        ..CdclOptions::default()
+      });
+      for pigeon in 0..pigeons {
+        let clause = (0..holes)
+          .map(|hole| Literal::new(variable(pigeon, hole), true))
+          .collect::<Vec<_>>();
+        assert!(solver.add_clause(&clause));
+      }
+      for hole in 0..holes {
+        let literals = (0..pigeons)
+          .map(|pigeon| Literal::new(variable(pigeon, hole), true))
+          .collect::<Vec<_>>();
+        assert!(solver.add_at_most(&literals, 1));
+      }
+      assert!(matches!(solver.solve(), SolveResult::Unsatisfiable));
+      let statistics = solver.statistics();
+      assert!(statistics.cardinality_propagations > 0);
+      assert!(statistics.cardinality_conflicts > 0);
+      assert!(statistics.conflicts > 0);
+    }
+  }
+}
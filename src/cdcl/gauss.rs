@@ -0,0 +1,357 @@
+use std::mem;
+
+use crate::{
+  cdcl::solver::{Cdcl, Reason},
+  encodings::xor::normalize,
+  expressions::literal::Literal,
+};
+
+/// XOR制約を行とするGF(2)上の行列
+/// 各行の基底変数の列が1なのはその行だけ(Gauss-Jordanの消去済み)に保ち，
+/// 基底変数が割り当てられたら未割り当ての変数に基底を取り替える
+/// こうしておくと，行の線形結合で未割り当ての変数が1つだけになるものは1つの行だけで見つかる
+#[derive(Clone, Debug, Default)]
+pub(super) struct Matrix {
+  rows: Vec<Vec<u64>>,
+  rhs: Vec<bool>,
+  /// 各行の基底変数の列
+  basics: Vec<usize>,
+  /// 列に対応する変数
+  columns: Vec<usize>,
+  /// 変数に対応する列
+  column_of: Vec<Option<usize>>,
+  /// 割り当てられてからまだ調べていない列
+  pending: Vec<usize>,
+  /// 調べ直す行
+  queue: Vec<usize>,
+  queued: Vec<bool>,
+}
+
+impl Matrix {
+  /// 変数が割り当てられたことを記録し，次の伝播でその変数を含む行を調べる
+  pub(super) fn notify(&mut self, variable: usize) {
+    if let Some(Some(column)) = self.column_of.get(variable) {
+      self.pending.push(*column);
+    }
+  }
+
+  /// 変数の数を増やす
+  pub(super) fn grow(&mut self, num_variables: usize) {
+    if !self.column_of.is_empty() {
+      self.column_of.resize(num_variables, None);
+    }
+  }
+
+  /// 調べる行が残っていないか
+  pub(super) fn is_idle(&self) -> bool {
+    self.pending.is_empty() && self.queue.is_empty()
+  }
+
+  fn contains(&self, row: usize, column: usize) -> bool {
+    self.rows[row][column / 64] >> (column % 64) & 1 == 1
+  }
+
+  fn enqueue(&mut self, row: usize) {
+    if !self.queued[row] {
+      self.queued[row] = true;
+      self.queue.push(row);
+    }
+  }
+
+  /// 変数の列を返す(なければ追加する)
+  fn column(&mut self, variable: usize, num_variables: usize) -> usize {
+    if self.column_of.is_empty() {
+      self.column_of = vec![None; num_variables];
+    }
+    if let Some(column) = self.column_of[variable] {
+      return column;
+    }
+
+    let column = self.columns.len();
+    self.columns.push(variable);
+    self.column_of[variable] = Some(column);
+    let words = self.columns.len().div_ceil(64);
+    for row in self.rows.iter_mut() {
+      row.resize(words, 0);
+    }
+    column
+  }
+
+  /// 列の和がrhsになる行を，既存の行の基底変数を消去してから加える
+  /// 0 = 1 になれば矛盾としてfalseを返す
+  fn add_row(&mut self, columns: &[usize], rhs: bool) -> bool {
+    let mut row = vec![0; self.columns.len().div_ceil(64)];
+    for &column in columns {
+      row[column / 64] ^= 1 << (column % 64);
+    }
+    let mut rhs = rhs;
+    for k in 0..self.rows.len() {
+      let basic = self.basics[k];
+      if row[basic / 64] >> (basic % 64) & 1 == 1 {
+        for (word, other) in row.iter_mut().zip(&self.rows[k]) {
+          *word ^= other;
+        }
+        rhs ^= self.rhs[k];
+      }
+    }
+
+    let Some(basic) = ones(&row).next() else {
+      return !rhs;
+    };
+    self.rows.push(row);
+    self.rhs.push(rhs);
+    self.basics.push(basic);
+    self.queued.push(false);
+    let index = self.rows.len() - 1;
+    self.pivot(index, basic);
+    self.enqueue(index);
+    true
+  }
+
+  /// 行rowの基底をcolumnに取り替え，他の行からcolumnを消去する
+  /// 変わった行は調べ直す
+  fn pivot(&mut self, row: usize, column: usize) {
+    self.basics[row] = column;
+    let pivot = mem::take(&mut self.rows[row]);
+    for other in 0..self.rows.len() {
+      if other == row || self.rows[other][column / 64] >> (column % 64) & 1 == 0 {
+        continue;
+      }
+      for (word, pivot_word) in self.rows[other].iter_mut().zip(&pivot) {
+        *word ^= pivot_word;
+      }
+      self.rhs[other] ^= self.rhs[row];
+      self.enqueue(other);
+    }
+    self.rows[row] = pivot;
+  }
+}
+
+/// 1になっているビットの位置
+fn ones(words: &[u64]) -> impl Iterator<Item = usize> + '_ {
+  words.iter().enumerate().flat_map(|(k, &word)| {
+    let mut word = word;
+    std::iter::from_fn(move || {
+      if word == 0 {
+        return None;
+      }
+      let bit = word.trailing_zeros() as usize;
+      word &= word - 1;
+      Some(64 * k + bit)
+    })
+  })
+}
+
+impl Cdcl {
+  /// literalsの排他的論理和がrhsになる制約を追加する
+  /// 節に変換せず，Gauss-Jordanの消去で単位伝播と同様に値を導く
+  /// 追加した時点で充足不能になればfalseを返す
+  pub fn add_xor(&mut self, literals: &[Literal], rhs: bool) -> bool {
+    if self.inconsistent {
+      return false;
+    }
+    self.backtrack(0);
+
+    let (variables, rhs) = normalize(literals, rhs);
+    if self.xor_reasons.is_empty() {
+      // 変数ごとの理由と，衝突の説明の分
+      self.xor_reasons = vec![vec![]; self.num_variables + 1];
+    }
+    let columns = variables
+      .iter()
+      .map(|&variable| self.matrix.column(variable, self.num_variables))
+      .collect::<Vec<_>>();
+    if !self.matrix.add_row(&columns, rhs) || self.propagate().is_some() {
+      self.inconsistent = true;
+    }
+    !self.inconsistent
+  }
+
+  /// 割り当てが変わった行を調べ，XOR制約から導かれるリテラルを割り当てる
+  /// 満たせない行があればその説明を衝突として返す
+  pub(super) fn propagate_xors(&mut self) -> Option<Reason> {
+    while let Some(column) = self.matrix.pending.pop() {
+      for row in 0..self.matrix.rows.len() {
+        if self.matrix.contains(row, column) {
+          self.matrix.enqueue(row);
+        }
+      }
+    }
+
+    while let Some(row) = self.matrix.queue.pop() {
+      self.matrix.queued[row] = false;
+      if let Some(conflict) = self.check_row(row) {
+        return Some(conflict);
+      }
+    }
+    None
+  }
+
+  /// 行の未割り当ての変数が1つなら値を導き，なければ満たされているか調べる
+  /// 基底変数が割り当て済みで未割り当ての変数が2つ以上あれば基底を取り替える
+  fn check_row(&mut self, row: usize) -> Option<Reason> {
+    self.statistics.ticks += 1;
+    let matrix = &self.matrix;
+    let mut unassigned = vec![];
+    let mut parity = matrix.rhs[row];
+    for column in ones(&matrix.rows[row]) {
+      match self.values[matrix.columns[column]] {
+        Some(value) => parity ^= value,
+        None => {
+          unassigned.push(column);
+          if unassigned.len() == 2 {
+            break;
+          }
+        }
+      }
+    }
+
+    if unassigned.len() == 2 {
+      if self.values[matrix.columns[matrix.basics[row]]].is_some() {
+        self.matrix.pivot(row, unassigned[0]);
+      }
+      return None;
+    }
+    if unassigned.is_empty() && !parity {
+      return None;
+    }
+
+    // 導いたリテラル(衝突なら無し)と，割り当て済みの変数の偽のリテラルからなる説明
+    let (slot, implied) = match unassigned.first() {
+      Some(&column) => {
+        let variable = matrix.columns[column];
+        (variable, Some(Literal::new(variable, parity)))
+      }
+      None => (self.num_variables, None),
+    };
+    let mut explanation = mem::take(&mut self.xor_reasons[slot]);
+    explanation.clear();
+    explanation.extend(implied);
+    for column in ones(&matrix.rows[row]) {
+      let variable = matrix.columns[column];
+      if let Some(value) = self.values[variable] {
+        explanation.push(Literal::new(variable, !value));
+      }
+    }
+    self.xor_reasons[slot] = explanation;
+
+    match implied {
+      Some(literal) => {
+        self.statistics.xor_propagations += 1;
+        self.assign(literal, Some(Reason::Xor(slot)));
+        None
+      }
+      None => {
+        self.statistics.xor_conflicts += 1;
+        Some(Reason::Xor(slot))
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    cdcl::{
+      options::CdclOptions,
+      solver::{Cdcl, SolveResult},
+    },
+    expressions::literal::Literal,
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  #[test]
+  fn solves_clauses_with_xor_constraints() {
+    let mut random = Random::new(11);
+    let mut unsatisfiable = 0;
+    let mut propagations = 0;
+    for i in 0..100 {
+      let chronological = i % 2 == 1;
+      let num_variables = 6 + random.below(7);
+      let clauses = random_clauses(&mut random, num_variables, 2 * num_variables, 3);
+      let xors = (0..1 + random.below(num_variables))
+        .map(|_| {
+          let literals = (0..1 + random.below(6))
+            .map(|_| Literal::new(random.below(num_variables), random.below(2) == 0))
+            .collect::<Vec<_>>();
+          (literals, random.below(2) == 0)
+        })
+        .collect::<Vec<_>>();
+      let expected = testing::models(num_variables, &clauses)
+        .into_iter()
+        .filter(|&assignment| {
+          xors.iter().all(|(literals, rhs)| {
+            let parity = literals
+              .iter()
+              .filter(|&&literal| testing::holds(assignment, literal))
+              .count()
+              % 2
+              == 1;
+            parity == *rhs
+          })
+        })
+        .collect::<Vec<_>>();
+      if expected.is_empty() {
+        unsatisfiable += 1;
+      }
+
+      let mut solver = Cdcl::new(num_variables);
+      // 割り当て順と決定レベルの順が異なっても説明の節が正しいか調べる
+      solver.set_options(CdclOptions {
+        chronological_backtracking: chronological,
+        chronological_levels: 0,
+        restart_interval: 5,
+        xor_recovery: false,
+        ..CdclOptions::default()
+      });
+      let consistent = clauses.iter().all(|clause| solver.add_clause(clause))
+        && xors
+          .iter()
+          .all(|(literals, rhs)| solver.add_xor(literals, *rhs));
+      if !consistent {
+        assert!(expected.is_empty());
+        continue;
+      }
+      match solver.solve() {
+        SolveResult::Satisfiable(model) => {
+          assert!(expected.contains(&testing::assignment(&model, num_variables)));
+        }
+        SolveResult::Unsatisfiable => assert!(expected.is_empty()),
+        SolveResult::Unknown => panic!("no limit is set"),
+      }
+      propagations += solver.statistics().xor_propagations;
+    }
+    assert!(unsatisfiable > 0);
+    assert!(propagations > 0);
+  }
+
+  #[test]
+  fn propagates_sums_of_rows() {
+    // x0 + x1 + x2 = 1, x1 + x2 + x3 = 0 の和から x0 + x3 = 1
+    // どちらの行も未割り当ての変数が2つ以上残るが，x0を決めればx3が決まる
+    let x = |variable| Literal::new(variable, true);
+    let mut solver = Cdcl::new(4);
+    assert!(solver.add_xor(&[x(0), x(1), x(2)], true));
+    assert!(solver.add_xor(&[x(1), x(2), x(3)], false));
+    assert!(solver.add_clause(&[x(0)]));
+    assert_eq!(solver.value(x(3)), Some(false));
+    assert_eq!(solver.value(x(1)), None);
+    assert!(solver.statistics().xor_propagations > 0);
+  }
+
+  #[test]
+  fn detects_inconsistent_rows() {
+    // 3つの行の和が 0 = 1 になる
+    let x = |variable| Literal::new(variable, true);
+    let mut solver = Cdcl::new(3);
+    assert!(solver.add_xor(&[x(0), x(1)], true));
+    assert!(solver.add_xor(&[x(1), x(2)], true));
+    assert!(!solver.add_xor(&[x(0), !x(2)], false));
+    assert!(matches!(solver.solve(), SolveResult::Unsatisfiable));
+    // 否定や重複は右辺に移して打ち消す
+    let mut solver = Cdcl::new(2);
+    assert!(solver.add_xor(&[x(0), x(0), !x(1)], true));
+    assert_eq!(solver.value(x(1)), Some(false));
+  }
+}
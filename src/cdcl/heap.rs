@@ -0,0 +1,92 @@
+/// 活性度の大きい変数を取り出すための二分ヒープ
+#[derive(Clone, Debug, Default)]
+pub(super) struct VariableHeap {
+  heap: Vec<usize>,
+  positions: Vec<Option<usize>>,
+}
+
+impl VariableHeap {
+  pub(super) fn new(num_variables: usize) -> Self {
+    Self {
+      heap: vec![],
+      positions: vec![None; num_variables],
+    }
+  }
+
+  /// 変数の数を増やす
+  pub(super) fn grow(&mut self, num_variables: usize) {
+    self.positions.resize(num_variables, None);
+  }
+
+  pub(super) fn contains(&self, variable: usize) -> bool {
+    self.positions[variable].is_some()
+  }
+
+  pub(super) fn insert(&mut self, variable: usize, activity: &[f64]) {
+    if self.contains(variable) {
+      return;
+    }
+    self.positions[variable] = Some(self.heap.len());
+    self.heap.push(variable);
+    self.sift_up(self.heap.len() - 1, activity);
+  }
+
+  /// 活性度が上がった変数の位置を直す
+  pub(super) fn update(&mut self, variable: usize, activity: &[f64]) {
+    if let Some(position) = self.positions[variable] {
+      self.sift_up(position, activity);
+    }
+  }
+
+  pub(super) fn pop(&mut self, activity: &[f64]) -> Option<usize> {
+    let top = *self.heap.first()?;
+    let last = self.heap.pop().unwrap();
+    self.positions[top] = None;
+    if !self.heap.is_empty() {
+      self.heap[0] = last;
+      self.positions[last] = Some(0);
+      self.sift_down(0, activity);
+    }
+    Some(top)
+  }
+
+  fn sift_up(&mut self, mut position: usize, activity: &[f64]) {
+    let variable = self.heap[position];
+    while position > 0 {
+      let parent = (position - 1) / 2;
+      if activity[self.heap[parent]] >= activity[variable] {
+        break;
+      }
+      self.heap[position] = self.heap[parent];
+      self.positions[self.heap[position]] = Some(position);
+      position = parent;
+    }
+    self.heap[position] = variable;
+    self.positions[variable] = Some(position);
+  }
+
+  fn sift_down(&mut self, mut position: usize, activity: &[f64]) {
+    let variable = self.heap[position];
+    loop {
+      let left = 2 * position + 1;
+      if left >= self.heap.len() {
+        break;
+      }
+      let right = left + 1;
+      let child =
+        if right < self.heap.len() && activity[self.heap[right]] > activity[self.heap[left]] {
+          right
+        } else {
+          left
+        };
+      if activity[self.heap[child]] <= activity[variable] {
+        break;
+      }
+      self.heap[position] = self.heap[child];
+      self.positions[self.heap[position]] = Some(position);
+      position = child;
+    }
+    self.heap[position] = variable;
+    self.positions[variable] = Some(position);
+  }
+}
@@ -0,0 +1,218 @@
+use crate::{
+  cdcl::{arena::ClauseRef, solver::Cdcl},
+  expressions::literal::Literal,
+};
+
+/// 最初のインプロセッシングまでの探索ticks
+const INPROCESSING_INTERVAL: u64 = 500_000;
+/// インプロセッシングに使うticksの最小値
+const MIN_EFFORT: u64 = 10_000;
+/// 直前の探索ticksに対する各手法の仕事量(千分率)
+const SUBSUMPTION_EFFORT: u64 = 50;
+const VIVIFICATION_EFFORT: u64 = 100;
+const PROBING_EFFORT: u64 = 50;
+
+/// 探索の仕事量(ticks)に応じてインプロセッシングの時期と仕事量を決める
+#[derive(Clone, Debug)]
+pub(super) struct Scheduler {
+  rounds: u64,
+  /// 前回のインプロセッシングが終わった時点のticks
+  last: u64,
+  next: u64,
+  /// 次に失敗リテラル探索を始める変数
+  pub(super) probing_position: usize,
+}
+
+impl Scheduler {
+  pub(super) fn new() -> Self {
+    Self {
+      rounds: 0,
+      last: 0,
+      next: INPROCESSING_INTERVAL,
+      probing_position: 0,
+    }
+  }
+
+  pub(super) fn is_due(&self, ticks: u64) -> bool {
+    ticks >= self.next
+  }
+
+  /// 前回のインプロセッシング以降の探索ticksに比例した仕事量
+  fn budget(&self, ticks: u64, effort: u64) -> u64 {
+    (ticks - self.last) * effort / 1000 + MIN_EFFORT
+  }
+
+  /// インプロセッシングの間隔は回数に比例して伸ばす
+  fn finish(&mut self, ticks: u64) {
+    self.rounds += 1;
+    self.last = ticks;
+    self.next = ticks + INPROCESSING_INTERVAL * (self.rounds + 1);
+  }
+}
+
+impl Cdcl {
+  /// レベル0で節の包摂・強化，失敗リテラル探索を行う
+  /// 充足不能になればfalseを返す
+  pub(super) fn inprocess(&mut self) -> bool {
+    let start = self.statistics.ticks;
+    self.statistics.inprocessings += 1;
+
+    let limit = start + self.scheduler.budget(start, SUBSUMPTION_EFFORT);
+    if !self.subsume(limit) {
+      return false;
+    }
+
+    let limit = self.statistics.ticks + self.scheduler.budget(start, VIVIFICATION_EFFORT);
+    if !self.vivify(limit) {
+      return false;
+    }
+
+    let limit = self.statistics.ticks + self.scheduler.budget(start, PROBING_EFFORT);
+    if !self.probe(limit) {
+      return false;
+    }
+
+    self.collect_garbage();
+    self.statistics.inprocessing_ticks += self.statistics.ticks - start;
+    self.scheduler.finish(self.statistics.ticks);
+    true
+  }
+
+  /// 見張りを外した節idをliteralsに置き換えて見張り直す(レベル0で呼ぶ)
+  /// 充足不能になればfalseを返す
+  pub(super) fn reattach(&mut self, id: ClauseRef, literals: Vec<Literal>) -> bool {
+    let mut clause = vec![];
+    for literal in literals {
+      match self.value(literal) {
+        Some(true) => {
+          self.delete(id);
+          return true;
+        }
+        Some(false) => {}
+        None => clause.push(literal),
+      }
+    }
+
+    match clause.len() {
+      0 => {
+        self.delete(id);
+        false
+      }
+      1 => {
+        self.delete(id);
+        self.assign(clause[0], None);
+        self.propagate().is_none()
+      }
+      // 二項節になったら見張りだけで表す
+      2 => {
+        self.delete(id);
+        self.attach_binary(clause[0], clause[1]);
+        true
+      }
+      _ => {
+        self.clauses.shrink(id, &clause);
+        self.attach(id);
+        true
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    cdcl::solver::SolveResult,
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  #[test]
+  fn schedules_by_search_ticks() {
+    let mut scheduler = Scheduler::new();
+    assert!(!scheduler.is_due(INPROCESSING_INTERVAL - 1));
+    assert!(scheduler.is_due(INPROCESSING_INTERVAL));
+    // 仕事量は前回からの探索ticksに比例する
+    assert_eq!(
+      scheduler.budget(200_000, VIVIFICATION_EFFORT),
+      20_000 + MIN_EFFORT
+    );
+    scheduler.finish(600_000);
+    assert!(!scheduler.is_due(600_000 + 2 * INPROCESSING_INTERVAL - 1));
+    assert!(scheduler.is_due(600_000 + 2 * INPROCESSING_INTERVAL));
+    assert_eq!(
+      scheduler.budget(700_000, PROBING_EFFORT),
+      5_000 + MIN_EFFORT
+    );
+  }
+
+  #[test]
+  fn keeps_the_models() {
+    // 解いて学習節を作ってからインプロセッシングしても，解の集合は変わらない
+    let mut random = Random::new(3);
+    let mut learned = 0;
+    for _ in 0..60 {
+      let num_variables = 10 + random.below(4);
+      let num_clauses = num_variables * 4;
+      let clauses = random_clauses(&mut random, num_variables, num_clauses, 3);
+      let models = testing::models(num_variables, &clauses);
+      let mut solver = Cdcl::new(num_variables);
+      for clause in &clauses {
+        solver.add_clause(clause);
+      }
+      if matches!(solver.solve(), SolveResult::Unsatisfiable) {
+        assert!(models.is_empty());
+        continue;
+      }
+      learned += solver.statistics().learned_clauses;
+      solver.backtrack(0);
+      assert!(solver.inprocess());
+
+      let mut found = vec![];
+      while let SolveResult::Satisfiable(model) = solver.solve() {
+        let assignment = testing::assignment(&model, num_variables);
+        assert!(models.contains(&assignment));
+        found.push(assignment);
+        let blocking = (0..num_variables)
+          .map(|variable| Literal::new(variable, assignment >> variable & 1 == 0))
+          .collect::<Vec<_>>();
+        solver.add_clause(&blocking);
+        solver.backtrack(0);
+        if !solver.inprocess() {
+          break;
+        }
+      }
+      found.sort();
+      assert_eq!(found, models);
+    }
+    assert!(learned > 0);
+  }
+
+  #[test]
+  fn runs_during_search() {
+    // 鳩の巣原理(8羽を7つの巣に入れる)は探索が長く，途中でインプロセッシングが行われる
+    let (pigeons, holes) = (8, 7);
+    let variable = |pigeon: usize, hole: usize| pigeon * holes + hole;
+    let mut solver = Cdcl::new(pigeons * holes);
+    for pigeon in 0..pigeons {
+      let clause = (0..holes)
+        .map(|hole| Literal::new(variable(pigeon, hole), true))
+        .collect::<Vec<_>>();
+      solver.add_clause(&clause);
+    }
+    for hole in 0..holes {
+      for first in 0..pigeons {
+        for second in first + 1..pigeons {
+          solver.add_clause(&[
+            Literal::new(variable(first, hole), false),
+            Literal::new(variable(second, hole), false),
+          ]);
+        }
+      }
+    }
+    assert!(matches!(solver.solve(), SolveResult::Unsatisfiable));
+    let statistics = solver.statistics();
+    assert!(statistics.inprocessings > 0);
+    assert!(statistics.inprocessing_ticks > 0);
+  }
+}
@@ -0,0 +1,333 @@
+use crate::{
+  cdcl::solver::{Cdcl, Reason, Watch},
+  expressions::literal::Literal,
+};
+
+/// これより長い学習節やLBDの大きい学習節は二項節で最小化しない
+const MAX_BINARY_MINIMIZATION_SIZE: usize = 30;
+const MAX_BINARY_MINIMIZATION_LBD: usize = 6;
+
+impl Cdcl {
+  /// 学習節の同じレベルのリテラルを，そのレベルでの含意点(UIP)の否定1つに置き換える(shrinking)
+  /// seenに印を付けた変数はclearedに加える
+  pub(super) fn shrink(&mut self, learnt: &mut Vec<Literal>, cleared: &mut Vec<usize>) {
+    let abstract_levels = self.abstract_levels(&learnt[1..]);
+    // 低いレベルから順に置き換える(高いレベルの置き換えで付けた印を低いレベルで使わないため)
+    let mut rest = learnt[1..].to_vec();
+    rest.sort_by_key(|literal| self.levels[literal.variable()]);
+
+    let blocks = rest
+      .chunk_by(|a, b| self.levels[a.variable()] == self.levels[b.variable()])
+      .map(<[Literal]>::to_vec)
+      .collect::<Vec<_>>();
+
+    learnt.truncate(1);
+    for block in blocks {
+      if block.len() == 1 {
+        learnt.push(block[0]);
+        continue;
+      }
+      let level = self.levels[block[0].variable()];
+      match self.shrink_block(level, block.len(), abstract_levels, cleared) {
+        Some(uip) => {
+          learnt.push(!uip);
+          self.statistics.shrunk_literals += block.len() as u64 - 1;
+        }
+        None => learnt.extend(block),
+      }
+    }
+  }
+
+  /// levelの印の付いたcount個のリテラルが全て導かれる，そのレベルで最後のリテラルを探す
+  /// 他のレベルの理由のリテラルは学習節から導かれなければならない
+  fn shrink_block(
+    &mut self,
+    level: usize,
+    count: usize,
+    abstract_levels: u32,
+    cleared: &mut Vec<usize>,
+  ) -> Option<Literal> {
+    let start = self.trail_limits[level - 1];
+    // 割り当て順と決定レベルの順が異なりうるときは末尾まで調べる
+    let end = if self.options.chronological_backtracking {
+      self.trail.len()
+    } else {
+      self.trail_limits[level]
+    };
+
+    let mut marked = vec![];
+    let mut open = count;
+    let mut uip = None;
+    'trail: for index in (start..end).rev() {
+      let literal = self.trail[index];
+      let variable = literal.variable();
+      if !self.seen[variable] || self.levels[variable] != level {
+        continue;
+      }
+      if open == 1 {
+        uip = Some(literal);
+        break;
+      }
+      open -= 1;
+
+      let Some(reason) = self.reasons[variable] else {
+        break;
+      };
+      for k in 1..self.reason_len(reason) {
+        let antecedent = self.reason_literal(reason, k);
+        let variable = antecedent.variable();
+        if self.seen[variable] || self.levels[variable] == 0 {
+          continue;
+        }
+        if self.levels[variable] == level {
+          self.seen[variable] = true;
+          marked.push(variable);
+          open += 1;
+        } else if !self.is_redundant(antecedent, abstract_levels, cleared) {
+          break 'trail;
+        }
+      }
+    }
+
+    if uip.is_none() {
+      for &variable in &marked {
+        self.seen[variable] = false;
+      }
+      return None;
+    }
+    cleared.extend(marked);
+    uip
+  }
+
+  /// 学習節の他のリテラルから導かれるリテラルを取り除く(再帰的な最小化)
+  pub(super) fn minimize(&mut self, learnt: &mut Vec<Literal>, cleared: &mut Vec<usize>) {
+    let abstract_levels = self.abstract_levels(&learnt[1..]);
+    let size = learnt.len();
+    let mut kept = 1;
+    for k in 1..size {
+      let literal = learnt[k];
+      if !self.is_redundant(literal, abstract_levels, cleared) {
+        learnt[kept] = literal;
+        kept += 1;
+      }
+    }
+    learnt.truncate(kept);
+    self.statistics.minimized_literals += (size - kept) as u64;
+  }
+
+  /// 二項節 (learnt[0] ∨ u) があれば，uとの導出で学習節から!uを取り除く
+  pub(super) fn minimize_with_binaries(&mut self, learnt: &mut Vec<Literal>) {
+    if learnt.len() > MAX_BINARY_MINIMIZATION_SIZE || self.lbd(learnt) > MAX_BINARY_MINIMIZATION_LBD
+    {
+      return;
+    }
+
+    let mut removable = self.binary_implied(learnt[0]);
+    if removable.is_empty() {
+      return;
+    }
+    removable.sort_unstable();
+    let size = learnt.len();
+    let first = learnt[0];
+    learnt.retain(|&literal| literal == first || removable.binary_search(&!literal).is_err());
+    self.statistics.binary_minimized_literals += (size - learnt.len()) as u64;
+  }
+
+  /// 二項節 (literal ∨ u) のうち真のu
+  fn binary_implied(&self, literal: Literal) -> Vec<Literal> {
+    self.watches[literal.index()]
+      .iter()
+      .filter_map(|watch| match *watch {
+        Watch::Binary(other) if self.value(other) == Some(true) => Some(other),
+        _ => None,
+      })
+      .collect()
+  }
+
+  /// literalの否定を導いた理由をさかのぼり，学習節のリテラルから導かれるか調べる
+  /// 導かれる場合に途中で印を付けた変数はclearedに加える
+  fn is_redundant(
+    &mut self,
+    literal: Literal,
+    abstract_levels: u32,
+    cleared: &mut Vec<usize>,
+  ) -> bool {
+    if self.reasons[literal.variable()].is_none() {
+      return false;
+    }
+    let top = cleared.len();
+    let mut stack = vec![literal];
+    while let Some(literal) = stack.pop() {
+      let reason = self.reasons[literal.variable()].unwrap();
+      for k in 1..self.reason_len(reason) {
+        let antecedent = self.reason_literal(reason, k);
+        let variable = antecedent.variable();
+        if self.seen[variable] || self.levels[variable] == 0 {
+          continue;
+        }
+        // 学習節に含まれないレベルの決定からは導かれない
+        if self.reasons[variable].is_some()
+          && abstract_level(self.levels[variable]) & abstract_levels != 0
+        {
+          self.seen[variable] = true;
+          stack.push(antecedent);
+          cleared.push(variable);
+          continue;
+        }
+        for &variable in &cleared[top..] {
+          self.seen[variable] = false;
+        }
+        cleared.truncate(top);
+        return false;
+      }
+    }
+    true
+  }
+
+  fn abstract_levels(&self, literals: &[Literal]) -> u32 {
+    literals.iter().fold(0, |levels, literal| {
+      levels | abstract_level(self.levels[literal.variable()])
+    })
+  }
+
+  /// 理由の節の長さ
+  /// 個数制約の説明はここで求めるので，reason_literalより先に呼ぶ
+  fn reason_len(&mut self, reason: Reason) -> usize {
+    match reason {
+      Reason::Binary(..) => 2,
+      Reason::Clause(id) => self.clauses.len(id),
+      Reason::Xor(slot) => self.xor_reasons[slot].len(),
+      Reason::Cardinality(slot) => {
+        self.explain_cardinality(slot);
+        self.cardinality_reasons[slot].len()
+      }
+    }
+  }
+
+  /// 理由の節のk番目のリテラル(0番目は導かれたリテラル)
+  fn reason_literal(&self, reason: Reason, k: usize) -> Literal {
+    match reason {
+      Reason::Binary(first, second) => [first, second][k],
+      Reason::Clause(id) => self.clauses.literals(id)[k],
+      Reason::Xor(slot) => self.xor_reasons[slot][k],
+      Reason::Cardinality(slot) => self.cardinality_reasons[slot][k],
+    }
+  }
+}
+
+/// レベルの集合を32ビットで近似する
+fn abstract_level(level: usize) -> u32 {
+  1 << (level & 31)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    cdcl::{
+      options::CdclOptions,
+      solver::{Cdcl, SolveResult},
+    },
+    random::Random,
+    statistics::Statistics,
+    testing::{self, random_clauses},
+  };
+
+  /// 学習節を全て残して解き，総当たりの解と学習節を照らし合わせる
+  fn check(options: CdclOptions, seed: u64) -> Statistics {
+    let mut random = Random::new(seed);
+    let mut statistics = Statistics::default();
+    for _ in 0..30 {
+      let num_variables = 16;
+      let num_clauses = 68 + random.below(6);
+      let clauses = random_clauses(&mut random, num_variables, num_clauses, 3);
+      let models = testing::models(num_variables, &clauses);
+      let mut solver = Cdcl::new(num_variables);
+      solver.set_options(CdclOptions {
+        reduce_interval: u64::MAX,
+        inprocessing: false,
+        ..options
+      });
+      for clause in &clauses {
+        solver.add_clause(clause);
+      }
+      match solver.solve() {
+        SolveResult::Satisfiable(model) => {
+          assert!(models.contains(&testing::assignment(&model, num_variables)));
+        }
+        SolveResult::Unsatisfiable => assert!(models.is_empty()),
+        SolveResult::Unknown => panic!("no limit is set"),
+      }
+
+      // 最小化した学習節も元の節集合から導かれる
+      let arena = &solver.clauses;
+      let learnts = arena
+        .refs()
+        .iter()
+        .filter(|&&id| arena.is_learnt(id) && !arena.is_deleted(id))
+        .map(|&id| arena.literals(id).to_vec())
+        .chain(solver.binary_clauses().iter().map(|binary| binary.to_vec()))
+        .collect::<Vec<_>>();
+      for model in &models {
+        assert!(learnts
+          .iter()
+          .all(|clause| testing::satisfies(*model, clause)));
+      }
+      statistics += solver.statistics();
+    }
+    statistics
+  }
+
+  #[test]
+  fn learns_implied_clauses_without_reduction() {
+    let statistics = check(
+      CdclOptions {
+        minimization: false,
+        shrinking: false,
+        ..CdclOptions::default()
+      },
+      1,
+    );
+    assert_eq!(statistics.minimized_literals, 0);
+    assert_eq!(statistics.binary_minimized_literals, 0);
+    assert_eq!(statistics.shrunk_literals, 0);
+  }
+
+  #[test]
+  fn minimizes_learned_clauses() {
+    let statistics = check(
+      CdclOptions {
+        shrinking: false,
+        ..CdclOptions::default()
+      },
+      2,
+    );
+    assert!(statistics.minimized_literals > 0);
+    assert_eq!(statistics.shrunk_literals, 0);
+  }
+
+  #[test]
+  fn shrinks_learned_clauses() {
+    let statistics = check(
+      CdclOptions {
+        minimization: false,
+        ..CdclOptions::default()
+      },
+      3,
+    );
+    assert!(statistics.shrunk_literals > 0);
+    assert_eq!(statistics.minimized_literals, 0);
+  }
+
+  #[test]
+  fn shrinks_with_chronological_backtracking() {
+    let statistics = check(
+      CdclOptions {
+        chronological_backtracking: true,
+        chronological_levels: 0,
+        ..CdclOptions::default()
+      },
+      4,
+    );
+    assert!(statistics.shrunk_literals > 0);
+  }
+}
@@ -0,0 +1,14 @@
+mod arena;
+mod cardinality;
+mod gauss;
+mod heap;
+mod inprocessing;
+mod minimization;
+pub mod options;
+mod probing;
+mod recovery;
+mod rephasing;
+pub mod sharing;
+pub mod solver;
+mod subsumption;
+mod vivification;
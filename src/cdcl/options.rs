@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+/// CDCLの探索の各パラメータ
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CdclOptions {
+  /// リスタート間隔(Luby列)の単位となる衝突回数
+  pub restart_interval: u64,
+  /// 最初に学習節を削減するまでの衝突回数
+  pub reduce_interval: u64,
+  /// 学習節を削減するたびに伸ばす間隔
+  pub reduce_increment: u64,
+  /// この値以下のLBDをもつ学習節は削減しない
+  pub keep_lbd: usize,
+  /// 衝突ごとに変数の活性度に掛ける減衰率
+  pub variable_decay: f64,
+  /// 局所探索による極性の設定(rephasing)を行うか
+  pub rephasing: bool,
+  /// リスタート時にインプロセッシングを行うか
+  pub inprocessing: bool,
+  /// 学習節から他のリテラルで導かれるリテラルを取り除くか(再帰的な最小化と二項節による最小化)
+  pub minimization: bool,
+  /// 学習節の同じレベルのリテラルをそのレベルの含意点1つに置き換えるか(shrinking)
+  pub shrinking: bool,
+  /// 衝突後の戻り先が遠いとき，直前のレベルまでしか戻らない(chronological backtracking)
+  pub chronological_backtracking: bool,
+  /// これより多くのレベルを戻るときに直前のレベルまでにとどめる
+  pub chronological_levels: usize,
+  /// 最初の探索の前に，節で表されたXOR制約を見つけてGauss-Jordanの消去でも伝播するか
+  pub xor_recovery: bool,
+}
+
+impl Default for CdclOptions {
+  fn default() -> Self {
+    Self {
+      restart_interval: 100,
+      reduce_interval: 2000,
+      reduce_increment: 300,
+      keep_lbd: 2,
+      variable_decay: 0.95,
+      rephasing: true,
+      inprocessing: true,
+      minimization: true,
+      shrinking: true,
+      chronological_backtracking: false,
+      chronological_levels: 100,
+      xor_recovery: true,
+    }
+  }
+}
+
+/// 探索を打ち切る上限
+/// 上限に達すると探索を中断して答えを出さずに終わる
+/// timeを指定すると結果が実行速度に依存し，同じシードでも再現しなくなる
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+  /// 1回の探索での衝突回数
+  pub conflicts: Option<u64>,
+  /// 1回の探索での単位伝播の回数
+  pub propagations: Option<u64>,
+  /// 1回の探索の実行時間
+  pub time: Option<Duration>,
+}
@@ -0,0 +1,76 @@
+use crate::{cdcl::solver::Cdcl, expressions::literal::Literal};
+
+impl Cdcl {
+  /// 二項節に現れる変数について失敗リテラルを探す
+  /// 充足不能になればfalseを返す
+  pub(super) fn probe(&mut self, limit: u64) -> bool {
+    let mut candidates = vec![false; self.num_variables];
+    for binary in self.binary_clauses() {
+      for literal in binary {
+        candidates[literal.variable()] = true;
+      }
+    }
+
+    for offset in 0..self.num_variables {
+      if self.statistics.ticks >= limit {
+        break;
+      }
+
+      let variable = (self.scheduler.probing_position + offset) % self.num_variables;
+      if !candidates[variable] || self.values[variable].is_some() {
+        continue;
+      }
+      self.scheduler.probing_position = variable + 1;
+
+      for sign in [true, false] {
+        let literal = Literal::new(variable, sign);
+        self.trail_limits.push(self.trail.len());
+        self.assign(literal, None);
+        let failed = self.propagate().is_some();
+        self.backtrack(0);
+
+        if failed {
+          self.statistics.failed_literals += 1;
+          self.assign(!literal, None);
+          if self.propagate().is_some() {
+            return false;
+          }
+          break;
+        }
+      }
+    }
+
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn fixes_failed_literals() {
+    // -1 -> 2 と -1 -> -2 なので-1は失敗リテラル
+    let mut solver = Cdcl::new(3);
+    solver.add_clause(&[Literal::new(0, true), Literal::new(1, true)]);
+    solver.add_clause(&[
+      Literal::new(0, true),
+      Literal::new(1, false),
+      Literal::new(2, true),
+    ]);
+    solver.add_clause(&[Literal::new(0, true), Literal::new(2, false)]);
+    assert!(solver.probe(u64::MAX));
+    assert_eq!(solver.value(Literal::new(0, true)), Some(true));
+    assert_eq!(solver.decision_level(), 0);
+    assert_eq!(solver.statistics().failed_literals, 1);
+  }
+
+  #[test]
+  fn detects_unsatisfiability() {
+    let mut solver = Cdcl::new(2);
+    for (first, second) in [(true, true), (true, false), (false, true), (false, false)] {
+      solver.add_clause(&[Literal::new(0, first), Literal::new(1, second)]);
+    }
+    assert!(!solver.probe(u64::MAX));
+  }
+}
@@ -0,0 +1,161 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{
+  cdcl::{arena::ClauseRef, solver::Cdcl},
+  expressions::literal::Literal,
+};
+
+/// 探すXOR制約の変数の数の上限(2^(k-1)個の節が必要になる)
+const MAX_XOR_SIZE: usize = 6;
+
+impl Cdcl {
+  /// 同じ変数の組の節が，和の偶奇の一方を禁止する2^(k-1)通りを全て含んでいればXOR制約として加える
+  /// 元の節は残し，Gauss-Jordanの消去による伝播を追加する
+  /// 充足不能になればfalseを返す
+  pub(super) fn recover_xors(&mut self) -> bool {
+    let mut groups: BTreeMap<Vec<usize>, Vec<ClauseRef>> = BTreeMap::new();
+    for &id in self.clauses.refs() {
+      let len = self.clauses.len(id);
+      if self.clauses.is_learnt(id)
+        || self.clauses.is_deleted(id)
+        || !(3..=MAX_XOR_SIZE).contains(&len)
+      {
+        continue;
+      }
+      let mut variables = self
+        .clauses
+        .literals(id)
+        .iter()
+        .map(|literal| literal.variable())
+        .collect::<Vec<_>>();
+      variables.sort_unstable();
+      groups.entry(variables).or_default().push(id);
+    }
+
+    let mut xors = vec![];
+    for (variables, ids) in groups {
+      let required = 1 << (variables.len() - 1);
+      if ids.len() < required {
+        continue;
+      }
+
+      // 節は全てのリテラルが偽になる割り当てを禁止する
+      // 負のリテラルを1とした割り当ての偶奇ごとに，禁止している割り当てを集める
+      let mut forbidden = [HashSet::new(), HashSet::new()];
+      for id in ids {
+        let mut assignment = 0usize;
+        for literal in self.clauses.literals(id) {
+          if !literal.sign() {
+            let k = variables.binary_search(&literal.variable()).unwrap();
+            assignment |= 1 << k;
+          }
+        }
+        forbidden[assignment.count_ones() as usize % 2].insert(assignment);
+      }
+
+      // 奇数の割り当てを全て禁止していれば和は偶数
+      for (parity, assignments) in forbidden.iter().enumerate() {
+        if assignments.len() == required {
+          xors.push((variables.clone(), parity == 0));
+        }
+      }
+    }
+
+    self.statistics.recovered_xors += xors.len() as u64;
+    for (variables, rhs) in xors {
+      let literals = variables
+        .iter()
+        .map(|&variable| Literal::new(variable, true))
+        .collect::<Vec<_>>();
+      if !self.add_xor(&literals, rhs) {
+        return false;
+      }
+    }
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    cdcl::{
+      options::CdclOptions,
+      solver::{Cdcl, SolveResult},
+    },
+    encodings::xor::encode_xor,
+    expressions::literal::Literal,
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  /// XOR制約を節に変換した問題で，復元したXOR制約を使っても答えが変わらない
+  #[test]
+  fn recovers_encoded_xors() {
+    let mut random = Random::new(12);
+    let mut recovered = 0;
+    for _ in 0..60 {
+      let num_variables = 8 + random.below(5);
+      let mut clauses = random_clauses(&mut random, num_variables, num_variables, 3);
+      for _ in 0..1 + random.below(num_variables) {
+        // 補助変数を使わない長さ3から4の制約
+        let mut variables = (0..num_variables).collect::<Vec<_>>();
+        let literals = (0..3 + random.below(2))
+          .map(|_| {
+            let variable = variables.swap_remove(random.below(variables.len()));
+            Literal::new(variable, random.below(2) == 0)
+          })
+          .collect::<Vec<_>>();
+        let mut next_variable = num_variables;
+        clauses.extend(encode_xor(
+          &literals,
+          random.below(2) == 0,
+          &mut next_variable,
+        ));
+      }
+      let models = testing::models(num_variables, &clauses);
+
+      let mut solver = Cdcl::new(num_variables);
+      if !clauses.iter().all(|clause| solver.add_clause(clause)) {
+        assert!(models.is_empty());
+        continue;
+      }
+      let result = solver.solve();
+      recovered += solver.statistics().recovered_xors;
+      match result {
+        SolveResult::Satisfiable(model) => {
+          assert!(models.contains(&testing::assignment(&model, num_variables)));
+        }
+        SolveResult::Unsatisfiable => assert!(models.is_empty()),
+        SolveResult::Unknown => panic!("no limit is set"),
+      }
+    }
+    assert!(recovered > 0);
+  }
+
+  /// 偶奇の一方を禁止する節が全て揃っているときだけ見つける
+  #[test]
+  fn requires_every_clause_of_the_parity() {
+    let literals = [
+      Literal::new(0, true),
+      Literal::new(1, false),
+      Literal::new(2, true),
+    ];
+    let mut next_variable = 3;
+    let clauses = encode_xor(&literals, true, &mut next_variable);
+    assert_eq!(clauses.len(), 4);
+    let recovered = |clauses: &[Vec<Literal>], xor_recovery: bool| {
+      let options = CdclOptions {
+        xor_recovery,
+        ..CdclOptions::default()
+      };
+      let mut solver = Cdcl::new(3);
+      solver.set_options(options);
+      assert!(clauses.iter().all(|clause| solver.add_clause(clause)));
+      assert!(matches!(solver.solve(), SolveResult::Satisfiable(_)));
+      solver.statistics().recovered_xors
+    };
+    assert_eq!(recovered(&clauses, true), 1);
+    assert_eq!(recovered(&clauses, false), 0);
+    assert_eq!(recovered(&clauses[1..], true), 0);
+  }
+}
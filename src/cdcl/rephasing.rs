@@ -0,0 +1,82 @@
+use crate::{
+  cdcl::solver::Cdcl,
+  expressions::clause::Clause,
+  local_search::{Algorithm, LocalSearch},
+};
+
+/// 局所探索で極性を設定する間隔(衝突回数)
+const REPHASE_INTERVAL: u64 = 1000;
+/// 局所探索の反転回数の上限
+const REPHASE_FLIPS: u64 = 100_000;
+
+impl Cdcl {
+  /// 保存した極性を初期割り当てとして局所探索を行い，
+  /// 偽の節が最も少なかった割り当てを新しい極性にする(レベル0で呼ぶ)
+  pub(super) fn rephase(&mut self) {
+    let mut clauses = self
+      .clauses
+      .refs()
+      .iter()
+      .filter(|&&id| !self.clauses.is_deleted(id) && !self.clauses.is_learnt(id))
+      .map(|&id| Clause::from(self.clauses.literals(id)))
+      .collect::<Vec<_>>();
+    clauses.extend(
+      self
+        .binary_clauses()
+        .iter()
+        .map(|binary| Clause::from(&binary[..])),
+    );
+    clauses.extend(
+      self
+        .trail
+        .iter()
+        .map(|&literal| Clause::from(&vec![literal])),
+    );
+
+    let assignment = (0..self.num_variables)
+      .map(|variable| self.values[variable].unwrap_or(self.phases[variable]))
+      .collect::<Vec<_>>();
+
+    let seed = self.random.next_u64();
+    let mut local_search = LocalSearch::new(self.num_variables, &clauses, Algorithm::ProbSat, seed);
+    local_search.set_assignment(&assignment);
+    local_search.run(REPHASE_FLIPS);
+
+    self.phases = local_search.best_assignment().to_vec();
+    self.statistics.rephases += 1;
+    self.statistics.local_search_flips += local_search.flips();
+  }
+
+  /// 次に局所探索で極性を設定する衝突回数
+  pub(super) fn next_rephase(&self) -> u64 {
+    self.statistics.conflicts + REPHASE_INTERVAL * (self.statistics.rephases + 1)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{random::Random, testing};
+
+  #[test]
+  fn sets_phases_to_a_local_search_model() {
+    // 充足可能な問題では局所探索の解がそのまま極性になる
+    let mut random = Random::new(3);
+    let num_variables = 40;
+    let hidden = random.next_u64() & ((1 << num_variables) - 1);
+    let clauses = testing::planted_clauses(&mut random, num_variables, 160, 3, hidden);
+    let mut solver = Cdcl::new(num_variables);
+    for clause in &clauses {
+      solver.add_clause(clause);
+    }
+    solver.rephase();
+    let phases = (0..num_variables)
+      .filter(|&variable| solver.phases[variable])
+      .fold(0, |assignment, variable| assignment | 1 << variable);
+    assert!(clauses
+      .iter()
+      .all(|clause| testing::satisfies(phases, clause)));
+    assert_eq!(solver.statistics().rephases, 1);
+    assert!(solver.statistics().local_search_flips > 0);
+  }
+}
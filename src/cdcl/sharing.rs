@@ -0,0 +1,199 @@
+use std::{
+  collections::VecDeque,
+  sync::{Arc, RwLock},
+};
+
+use crate::{cdcl::solver::Cdcl, expressions::literal::Literal};
+
+/// これ以下の長さの学習節を他のソルバと共有する
+const MAX_SHARED_SIZE: usize = 8;
+/// ソルバごとに残しておく節の数(超えたら古いものから捨てる)
+const QUEUE_CAPACITY: usize = 1 << 12;
+
+/// 1つのソルバが送った節のうち新しいものをQUEUE_CAPACITY個まで残す
+#[derive(Debug, Default)]
+struct Queue {
+  /// clausesの先頭の節の通し番号
+  first: usize,
+  clauses: VecDeque<Arc<[Literal]>>,
+}
+
+/// 複数のソルバの間で短い学習節と単位節を共有する
+/// 送る側は自分のキューだけを書き換え，受け取る側はキューごとの読み出し位置から先を読む
+/// 読み出しが遅れて捨てられた節は受け取らない
+#[derive(Debug)]
+pub struct ClauseExchange {
+  queues: Vec<RwLock<Queue>>,
+}
+
+impl ClauseExchange {
+  /// num_workers個のソルバ(番号0..num_workers)で共有する
+  pub fn new(num_workers: usize) -> Self {
+    Self {
+      queues: (0..num_workers).map(|_| RwLock::default()).collect(),
+    }
+  }
+
+  fn push(&self, worker: usize, literals: &[Literal]) {
+    let mut queue = self.queues[worker].write().unwrap();
+    if queue.clauses.len() == QUEUE_CAPACITY {
+      queue.clauses.pop_front();
+      queue.first += 1;
+    }
+    queue.clauses.push_back(literals.into());
+  }
+
+  /// 他のソルバのキューからcursorsの位置以降の節を取り出す
+  /// 節は複製せずに参照を共有する
+  fn fetch(&self, worker: usize, cursors: &mut [usize]) -> Vec<Arc<[Literal]>> {
+    let mut result = vec![];
+    for (sender, queue) in self.queues.iter().enumerate() {
+      if sender == worker {
+        continue;
+      }
+      let queue = queue.read().unwrap();
+      let start = cursors[sender].max(queue.first) - queue.first;
+      result.extend(queue.clauses.range(start..).cloned());
+      cursors[sender] = queue.first + queue.clauses.len();
+    }
+    result
+  }
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct Sharing {
+  exchange: Arc<ClauseExchange>,
+  worker: usize,
+  /// 送ったソルバごとの次に読む節の通し番号
+  cursors: Vec<usize>,
+}
+
+impl Cdcl {
+  /// 学習節をexchangeを通じて他のソルバと共有する
+  /// 全てのソルバは同じ節集合を解いていなければならない
+  /// workerはexchangeを作ったときのソルバの数より小さい番号
+  pub fn set_exchange(&mut self, exchange: Arc<ClauseExchange>, worker: usize) {
+    let cursors = vec![0; exchange.queues.len()];
+    self.sharing = Some(Sharing {
+      exchange,
+      worker,
+      cursors,
+    });
+  }
+
+  pub(super) fn export_clause(&mut self, literals: &[Literal]) {
+    if literals.len() > MAX_SHARED_SIZE {
+      return;
+    }
+    if let Some(sharing) = &self.sharing {
+      sharing.exchange.push(sharing.worker, literals);
+      self.statistics.exported_clauses += 1;
+    }
+  }
+
+  /// 他のソルバが学習した節を追加する(レベル0で呼ぶ)
+  /// 充足不能になればfalseを返す
+  pub(super) fn import_clauses(&mut self) -> bool {
+    let Some(sharing) = &mut self.sharing else {
+      return true;
+    };
+    let clauses = sharing.exchange.fetch(sharing.worker, &mut sharing.cursors);
+
+    'clause: for literals in clauses {
+      self.statistics.imported_clauses += 1;
+      let mut clause = vec![];
+      for &literal in literals.iter() {
+        match self.value(literal) {
+          Some(true) => continue 'clause,
+          Some(false) => {}
+          None => clause.push(literal),
+        }
+      }
+
+      match clause.len() {
+        0 => return false,
+        1 => {
+          self.assign(clause[0], None);
+          if self.propagate().is_some() {
+            return false;
+          }
+        }
+        _ => {
+          let lbd = clause.len();
+          self.attach_new(&clause, true, lbd);
+        }
+      }
+    }
+
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn fetch(exchange: &ClauseExchange, worker: usize, cursors: &mut [usize]) -> Vec<Vec<Literal>> {
+    exchange
+      .fetch(worker, cursors)
+      .iter()
+      .map(|clause| clause.to_vec())
+      .collect()
+  }
+
+  #[test]
+  fn delivers_clauses_to_other_workers() {
+    let exchange = ClauseExchange::new(3);
+    let clause = |variable| vec![Literal::new(variable, true)];
+    exchange.push(0, &clause(0));
+    exchange.push(1, &clause(1));
+    let mut cursors = [[0; 3]; 3];
+    // 自分が送った節は受け取らない
+    assert_eq!(fetch(&exchange, 0, &mut cursors[0]), [clause(1)]);
+    assert_eq!(fetch(&exchange, 1, &mut cursors[1]), [clause(0)]);
+    assert_eq!(fetch(&exchange, 2, &mut cursors[2]), [clause(0), clause(1)]);
+    // 一度受け取った節は再び受け取らない
+    exchange.push(0, &clause(2));
+    assert!(fetch(&exchange, 0, &mut cursors[0]).is_empty());
+    assert_eq!(fetch(&exchange, 1, &mut cursors[1]), [clause(2)]);
+    assert_eq!(fetch(&exchange, 2, &mut cursors[2]), [clause(2)]);
+  }
+
+  #[test]
+  fn drops_old_clauses_of_full_queues() {
+    let exchange = ClauseExchange::new(2);
+    let clause = |variable| vec![Literal::new(variable, true)];
+    for variable in 0..QUEUE_CAPACITY + 10 {
+      exchange.push(0, &clause(variable));
+    }
+    let mut cursors = [0; 2];
+    let received = fetch(&exchange, 1, &mut cursors);
+    assert_eq!(received.len(), QUEUE_CAPACITY);
+    assert_eq!(received[0], clause(10));
+    assert_eq!(cursors[0], QUEUE_CAPACITY + 10);
+    exchange.push(0, &clause(0));
+    assert_eq!(fetch(&exchange, 1, &mut cursors), [clause(0)]);
+  }
+
+  #[test]
+  fn imports_units_and_short_clauses() {
+    let exchange = Arc::new(ClauseExchange::new(2));
+    let mut sender = Cdcl::new(3);
+    let mut receiver = Cdcl::new(3);
+    sender.set_exchange(exchange.clone(), 0);
+    receiver.set_exchange(exchange, 1);
+    let long = (0..MAX_SHARED_SIZE + 1)
+      .map(|variable| Literal::new(variable % 3, true))
+      .collect::<Vec<_>>();
+    sender.export_clause(&long);
+    sender.export_clause(&[Literal::new(0, false)]);
+    sender.export_clause(&[Literal::new(0, true), Literal::new(1, true)]);
+    assert_eq!(sender.statistics().exported_clauses, 2);
+
+    assert!(receiver.import_clauses());
+    assert_eq!(receiver.statistics().imported_clauses, 2);
+    // 単位節!0から(0 | 1)で1が導かれる
+    assert_eq!(receiver.value(Literal::new(0, false)), Some(true));
+    assert_eq!(receiver.value(Literal::new(1, true)), Some(true));
+  }
+}
@@ -0,0 +1,1388 @@
+use std::{
+  cmp::Reverse,
+  mem,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  time::Instant,
+};
+
+use crate::{
+  cdcl::{
+    arena::{ClauseArena, ClauseRef},
+    cardinality::Cardinalities,
+    gauss::Matrix,
+    heap::VariableHeap,
+    inprocessing::Scheduler,
+    options::{CdclOptions, Limits},
+    sharing::Sharing,
+  },
+  expressions::{literal::Literal, model::Model},
+  random::Random,
+  statistics::Statistics,
+};
+
+/// 探索の結果
+#[derive(Clone, Debug)]
+pub enum SolveResult {
+  Satisfiable(Model),
+  Unsatisfiable,
+  /// 探索を中断した
+  Unknown,
+}
+
+/// 見張られたリテラルが偽になったときに調べる節
+#[derive(Clone, Copy, Debug)]
+pub(super) enum Watch {
+  /// 二項節は節の配列に格納せず，もう一方のリテラルだけを見張りに持つ
+  Binary(Literal),
+  Clause {
+    clause: ClauseRef,
+    /// このリテラルが真なら節を調べずに済む
+    blocker: Literal,
+  },
+}
+
+/// 単位伝播の理由や衝突の原因になった節
+/// 理由のときは最初のリテラルが導かれたリテラル
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Reason {
+  Binary(Literal, Literal),
+  Clause(ClauseRef),
+  /// XOR制約から求めた説明の節(xor_reasonsの添字)
+  Xor(usize),
+  /// 個数制約から求めた説明の節(cardinality_reasonsの添字)
+  Cardinality(usize),
+}
+
+/// CDCL(Conflict-Driven Clause Learning)ソルバ
+#[derive(Clone, Debug)]
+pub struct Cdcl {
+  pub(super) num_variables: usize,
+  pub(super) clauses: ClauseArena,
+  pub(super) watches: Vec<Vec<Watch>>,
+  pub(super) values: Vec<Option<bool>>,
+  pub(super) levels: Vec<usize>,
+  pub(super) reasons: Vec<Option<Reason>>,
+  pub(super) trail: Vec<Literal>,
+  /// 各変数の割り当てのtrail上の位置
+  pub(super) positions: Vec<usize>,
+  pub(super) trail_limits: Vec<usize>,
+  pub(super) head: usize,
+  pub(super) inconsistent: bool,
+  pub(super) statistics: Statistics,
+  pub(super) scheduler: Scheduler,
+  /// 保存した極性
+  pub(super) phases: Vec<bool>,
+  pub(super) random: Random,
+  pub(super) sharing: Option<Sharing>,
+  pub(super) options: CdclOptions,
+  limits: Limits,
+  rephase_limit: u64,
+  /// 学習節を削減する間隔と，次に削減する衝突数
+  /// 仮定を変えて何度もsolveを呼ぶ場合にも削減されるようにsolveをまたいで保持する
+  reduce_interval: u64,
+  reduce_limit: u64,
+  /// XOR制約を探し終えたか
+  xors_recovered: bool,
+  stop: Option<Arc<AtomicBool>>,
+  activity: Vec<f64>,
+  activity_increment: f64,
+  heap: VariableHeap,
+  pub(super) seen: Vec<bool>,
+  /// 直前の探索で矛盾の原因になった仮定
+  failed_assumptions: Vec<Literal>,
+  /// XOR制約
+  pub(super) matrix: Matrix,
+  /// XOR制約で導いた変数ごとの説明の節(最後は衝突の説明)
+  pub(super) xor_reasons: Vec<Vec<Literal>>,
+  /// 個数制約
+  pub(super) cardinalities: Cardinalities,
+  /// 個数制約で導いた変数ごとの説明の節(最後は衝突の説明)
+  /// 空なら衝突の解析で必要になったときに求める
+  pub(super) cardinality_reasons: Vec<Vec<Literal>>,
+}
+
+impl Cdcl {
+  pub fn new(num_variables: usize) -> Self {
+    let activity = vec![0.0; num_variables];
+    let mut heap = VariableHeap::new(num_variables);
+    for variable in 0..num_variables {
+      heap.insert(variable, &activity);
+    }
+
+    let mut solver = Self {
+      num_variables,
+      clauses: ClauseArena::default(),
+      watches: vec![vec![]; 2 * num_variables],
+      values: vec![None; num_variables],
+      levels: vec![0; num_variables],
+      reasons: vec![None; num_variables],
+      trail: vec![],
+      positions: vec![0; num_variables],
+      trail_limits: vec![],
+      head: 0,
+      inconsistent: false,
+      statistics: Statistics::default(),
+      scheduler: Scheduler::new(),
+      phases: vec![false; num_variables],
+      random: Random::new(0),
+      sharing: None,
+      options: CdclOptions::default(),
+      limits: Limits::default(),
+      rephase_limit: 0,
+      reduce_interval: 0,
+      reduce_limit: 0,
+      xors_recovered: false,
+      stop: None,
+      activity,
+      activity_increment: 1.0,
+      heap,
+      seen: vec![false; num_variables],
+      failed_assumptions: vec![],
+      matrix: Matrix::default(),
+      xor_reasons: vec![],
+      cardinalities: Cardinalities::default(),
+      cardinality_reasons: vec![],
+    };
+    solver.rephase_limit = solver.next_rephase();
+    solver
+  }
+
+  pub fn num_variables(&self) -> usize {
+    self.num_variables
+  }
+
+  /// count個の変数を追加し，最初の変数の番号を返す
+  /// 解いた後に補助変数を使う制約を加えるときに使う
+  pub fn add_variables(&mut self, count: usize) -> usize {
+    let first = self.num_variables;
+    self.backtrack(0);
+    self.num_variables += count;
+    let num_variables = self.num_variables;
+    self.watches.resize(2 * num_variables, vec![]);
+    self.values.resize(num_variables, None);
+    self.levels.resize(num_variables, 0);
+    self.reasons.resize(num_variables, None);
+    self.positions.resize(num_variables, 0);
+    self.phases.resize(num_variables, false);
+    self.activity.resize(num_variables, 0.0);
+    self.seen.resize(num_variables, false);
+    self.heap.grow(num_variables);
+    for variable in first..num_variables {
+      self.heap.insert(variable, &self.activity);
+    }
+    self.matrix.grow(num_variables);
+    self.cardinalities.grow(num_variables);
+    // 最後の衝突の説明の分も含めて増やす
+    for reasons in [&mut self.xor_reasons, &mut self.cardinality_reasons] {
+      if !reasons.is_empty() {
+        reasons.resize(num_variables + 1, vec![]);
+      }
+    }
+    first
+  }
+
+  pub fn statistics(&self) -> &Statistics {
+    &self.statistics
+  }
+
+  pub fn statistics_mut(&mut self) -> &mut Statistics {
+    &mut self.statistics
+  }
+
+  pub fn options(&self) -> &CdclOptions {
+    &self.options
+  }
+
+  /// 探索のパラメータを設定する
+  pub fn set_options(&mut self, options: CdclOptions) {
+    self.options = options;
+  }
+
+  /// 局所探索による極性の設定(rephasing)を行うかどうか
+  pub fn set_rephasing(&mut self, rephasing: bool) {
+    self.options.rephasing = rephasing;
+  }
+
+  /// solveを呼ぶたびに適用する探索の上限を設定する
+  pub fn set_limits(&mut self, limits: Limits) {
+    self.limits = limits;
+  }
+
+  pub fn limits(&self) -> Limits {
+    self.limits
+  }
+
+  /// 乱数のシードを設定する
+  /// 0以外のシードでは極性と変数の活性度の初期値を乱数で決める
+  pub fn set_seed(&mut self, seed: u64) {
+    self.random = Random::new(seed);
+    if seed == 0 {
+      return;
+    }
+    for variable in 0..self.num_variables {
+      self.phases[variable] = self.random.below(2) == 1;
+      self.activity[variable] = self.random.next_f64() * 1e-5;
+      self.heap.update(variable, &self.activity);
+    }
+  }
+
+  /// 他のスレッドからtrueにされたら探索を中断する
+  pub fn set_stop_flag(&mut self, stop: Arc<AtomicBool>) {
+    self.stop = Some(stop);
+  }
+
+  /// 節を追加する
+  /// 追加した時点で充足不能になればfalseを返す
+  pub fn add_clause(&mut self, literals: &[Literal]) -> bool {
+    if self.inconsistent {
+      return false;
+    }
+    self.backtrack(0);
+
+    let mut clause: Vec<Literal> = vec![];
+    for &literal in literals {
+      if clause.contains(&!literal) || self.value(literal) == Some(true) {
+        return true;
+      }
+      if !clause.contains(&literal) && self.value(literal).is_none() {
+        clause.push(literal);
+      }
+    }
+
+    match clause.len() {
+      0 => self.inconsistent = true,
+      1 => {
+        self.assign(clause[0], None);
+        if self.propagate().is_some() {
+          self.inconsistent = true;
+        }
+      }
+      _ => {
+        self.attach_new(&clause, false, 0);
+      }
+    }
+
+    !self.inconsistent
+  }
+
+  pub fn solve(&mut self) -> SolveResult {
+    self.solve_with_assumptions(&[])
+  }
+
+  /// assumptionsのリテラルを全て真と仮定して解く
+  /// 仮定の下で充足不能ならUnsatisfiableを返し，原因となった仮定はfailed_assumptionsで得られる
+  /// 学習節は仮定に依存しないので，続けて別の仮定で解くときにも使われる
+  pub fn solve_with_assumptions(&mut self, assumptions: &[Literal]) -> SolveResult {
+    self.failed_assumptions.clear();
+    if self.inconsistent {
+      return SolveResult::Unsatisfiable;
+    }
+    self.backtrack(0);
+    if self.propagate().is_some() {
+      self.inconsistent = true;
+      return SolveResult::Unsatisfiable;
+    }
+    if self.options.xor_recovery && !self.xors_recovered {
+      self.xors_recovered = true;
+      if !self.recover_xors() {
+        return SolveResult::Unsatisfiable;
+      }
+    }
+
+    let conflict_limit = self
+      .limits
+      .conflicts
+      .map(|limit| self.statistics.conflicts + limit);
+    let propagation_limit = self
+      .limits
+      .propagations
+      .map(|limit| self.statistics.propagations + limit);
+    let deadline = self.limits.time.map(|time| Instant::now() + time);
+
+    let mut restarts = 0;
+    let mut conflicts_until_restart = luby(restarts) * self.options.restart_interval;
+    if self.reduce_interval == 0 {
+      self.reduce_interval = self.options.reduce_interval;
+      self.reduce_limit = self.statistics.conflicts + self.reduce_interval;
+    }
+
+    loop {
+      if let Some(conflict) = self.propagate() {
+        self.statistics.conflicts += 1;
+        let conflict = if self.options.chronological_backtracking {
+          match self.backtrack_to_conflict(conflict) {
+            Some(conflict) => conflict,
+            None => continue,
+          }
+        } else {
+          conflict
+        };
+        if self.decision_level() == 0 {
+          self.inconsistent = true;
+          return SolveResult::Unsatisfiable;
+        }
+        if self.is_stopped()
+          || conflict_limit.is_some_and(|limit| self.statistics.conflicts >= limit)
+          || propagation_limit.is_some_and(|limit| self.statistics.propagations >= limit)
+          || deadline.is_some_and(|deadline| Instant::now() >= deadline)
+        {
+          self.backtrack(0);
+          return SolveResult::Unknown;
+        }
+
+        let (learnt, mut level, lbd) = self.analyze(conflict);
+        self.export_clause(&learnt);
+        if self.options.chronological_backtracking
+          && learnt.len() > 1
+          && self.decision_level() - level > self.options.chronological_levels
+        {
+          level = self.decision_level() - 1;
+          self.statistics.chronological_backtracks += 1;
+        }
+        self.backtrack(level);
+        if learnt.len() == 1 {
+          self.assign(learnt[0], None);
+        } else {
+          let reason = self.attach_new(&learnt, true, lbd);
+          self.assign(learnt[0], Some(reason));
+          self.statistics.learned_clauses += 1;
+        }
+        self.activity_increment /= self.options.variable_decay;
+
+        conflicts_until_restart = conflicts_until_restart.saturating_sub(1);
+        continue;
+      }
+
+      if conflicts_until_restart == 0 {
+        restarts += 1;
+        conflicts_until_restart = luby(restarts) * self.options.restart_interval;
+        self.statistics.restarts += 1;
+        self.backtrack(0);
+
+        if !self.import_clauses()
+          || self.options.inprocessing
+            && self.scheduler.is_due(self.statistics.ticks)
+            && !self.inprocess()
+        {
+          self.inconsistent = true;
+          return SolveResult::Unsatisfiable;
+        }
+        if self.options.rephasing && self.statistics.conflicts >= self.rephase_limit {
+          self.rephase();
+          self.rephase_limit = self.next_rephase();
+        }
+        continue;
+      }
+
+      if self.statistics.conflicts >= self.reduce_limit {
+        self.reduce_interval += self.options.reduce_increment;
+        self.reduce_limit = self.statistics.conflicts + self.reduce_interval;
+        self.reduce();
+      }
+
+      let decision = if let Some(&assumption) = assumptions.get(self.decision_level()) {
+        match self.value(assumption) {
+          Some(true) => {
+            // 既に真の仮定にも決定レベルを割り当てて，レベルと仮定の位置を揃える
+            self.trail_limits.push(self.trail.len());
+            continue;
+          }
+          Some(false) => {
+            self.analyze_final(assumption);
+            self.backtrack(0);
+            return SolveResult::Unsatisfiable;
+          }
+          None => assumption,
+        }
+      } else if let Some(decision) = self.decide() {
+        decision
+      } else {
+        return SolveResult::Satisfiable(self.model());
+      };
+      self.statistics.decisions += 1;
+      self.trail_limits.push(self.trail.len());
+      self.assign(decision, None);
+    }
+  }
+
+  /// 衝突回数の上限と期限を今回の探索だけ加えて，仮定の下で解く
+  /// 設定済みの上限のほうが厳しければそちらを使う
+  pub fn solve_with_budget(
+    &mut self,
+    assumptions: &[Literal],
+    conflicts: Option<u64>,
+    deadline: Option<Instant>,
+  ) -> SolveResult {
+    let limits = self.limits;
+    if let Some(conflicts) = conflicts {
+      self.limits.conflicts = Some(
+        limits
+          .conflicts
+          .map_or(conflicts, |limit| limit.min(conflicts)),
+      );
+    }
+    if let Some(deadline) = deadline {
+      let remaining = deadline.saturating_duration_since(Instant::now());
+      if remaining.is_zero() {
+        return SolveResult::Unknown;
+      }
+      self.limits.time = Some(limits.time.map_or(remaining, |limit| limit.min(remaining)));
+    }
+    let result = self.solve_with_assumptions(assumptions);
+    self.limits = limits;
+    result
+  }
+
+  /// 仮定の下で充足不能だったとき，矛盾の原因になった仮定
+  /// 空なら仮定によらず充足不能
+  pub fn failed_assumptions(&self) -> &[Literal] {
+    &self.failed_assumptions
+  }
+
+  fn is_stopped(&self) -> bool {
+    self
+      .stop
+      .as_ref()
+      .is_some_and(|stop| stop.load(Ordering::Relaxed))
+  }
+
+  pub(super) fn decision_level(&self) -> usize {
+    self.trail_limits.len()
+  }
+
+  pub(super) fn value(&self, literal: Literal) -> Option<bool> {
+    self.values[literal.variable()].map(|sign| sign == literal.sign())
+  }
+
+  pub(super) fn assign(&mut self, literal: Literal, reason: Option<Reason>) {
+    let variable = literal.variable();
+    self.values[variable] = Some(literal.sign());
+    self.levels[variable] = match reason {
+      // 割り当て順と決定レベルの順が異なりうるので，理由の節のリテラルの最大のレベルにする
+      Some(reason) if self.options.chronological_backtracking => self.reason_level(reason),
+      _ => self.decision_level(),
+    };
+    self.reasons[variable] = reason;
+    self.positions[variable] = self.trail.len();
+    self.trail.push(literal);
+    self.matrix.notify(variable);
+    self.cardinalities.assigned(literal);
+  }
+
+  pub(super) fn backtrack(&mut self, level: usize) {
+    if self.decision_level() <= level {
+      return;
+    }
+
+    // levelより前のレベルで割り当てたリテラルは順に詰めて残す
+    let limit = self.trail_limits[level];
+    let mut kept = limit;
+    for index in limit..self.trail.len() {
+      let literal = self.trail[index];
+      let variable = literal.variable();
+      if self.levels[variable] <= level {
+        self.trail[kept] = literal;
+        self.positions[variable] = kept;
+        kept += 1;
+        self.matrix.notify(variable);
+        continue;
+      }
+      self.values[variable] = None;
+      self.reasons[variable] = None;
+      self.phases[variable] = literal.sign();
+      self.cardinalities.unassigned(literal);
+      self.heap.insert(variable, &self.activity);
+    }
+    self.trail.truncate(kept);
+    self.trail_limits.truncate(level);
+    // 残したリテラルは伝播し直す
+    self.head = limit;
+  }
+
+  /// 理由の節で導かれたリテラルの決定レベル
+  fn reason_level(&self, reason: Reason) -> usize {
+    match reason {
+      Reason::Binary(_, other) => self.levels[other.variable()],
+      Reason::Clause(id) => self.clauses.literals(id)[1..]
+        .iter()
+        .map(|literal| self.levels[literal.variable()])
+        .max()
+        .unwrap_or(0),
+      Reason::Xor(slot) => self.xor_reasons[slot][1..]
+        .iter()
+        .map(|literal| self.levels[literal.variable()])
+        .max()
+        .unwrap_or(0),
+      Reason::Cardinality(slot) => self.cardinality_reason_level(slot),
+    }
+  }
+
+  /// 衝突した節のリテラルのうち最も高いレベルまで戻る(chronological backtracking)
+  /// 衝突した節の見張りはレベルの高い2つのリテラルに付け替える
+  /// そのレベルのリテラルが1つだけなら，それを節から導いて衝突を解消しNoneを返す
+  fn backtrack_to_conflict(&mut self, conflict: Reason) -> Option<Reason> {
+    let mut literals = match conflict {
+      Reason::Binary(first, second) => vec![first, second],
+      Reason::Clause(id) => self.clauses.literals(id).to_vec(),
+      Reason::Xor(slot) => self.xor_reasons[slot].clone(),
+      Reason::Cardinality(slot) => self.cardinality_reasons[slot].clone(),
+    };
+    literals.sort_by_key(|literal| Reverse(self.levels[literal.variable()]));
+    let conflict = match conflict {
+      Reason::Binary(..) => Reason::Binary(literals[0], literals[1]),
+      Reason::Clause(id) => {
+        let watched = &self.clauses.literals(id)[..2];
+        if watched.contains(&literals[0]) && watched.contains(&literals[1]) {
+          self.clauses.literals_mut(id).copy_from_slice(&literals);
+        } else {
+          self.detach(id);
+          self.clauses.literals_mut(id).copy_from_slice(&literals);
+          self.attach(id);
+        }
+        Reason::Clause(id)
+      }
+      Reason::Xor(slot) => {
+        self.xor_reasons[slot].copy_from_slice(&literals);
+        Reason::Xor(slot)
+      }
+      Reason::Cardinality(slot) => {
+        self.cardinality_reasons[slot].copy_from_slice(&literals);
+        Reason::Cardinality(slot)
+      }
+    };
+
+    let level = self.levels[literals[0].variable()];
+    if level == 0 || self.levels[literals[1].variable()] == level {
+      self.backtrack(level);
+      return Some(conflict);
+    }
+    self.backtrack(level - 1);
+    let reason = match conflict {
+      // 衝突の説明は次の衝突で上書きされるので，導く変数の理由の位置に移す
+      Reason::Xor(slot) => {
+        let variable = literals[0].variable();
+        self.xor_reasons[variable] = mem::take(&mut self.xor_reasons[slot]);
+        Reason::Xor(variable)
+      }
+      Reason::Cardinality(slot) => {
+        let variable = literals[0].variable();
+        self.cardinality_reasons[variable] = mem::take(&mut self.cardinality_reasons[slot]);
+        Reason::Cardinality(variable)
+      }
+      reason => reason,
+    };
+    self.assign(literals[0], Some(reason));
+    None
+  }
+
+  /// 単位伝播を行い，衝突した節があればそれを返す
+  /// 個数制約・XOR制約は節による伝播が止まってから調べる
+  pub(super) fn propagate(&mut self) -> Option<Reason> {
+    loop {
+      if let Some(conflict) = self.propagate_clauses() {
+        return Some(conflict);
+      }
+      if !self.cardinalities.is_idle() {
+        if let Some(conflict) = self.propagate_cardinalities() {
+          self.head = self.trail.len();
+          return Some(conflict);
+        }
+        continue;
+      }
+      if self.matrix.is_idle() {
+        return None;
+      }
+      if let Some(conflict) = self.propagate_xors() {
+        self.head = self.trail.len();
+        return Some(conflict);
+      }
+    }
+  }
+
+  fn propagate_clauses(&mut self) -> Option<Reason> {
+    while self.head < self.trail.len() {
+      let falsified = !self.trail[self.head];
+      self.head += 1;
+      self.statistics.propagations += 1;
+
+      let mut watches = mem::take(&mut self.watches[falsified.index()]);
+      let mut conflict = None;
+      let mut i = 0;
+      let mut j = 0;
+
+      while i < watches.len() {
+        let watch = watches[i];
+        i += 1;
+        self.statistics.ticks += 1;
+
+        let (id, blocker) = match watch {
+          Watch::Binary(other) => {
+            watches[j] = watch;
+            j += 1;
+            match self.value(other) {
+              Some(true) => {}
+              Some(false) => {
+                conflict = Some(Reason::Binary(other, falsified));
+                while i < watches.len() {
+                  watches[j] = watches[i];
+                  i += 1;
+                  j += 1;
+                }
+              }
+              None => self.assign(other, Some(Reason::Binary(other, falsified))),
+            }
+            continue;
+          }
+          Watch::Clause { clause, blocker } => (clause, blocker),
+        };
+
+        if self.value(blocker) == Some(true) {
+          watches[j] = watch;
+          j += 1;
+          continue;
+        }
+        if self.clauses.is_deleted(id) {
+          continue;
+        }
+
+        let literals = self.clauses.literals_mut(id);
+        if literals[0] == falsified {
+          literals.swap(0, 1);
+        }
+        let first = literals[0];
+        if first != blocker && self.values[first.variable()] == Some(first.sign()) {
+          watches[j] = Watch::Clause {
+            clause: id,
+            blocker: first,
+          };
+          j += 1;
+          continue;
+        }
+
+        // 偽でない別のリテラルを見張りにする
+        let replacement = (2..literals.len()).find(|&k| {
+          let literal = literals[k];
+          self.values[literal.variable()] != Some(!literal.sign())
+        });
+        if let Some(k) = replacement {
+          literals.swap(1, k);
+          let watched = literals[1];
+          self.watches[watched.index()].push(Watch::Clause {
+            clause: id,
+            blocker: first,
+          });
+          continue;
+        }
+
+        // 割り当て順と決定レベルの順が異なりうるので，レベルの最も高い偽のリテラルを見張る
+        let mut moved = false;
+        if self.options.chronological_backtracking {
+          let levels = &self.levels;
+          let k = (1..literals.len())
+            .max_by_key(|&k| levels[literals[k].variable()])
+            .unwrap();
+          if levels[literals[k].variable()] > levels[literals[1].variable()] {
+            literals.swap(1, k);
+            self.watches[literals[1].index()].push(Watch::Clause {
+              clause: id,
+              blocker: first,
+            });
+            moved = true;
+          }
+        }
+        if !moved {
+          watches[j] = watch;
+          j += 1;
+        }
+
+        if self.value(first) == Some(false) {
+          conflict = Some(Reason::Clause(id));
+          while i < watches.len() {
+            watches[j] = watches[i];
+            i += 1;
+            j += 1;
+          }
+        } else {
+          self.assign(first, Some(Reason::Clause(id)));
+        }
+      }
+
+      watches.truncate(j);
+      self.watches[falsified.index()] = watches;
+
+      if conflict.is_some() {
+        self.head = self.trail.len();
+        return conflict;
+      }
+    }
+
+    None
+  }
+
+  /// 新しい節を追加して見張りを設定し，literals[0]を導く理由を返す
+  /// literals[0], literals[1]を見張る
+  pub(super) fn attach_new(&mut self, literals: &[Literal], learnt: bool, lbd: usize) -> Reason {
+    if literals.len() == 2 {
+      self.attach_binary(literals[0], literals[1]);
+      return Reason::Binary(literals[0], literals[1]);
+    }
+    let id = self.clauses.alloc(literals, learnt, lbd);
+    self.attach(id);
+    Reason::Clause(id)
+  }
+
+  /// 二項節(first ∨ second)を見張りにだけ追加する
+  pub(super) fn attach_binary(&mut self, first: Literal, second: Literal) {
+    self.watches[first.index()].push(Watch::Binary(second));
+    self.watches[second.index()].push(Watch::Binary(first));
+  }
+
+  /// 全ての二項節(各節を1回ずつ)
+  pub(super) fn binary_clauses(&self) -> Vec<[Literal; 2]> {
+    let mut binaries = vec![];
+    for (index, watches) in self.watches.iter().enumerate() {
+      let literal = Literal::from_index(index);
+      for watch in watches {
+        if let Watch::Binary(other) = *watch {
+          if literal < other {
+            binaries.push([literal, other]);
+          }
+        }
+      }
+    }
+    binaries
+  }
+
+  pub(super) fn attach(&mut self, id: ClauseRef) {
+    let literals = self.clauses.literals(id);
+    let (first, second) = (literals[0], literals[1]);
+    self.watches[first.index()].push(Watch::Clause {
+      clause: id,
+      blocker: second,
+    });
+    self.watches[second.index()].push(Watch::Clause {
+      clause: id,
+      blocker: first,
+    });
+  }
+
+  pub(super) fn detach(&mut self, id: ClauseRef) {
+    let literals = self.clauses.literals(id);
+    let (first, second) = (literals[0], literals[1]);
+    let watching = |watch: &Watch| matches!(*watch, Watch::Clause { clause, .. } if clause == id);
+    self.watches[first.index()].retain(|watch| !watching(watch));
+    self.watches[second.index()].retain(|watch| !watching(watch));
+  }
+
+  /// 節を削除する(見張りは単位伝播中に取り除かれる)
+  pub(super) fn delete(&mut self, id: ClauseRef) {
+    self.clauses.delete(id);
+  }
+
+  /// 節が単位伝播の理由として使われているか
+  pub(super) fn is_locked(&self, id: ClauseRef) -> bool {
+    let first = self.clauses.literals(id)[0];
+    self.reasons[first.variable()] == Some(Reason::Clause(id)) && self.value(first) == Some(true)
+  }
+
+  /// 衝突を解析して1UIPの学習節を求める
+  /// (学習節, バックトラック先のレベル, LBD)を返す
+  fn analyze(&mut self, conflict: Reason) -> (Vec<Literal>, usize, usize) {
+    let mut learnt = vec![Literal::new(0, true)];
+    let mut path_count = 0;
+    let mut index = self.trail.len();
+    let mut reason = conflict;
+    let mut implied = None;
+
+    loop {
+      let skip = usize::from(implied.is_some());
+      match reason {
+        Reason::Binary(first, second) => {
+          for &literal in &[first, second][skip..] {
+            self.analyze_literal(literal, &mut learnt, &mut path_count);
+          }
+        }
+        Reason::Clause(clause) => {
+          self.clauses.set_used(clause, true);
+          for k in skip..self.clauses.len(clause) {
+            let literal = self.clauses.literals(clause)[k];
+            self.analyze_literal(literal, &mut learnt, &mut path_count);
+          }
+        }
+        Reason::Xor(slot) => {
+          for k in skip..self.xor_reasons[slot].len() {
+            let literal = self.xor_reasons[slot][k];
+            self.analyze_literal(literal, &mut learnt, &mut path_count);
+          }
+        }
+        Reason::Cardinality(slot) => {
+          self.explain_cardinality(slot);
+          for k in skip..self.cardinality_reasons[slot].len() {
+            let literal = self.cardinality_reasons[slot][k];
+            self.analyze_literal(literal, &mut learnt, &mut path_count);
+          }
+        }
+      }
+
+      // 衝突に関わったリテラルを割り当ての新しい順に探す
+      loop {
+        index -= 1;
+        let variable = self.trail[index].variable();
+        if self.seen[variable] && self.levels[variable] == self.decision_level() {
+          break;
+        }
+      }
+      let literal = self.trail[index];
+      self.seen[literal.variable()] = false;
+      implied = Some(literal);
+      path_count -= 1;
+      if path_count == 0 {
+        break;
+      }
+      reason = self.reasons[literal.variable()].unwrap();
+    }
+
+    learnt[0] = !implied.unwrap();
+    let mut cleared = learnt
+      .iter()
+      .map(|literal| literal.variable())
+      .collect::<Vec<_>>();
+    if self.options.shrinking {
+      self.shrink(&mut learnt, &mut cleared);
+    }
+    if self.options.minimization {
+      self.minimize(&mut learnt, &mut cleared);
+    }
+    for variable in cleared {
+      self.seen[variable] = false;
+    }
+    if self.options.minimization {
+      self.minimize_with_binaries(&mut learnt);
+    }
+
+    // 2番目に大きいレベルのリテラルを見張りの位置に置く
+    let mut level = 0;
+    if learnt.len() > 1 {
+      let k = (1..learnt.len())
+        .max_by_key(|&k| self.levels[learnt[k].variable()])
+        .unwrap();
+      learnt.swap(1, k);
+      level = self.levels[learnt[1].variable()];
+    }
+
+    let lbd = self.lbd(&learnt);
+    (learnt, level, lbd)
+  }
+
+  /// 節に含まれるリテラルの決定レベルの種類数
+  pub(super) fn lbd(&self, literals: &[Literal]) -> usize {
+    let mut levels = literals
+      .iter()
+      .map(|literal| self.levels[literal.variable()])
+      .collect::<Vec<_>>();
+    levels.sort_unstable();
+    levels.dedup();
+    levels.len()
+  }
+
+  /// 衝突に関わったリテラルに印を付け，現在のレベルより前のものを学習節に加える
+  fn analyze_literal(
+    &mut self,
+    literal: Literal,
+    learnt: &mut Vec<Literal>,
+    path_count: &mut usize,
+  ) {
+    let variable = literal.variable();
+    if self.seen[variable] || self.levels[variable] == 0 {
+      return;
+    }
+    self.seen[variable] = true;
+    self.bump_variable(variable);
+    if self.levels[variable] >= self.decision_level() {
+      *path_count += 1;
+    } else {
+      learnt.push(literal);
+    }
+  }
+
+  /// 偽になった仮定assumptionを導いた仮定を集める
+  fn analyze_final(&mut self, assumption: Literal) {
+    self.failed_assumptions.push(assumption);
+    if self.decision_level() == 0 {
+      return;
+    }
+
+    self.seen[assumption.variable()] = true;
+    for index in (self.trail_limits[0]..self.trail.len()).rev() {
+      let literal = self.trail[index];
+      let variable = literal.variable();
+      if !self.seen[variable] {
+        continue;
+      }
+      self.seen[variable] = false;
+
+      match self.reasons[variable] {
+        // 仮定を処理している間の決定は全て仮定
+        None => self.failed_assumptions.push(literal),
+        Some(Reason::Binary(_, other)) => {
+          if self.levels[other.variable()] > 0 {
+            self.seen[other.variable()] = true;
+          }
+        }
+        Some(Reason::Clause(reason)) => {
+          for &reason_literal in &self.clauses.literals(reason)[1..] {
+            if self.levels[reason_literal.variable()] > 0 {
+              self.seen[reason_literal.variable()] = true;
+            }
+          }
+        }
+        Some(Reason::Xor(slot)) => {
+          for &reason_literal in &self.xor_reasons[slot][1..] {
+            if self.levels[reason_literal.variable()] > 0 {
+              self.seen[reason_literal.variable()] = true;
+            }
+          }
+        }
+        Some(Reason::Cardinality(slot)) => {
+          self.explain_cardinality(slot);
+          for &reason_literal in &self.cardinality_reasons[slot][1..] {
+            if self.levels[reason_literal.variable()] > 0 {
+              self.seen[reason_literal.variable()] = true;
+            }
+          }
+        }
+      }
+    }
+    self.seen[assumption.variable()] = false;
+  }
+
+  fn bump_variable(&mut self, variable: usize) {
+    self.activity[variable] += self.activity_increment;
+    if self.activity[variable] > 1e100 {
+      for activity in self.activity.iter_mut() {
+        *activity *= 1e-100;
+      }
+      self.activity_increment *= 1e-100;
+    }
+    self.heap.update(variable, &self.activity);
+  }
+
+  /// 未割り当ての変数のうち活性度最大のものを保存した極性で選ぶ
+  fn decide(&mut self) -> Option<Literal> {
+    while let Some(variable) = self.heap.pop(&self.activity) {
+      if self.values[variable].is_none() {
+        return Some(Literal::new(variable, self.phases[variable]));
+      }
+    }
+    None
+  }
+
+  /// LBDの大きい学習節のうち最近使われていないものを半分削除する
+  fn reduce(&mut self) {
+    let mut candidates = self
+      .clauses
+      .refs()
+      .iter()
+      .copied()
+      .filter(|&id| {
+        self.clauses.is_learnt(id)
+          && !self.clauses.is_deleted(id)
+          && self.clauses.lbd(id) > self.options.keep_lbd
+          && !self.is_locked(id)
+      })
+      .collect::<Vec<_>>();
+    candidates.sort_by_key(|&id| (self.clauses.is_used(id), usize::MAX - self.clauses.lbd(id)));
+
+    for &id in &candidates[..candidates.len() / 2] {
+      self.delete(id);
+      self.statistics.deleted_clauses += 1;
+    }
+    for k in 0..self.clauses.refs().len() {
+      let id = self.clauses.refs()[k];
+      self.clauses.set_used(id, false);
+    }
+    self.collect_garbage();
+  }
+
+  /// 削除した節が多ければ節の配列を詰め直し，見張りと理由の参照を付け替える
+  pub(super) fn collect_garbage(&mut self) {
+    if !self.clauses.needs_collection() {
+      return;
+    }
+    let old = self.clauses.collect();
+    for watches in self.watches.iter_mut() {
+      watches.retain_mut(|watch| match watch {
+        Watch::Binary(_) => true,
+        Watch::Clause { clause, .. } => match old.forward(*clause) {
+          Some(moved) => {
+            *clause = moved;
+            true
+          }
+          None => false,
+        },
+      });
+    }
+    // 削除した節が理由になっているのはレベル0の割り当てだけなので，理由を消してよい
+    for reason in self.reasons.iter_mut() {
+      *reason = match *reason {
+        Some(Reason::Clause(clause)) => old.forward(clause).map(Reason::Clause),
+        reason => reason,
+      };
+    }
+    self.statistics.garbage_collections += 1;
+  }
+
+  fn model(&self) -> Model {
+    let mut model = Model::new(self.num_variables);
+    for (variable, value) in self.values.iter().enumerate() {
+      if let Some(sign) = value {
+        model.assign(variable, *sign);
+      }
+    }
+    model
+  }
+}
+
+/// Luby列の第i項(1, 1, 2, 1, 1, 2, 4, ...)
+fn luby(i: u64) -> u64 {
+  let mut size = 1;
+  let mut sequence = 0;
+  while size < i + 1 {
+    sequence += 1;
+    size = 2 * size + 1;
+  }
+
+  let mut i = i;
+  while size - 1 != i {
+    size = (size - 1) / 2;
+    sequence -= 1;
+    i %= size;
+  }
+
+  1 << sequence
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{self, random_clauses};
+
+  /// 鳩の巣原理(pigeons羽をholes個の巣に1羽ずつ入れる)
+  fn pigeonhole(pigeons: usize, holes: usize) -> Vec<Vec<Literal>> {
+    let variable = |pigeon: usize, hole: usize| pigeon * holes + hole;
+    let mut clauses = (0..pigeons)
+      .map(|pigeon| {
+        (0..holes)
+          .map(|hole| Literal::new(variable(pigeon, hole), true))
+          .collect()
+      })
+      .collect::<Vec<_>>();
+    for hole in 0..holes {
+      for first in 0..pigeons {
+        for second in first + 1..pigeons {
+          clauses.push(vec![
+            Literal::new(variable(first, hole), false),
+            Literal::new(variable(second, hole), false),
+          ]);
+        }
+      }
+    }
+    clauses
+  }
+
+  #[test]
+  fn decides_random_3sat() {
+    // 節と変数の比が4.3前後で充足可能なものと不能なものが混ざる
+    let mut random = Random::new(1);
+    let mut unsatisfiable = 0;
+    for _ in 0..60 {
+      let num_variables = 8 + random.below(7);
+      let num_clauses = num_variables * 4 + random.below(num_variables);
+      let clauses = random_clauses(&mut random, num_variables, num_clauses, 3);
+      let models = testing::models(num_variables, &clauses);
+      let mut solver = Cdcl::new(num_variables);
+      for clause in &clauses {
+        solver.add_clause(clause);
+      }
+      match solver.solve() {
+        SolveResult::Satisfiable(model) => {
+          assert!(models.contains(&testing::assignment(&model, num_variables)));
+        }
+        SolveResult::Unsatisfiable => {
+          assert!(models.is_empty());
+          unsatisfiable += 1;
+        }
+        SolveResult::Unknown => panic!("no limit is set"),
+      }
+    }
+    assert!(unsatisfiable > 0);
+  }
+
+  #[test]
+  fn learns_from_pigeonhole_conflicts() {
+    let clauses = pigeonhole(6, 5);
+    let mut solver = Cdcl::new(30);
+    for clause in &clauses {
+      assert!(solver.add_clause(clause));
+    }
+    assert!(matches!(solver.solve(), SolveResult::Unsatisfiable));
+    let statistics = solver.statistics();
+    assert!(statistics.conflicts > 0);
+    assert!(statistics.learned_clauses > 0);
+    // 一度充足不能になれば以後の節の追加も失敗する
+    assert!(!solver.add_clause(&[Literal::new(0, true)]));
+
+    // 巣が足りれば解ける
+    let clauses = pigeonhole(5, 5);
+    let mut solver = Cdcl::new(25);
+    for clause in &clauses {
+      solver.add_clause(clause);
+    }
+    let SolveResult::Satisfiable(model) = solver.solve() else {
+      panic!("5 pigeons fit in 5 holes");
+    };
+    let assignment = testing::assignment(&model, 25);
+    assert!(clauses
+      .iter()
+      .all(|clause| testing::satisfies(assignment, clause)));
+  }
+
+  #[test]
+  fn adds_clauses_between_solves() {
+    // 解を1つずつ禁止していくと，解の数だけ解いた後に充足不能になる
+    let mut random = Random::new(2);
+    for _ in 0..20 {
+      let num_variables = 7;
+      let clauses = random_clauses(&mut random, num_variables, 14, 3);
+      let models = testing::models(num_variables, &clauses);
+      let mut solver = Cdcl::new(num_variables);
+      for clause in &clauses {
+        solver.add_clause(clause);
+      }
+      let mut found = vec![];
+      while let SolveResult::Satisfiable(model) = solver.solve() {
+        let assignment = testing::assignment(&model, num_variables);
+        assert!(models.contains(&assignment) && !found.contains(&assignment));
+        found.push(assignment);
+        let blocking = (0..num_variables)
+          .map(|variable| Literal::new(variable, assignment >> variable & 1 == 0))
+          .collect::<Vec<_>>();
+        solver.add_clause(&blocking);
+      }
+      assert_eq!(found.len(), models.len());
+    }
+  }
+
+  #[test]
+  fn solves_under_assumptions() {
+    let mut random = Random::new(3);
+    for _ in 0..40 {
+      let num_variables = 8;
+      let clauses = random_clauses(&mut random, num_variables, 24, 3);
+      let mut solver = Cdcl::new(num_variables);
+      for clause in &clauses {
+        solver.add_clause(clause);
+      }
+      // 同じソルバで仮定を変えながら解く
+      for _ in 0..5 {
+        let assumptions = (0..3)
+          .map(|_| Literal::new(random.below(num_variables), random.below(2) == 0))
+          .collect::<Vec<_>>();
+        let mut constrained = clauses.clone();
+        constrained.extend(assumptions.iter().map(|&literal| vec![literal]));
+        let models = testing::models(num_variables, &constrained);
+        match solver.solve_with_assumptions(&assumptions) {
+          SolveResult::Satisfiable(model) => {
+            assert!(models.contains(&testing::assignment(&model, num_variables)));
+          }
+          SolveResult::Unsatisfiable => {
+            assert!(models.is_empty());
+            // 原因となった仮定だけでも充足不能
+            let failed = solver.failed_assumptions();
+            assert!(failed.iter().all(|literal| assumptions.contains(literal)));
+            let mut core = clauses.clone();
+            core.extend(failed.iter().map(|&literal| vec![literal]));
+            assert!(testing::models(num_variables, &core).is_empty());
+          }
+          SolveResult::Unknown => panic!("no limit is set"),
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn collects_garbage_during_search() {
+    // 元の節の複製を大量に学習節として加え，衝突のたびに削減して配列を詰め直させる
+    let mut random = Random::new(4);
+    let mut collections = 0;
+    for _ in 0..8 {
+      let num_variables = 16;
+      let clauses = random_clauses(&mut random, num_variables, 68, 3);
+      let models = testing::models(num_variables, &clauses);
+      let mut solver = Cdcl::new(num_variables);
+      solver.set_options(CdclOptions {
+        reduce_interval: 1,
+        reduce_increment: 0,
+        keep_lbd: 0,
+        ..CdclOptions::default()
+      });
+      for clause in &clauses {
+        solver.add_clause(clause);
+      }
+      for k in 0..40000 {
+        solver.attach_new(&clauses[k % clauses.len()], true, 3);
+      }
+      match solver.solve() {
+        SolveResult::Satisfiable(model) => {
+          assert!(models.contains(&testing::assignment(&model, num_variables)));
+        }
+        SolveResult::Unsatisfiable => assert!(models.is_empty()),
+        SolveResult::Unknown => panic!("no limit is set"),
+      }
+      collections += solver.statistics().garbage_collections;
+    }
+    assert!(collections > 0);
+  }
+
+  #[test]
+  fn stores_binary_clauses_in_watches() {
+    let literal = |variable, sign| Literal::new(variable, sign);
+    let mut solver = Cdcl::new(3);
+    solver.add_clause(&[literal(0, true), literal(1, false)]);
+    solver.add_clause(&[literal(1, true), literal(2, true)]);
+    solver.add_clause(&[literal(0, false), literal(1, false), literal(2, false)]);
+    assert_eq!(solver.clauses.refs().len(), 1);
+    let mut binaries = solver
+      .binary_clauses()
+      .iter()
+      .map(|binary| binary.map(|literal| literal.index()))
+      .collect::<Vec<_>>();
+    binaries.sort();
+    // 添字は2 * 変数 + 符号
+    assert_eq!(binaries, [[1, 2], [3, 5]]);
+    // !x0から!x1，x2が導かれ，3リテラルの節は充足される
+    assert!(solver.add_clause(&[literal(0, false)]));
+    assert_eq!(solver.value(literal(1, false)), Some(true));
+    assert_eq!(solver.value(literal(2, true)), Some(true));
+  }
+
+  #[test]
+  fn learns_implied_clauses_from_binary_heavy_formulas() {
+    // 数独のように二項のat-most-one節が多い問題でも，学習した節は全て元の節から導かれる
+    let mut random = Random::new(5);
+    for _ in 0..40 {
+      let num_variables = 12;
+      let mut clauses = random_clauses(&mut random, num_variables, 30, 2);
+      clauses.extend(random_clauses(&mut random, num_variables, 20, 3));
+      let models = testing::models(num_variables, &clauses);
+      let mut solver = Cdcl::new(num_variables);
+      solver.set_options(CdclOptions {
+        restart_interval: 1,
+        reduce_interval: 5,
+        ..CdclOptions::default()
+      });
+      for clause in &clauses {
+        solver.add_clause(clause);
+      }
+      match solver.solve() {
+        SolveResult::Satisfiable(model) => {
+          assert!(models.contains(&testing::assignment(&model, num_variables)));
+        }
+        SolveResult::Unsatisfiable => assert!(models.is_empty()),
+        SolveResult::Unknown => panic!("no limit is set"),
+      }
+      let arena = &solver.clauses;
+      let stored = solver
+        .binary_clauses()
+        .iter()
+        .map(|binary| binary.to_vec())
+        .chain(
+          arena
+            .refs()
+            .iter()
+            .filter(|&&id| !arena.is_deleted(id))
+            .map(|&id| arena.literals(id).to_vec()),
+        )
+        .collect::<Vec<_>>();
+      for model in &models {
+        assert!(stored
+          .iter()
+          .all(|clause| testing::satisfies(*model, clause)));
+      }
+    }
+  }
+
+  #[test]
+  fn backtracks_chronologically() {
+    // 常に直前のレベルまでしか戻らず，リスタートと削減も頻繁に行う
+    let options = CdclOptions {
+      chronological_backtracking: true,
+      chronological_levels: 0,
+      restart_interval: 2,
+      reduce_interval: 10,
+      reduce_increment: 1,
+      ..CdclOptions::default()
+    };
+    let mut random = Random::new(6);
+    let mut backtracks = 0;
+    for _ in 0..60 {
+      let num_variables = 10 + random.below(6);
+      let num_clauses = num_variables * 4 + random.below(num_variables);
+      let clauses = random_clauses(&mut random, num_variables, num_clauses, 3);
+      let models = testing::models(num_variables, &clauses);
+      let mut solver = Cdcl::new(num_variables);
+      solver.set_options(options);
+      for clause in &clauses {
+        solver.add_clause(clause);
+      }
+      // 解を禁止しながら全ての解を列挙する
+      let mut found = vec![];
+      loop {
+        match solver.solve() {
+          SolveResult::Satisfiable(model) => {
+            let assignment = testing::assignment(&model, num_variables);
+            assert!(models.contains(&assignment) && !found.contains(&assignment));
+            found.push(assignment);
+            let blocking = (0..num_variables)
+              .map(|variable| Literal::new(variable, assignment >> variable & 1 == 0))
+              .collect::<Vec<_>>();
+            solver.add_clause(&blocking);
+          }
+          SolveResult::Unsatisfiable => break,
+          SolveResult::Unknown => panic!("no limit is set"),
+        }
+      }
+      assert_eq!(found.len(), models.len());
+      backtracks += solver.statistics().chronological_backtracks;
+    }
+    assert!(backtracks > 0);
+  }
+
+  #[test]
+  fn follows_the_luby_sequence() {
+    let sequence = (0..15).map(luby).collect::<Vec<_>>();
+    assert_eq!(sequence, [1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+  }
+
+  #[test]
+  fn stops_at_the_budget() {
+    // 鳩の巣原理は衝突なしには解けないので，衝突1回の予算では分からない
+    let mut solver = Cdcl::new(20);
+    for clause in &pigeonhole(5, 4) {
+      solver.add_clause(clause);
+    }
+    assert!(matches!(
+      solver.solve_with_budget(&[], Some(1), None),
+      SolveResult::Unknown
+    ));
+    // 期限を過ぎていれば探索しない
+    assert!(matches!(
+      solver.solve_with_budget(&[], None, Some(Instant::now())),
+      SolveResult::Unknown
+    ));
+    assert!(matches!(solver.solve(), SolveResult::Unsatisfiable));
+  }
+}
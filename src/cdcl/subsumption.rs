@@ -0,0 +1,196 @@
+use crate::cdcl::{arena::ClauseRef, solver::Cdcl};
+
+/// これより長い節では他の節の包摂を調べない
+const MAX_SUBSUMING_SIZE: usize = 16;
+
+impl Cdcl {
+  /// 包摂される節を削除し，自己包摂導出で節を強化する
+  /// 二項節は他の節を包摂・強化するのにだけ使う
+  /// 充足不能になればfalseを返す
+  pub(super) fn subsume(&mut self, limit: u64) -> bool {
+    let mut ids = self
+      .clauses
+      .refs()
+      .iter()
+      .copied()
+      .filter(|&id| !self.clauses.is_deleted(id))
+      .collect::<Vec<_>>();
+    ids.sort_by_key(|&id| self.clauses.len(id));
+
+    let mut occurrences = vec![vec![]; 2 * self.num_variables];
+    for &id in &ids {
+      for literal in self.clauses.literals(id) {
+        occurrences[literal.index()].push(id);
+      }
+    }
+
+    let mut marks = vec![false; 2 * self.num_variables];
+
+    let subsumers = self
+      .binary_clauses()
+      .into_iter()
+      .map(|binary| (None, binary.to_vec()))
+      .chain(ids.iter().map(|&id| (Some(id), vec![])))
+      .collect::<Vec<(Option<ClauseRef>, _)>>();
+
+    for (id, clause) in subsumers {
+      if self.statistics.ticks >= limit {
+        break;
+      }
+      let clause = match id {
+        None => clause,
+        Some(id) if self.clauses.is_deleted(id) || self.clauses.len(id) > MAX_SUBSUMING_SIZE => {
+          continue
+        }
+        Some(id) => self.clauses.literals(id).to_vec(),
+      };
+      for literal in &clause {
+        marks[literal.index()] = true;
+      }
+
+      // 出現回数の最も少ないリテラルの節だけを候補にする
+      let pivot = *clause
+        .iter()
+        .min_by_key(|literal| {
+          occurrences[literal.index()].len() + occurrences[(!**literal).index()].len()
+        })
+        .unwrap();
+
+      let candidates = occurrences[pivot.index()]
+        .iter()
+        .chain(&occurrences[(!pivot).index()])
+        .copied()
+        .collect::<Vec<_>>();
+
+      for other in candidates {
+        if id == Some(other) || self.clauses.is_deleted(other) {
+          continue;
+        }
+        let literals = self.clauses.literals(other);
+        if literals.len() < clause.len() {
+          continue;
+        }
+        self.statistics.ticks += literals.len() as u64;
+
+        let mut matched = 0;
+        let mut flipped = None;
+        let mut failed = false;
+        for &literal in literals {
+          if marks[literal.index()] {
+            matched += 1;
+          } else if marks[(!literal).index()] {
+            if flipped.is_some() {
+              failed = true;
+              break;
+            }
+            flipped = Some(literal);
+          }
+        }
+        if failed || matched + usize::from(flipped.is_some()) != clause.len() {
+          continue;
+        }
+
+        match flipped {
+          None => {
+            // 学習節が元の節を包摂する場合は学習節を残す
+            if let Some(id) = id.filter(|_| !self.clauses.is_learnt(other)) {
+              self.clauses.set_learnt(id, false);
+            }
+            self.detach(other);
+            self.delete(other);
+            self.statistics.subsumed_clauses += 1;
+          }
+          Some(flipped) => {
+            let strengthened = literals
+              .iter()
+              .copied()
+              .filter(|&literal| literal != flipped)
+              .collect::<Vec<_>>();
+            self.detach(other);
+            self.statistics.strengthened_clauses += 1;
+            if !self.reattach(other, strengthened) {
+              return false;
+            }
+          }
+        }
+      }
+
+      for literal in &clause {
+        marks[literal.index()] = false;
+      }
+    }
+
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    cdcl::solver::{Cdcl, Reason},
+    expressions::literal::Literal,
+  };
+
+  fn literals(clause: &[i32]) -> Vec<Literal> {
+    clause
+      .iter()
+      .map(|&literal| Literal::new(literal.unsigned_abs() as usize - 1, literal > 0))
+      .collect()
+  }
+
+  /// 二項節と削除されていない節をリテラルの添字の列で表して整列する
+  fn live_clauses(solver: &Cdcl) -> Vec<Vec<usize>> {
+    let arena = &solver.clauses;
+    let mut clauses = arena
+      .refs()
+      .iter()
+      .filter(|&&id| !arena.is_deleted(id))
+      .map(|&id| {
+        arena
+          .literals(id)
+          .iter()
+          .map(|literal| literal.index())
+          .collect::<Vec<_>>()
+      })
+      .chain(
+        solver
+          .binary_clauses()
+          .iter()
+          .map(|binary| binary.map(|literal| literal.index()).to_vec()),
+      )
+      .map(|mut indices| {
+        indices.sort();
+        indices
+      })
+      .collect::<Vec<_>>();
+    clauses.sort();
+    clauses
+  }
+
+  #[test]
+  fn removes_subsumed_and_strengthens_clauses() {
+    let mut solver = Cdcl::new(4);
+    for clause in [&[1, 2][..], &[1, 2, 3], &[-1, 2, 4]] {
+      solver.add_clause(&literals(clause));
+    }
+    assert!(solver.subsume(u64::MAX));
+    // (1 | 2)が(1 | 2 | 3)を包摂し，(-1 | 2 | 4)を(2 | 4)に強化する
+    // 添字は2 * 変数 + 符号
+    assert_eq!(live_clauses(&solver), [vec![1, 3], vec![3, 7]]);
+    assert_eq!(solver.statistics().subsumed_clauses, 1);
+    assert_eq!(solver.statistics().strengthened_clauses, 1);
+  }
+
+  #[test]
+  fn keeps_subsuming_learned_clauses() {
+    // 元の節を包摂した学習節は削減で消えないように元の節として扱う
+    let mut solver = Cdcl::new(4);
+    solver.add_clause(&literals(&[1, 2, 3, 4]));
+    let Reason::Clause(learnt) = solver.attach_new(&literals(&[1, 2, 3]), true, 3) else {
+      panic!("clauses with three literals are stored in the arena");
+    };
+    assert!(solver.subsume(u64::MAX));
+    assert!(solver.clauses.is_deleted(solver.clauses.refs()[0]));
+    assert!(!solver.clauses.is_learnt(learnt));
+  }
+}
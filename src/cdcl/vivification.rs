@@ -0,0 +1,120 @@
+use crate::cdcl::{arena::ClauseRef, solver::Cdcl};
+
+impl Cdcl {
+  /// 節のリテラルの否定を順に仮定して単位伝播し，不要なリテラルを取り除く
+  /// 充足不能になればfalseを返す
+  pub(super) fn vivify(&mut self, limit: u64) -> bool {
+    let mut candidates = self
+      .clauses
+      .refs()
+      .iter()
+      .copied()
+      .filter(|&id| {
+        !self.clauses.is_deleted(id) && !self.clauses.is_vivified(id) && self.clauses.len(id) > 2
+      })
+      .collect::<Vec<_>>();
+    // LBDの小さい学習節から順に調べる
+    candidates.sort_by_key(|&id| (!self.clauses.is_learnt(id), self.clauses.lbd(id)));
+
+    for id in candidates {
+      if self.statistics.ticks >= limit {
+        break;
+      }
+      if self.clauses.is_deleted(id) {
+        continue;
+      }
+      self.clauses.set_vivified(id, true);
+      if !self.vivify_clause(id) {
+        return false;
+      }
+    }
+
+    true
+  }
+
+  fn vivify_clause(&mut self, id: ClauseRef) -> bool {
+    let literals = self.clauses.literals(id).to_vec();
+    // 節自身が単位伝播に使われないように見張りを外す
+    self.detach(id);
+
+    let mut kept = vec![];
+    for &literal in &literals {
+      match self.value(literal) {
+        // 残りのリテラルの否定から導かれるので，ここまでのリテラルで十分
+        Some(true) => {
+          kept.push(literal);
+          break;
+        }
+        // 残りのリテラルの否定から否定が導かれるので取り除ける
+        Some(false) => {}
+        None => {
+          kept.push(literal);
+          self.trail_limits.push(self.trail.len());
+          self.assign(!literal, None);
+          if self.propagate().is_some() {
+            break;
+          }
+        }
+      }
+    }
+    self.backtrack(0);
+
+    if kept.len() < literals.len() {
+      self.statistics.vivified_clauses += 1;
+      self.statistics.vivified_literals += (literals.len() - kept.len()) as u64;
+      self.reattach(id, kept)
+    } else {
+      self.reattach(id, literals)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{cdcl::solver::Cdcl, expressions::literal::Literal};
+
+  fn literals(clause: &[i32]) -> Vec<Literal> {
+    clause
+      .iter()
+      .map(|&literal| Literal::new(literal.unsigned_abs() as usize - 1, literal > 0))
+      .collect()
+  }
+
+  fn has_binary(solver: &Cdcl, clause: &[i32]) -> bool {
+    let clause = literals(clause);
+    solver
+      .binary_clauses()
+      .iter()
+      .any(|binary| clause.iter().all(|literal| binary.contains(literal)))
+  }
+
+  #[test]
+  fn removes_implied_literals() {
+    // -1 -> 4 -> 2 なので(1 | 2 | 3 | 5)は(1 | 2)に強化できる
+    let mut solver = Cdcl::new(5);
+    solver.add_clause(&literals(&[1, 4]));
+    solver.add_clause(&literals(&[-4, 2]));
+    solver.add_clause(&literals(&[1, 2, 3, 5]));
+    assert!(solver.vivify(u64::MAX));
+    // 二項節になったので節の配列から見張りに移る
+    let id = solver.clauses.refs()[0];
+    assert!(solver.clauses.is_vivified(id) && solver.clauses.is_deleted(id));
+    assert!(has_binary(&solver, &[1, 2]));
+    assert_eq!(solver.statistics().vivified_clauses, 1);
+    assert_eq!(solver.statistics().vivified_literals, 2);
+    // 強化した節も見張りを付け直しているので単位伝播に使われる
+    assert!(solver.add_clause(&literals(&[-2])));
+    assert_eq!(solver.value(literals(&[1])[0]), Some(true));
+  }
+
+  #[test]
+  fn removes_falsified_literals() {
+    // -1 -> -3 なので(1 | 3 | 4)から3を取り除ける
+    let mut solver = Cdcl::new(4);
+    solver.add_clause(&literals(&[1, -3]));
+    solver.add_clause(&literals(&[1, 3, 4, 2]));
+    solver.add_clause(&literals(&[-2, 1]));
+    assert!(solver.vivify(u64::MAX));
+    assert!(has_binary(&solver, &[1, 4]));
+  }
+}
@@ -0,0 +1,270 @@
+use std::{error::Error, fmt, str::FromStr, time::Duration};
+
+use crate::{
+  cdcl::{
+    options::{CdclOptions, Limits},
+    solver::Cdcl,
+  },
+  preprocess::options::PreprocessOptions,
+  sat_solver::Engine,
+};
+
+/// 局所探索エンジンがCDCLに切り替えるまでの反転回数の既定値
+const LOCAL_SEARCH_FLIPS: u64 = 10_000_000;
+
+/// ソルバの設定
+/// スレッドを1つしか使わず，時間の上限を指定しなければ，
+/// 同じ設定・同じ入力に対して常に同じ探索を行う
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolverConfig {
+  pub engine: Engine,
+  pub preprocess: PreprocessOptions,
+  pub cdcl: CdclOptions,
+  pub limits: Limits,
+  /// 乱数のシード
+  pub seed: u64,
+  /// 2以上ならポートフォリオで並列に解く
+  pub num_threads: usize,
+  /// 局所探索エンジンがCDCLに切り替えるまでの反転回数
+  pub local_search_flips: u64,
+}
+
+impl Default for SolverConfig {
+  fn default() -> Self {
+    Self {
+      engine: Engine::default(),
+      preprocess: PreprocessOptions::default(),
+      cdcl: CdclOptions::default(),
+      limits: Limits::default(),
+      seed: 0,
+      num_threads: 1,
+      local_search_flips: LOCAL_SEARCH_FLIPS,
+    }
+  }
+}
+
+impl SolverConfig {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_engine(mut self, engine: Engine) -> Self {
+    self.engine = engine;
+    self
+  }
+
+  pub fn with_preprocess(mut self, preprocess: PreprocessOptions) -> Self {
+    self.preprocess = preprocess;
+    self
+  }
+
+  pub fn with_cdcl(mut self, cdcl: CdclOptions) -> Self {
+    self.cdcl = cdcl;
+    self
+  }
+
+  pub fn with_limits(mut self, limits: Limits) -> Self {
+    self.limits = limits;
+    self
+  }
+
+  pub fn with_conflict_limit(mut self, conflicts: u64) -> Self {
+    self.limits.conflicts = Some(conflicts);
+    self
+  }
+
+  pub fn with_propagation_limit(mut self, propagations: u64) -> Self {
+    self.limits.propagations = Some(propagations);
+    self
+  }
+
+  pub fn with_time_limit(mut self, time: Duration) -> Self {
+    self.limits.time = Some(time);
+    self
+  }
+
+  pub fn with_seed(mut self, seed: u64) -> Self {
+    self.seed = seed;
+    self
+  }
+
+  pub fn with_num_threads(mut self, num_threads: usize) -> Self {
+    self.num_threads = num_threads;
+    self
+  }
+
+  pub fn with_local_search_flips(mut self, flips: u64) -> Self {
+    self.local_search_flips = flips;
+    self
+  }
+
+  /// 名前付きの設定
+  pub fn preset(preset: Preset) -> Self {
+    let config = Self::default();
+    match preset {
+      // 長めのリスタート間隔で探索を安定させる(局所探索による極性の設定は既定どおり行う)
+      Preset::Sat => config.with_cdcl(CdclOptions {
+        restart_interval: 500,
+        rephasing: true,
+        ..CdclOptions::default()
+      }),
+      // 頻繁なリスタートと前処理・インプロセッシングで証明を短くする
+      Preset::Unsat => config
+        .with_preprocess(PreprocessOptions {
+          covered_clause_elimination: true,
+          ..PreprocessOptions::default()
+        })
+        .with_cdcl(CdclOptions {
+          restart_interval: 50,
+          keep_lbd: 3,
+          rephasing: false,
+          ..CdclOptions::default()
+        }),
+      Preset::Plain => config
+        .with_preprocess(PreprocessOptions::none())
+        .with_cdcl(CdclOptions {
+          rephasing: false,
+          inprocessing: false,
+          ..CdclOptions::default()
+        }),
+      // 小さな問題では前処理や局所探索の準備の方が探索より重い
+      Preset::FastSmall => {
+        config
+          .with_preprocess(PreprocessOptions::none())
+          .with_cdcl(CdclOptions {
+            restart_interval: 1000,
+            rephasing: false,
+            inprocessing: false,
+            ..CdclOptions::default()
+          })
+      }
+    }
+  }
+
+  /// 名前("sat", "unsat", "plain", "fast-small")で指定した設定
+  pub fn preset_by_name(name: &str) -> Result<Self, UnknownPresetError> {
+    name.parse().map(Self::preset)
+  }
+
+  /// この設定のパラメータ・上限・シードをもつCDCLソルバを作る
+  pub fn cdcl_solver(&self, num_variables: usize) -> Cdcl {
+    let mut solver = Cdcl::new(num_variables);
+    solver.set_options(self.cdcl);
+    solver.set_limits(self.limits);
+    solver.set_seed(self.seed);
+    solver
+  }
+}
+
+/// 問題の性質に合わせた名前付きの設定
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Preset {
+  /// 充足可能な問題向け
+  Sat,
+  /// 充足不能な問題向け
+  Unsat,
+  /// 前処理・インプロセッシング・局所探索を行わない
+  Plain,
+  /// 4x4の数独のような小さな問題向け
+  FastSmall,
+}
+
+impl Preset {
+  pub const ALL: [Preset; 4] = [Preset::Sat, Preset::Unsat, Preset::Plain, Preset::FastSmall];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Preset::Sat => "sat",
+      Preset::Unsat => "unsat",
+      Preset::Plain => "plain",
+      Preset::FastSmall => "fast-small",
+    }
+  }
+}
+
+impl fmt::Display for Preset {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl FromStr for Preset {
+  type Err = UnknownPresetError;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    Preset::ALL
+      .into_iter()
+      .find(|preset| preset.name() == name)
+      .ok_or_else(|| UnknownPresetError(name.to_string()))
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct UnknownPresetError(pub String);
+
+impl fmt::Display for UnknownPresetError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let names = Preset::ALL.map(|preset| preset.name()).join(", ");
+    write!(f, "unknown preset: {} (expected one of {})", self.0, names)
+  }
+}
+
+impl Error for UnknownPresetError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn builds_cdcl_solvers_with_the_options() {
+    let cdcl = CdclOptions {
+      restart_interval: 50,
+      rephasing: false,
+      ..CdclOptions::default()
+    };
+    let config = SolverConfig::new()
+      .with_engine(Engine::Dpll)
+      .with_cdcl(cdcl)
+      .with_conflict_limit(100)
+      .with_time_limit(Duration::from_secs(1))
+      .with_seed(3)
+      .with_num_threads(4);
+    assert_eq!(config.engine, Engine::Dpll);
+    assert_eq!(config.limits.conflicts, Some(100));
+    assert_eq!(config.limits.propagations, None);
+    assert_eq!(config.limits.time, Some(Duration::from_secs(1)));
+    assert_eq!((config.seed, config.num_threads), (3, 4));
+    assert_eq!(config.cdcl_solver(10).options(), &cdcl);
+  }
+
+  #[test]
+  fn parses_preset_names() {
+    for preset in Preset::ALL {
+      assert_eq!(preset.to_string().parse::<Preset>().unwrap(), preset);
+      assert_eq!(
+        SolverConfig::preset_by_name(preset.name()).unwrap(),
+        SolverConfig::preset(preset)
+      );
+    }
+    let error = "fast".parse::<Preset>().unwrap_err();
+    assert_eq!(error.0, "fast");
+    assert_eq!(
+      error.to_string(),
+      "unknown preset: fast (expected one of sat, unsat, plain, fast-small)"
+    );
+    assert!(SolverConfig::preset_by_name("Sat").is_err());
+  }
+
+  #[test]
+  fn presets_turn_off_techniques() {
+    let plain = SolverConfig::preset(Preset::Plain);
+    assert_eq!(plain.preprocess, PreprocessOptions::none());
+    assert!(!plain.cdcl.inprocessing && !plain.cdcl.rephasing);
+    let unsat = SolverConfig::preset(Preset::Unsat);
+    assert!(unsat.preprocess.covered_clause_elimination);
+    assert!(unsat.cdcl.restart_interval < CdclOptions::default().restart_interval);
+    let sat = SolverConfig::preset(Preset::Sat);
+    assert!(sat.cdcl.rephasing);
+    assert!(sat.cdcl.restart_interval > CdclOptions::default().restart_interval);
+  }
+}
@@ -0,0 +1,345 @@
+use crate::{
+  cdcl::solver::{Cdcl, SolveResult},
+  config::SolverConfig,
+  count::natural::Natural,
+  expressions::{clause::Clause, literal::Literal},
+  random::Random,
+  statistics::Statistics,
+};
+
+/// ランダムなXOR制約で解空間を小さなセルに分け，セルの解の個数から全体の個数を推定する(ApproxMC)
+/// 確率1-delta以上で，推定値は真の値の1/(1+epsilon)倍から(1+epsilon)倍の間に入る
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ApproxMc {
+  epsilon: f64,
+  delta: f64,
+}
+
+impl Default for ApproxMc {
+  fn default() -> Self {
+    Self::new(0.8, 0.2)
+  }
+}
+
+impl ApproxMc {
+  pub fn new(epsilon: f64, delta: f64) -> Self {
+    assert!(epsilon > 0.0 && 0.0 < delta && delta < 1.0);
+    Self { epsilon, delta }
+  }
+
+  pub fn epsilon(&self) -> f64 {
+    self.epsilon
+  }
+
+  pub fn delta(&self) -> f64 {
+    self.delta
+  }
+
+  /// 1つのセルで数える解の個数の上限
+  fn threshold(&self) -> usize {
+    let epsilon = self.epsilon;
+    let threshold = 9.84 * (1.0 + epsilon / (1.0 + epsilon)) * (1.0 + 1.0 / epsilon).powi(2);
+    1 + threshold.ceil() as usize
+  }
+
+  /// 推定を繰り返す回数(推定値の中央値を答える)
+  fn iterations(&self) -> usize {
+    (17.0 * (3.0 / self.delta).log2()).ceil() as usize
+  }
+
+  /// projectionの変数の値の組み合わせのうち，解に拡張できるものの個数を推定する
+  /// configのシードでXOR制約を選び，パラメータ・上限をもつCDCLでセルを数える
+  /// 探索が上限に達した場合はNoneを返す
+  pub fn count(
+    &self,
+    problem: &Problem,
+    projection: &[usize],
+    config: &SolverConfig,
+    statistics: &mut Statistics,
+  ) -> Option<Natural> {
+    let threshold = self.threshold();
+
+    // 解が少なければ正確に数える
+    let mut cells = Cells::new(problem, projection, &[], config);
+    let count = cells.count(0, threshold, statistics);
+    *statistics += cells.solver.statistics();
+    let count = count?;
+    if count < threshold {
+      return Some(Natural::from(count as u64));
+    }
+
+    let mut random = Random::new(config.seed);
+    let mut estimates = vec![];
+    for _ in 0..self.iterations() {
+      // 先頭のm個を使うので，mを増やすとセルは小さくなる
+      let hashes = (0..projection.len())
+        .map(|_| random_xor(projection, &mut random))
+        .collect::<Vec<_>>();
+      let mut cells = Cells::new(problem, projection, &hashes, config);
+      let estimate = cells.estimate(count, threshold, statistics);
+      *statistics += cells.solver.statistics();
+      estimates.push(estimate?);
+    }
+
+    estimates.sort();
+    Some(estimates.swap_remove(estimates.len() / 2))
+  }
+}
+
+/// 射影先の変数からそれぞれ確率1/2で選んだ変数の和と，ランダムな右辺
+fn random_xor(projection: &[usize], random: &mut Random) -> (Vec<Literal>, bool) {
+  let literals = projection
+    .iter()
+    .filter(|_| random.below(2) == 0)
+    .map(|&variable| Literal::new(variable, true))
+    .collect();
+  (literals, random.below(2) == 0)
+}
+
+/// 数える問題
+pub struct Problem<'a> {
+  pub num_variables: usize,
+  pub clauses: &'a [Clause],
+  /// リテラルの排他的論理和が右辺になるXOR制約
+  pub xors: &'a [(Vec<Literal>, bool)],
+  /// 真のリテラルの個数が上限以下になる個数制約
+  pub cardinalities: &'a [(Vec<Literal>, usize)],
+}
+
+/// XOR制約の先頭m個で区切ったセルの解を数える
+/// 1つのCDCLで学習節を保ったまま，mを変えて繰り返し数える
+struct Cells<'a> {
+  solver: Cdcl,
+  projection: &'a [usize],
+  /// 偽と仮定するとXOR制約が有効になる変数(各XOR制約に加えておく)
+  selectors: Vec<Literal>,
+  /// 1回の数え上げで加える節を有効にする変数
+  activations: Vec<Literal>,
+}
+
+impl<'a> Cells<'a> {
+  fn new(
+    problem: &Problem,
+    projection: &'a [usize],
+    hashes: &[(Vec<Literal>, bool)],
+    config: &SolverConfig,
+  ) -> Self {
+    // 二分探索で数える回数は高々log2(hashes.len()) + 2
+    let num_activations = hashes.len().max(1).ilog2() as usize + 2;
+    let mut next_variable = problem.num_variables;
+    let mut fresh = |count: usize| {
+      let literals = (next_variable..next_variable + count)
+        .map(|variable| Literal::new(variable, true))
+        .collect::<Vec<_>>();
+      next_variable += count;
+      literals
+    };
+    let selectors = fresh(hashes.len());
+    let activations = fresh(num_activations);
+
+    let mut solver = config.cdcl_solver(next_variable);
+    for clause in problem.clauses {
+      solver.add_clause(&clause.iter().copied().collect::<Vec<_>>());
+    }
+    for (literals, rhs) in problem.xors {
+      solver.add_xor(literals, *rhs);
+    }
+    for (literals, bound) in problem.cardinalities {
+      solver.add_at_most(literals, *bound);
+    }
+    for ((literals, rhs), &selector) in hashes.iter().zip(&selectors) {
+      let mut literals = literals.clone();
+      literals.push(selector);
+      solver.add_xor(&literals, *rhs);
+    }
+
+    Self {
+      solver,
+      projection,
+      selectors: selectors.into_iter().map(|selector| !selector).collect(),
+      activations,
+    }
+  }
+
+  /// セルの解の個数がthreshold未満になる最小のmを二分探索し，(セルの解の個数)*2^mを返す
+  /// XOR制約なしの解の個数countはthreshold以上とする
+  fn estimate(
+    &mut self,
+    count: usize,
+    threshold: usize,
+    statistics: &mut Statistics,
+  ) -> Option<Natural> {
+    let (mut low, mut high) = (0, self.selectors.len());
+    let mut counts = vec![None; high + 1];
+    counts[0] = Some(count);
+    while high - low > 1 {
+      let middle = (low + high) / 2;
+      let count = self.count(middle, threshold, statistics)?;
+      counts[middle] = Some(count);
+      if count < threshold {
+        high = middle;
+      } else {
+        low = middle;
+      }
+    }
+    let count = match counts[high] {
+      Some(count) => count,
+      None => self.count(high, threshold, statistics)?,
+    };
+    Some(Natural::from(count as u64).shift_left(high))
+  }
+
+  /// 先頭m個のXOR制約を満たす解を射影先で区別してlimit個まで数える
+  fn count(&mut self, m: usize, limit: usize, statistics: &mut Statistics) -> Option<usize> {
+    statistics.cells += 1;
+    let activation = self.activations.pop().unwrap();
+    let mut assumptions = self.selectors[..m].to_vec();
+    assumptions.push(activation);
+
+    let mut count = 0;
+    let mut interrupted = false;
+    while count < limit {
+      match self.solver.solve_with_assumptions(&assumptions) {
+        SolveResult::Satisfiable(model) => {
+          count += 1;
+          // 見つけた解を除く節は今回の数え上げの間だけ有効にする
+          let mut blocking = self
+            .projection
+            .iter()
+            .map(|&variable| Literal::new(variable, !model.sign(variable).unwrap_or(false)))
+            .collect::<Vec<_>>();
+          blocking.push(!activation);
+          self.solver.add_clause(&blocking);
+        }
+        SolveResult::Unsatisfiable => break,
+        SolveResult::Unknown => {
+          interrupted = true;
+          break;
+        }
+      }
+    }
+    self.solver.add_clause(&[!activation]);
+    (!interrupted).then_some(count)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::BTreeSet;
+
+  use super::*;
+  use crate::{
+    encodings::xor::encode_xor,
+    testing::{self, random_clauses},
+  };
+
+  /// 総当たりで数えた，projectionの変数の値の組み合わせの個数
+  fn exact(num_variables: usize, clauses: &[Vec<Literal>], projection: &[usize]) -> u64 {
+    let mask = projection
+      .iter()
+      .fold(0u64, |mask, &variable| mask | 1 << variable);
+    testing::models(num_variables, clauses)
+      .into_iter()
+      .map(|assignment| assignment & mask)
+      .collect::<BTreeSet<_>>()
+      .len() as u64
+  }
+
+  fn estimate(
+    approx: ApproxMc,
+    num_variables: usize,
+    clauses: &[Vec<Literal>],
+    xors: &[(Vec<Literal>, bool)],
+    projection: &[usize],
+    seed: u64,
+  ) -> u64 {
+    let clauses = clauses.iter().map(Clause::from).collect::<Vec<_>>();
+    let config = SolverConfig::new().with_seed(seed);
+    let problem = Problem {
+      num_variables,
+      clauses: &clauses,
+      xors,
+      cardinalities: &[],
+    };
+    approx
+      .count(&problem, projection, &config, &mut Statistics::default())
+      .unwrap()
+      .to_u64()
+      .unwrap()
+  }
+
+  #[test]
+  fn counts_few_models_exactly() {
+    // 解の個数がセルの上限未満ならXOR制約を加えずに数える
+    let mut random = Random::new(8);
+    for seed in 0..20 {
+      let num_variables = 4 + random.below(6);
+      let clauses = random_clauses(&mut random, num_variables, 2 * num_variables, 3);
+      let projection = (0..num_variables).collect::<Vec<_>>();
+      assert_eq!(
+        estimate(
+          ApproxMc::default(),
+          num_variables,
+          &clauses,
+          &[],
+          &projection,
+          seed
+        ),
+        exact(num_variables, &clauses, &projection)
+      );
+    }
+  }
+
+  #[test]
+  fn estimates_within_tolerance() {
+    let approx = ApproxMc::new(0.8, 0.5);
+    let mut random = Random::new(9);
+    for seed in 0..3 {
+      let num_variables = 14;
+      let mut clauses = random_clauses(&mut random, num_variables, 10, 3);
+      // XOR制約はそのまま渡し，総当たりでは節に変換して数える
+      let xor = (0..4)
+        .map(|_| Literal::new(random.below(num_variables), true))
+        .collect::<Vec<_>>();
+      let mut next_variable = num_variables;
+      let encoded = encode_xor(&xor, true, &mut next_variable);
+      assert_eq!(next_variable, num_variables);
+      clauses.extend(encoded);
+      let projection = (0..12).collect::<Vec<_>>();
+      let expected = exact(num_variables, &clauses, &projection) as f64;
+      let estimate = estimate(
+        approx,
+        num_variables,
+        &clauses[..10],
+        &[(xor, true)],
+        &projection,
+        seed,
+      ) as f64;
+      let tolerance = 1.0 + approx.epsilon();
+      assert!(
+        expected / tolerance <= estimate && estimate <= expected * tolerance,
+        "expected {} estimate {}",
+        expected,
+        estimate
+      );
+    }
+  }
+
+  #[test]
+  fn stops_at_the_search_limit() {
+    let clauses = [Clause::from(&vec![
+      Literal::new(0, true),
+      Literal::new(1, true),
+    ])];
+    let config = SolverConfig::new().with_conflict_limit(0);
+    let problem = Problem {
+      num_variables: 30,
+      clauses: &clauses,
+      xors: &[],
+      cardinalities: &[],
+    };
+    let count =
+      ApproxMc::default().count(&problem, &[0, 1, 2], &config, &mut Statistics::default());
+    assert_eq!(count, None);
+  }
+}
@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+
+use crate::{
+  count::natural::Natural,
+  expressions::{clause::Clause, literal::Literal},
+  statistics::Statistics,
+};
+
+/// 連結成分ごとの解の個数をキャッシュするDPLLで解の個数を数える(sharpSATの方式)
+/// 割り当て後に共通の変数をもたない節の集まりは独立に数えて掛け合わせる
+#[derive(Clone, Debug)]
+pub struct ModelCounter {
+  num_variables: usize,
+  clauses: Vec<Vec<Literal>>,
+  /// リテラルを含む節
+  occurrences: Vec<Vec<usize>>,
+  values: Vec<Option<bool>>,
+  trail: Vec<Literal>,
+  head: usize,
+  /// 値の組み合わせを数える変数
+  projected: Vec<bool>,
+  /// (変数, 節)の組で表した連結成分ごとの解の個数
+  cache: HashMap<Vec<u32>, Natural>,
+  /// 連結成分を求めるためのunion-find
+  parents: Vec<usize>,
+  units: Vec<Literal>,
+  inconsistent: bool,
+}
+
+impl ModelCounter {
+  pub fn new(num_variables: usize, clauses: &[Clause]) -> Self {
+    let mut counter = Self {
+      num_variables,
+      clauses: vec![],
+      occurrences: vec![vec![]; 2 * num_variables],
+      values: vec![None; num_variables],
+      trail: vec![],
+      head: 0,
+      projected: vec![true; num_variables],
+      cache: HashMap::new(),
+      parents: (0..num_variables).collect(),
+      units: vec![],
+      inconsistent: false,
+    };
+
+    for clause in clauses {
+      let mut literals = clause.iter().copied().collect::<Vec<_>>();
+      literals.sort_unstable();
+      literals.dedup();
+      if literals.windows(2).any(|pair| pair[0] == !pair[1]) {
+        continue;
+      }
+
+      match literals.len() {
+        0 => counter.inconsistent = true,
+        1 => counter.units.push(literals[0]),
+        _ => {
+          let id = counter.clauses.len();
+          for literal in &literals {
+            counter.occurrences[literal.index()].push(id);
+          }
+          counter.clauses.push(literals);
+        }
+      }
+    }
+
+    counter
+  }
+
+  /// projectionの変数の値の組み合わせのうち，解に拡張できるものの個数を数える(射影モデル計数)
+  pub fn set_projection(&mut self, projection: &[usize]) {
+    self.projected = vec![false; self.num_variables];
+    for &variable in projection {
+      self.projected[variable] = true;
+    }
+    self.cache.clear();
+  }
+
+  pub fn count(&mut self, statistics: &mut Statistics) -> Natural {
+    if self.inconsistent {
+      return Natural::zero();
+    }
+
+    let mut consistent = true;
+    for k in 0..self.units.len() {
+      let unit = self.units[k];
+      match self.value(unit) {
+        Some(true) => {}
+        Some(false) => consistent = false,
+        None => self.assign(unit),
+      }
+    }
+    let count = if consistent && self.propagate() {
+      let clauses = (0..self.clauses.len())
+        .filter(|&id| !self.is_satisfied(id))
+        .collect::<Vec<_>>();
+      let variables = (0..self.num_variables)
+        .filter(|&variable| self.values[variable].is_none())
+        .collect::<Vec<_>>();
+      self.count_components(&clauses, &variables, statistics)
+    } else {
+      Natural::zero()
+    };
+    self.undo(0);
+    count
+  }
+
+  /// 未割り当ての変数variablesと未充足の節clausesを連結成分に分けて数える
+  fn count_components(
+    &mut self,
+    clauses: &[usize],
+    variables: &[usize],
+    statistics: &mut Statistics,
+  ) -> Natural {
+    for &variable in variables {
+      self.parents[variable] = variable;
+    }
+    for &id in clauses {
+      let mut first = None;
+      for k in 0..self.clauses[id].len() {
+        let literal = self.clauses[id][k];
+        if self.value(literal).is_some() {
+          continue;
+        }
+        let root = self.find(literal.variable());
+        match first {
+          None => first = Some(root),
+          Some(first) => self.parents[root] = first,
+        }
+      }
+    }
+
+    let mut index_of = HashMap::new();
+    let mut components: Vec<(Vec<usize>, Vec<usize>)> = vec![];
+    for &id in clauses {
+      let variable = self.clauses[id]
+        .iter()
+        .find(|literal| self.value(**literal).is_none())
+        .unwrap()
+        .variable();
+      let root = self.find(variable);
+      let index = *index_of.entry(root).or_insert_with(|| {
+        components.push((vec![], vec![]));
+        components.len() - 1
+      });
+      components[index].1.push(id);
+    }
+
+    // どの節にも現れない変数は自由に値を選べる
+    let mut free = 0;
+    for &variable in variables {
+      let root = self.find(variable);
+      match index_of.get(&root) {
+        Some(&index) => components[index].0.push(variable),
+        None if self.projected[variable] => free += 1,
+        None => {}
+      }
+    }
+
+    let mut count = Natural::power_of_two(free);
+    for (variables, clauses) in components {
+      let component = self.count_component(&variables, &clauses, statistics);
+      if component.is_zero() {
+        return component;
+      }
+      count *= &component;
+    }
+    count
+  }
+
+  /// 1つの連結成分の解の個数
+  /// 数える変数を含まない成分は充足可能かどうかだけを調べる(1か0)
+  fn count_component(
+    &mut self,
+    variables: &[usize],
+    clauses: &[usize],
+    statistics: &mut Statistics,
+  ) -> Natural {
+    statistics.components += 1;
+    // 成分の節の未割り当てのリテラルは成分の変数だけなので，変数と節の組で成分が決まる
+    let key = variables
+      .iter()
+      .map(|&variable| variable as u32)
+      .chain([u32::MAX])
+      .chain(clauses.iter().map(|&id| id as u32))
+      .collect::<Vec<_>>();
+    if let Some(count) = self.cache.get(&key) {
+      statistics.component_cache_hits += 1;
+      return count.clone();
+    }
+
+    let projected = variables.iter().any(|&variable| self.projected[variable]);
+    let variable = self.select_variable(variables, clauses, projected);
+
+    let mut count = Natural::zero();
+    for sign in [true, false] {
+      let mark = self.trail.len();
+      self.assign(Literal::new(variable, sign));
+      if self.propagate() {
+        let rest = clauses
+          .iter()
+          .copied()
+          .filter(|&id| !self.is_satisfied(id))
+          .collect::<Vec<_>>();
+        let unassigned = variables
+          .iter()
+          .copied()
+          .filter(|&variable| self.values[variable].is_none())
+          .collect::<Vec<_>>();
+        count += &self.count_components(&rest, &unassigned, statistics);
+      }
+      self.undo(mark);
+
+      if !projected && !count.is_zero() {
+        count = Natural::one();
+        break;
+      }
+    }
+
+    self.cache.insert(key, count.clone());
+    count
+  }
+
+  /// 成分の節に最も多く現れる変数(数える変数があればその中から)
+  fn select_variable(&self, variables: &[usize], clauses: &[usize], projected: bool) -> usize {
+    let mut occurrences = HashMap::new();
+    for &id in clauses {
+      for literal in &self.clauses[id] {
+        if self.value(*literal).is_none() {
+          *occurrences.entry(literal.variable()).or_insert(0) += 1;
+        }
+      }
+    }
+    variables
+      .iter()
+      .copied()
+      .filter(|&variable| !projected || self.projected[variable])
+      .max_by_key(|&variable| (occurrences.get(&variable).copied().unwrap_or(0), !variable))
+      .unwrap()
+  }
+
+  fn find(&mut self, variable: usize) -> usize {
+    let mut root = variable;
+    while self.parents[root] != root {
+      root = self.parents[root];
+    }
+    let mut current = variable;
+    while self.parents[current] != root {
+      let next = self.parents[current];
+      self.parents[current] = root;
+      current = next;
+    }
+    root
+  }
+
+  fn value(&self, literal: Literal) -> Option<bool> {
+    self.values[literal.variable()].map(|sign| sign == literal.sign())
+  }
+
+  fn is_satisfied(&self, id: usize) -> bool {
+    self.clauses[id]
+      .iter()
+      .any(|&literal| self.value(literal) == Some(true))
+  }
+
+  fn assign(&mut self, literal: Literal) {
+    self.values[literal.variable()] = Some(literal.sign());
+    self.trail.push(literal);
+  }
+
+  fn undo(&mut self, mark: usize) {
+    for literal in self.trail.drain(mark..) {
+      self.values[literal.variable()] = None;
+    }
+    self.head = mark;
+  }
+
+  /// 単位伝播を行う
+  /// 矛盾した場合falseを返す
+  fn propagate(&mut self) -> bool {
+    while self.head < self.trail.len() {
+      let falsified = !self.trail[self.head];
+      self.head += 1;
+
+      for k in 0..self.occurrences[falsified.index()].len() {
+        let id = self.occurrences[falsified.index()][k];
+        let mut unassigned = None;
+        let mut count = 0;
+        let mut satisfied = false;
+        for &literal in &self.clauses[id] {
+          match self.value(literal) {
+            Some(true) => {
+              satisfied = true;
+              break;
+            }
+            Some(false) => {}
+            None => {
+              unassigned = Some(literal);
+              count += 1;
+            }
+          }
+        }
+        if satisfied {
+          continue;
+        }
+        match (count, unassigned) {
+          (0, _) => return false,
+          (1, Some(literal)) => self.assign(literal),
+          _ => {}
+        }
+      }
+    }
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::BTreeSet;
+
+  use super::*;
+  use crate::{
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  fn count(
+    num_variables: usize,
+    clauses: &[Vec<Literal>],
+    projection: Option<&[usize]>,
+  ) -> Natural {
+    let clauses = clauses.iter().map(Clause::from).collect::<Vec<_>>();
+    let mut counter = ModelCounter::new(num_variables, &clauses);
+    if let Some(projection) = projection {
+      counter.set_projection(projection);
+    }
+    counter.count(&mut Statistics::default())
+  }
+
+  #[test]
+  fn counts_models() {
+    let mut random = Random::new(6);
+    for _ in 0..100 {
+      let num_variables = 1 + random.below(12);
+      let num_clauses = random.below(4 * num_variables);
+      let length = 1 + random.below(3);
+      let clauses = random_clauses(&mut random, num_variables, num_clauses, length);
+      let expected = testing::models(num_variables, &clauses).len() as u64;
+      assert_eq!(
+        count(num_variables, &clauses, None),
+        Natural::from(expected)
+      );
+    }
+  }
+
+  #[test]
+  fn counts_projected_models() {
+    let mut random = Random::new(7);
+    for _ in 0..100 {
+      let num_variables = 1 + random.below(12);
+      let num_clauses = random.below(4 * num_variables);
+      let clauses = random_clauses(&mut random, num_variables, num_clauses, 3);
+      let projection = (0..num_variables)
+        .filter(|_| random.below(2) == 0)
+        .collect::<Vec<_>>();
+      let mask = projection
+        .iter()
+        .fold(0u64, |mask, &variable| mask | 1 << variable);
+      let expected = testing::models(num_variables, &clauses)
+        .into_iter()
+        .map(|assignment| assignment & mask)
+        .collect::<BTreeSet<_>>()
+        .len() as u64;
+      assert_eq!(
+        count(num_variables, &clauses, Some(&projection)),
+        Natural::from(expected)
+      );
+    }
+  }
+
+  #[test]
+  fn counts_beyond_u64() {
+    // 制約のない70変数
+    let free = count(70, &[], None);
+    assert_eq!(free, Natural::power_of_two(70));
+    assert_eq!(free.to_u64(), None);
+    assert_eq!(free.to_string(), "1180591620717411303424");
+
+    // 独立な45個の成分がそれぞれ3通り
+    let clauses = (0..45)
+      .map(|i| vec![Literal::new(2 * i, true), Literal::new(2 * i + 1, true)])
+      .collect::<Vec<_>>();
+    assert_eq!(
+      count(90, &clauses, None).to_string(),
+      "2954312706550833698643"
+    );
+  }
+
+  #[test]
+  fn splits_into_components() {
+    // 独立な二項節はそれぞれ別の成分として数える
+    let clauses = (0..10)
+      .map(|i| vec![Literal::new(2 * i, true), Literal::new(2 * i + 1, false)])
+      .map(|clause| Clause::from(&clause))
+      .collect::<Vec<_>>();
+    let mut statistics = Statistics::default();
+    let count = ModelCounter::new(20, &clauses).count(&mut statistics);
+    assert_eq!(count.to_u64(), Some(3u64.pow(10)));
+    assert!(statistics.components >= 10);
+
+    // 同じ成分が別の枝で何度も現れる
+    let mut random = Random::new(8);
+    let mut hits = 0;
+    for _ in 0..20 {
+      let clauses = random_clauses(&mut random, 14, 20, 3)
+        .iter()
+        .map(Clause::from)
+        .collect::<Vec<_>>();
+      let mut statistics = Statistics::default();
+      ModelCounter::new(14, &clauses).count(&mut statistics);
+      hits += statistics.component_cache_hits;
+    }
+    assert!(hits > 0);
+  }
+}
@@ -0,0 +1,3 @@
+pub mod approximate;
+pub mod counter;
+pub mod natural;
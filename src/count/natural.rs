@@ -0,0 +1,244 @@
+use std::{
+  cmp::Ordering,
+  fmt,
+  ops::{Add, AddAssign, Mul, MulAssign},
+};
+
+/// 10進表示で一度に取り出す桁
+const DECIMAL_BASE: u64 = 1_000_000_000;
+
+/// 任意精度の非負整数(解の個数を表す)
+/// 2^32進数の各桁を下位から並べ，上位の0は持たない
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Natural {
+  digits: Vec<u32>,
+}
+
+impl Natural {
+  pub fn zero() -> Self {
+    Self::default()
+  }
+
+  pub fn one() -> Self {
+    Self::from(1)
+  }
+
+  /// 2^exponent
+  pub fn power_of_two(exponent: usize) -> Self {
+    let mut digits = vec![0; exponent / 32 + 1];
+    digits[exponent / 32] = 1 << (exponent % 32);
+    Self { digits }
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.digits.is_empty()
+  }
+
+  /// u64に収まればその値
+  pub fn to_u64(&self) -> Option<u64> {
+    match self.digits[..] {
+      [] => Some(0),
+      [low] => Some(low as u64),
+      [low, high] => Some((high as u64) << 32 | low as u64),
+      _ => None,
+    }
+  }
+
+  /// 2進表示の桁数(0なら0)
+  pub fn bits(&self) -> usize {
+    match self.digits.last() {
+      Some(top) => 32 * self.digits.len() - top.leading_zeros() as usize,
+      None => 0,
+    }
+  }
+
+  /// 2^exponent倍する
+  pub fn shift_left(&self, exponent: usize) -> Self {
+    if self.is_zero() {
+      return Self::zero();
+    }
+    let (words, bits) = (exponent / 32, exponent % 32);
+    let mut digits = vec![0; words];
+    let mut carry = 0;
+    for &digit in &self.digits {
+      let value = (digit as u64) << bits | carry;
+      digits.push(value as u32);
+      carry = value >> 32;
+    }
+    digits.push(carry as u32);
+    Self::normalized(digits)
+  }
+
+  /// 小さな数で割った商と余り
+  fn div_rem_small(&self, divisor: u32) -> (Self, u32) {
+    let mut digits = vec![0; self.digits.len()];
+    let mut remainder = 0u64;
+    for (k, &digit) in self.digits.iter().enumerate().rev() {
+      let value = remainder << 32 | digit as u64;
+      digits[k] = (value / divisor as u64) as u32;
+      remainder = value % divisor as u64;
+    }
+    (Self::normalized(digits), remainder as u32)
+  }
+
+  fn normalized(mut digits: Vec<u32>) -> Self {
+    while digits.last() == Some(&0) {
+      digits.pop();
+    }
+    Self { digits }
+  }
+}
+
+impl From<u64> for Natural {
+  fn from(value: u64) -> Self {
+    Self::normalized(vec![value as u32, (value >> 32) as u32])
+  }
+}
+
+impl AddAssign<&Natural> for Natural {
+  fn add_assign(&mut self, other: &Natural) {
+    if self.digits.len() < other.digits.len() {
+      self.digits.resize(other.digits.len(), 0);
+    }
+    let mut carry = 0;
+    for k in 0..self.digits.len() {
+      let value = self.digits[k] as u64 + other.digits.get(k).copied().unwrap_or(0) as u64 + carry;
+      self.digits[k] = value as u32;
+      carry = value >> 32;
+      if carry == 0 && k >= other.digits.len() {
+        break;
+      }
+    }
+    if carry > 0 {
+      self.digits.push(carry as u32);
+    }
+  }
+}
+
+impl Add<&Natural> for &Natural {
+  type Output = Natural;
+
+  fn add(self, other: &Natural) -> Natural {
+    let mut sum = self.clone();
+    sum += other;
+    sum
+  }
+}
+
+impl Mul<&Natural> for &Natural {
+  type Output = Natural;
+
+  fn mul(self, other: &Natural) -> Natural {
+    if self.is_zero() || other.is_zero() {
+      return Natural::zero();
+    }
+    let mut digits = vec![0u32; self.digits.len() + other.digits.len()];
+    for (i, &a) in self.digits.iter().enumerate() {
+      let mut carry = 0u64;
+      for (j, &b) in other.digits.iter().enumerate() {
+        let value = a as u64 * b as u64 + digits[i + j] as u64 + carry;
+        digits[i + j] = value as u32;
+        carry = value >> 32;
+      }
+      digits[i + other.digits.len()] = carry as u32;
+    }
+    Natural::normalized(digits)
+  }
+}
+
+impl MulAssign<&Natural> for Natural {
+  fn mul_assign(&mut self, other: &Natural) {
+    *self = &*self * other;
+  }
+}
+
+impl Ord for Natural {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self
+      .digits
+      .len()
+      .cmp(&other.digits.len())
+      .then_with(|| self.digits.iter().rev().cmp(other.digits.iter().rev()))
+  }
+}
+
+impl PartialOrd for Natural {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl fmt::Display for Natural {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    // 10^9ごとに区切って下位から求める
+    let mut chunks = vec![];
+    let mut rest = self.clone();
+    while !rest.is_zero() {
+      let (quotient, remainder) = rest.div_rem_small(DECIMAL_BASE as u32);
+      chunks.push(remainder);
+      rest = quotient;
+    }
+
+    match chunks.split_last() {
+      None => write!(f, "0"),
+      Some((top, lower)) => {
+        write!(f, "{}", top)?;
+        for chunk in lower.iter().rev() {
+          write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::random::Random;
+
+  fn natural(value: u128) -> Natural {
+    let low = Natural::from(value as u64);
+    &Natural::from((value >> 64) as u64).shift_left(64) + &low
+  }
+
+  #[test]
+  fn computes_like_u128() {
+    let mut random = Random::new(8);
+    for _ in 0..200 {
+      let a = random.next_u64() >> random.below(64);
+      let b = random.next_u64() >> random.below(64);
+      let product = a as u128 * b as u128;
+      assert_eq!(&Natural::from(a) * &Natural::from(b), natural(product));
+      assert_eq!(natural(product).to_string(), product.to_string());
+      let sum = a as u128 + b as u128;
+      let mut accumulated = Natural::from(a);
+      accumulated += &Natural::from(b);
+      assert_eq!(accumulated, natural(sum));
+      assert_eq!(accumulated.bits(), 128 - sum.leading_zeros() as usize);
+      assert_eq!(
+        Natural::from(a).cmp(&Natural::from(b)),
+        a.cmp(&b),
+        "{} {}",
+        a,
+        b
+      );
+    }
+  }
+
+  #[test]
+  fn handles_zero_and_powers_of_two() {
+    assert!(Natural::zero().is_zero());
+    assert_eq!(Natural::zero().to_string(), "0");
+    assert_eq!(Natural::from(0), Natural::zero());
+    assert_eq!(Natural::one().to_u64(), Some(1));
+    assert_eq!(Natural::power_of_two(63).to_u64(), Some(1 << 63));
+    assert_eq!(Natural::power_of_two(64).to_u64(), None);
+    assert_eq!(Natural::power_of_two(100), Natural::one().shift_left(100));
+    assert_eq!(
+      Natural::power_of_two(100).to_string(),
+      "1267650600228229401496703205376"
+    );
+    assert!(Natural::power_of_two(64) > Natural::from(u64::MAX));
+  }
+}
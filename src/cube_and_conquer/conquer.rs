@@ -0,0 +1,202 @@
+use std::{
+  sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    mpsc, Arc,
+  },
+  thread,
+};
+
+use crate::{
+  cdcl::solver::SolveResult,
+  config::SolverConfig,
+  cube_and_conquer::lookahead::{LookAhead, DEFAULT_DEPTH},
+  expressions::{clause::Clause, literal::Literal},
+  statistics::Statistics,
+};
+
+/// 先読みで節集合をキューブに分割し，各キューブを仮定としてCDCLで並列に解く
+/// 各スレッドのCDCLは学習節を保ったまま次のキューブを解く
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CubeAndConquer {
+  num_threads: usize,
+  depth: usize,
+}
+
+impl Default for CubeAndConquer {
+  fn default() -> Self {
+    Self::new(1)
+  }
+}
+
+impl CubeAndConquer {
+  pub fn new(num_threads: usize) -> Self {
+    Self {
+      num_threads: num_threads.max(1),
+      depth: DEFAULT_DEPTH,
+    }
+  }
+
+  pub fn num_threads(&self) -> usize {
+    self.num_threads
+  }
+
+  pub fn depth(&self) -> usize {
+    self.depth
+  }
+
+  /// キューブの決定リテラルの数の上限(最大で2^depth個のキューブに分割する)
+  pub fn set_depth(&mut self, depth: usize) {
+    self.depth = depth;
+  }
+
+  /// 各キューブはconfigのパラメータ・上限をもつCDCLで解く
+  pub fn solve(
+    &self,
+    num_variables: usize,
+    clauses: &[Clause],
+    config: &SolverConfig,
+    statistics: &mut Statistics,
+  ) -> SolveResult {
+    self.solve_until(
+      num_variables,
+      clauses,
+      config,
+      statistics,
+      Arc::new(AtomicBool::new(false)),
+    )
+  }
+
+  /// stopがtrueにされたら中断してUnknownを返す
+  /// 答えが得られたときはstopをtrueにする
+  pub fn solve_until(
+    &self,
+    num_variables: usize,
+    clauses: &[Clause],
+    config: &SolverConfig,
+    statistics: &mut Statistics,
+    stop: Arc<AtomicBool>,
+  ) -> SolveResult {
+    let mut lookahead = LookAhead::new(num_variables, clauses);
+    lookahead.set_max_depth(self.depth);
+    let cubes = lookahead.cubes(statistics);
+    conquer(
+      num_variables,
+      clauses,
+      &cubes,
+      self.num_threads,
+      config,
+      statistics,
+      stop,
+    )
+  }
+}
+
+/// cubesを仮定として，num_threads個のCDCLで手分けして解く
+/// cubesは全ての解を覆っていなければならない
+pub fn conquer(
+  num_variables: usize,
+  clauses: &[Clause],
+  cubes: &[Vec<Literal>],
+  num_threads: usize,
+  config: &SolverConfig,
+  statistics: &mut Statistics,
+  stop: Arc<AtomicBool>,
+) -> SolveResult {
+  let next = AtomicUsize::new(0);
+  let refuted = AtomicUsize::new(0);
+
+  let answer = thread::scope(|scope| {
+    let (sender, receiver) = mpsc::channel();
+
+    for _ in 0..num_threads.min(cubes.len()) {
+      let sender = sender.clone();
+      let stop = stop.clone();
+      let (next, refuted) = (&next, &refuted);
+      scope.spawn(move || {
+        let mut solver = config.cdcl_solver(num_variables);
+        solver.set_stop_flag(stop.clone());
+        for clause in clauses {
+          solver.add_clause(&clause.iter().copied().collect::<Vec<_>>());
+        }
+
+        let mut answer = None;
+        loop {
+          let index = next.fetch_add(1, Ordering::Relaxed);
+          if index >= cubes.len() {
+            break;
+          }
+          match solver.solve_with_assumptions(&cubes[index]) {
+            SolveResult::Satisfiable(model) => {
+              answer = Some(SolveResult::Satisfiable(model));
+              break;
+            }
+            // 仮定によらず充足不能
+            SolveResult::Unsatisfiable if solver.failed_assumptions().is_empty() => {
+              answer = Some(SolveResult::Unsatisfiable);
+              break;
+            }
+            SolveResult::Unsatisfiable => {
+              refuted.fetch_add(1, Ordering::Relaxed);
+            }
+            SolveResult::Unknown => break,
+          }
+        }
+
+        if answer.is_some() {
+          stop.store(true, Ordering::Relaxed);
+        }
+        let _ = sender.send((answer, solver.statistics().clone()));
+      });
+    }
+    drop(sender);
+
+    let mut answer = None;
+    for (result, worker_statistics) in receiver {
+      *statistics += &worker_statistics;
+      if answer.is_none() {
+        answer = result;
+      }
+    }
+    answer
+  });
+
+  match answer {
+    Some(answer) => answer,
+    None if refuted.load(Ordering::Relaxed) == cubes.len() => SolveResult::Unsatisfiable,
+    None => SolveResult::Unknown,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  #[test]
+  fn agrees_with_brute_force() {
+    let mut random = Random::new(4);
+    let config = SolverConfig::default();
+    for (num_threads, depth) in [(1, 0), (1, 3), (3, 4)] {
+      let mut cube_and_conquer = CubeAndConquer::new(num_threads);
+      cube_and_conquer.set_depth(depth);
+      for _ in 0..30 {
+        let num_variables = 8 + random.below(5);
+        let num_clauses = num_variables * 4 + random.below(num_variables);
+        let clauses = random_clauses(&mut random, num_variables, num_clauses, 3);
+        let models = testing::models(num_variables, &clauses);
+        let clauses = clauses.iter().map(Clause::from).collect::<Vec<_>>();
+        let mut statistics = Statistics::default();
+        match cube_and_conquer.solve(num_variables, &clauses, &config, &mut statistics) {
+          SolveResult::Satisfiable(model) => {
+            assert!(models.contains(&testing::assignment(&model, num_variables)));
+          }
+          SolveResult::Unsatisfiable => assert!(models.is_empty()),
+          SolveResult::Unknown => panic!("no limit is set"),
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,351 @@
+use crate::{
+  expressions::{clause::Clause, literal::Literal},
+  statistics::Statistics,
+};
+
+/// 既定の分割の深さ
+pub const DEFAULT_DEPTH: usize = 8;
+/// 先読みする変数の数の上限
+const MAX_CANDIDATES: usize = 64;
+/// 短くなった節の重み(残りのリテラルが1つ増えるごとにこの値で割る)
+const REDUCTION_WEIGHT: f64 = 5.0;
+/// 両極性の差分の積に掛ける係数(march の MIX_DIFF)
+const PRODUCT_WEIGHT: f64 = 1024.0;
+
+/// 先読み(look-ahead)で分岐変数を選び，節集合をキューブに分割する
+/// 各変数の両極性を仮に割り当てて単位伝播し，短くなった節の多い変数で分岐する
+#[derive(Clone, Debug)]
+pub struct LookAhead {
+  clauses: Vec<Vec<Literal>>,
+  occurrences: Vec<Vec<usize>>,
+  values: Vec<Option<bool>>,
+  trail: Vec<Literal>,
+  head: usize,
+  /// 先読み中に調べた節の印
+  stamps: Vec<usize>,
+  stamp: usize,
+  touched: Vec<usize>,
+  max_depth: usize,
+  inconsistent: bool,
+}
+
+impl LookAhead {
+  pub fn new(num_variables: usize, clauses: &[Clause]) -> Self {
+    let mut lookahead = Self {
+      clauses: vec![],
+      occurrences: vec![vec![]; 2 * num_variables],
+      values: vec![None; num_variables],
+      trail: vec![],
+      head: 0,
+      stamps: vec![],
+      stamp: 0,
+      touched: vec![],
+      max_depth: DEFAULT_DEPTH,
+      inconsistent: false,
+    };
+
+    for clause in clauses {
+      let mut literals = clause.iter().copied().collect::<Vec<_>>();
+      literals.sort_unstable();
+      literals.dedup();
+      if literals.windows(2).any(|pair| pair[0] == !pair[1]) {
+        continue;
+      }
+
+      match literals.len() {
+        0 => lookahead.inconsistent = true,
+        1 => {
+          if !lookahead.assign(literals[0]) {
+            lookahead.inconsistent = true;
+          }
+        }
+        _ => {
+          let id = lookahead.clauses.len();
+          for literal in &literals {
+            lookahead.occurrences[literal.index()].push(id);
+          }
+          lookahead.clauses.push(literals);
+        }
+      }
+    }
+    lookahead.stamps = vec![0; lookahead.clauses.len()];
+    lookahead
+  }
+
+  /// 決定リテラルの数の上限
+  pub fn set_max_depth(&mut self, max_depth: usize) {
+    self.max_depth = max_depth;
+  }
+
+  /// 節集合をキューブ(決定リテラルの列)に分割する
+  /// 単位伝播と失敗リテラルで矛盾した枝は含めないので，空なら充足不能
+  pub fn cubes(&mut self, statistics: &mut Statistics) -> Vec<Vec<Literal>> {
+    let mut cubes = vec![];
+    if self.inconsistent || !self.propagate() {
+      return cubes;
+    }
+    self.split(&mut vec![], &mut cubes, statistics);
+    statistics.cubes += cubes.len() as u64;
+    cubes
+  }
+
+  fn split(
+    &mut self,
+    decisions: &mut Vec<Literal>,
+    cubes: &mut Vec<Vec<Literal>>,
+    statistics: &mut Statistics,
+  ) {
+    let start = self.trail.len();
+
+    match self.lookahead(statistics) {
+      None => {}
+      Some(Some(literal)) if decisions.len() < self.max_depth => {
+        for decision in [literal, !literal] {
+          let level = self.trail.len();
+          decisions.push(decision);
+          if self.assign(decision) && self.propagate() {
+            self.split(decisions, cubes, statistics);
+          }
+          self.undo(level);
+          decisions.pop();
+        }
+      }
+      Some(_) => cubes.push(decisions.clone()),
+    }
+
+    self.undo(start);
+  }
+
+  /// 候補の変数を先読みして分岐リテラルを選ぶ
+  /// 失敗リテラルは否定を割り当てる
+  /// 矛盾すればNone，全ての節が充足されていればSome(None)を返す
+  fn lookahead(&mut self, statistics: &mut Statistics) -> Option<Option<Literal>> {
+    let mut best = None;
+    let mut best_score = f64::MIN;
+    let mut found_failed = false;
+
+    for variable in self.candidates() {
+      if self.values[variable].is_some() {
+        continue;
+      }
+
+      let positive = Literal::new(variable, true);
+      let Some(positive_diff) = self.diff(positive) else {
+        statistics.lookahead_failed_literals += 1;
+        found_failed = true;
+        if !self.assign(!positive) || !self.propagate() {
+          return None;
+        }
+        continue;
+      };
+      let Some(negative_diff) = self.diff(!positive) else {
+        statistics.lookahead_failed_literals += 1;
+        found_failed = true;
+        if !self.assign(positive) || !self.propagate() {
+          return None;
+        }
+        continue;
+      };
+
+      let score = PRODUCT_WEIGHT * positive_diff * negative_diff + positive_diff + negative_diff;
+      if score > best_score {
+        best_score = score;
+        // 短くなる節の多い方を先に調べる
+        best = Some(if positive_diff >= negative_diff {
+          positive
+        } else {
+          !positive
+        });
+      }
+    }
+
+    // 失敗リテラルの割り当てで選んだ変数が確定したり，候補が尽きたりしていればやり直す
+    let settled = best.is_none_or(|literal| self.values[literal.variable()].is_some());
+    if found_failed && settled {
+      self.lookahead(statistics)
+    } else {
+      Some(best)
+    }
+  }
+
+  /// 充足されていない節に現れる変数を出現回数の多い順に選ぶ
+  fn candidates(&self) -> Vec<usize> {
+    let mut counts = vec![[0usize; 2]; self.values.len()];
+    for clause in &self.clauses {
+      if clause
+        .iter()
+        .any(|&literal| self.value(literal) == Some(true))
+      {
+        continue;
+      }
+      for &literal in clause {
+        if self.value(literal).is_none() {
+          counts[literal.variable()][usize::from(literal.sign())] += 1;
+        }
+      }
+    }
+
+    let mut candidates = (0..self.values.len())
+      .filter(|&variable| counts[variable] != [0, 0])
+      .collect::<Vec<_>>();
+    candidates.sort_by_key(|&variable| {
+      let [negative, positive] = counts[variable];
+      std::cmp::Reverse((negative * positive, negative + positive))
+    });
+    candidates.truncate(MAX_CANDIDATES);
+    candidates
+  }
+
+  /// literalを仮に割り当てて，短くなった節の重み付きの数を返す
+  /// 矛盾すればNoneを返す
+  fn diff(&mut self, literal: Literal) -> Option<f64> {
+    let level = self.trail.len();
+    self.stamp += 1;
+    self.touched.clear();
+
+    let result = if self.assign(literal) && self.propagate() {
+      let mut diff = 0.0;
+      for &id in &self.touched {
+        let clause = &self.clauses[id];
+        if clause
+          .iter()
+          .any(|&literal| self.value(literal) == Some(true))
+        {
+          continue;
+        }
+        let remaining = clause
+          .iter()
+          .filter(|&&literal| self.value(literal).is_none())
+          .count();
+        diff += REDUCTION_WEIGHT.powi(2 - remaining as i32);
+      }
+      Some(diff)
+    } else {
+      None
+    };
+
+    self.undo(level);
+    result
+  }
+
+  fn value(&self, literal: Literal) -> Option<bool> {
+    self.values[literal.variable()].map(|sign| sign == literal.sign())
+  }
+
+  /// 矛盾すればfalseを返す
+  fn assign(&mut self, literal: Literal) -> bool {
+    match self.value(literal) {
+      Some(value) => value,
+      None => {
+        self.values[literal.variable()] = Some(literal.sign());
+        self.trail.push(literal);
+        true
+      }
+    }
+  }
+
+  fn undo(&mut self, level: usize) {
+    for literal in self.trail.drain(level..) {
+      self.values[literal.variable()] = None;
+    }
+    self.head = self.head.min(level);
+  }
+
+  /// 単位伝播を行い，矛盾すればfalseを返す
+  fn propagate(&mut self) -> bool {
+    while self.head < self.trail.len() {
+      let falsified = !self.trail[self.head];
+      self.head += 1;
+
+      for k in 0..self.occurrences[falsified.index()].len() {
+        let id = self.occurrences[falsified.index()][k];
+        if self.stamps[id] != self.stamp {
+          self.stamps[id] = self.stamp;
+          self.touched.push(id);
+        }
+
+        let mut unit = None;
+        let mut unassigned = 0;
+        for &literal in &self.clauses[id] {
+          match self.value(literal) {
+            Some(true) => {
+              unassigned = usize::MAX;
+              break;
+            }
+            Some(false) => {}
+            None => {
+              unassigned += 1;
+              unit = Some(literal);
+            }
+          }
+        }
+
+        match unassigned {
+          0 => {
+            self.head = self.trail.len();
+            return false;
+          }
+          1 => {
+            self.assign(unit.unwrap());
+          }
+          _ => {}
+        }
+      }
+    }
+
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  fn cubes(num_variables: usize, clauses: &[Vec<Literal>], depth: usize) -> Vec<Vec<Literal>> {
+    let clauses = clauses.iter().map(Clause::from).collect::<Vec<_>>();
+    let mut lookahead = LookAhead::new(num_variables, &clauses);
+    lookahead.set_max_depth(depth);
+    lookahead.cubes(&mut Statistics::default())
+  }
+
+  #[test]
+  fn cubes_partition_the_models() {
+    let mut random = Random::new(3);
+    for depth in [0, 1, 3, 6] {
+      for _ in 0..30 {
+        let num_variables = 8 + random.below(5);
+        let num_clauses = num_variables * 4 + random.below(num_variables);
+        let clauses = random_clauses(&mut random, num_variables, num_clauses, 3);
+        let cubes = cubes(num_variables, &clauses, depth);
+        assert!(cubes.len() <= 1 << depth);
+        assert!(cubes.iter().all(|cube| cube.len() <= depth));
+        // キューブの否定と節集合を合わせると充足不能になり，キューブどうしは交わらない
+        for model in testing::models(num_variables, &clauses) {
+          let covering = cubes
+            .iter()
+            .filter(|cube| cube.iter().all(|&literal| testing::holds(model, literal)))
+            .count();
+          assert_eq!(covering, 1);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn returns_no_cubes_for_unsatisfiable_formulas() {
+    let literal = |variable, sign| Literal::new(variable, sign);
+    // (x0 | x1) & (x0 | !x1) & (!x0 | x2) & (!x0 | !x2)
+    let clauses = vec![
+      vec![literal(0, true), literal(1, true)],
+      vec![literal(0, true), literal(1, false)],
+      vec![literal(0, false), literal(2, true)],
+      vec![literal(0, false), literal(2, false)],
+    ];
+    assert!(cubes(3, &clauses, 4).is_empty());
+    assert!(cubes(1, &[vec![]], 4).is_empty());
+  }
+}
@@ -0,0 +1,2 @@
+pub mod conquer;
+pub mod lookahead;
@@ -8,6 +8,14 @@ pub struct Dimacs {
   num_variables: usize,
   num_clauses: usize,
   clauses: Vec<Vec<(usize, bool)>>,
+  /// xで始まる行のXOR制約(リテラルの排他的論理和が真)
+  xors: Vec<Vec<(usize, bool)>>,
+}
+
+impl Default for Dimacs {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 #[allow(dead_code)]
@@ -17,24 +25,38 @@ impl Dimacs {
       num_variables: 0,
       num_clauses: 0,
       clauses: vec![],
+      xors: vec![],
     }
   }
 
   /// parse dimacs file
+  /// `x1 -2 3 0` のようにxで始まる行はXOR制約として読み，節の数に含める
   pub fn from<P: AsRef<Path>>(dimacs_file: P) -> Result<Dimacs, Box<dyn Error>> {
     let mut has_read_header = false;
     let mut num_variables = 0;
     let mut num_clauses = 0;
     let mut clauses = vec![];
+    let mut xors = vec![];
 
     for line in &io::read_file(dimacs_file)? {
-      let words = line
+      let mut words = line
         .split_whitespace()
         .map(|s| s.trim())
         .filter(|s| !s.is_empty())
         .collect::<Vec<_>>();
 
       if has_read_header {
+        let is_xor = words.first().is_some_and(|word| word.starts_with('x'));
+        if is_xor {
+          words[0] = &words[0][1..];
+          if words[0].is_empty() {
+            words.remove(0);
+          }
+        }
+        if words.is_empty() {
+          return Err(Box::new(DimacsParseError));
+        }
+
         if words[words.len() - 1] != "0" {
           return Err(Box::new(DimacsParseError));
         }
@@ -52,13 +74,17 @@ impl Dimacs {
 
           let num = words[i].parse::<i64>()?;
           clause.push(if num < 0 {
-            (num.abs() as usize, false)
+            (num.unsigned_abs() as usize, false)
           } else {
             (num as usize, true)
           });
         }
 
-        clauses.push(clause);
+        if is_xor {
+          xors.push(clause);
+        } else {
+          clauses.push(clause);
+        }
       } else {
         if words[0].chars().nth(0).unwrap() == 'c' {
           continue;
@@ -74,7 +100,7 @@ impl Dimacs {
       }
     }
 
-    if !has_read_header || num_clauses != clauses.len() {
+    if !has_read_header || num_clauses != clauses.len() + xors.len() {
       return Err(Box::new(DimacsParseError));
     }
 
@@ -82,11 +108,22 @@ impl Dimacs {
       num_variables,
       num_clauses,
       clauses,
+      xors,
     })
   }
 
-  pub fn solve(&mut self) -> Option<Vec<i64>> {
+  pub fn solve(&mut self) -> Option<Option<Vec<i64>>> {
+    self.solve_with(|_| {})
+  }
+
+  /// 解があればSome(Some(解))，充足不能ならSome(None)，上限に達して分からなければNoneを返す
+  /// 解く前にconfigureでソルバの設定を変更する
+  pub fn solve_with<F: FnOnce(&mut SATSolver<usize>)>(
+    &mut self,
+    configure: F,
+  ) -> Option<Option<Vec<i64>>> {
     let mut solver = SATSolver::new();
+    configure(&mut solver);
     for i in 1..=self.num_variables {
       let _ = solver.variable(i);
     }
@@ -105,8 +142,22 @@ impl Dimacs {
       solver.add_clause(&clause);
     }
 
-    if !solver.solve() {
-      return None;
+    for xor in &self.xors {
+      let xor = xor
+        .iter()
+        .map(|&(i, sign)| {
+          if sign {
+            solver.variable(i)
+          } else {
+            !solver.variable(i)
+          }
+        })
+        .collect::<Vec<_>>();
+      solver.add_xor(&xor, true);
+    }
+
+    if !solver.solve_limited()? {
+      return Some(None);
     }
 
     let mut solution = (1..=self.num_variables)
@@ -121,7 +172,7 @@ impl Dimacs {
 
     solution.push(0);
 
-    Some(solution)
+    Some(Some(solution))
   }
 }
 
@@ -130,8 +181,89 @@ pub struct DimacsParseError;
 
 impl fmt::Display for DimacsParseError {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    write!(f, "{}", self)
+    write!(f, "failed to parse dimacs file")
   }
 }
 
 impl Error for DimacsParseError {}
+
+#[cfg(test)]
+mod tests {
+  use std::{env, fs};
+
+  use super::*;
+  use crate::{config::SolverConfig, random::Random, testing::random_clauses};
+
+  /// textを一時ファイルに書いて読む
+  fn parse(name: &str, text: &str) -> Result<Dimacs, Box<dyn Error>> {
+    let path = env::temp_dir().join(format!("rewsat-{}-{}.cnf", std::process::id(), name));
+    fs::write(&path, text).unwrap();
+    let dimacs = Dimacs::from(&path);
+    fs::remove_file(&path).unwrap();
+    dimacs
+  }
+
+  #[test]
+  fn reads_xor_lines() {
+    let dimacs = parse("xor", "c comment\np cnf 3 3\n1 -2 0\nx1 -2 3 0\nx -1 2 0\n").unwrap();
+    assert_eq!(dimacs.clauses, vec![vec![(1, true), (2, false)]]);
+    assert_eq!(
+      dimacs.xors,
+      vec![
+        vec![(1, true), (2, false), (3, true)],
+        vec![(1, false), (2, true)],
+      ]
+    );
+  }
+
+  #[test]
+  fn rejects_malformed_input() {
+    let inputs = [
+      // XOR制約も節の数に含める
+      "p cnf 2 1\n1 0\nx1 2 0\n",
+      "p cnf 2 1\nx1 2\n",
+      "p cnf 2 1\nx\n",
+      "p cnf 2 1\nx1 0 2 0\n",
+      "p cnf 2 1\nxa 1 0\n",
+      "1 2 0\n",
+    ];
+    for (i, input) in inputs.iter().enumerate() {
+      assert!(
+        parse(&format!("malformed{}", i), input).is_err(),
+        "{}",
+        input
+      );
+    }
+  }
+
+  #[test]
+  fn solves_with_xors() {
+    // x1 + x2 + x3 = 1, x1 + x2 = 1, !x3 のとき x1 != x2
+    let mut dimacs = parse("solve", "p cnf 3 3\nx1 2 3 0\nx1 2 0\n-3 0\n").unwrap();
+    let solution = dimacs.solve().unwrap().unwrap();
+    assert_eq!(solution[2], -3);
+    assert!((solution[0] > 0) != (solution[1] > 0));
+
+    let mut dimacs = parse("unsat", "p cnf 2 2\nx1 2 0\nx-1 2 0\n").unwrap();
+    assert_eq!(dimacs.solve(), Some(None));
+  }
+
+  #[test]
+  fn reports_unknown_at_the_search_limit() {
+    // 変数80個・節480個のランダム3-SATは衝突10回では答えが出ない
+    let mut random = Random::new(7);
+    let clauses = random_clauses(&mut random, 80, 480, 3);
+    let mut text = "p cnf 80 480\n".to_string();
+    for clause in &clauses {
+      for literal in clause {
+        let variable = literal.variable() as i64 + 1;
+        text += &format!("{} ", if literal.sign() { variable } else { -variable });
+      }
+      text += "0\n";
+    }
+    let mut dimacs = parse("limit", &text).unwrap();
+    let config = SolverConfig::default().with_conflict_limit(10);
+    assert_eq!(dimacs.solve_with(|solver| solver.set_config(config)), None);
+    assert_eq!(dimacs.solve(), Some(None));
+  }
+}
@@ -1,6 +1,6 @@
 use crate::expressions::{clause::Clause, literal::Literal, model::Model};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Dpll {}
 
 impl Dpll {
@@ -9,17 +9,12 @@ impl Dpll {
   }
 
   pub fn solve(&mut self, num_variables: usize, clauses: &[Clause]) -> Option<Model> {
-    let result = solve_recursive(num_variables, &clauses, &Model::new(num_variables));
-    result
+    solve_recursive(num_variables, clauses, &Model::new(num_variables))
   }
 }
 
 fn solve_recursive(num_variables: usize, clauses: &[Clause], model: &Model) -> Option<Model> {
-  let (clauses, model) = if let Some(res) = simplify(clauses, model) {
-    res
-  } else {
-    return None;
-  };
+  let (clauses, model) = simplify(clauses, model)?;
 
   if let Some(variable) = select_variable(num_variables, &clauses, &model) {
     for sign in [true, false] {
@@ -50,13 +45,7 @@ fn simplify(clauses: &[Clause], model: &Model) -> Option<(Vec<Clause>, Model)> {
   let mut model = model.clone();
 
   while let Some(literal) = find_unit(&clauses) {
-    clauses = if let Some(clauses) =
-      apply_unit_rule(&clauses, &model, literal.variable(), literal.sign())
-    {
-      clauses
-    } else {
-      return None;
-    };
+    clauses = apply_unit_rule(&clauses, &model, literal.variable(), literal.sign())?;
     model.assign(literal.variable(), literal.sign());
   }
 
@@ -90,15 +79,11 @@ fn apply_unit_rule(
 
 /// 単リテラル節を探し，あればそのリテラルを返す
 fn find_unit(clauses: &[Clause]) -> Option<Literal> {
-  if let Some(unit) = clauses
+  clauses
     .iter()
     .filter(|clause| clause.is_valid() && !clause.has_deleted())
     .find(|clause| clause.is_unit())
-  {
-    Some(unit[0])
-  } else {
-    None
-  }
+    .map(|unit| unit[0])
 }
 
 /// 節集合の中から未割り当ての変数を選択する
@@ -106,13 +91,10 @@ fn find_unit(clauses: &[Clause]) -> Option<Literal> {
 fn select_variable(num_variables: usize, clauses: &[Clause], model: &Model) -> Option<usize> {
   if let Some(clause) = clauses
     .iter()
-    .filter(|clause| clause.is_valid() && !clause.has_deleted())
-    .nth(0)
+    .find(|clause| clause.is_valid() && !clause.has_deleted())
   {
     Some(clause[0].variable())
-  } else if let Some(i) = (0..num_variables).find(|&i| !model.has_assigned(i)) {
-    Some(i)
   } else {
-    None
+    (0..num_variables).find(|&i| !model.has_assigned(i))
   }
 }
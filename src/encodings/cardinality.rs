@@ -0,0 +1,617 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::expressions::literal::Literal;
+
+/// 個数制約を節に変換する方法
+/// Commander・Product・Bimanderはk=1専用で，k>1では逐次カウンタを使う
+/// Nativeは節に変換せずCDCLの探索中に直接扱う(節に変換するときは逐次カウンタを使う)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CardinalityEncoding {
+  /// k+1個の組を全て禁止する(補助変数は使わないが節はO(n^(k+1))個)
+  Pairwise,
+  /// 逐次カウンタ(Sinz)
+  #[default]
+  Sequential,
+  /// 3個ずつのグループの代表変数に再帰的に制約をかける(Klieber & Kwon)
+  Commander,
+  /// 変数を格子に並べ，行と列の変数に再帰的に制約をかける(Chen)
+  Product,
+  /// 2個ずつのグループの番号を2進数で表す(Nguyen & Mai)
+  Bimander,
+  /// 二分木の各節点で部分和を1進数で数える(Bailleux & Boufkhad)
+  Totalizer,
+  /// 部分和をpで割った商と余りで数える(Ogawa et al.)
+  ModuloTotalizer,
+  /// Batcherの奇偶マージソートで並べ替える
+  SortingNetwork,
+  /// 真のリテラルを数えて上限に達したら残りを偽にする
+  Native,
+}
+
+impl CardinalityEncoding {
+  pub const ALL: [CardinalityEncoding; 9] = [
+    CardinalityEncoding::Pairwise,
+    CardinalityEncoding::Sequential,
+    CardinalityEncoding::Commander,
+    CardinalityEncoding::Product,
+    CardinalityEncoding::Bimander,
+    CardinalityEncoding::Totalizer,
+    CardinalityEncoding::ModuloTotalizer,
+    CardinalityEncoding::SortingNetwork,
+    CardinalityEncoding::Native,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      CardinalityEncoding::Pairwise => "pairwise",
+      CardinalityEncoding::Sequential => "sequential",
+      CardinalityEncoding::Commander => "commander",
+      CardinalityEncoding::Product => "product",
+      CardinalityEncoding::Bimander => "bimander",
+      CardinalityEncoding::Totalizer => "totalizer",
+      CardinalityEncoding::ModuloTotalizer => "modulo-totalizer",
+      CardinalityEncoding::SortingNetwork => "sorting-network",
+      CardinalityEncoding::Native => "native",
+    }
+  }
+}
+
+impl fmt::Display for CardinalityEncoding {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl FromStr for CardinalityEncoding {
+  type Err = UnknownEncodingError;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    CardinalityEncoding::ALL
+      .into_iter()
+      .find(|encoding| encoding.name() == name)
+      .ok_or_else(|| UnknownEncodingError(name.to_string()))
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct UnknownEncodingError(pub String);
+
+impl fmt::Display for UnknownEncodingError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let names = CardinalityEncoding::ALL
+      .map(|encoding| encoding.name())
+      .join(", ");
+    write!(
+      f,
+      "unknown encoding: {} (expected one of {})",
+      self.0, names
+    )
+  }
+}
+
+impl Error for UnknownEncodingError {}
+
+/// literalsのうち真になるものがk個以下になる制約を節に変換する
+/// 補助変数にはnext_variableから順に番号を振る
+pub fn at_most_k(
+  literals: &[Literal],
+  k: usize,
+  encoding: CardinalityEncoding,
+  next_variable: &mut usize,
+) -> Vec<Vec<Literal>> {
+  if k >= literals.len() {
+    return vec![];
+  }
+  if k == 0 {
+    return literals.iter().map(|&literal| vec![!literal]).collect();
+  }
+
+  let mut encoder = Encoder {
+    next_variable,
+    clauses: vec![],
+  };
+  match encoding {
+    CardinalityEncoding::Pairwise => encoder.pairwise(literals, k),
+    CardinalityEncoding::Commander if k == 1 => encoder.commander(literals),
+    CardinalityEncoding::Product if k == 1 => encoder.product(literals),
+    CardinalityEncoding::Bimander if k == 1 => encoder.bimander(literals),
+    CardinalityEncoding::Totalizer => encoder.totalizer(literals, k),
+    CardinalityEncoding::ModuloTotalizer => encoder.modulo_totalizer(literals, k),
+    CardinalityEncoding::SortingNetwork => encoder.sorting_network(literals, k),
+    _ => encoder.sequential(literals, k),
+  }
+  encoder.clauses
+}
+
+/// literalsのうち真になるものがk個以上になる制約を節に変換する
+/// 偽になるものがn-k個以下になる制約として変換する
+pub fn at_least_k(
+  literals: &[Literal],
+  k: usize,
+  encoding: CardinalityEncoding,
+  next_variable: &mut usize,
+) -> Vec<Vec<Literal>> {
+  match k {
+    0 => vec![],
+    _ if k > literals.len() => vec![vec![]],
+    1 => vec![literals.to_vec()],
+    _ => {
+      let negated = literals.iter().map(|&literal| !literal).collect::<Vec<_>>();
+      at_most_k(&negated, literals.len() - k, encoding, next_variable)
+    }
+  }
+}
+
+/// literalsのうち真になるものがちょうどk個になる制約を節に変換する
+pub fn exactly_k(
+  literals: &[Literal],
+  k: usize,
+  encoding: CardinalityEncoding,
+  next_variable: &mut usize,
+) -> Vec<Vec<Literal>> {
+  let mut clauses = at_most_k(literals, k, encoding, next_variable);
+  clauses.extend(at_least_k(literals, k, encoding, next_variable));
+  clauses
+}
+
+/// 真のリテラルの個数を1進数で表すリテラル(j番目はj+1個以上が真)と，それを導く節を返す(totalizer)
+/// limit個以上は区別せず，真になる向きの含意だけを節にする
+pub(crate) fn unary_count(
+  literals: &[Literal],
+  limit: usize,
+  next_variable: &mut usize,
+) -> (Vec<Literal>, Vec<Vec<Literal>>) {
+  if literals.is_empty() {
+    return (vec![], vec![]);
+  }
+  let mut encoder = Encoder {
+    next_variable,
+    clauses: vec![],
+  };
+  let counts = encoder.count(literals, limit);
+  (counts, encoder.clauses)
+}
+
+struct Encoder<'a> {
+  next_variable: &'a mut usize,
+  clauses: Vec<Vec<Literal>>,
+}
+
+impl Encoder<'_> {
+  fn fresh(&mut self) -> Literal {
+    let literal = Literal::new(*self.next_variable, true);
+    *self.next_variable += 1;
+    literal
+  }
+
+  fn fresh_literals(&mut self, count: usize) -> Vec<Literal> {
+    (0..count).map(|_| self.fresh()).collect()
+  }
+
+  /// k+1個のリテラルの組ごとに，全てが真になることを禁止する
+  fn pairwise(&mut self, literals: &[Literal], k: usize) {
+    if literals.len() <= k {
+      return;
+    }
+    let mut chosen = vec![];
+    self.forbid_combinations(literals, k + 1, &mut chosen);
+  }
+
+  fn forbid_combinations(&mut self, literals: &[Literal], size: usize, chosen: &mut Vec<Literal>) {
+    if chosen.len() == size {
+      self.clauses.push(chosen.clone());
+      return;
+    }
+    // 残りのリテラルで組を作れる範囲だけ選ぶ
+    let remaining = size - chosen.len();
+    for i in 0..=literals.len() - remaining {
+      chosen.push(!literals[i]);
+      self.forbid_combinations(&literals[i + 1..], size, chosen);
+      chosen.pop();
+    }
+  }
+
+  /// s[i][j]: 先頭i+1個のうちj+1個以上が真
+  fn sequential(&mut self, literals: &[Literal], k: usize) {
+    let n = literals.len();
+    let mut previous: Vec<Literal> = vec![];
+    for (i, &literal) in literals.iter().enumerate() {
+      if i > 0 {
+        // i個目が真なら，先頭i個でk個以上が真であってはならない
+        self.clauses.push(vec![!literal, !previous[k - 1]]);
+      }
+      if i == n - 1 {
+        break;
+      }
+
+      let counter = self.fresh_literals(k);
+      self.clauses.push(vec![!literal, counter[0]]);
+      for j in 0..k {
+        if i == 0 {
+          if j > 0 {
+            self.clauses.push(vec![!counter[j]]);
+          }
+          continue;
+        }
+        self.clauses.push(vec![!previous[j], counter[j]]);
+        if j > 0 {
+          self
+            .clauses
+            .push(vec![!literal, !previous[j - 1], counter[j]]);
+        }
+      }
+      previous = counter;
+    }
+  }
+
+  fn at_most_one_pairwise(&mut self, literals: &[Literal]) {
+    self.pairwise(literals, 1);
+  }
+
+  fn commander(&mut self, literals: &[Literal]) {
+    if literals.len() <= 4 {
+      self.at_most_one_pairwise(literals);
+      return;
+    }
+    let mut commanders = vec![];
+    for group in literals.chunks(3) {
+      self.at_most_one_pairwise(group);
+      let commander = self.fresh();
+      for &literal in group {
+        self.clauses.push(vec![!literal, commander]);
+      }
+      commanders.push(commander);
+    }
+    self.commander(&commanders);
+  }
+
+  fn product(&mut self, literals: &[Literal]) {
+    if literals.len() <= 4 {
+      self.at_most_one_pairwise(literals);
+      return;
+    }
+    let columns = (literals.len() as f64).sqrt().ceil() as usize;
+    let rows = literals.len().div_ceil(columns);
+    let row_literals = self.fresh_literals(rows);
+    let column_literals = self.fresh_literals(columns);
+    for (index, &literal) in literals.iter().enumerate() {
+      self
+        .clauses
+        .push(vec![!literal, row_literals[index / columns]]);
+      self
+        .clauses
+        .push(vec![!literal, column_literals[index % columns]]);
+    }
+    self.product(&row_literals);
+    self.product(&column_literals);
+  }
+
+  fn bimander(&mut self, literals: &[Literal]) {
+    let groups = literals.chunks(2).collect::<Vec<_>>();
+    if groups.len() == 1 {
+      self.at_most_one_pairwise(literals);
+      return;
+    }
+    let bits = self.fresh_literals(groups.len().next_power_of_two().ilog2() as usize);
+    for (index, group) in groups.into_iter().enumerate() {
+      self.at_most_one_pairwise(group);
+      for &literal in group {
+        for (h, &bit) in bits.iter().enumerate() {
+          let bit = if index >> h & 1 == 1 { bit } else { !bit };
+          self.clauses.push(vec![!literal, bit]);
+        }
+      }
+    }
+  }
+
+  fn totalizer(&mut self, literals: &[Literal], k: usize) {
+    let counts = self.count(literals, k + 1);
+    if counts.len() > k {
+      self.clauses.push(vec![!counts[k]]);
+    }
+  }
+
+  /// 真のリテラルの個数を1進数で表すリテラル(j番目: j+1個以上が真)
+  /// limit個以上は区別しない
+  fn count(&mut self, literals: &[Literal], limit: usize) -> Vec<Literal> {
+    if literals.len() == 1 {
+      return literals.to_vec();
+    }
+    let (left, right) = literals.split_at(literals.len() / 2);
+    let left = self.count(left, limit);
+    let right = self.count(right, limit);
+    let counts = self.fresh_literals((left.len() + right.len()).min(limit));
+    for i in 0..=left.len() {
+      for j in 0..=right.len() {
+        if i + j == 0 || i + j > counts.len() {
+          continue;
+        }
+        let mut clause = premise(&left, i, &right, j);
+        clause.push(counts[i + j - 1]);
+        self.clauses.push(clause);
+      }
+    }
+    counts
+  }
+
+  fn modulo_totalizer(&mut self, literals: &[Literal], k: usize) {
+    let modulo = ((k + 1) as f64).sqrt().ceil().max(2.0) as usize;
+    let (quotient, remainder) = (k / modulo, k % modulo);
+    let (upper, lower) = self.count_modulo(literals, modulo, quotient + 1);
+
+    // 個数 > k = quotient * modulo + remainder を禁止する
+    if upper.len() > quotient {
+      self.clauses.push(vec![!upper[quotient]]);
+    }
+    if lower.len() > remainder {
+      match quotient {
+        0 => self.clauses.push(vec![!lower[remainder]]),
+        _ if upper.len() >= quotient => self
+          .clauses
+          .push(vec![!upper[quotient - 1], !lower[remainder]]),
+        _ => {}
+      }
+    }
+  }
+
+  /// 真のリテラルの個数をmoduloで割った商と余りを1進数で表すリテラル
+  /// 商はlimit以上を区別しない
+  fn count_modulo(
+    &mut self,
+    literals: &[Literal],
+    modulo: usize,
+    limit: usize,
+  ) -> (Vec<Literal>, Vec<Literal>) {
+    if literals.len() == 1 {
+      return (vec![], literals.to_vec());
+    }
+    let (left, right) = literals.split_at(literals.len() / 2);
+    let (left_upper, left_lower) = self.count_modulo(left, modulo, limit);
+    let (right_upper, right_lower) = self.count_modulo(right, modulo, limit);
+
+    let max_lower = left_lower.len() + right_lower.len();
+    // 余りの和がmodulo以上になったときの繰り上がり
+    let carry = (max_lower >= modulo).then(|| self.fresh());
+    let lower = self.fresh_literals(max_lower.min(modulo - 1));
+    let upper_len = left_upper.len() + right_upper.len() + usize::from(carry.is_some());
+    let upper = self.fresh_literals(upper_len.min(limit));
+
+    for i in 0..=left_lower.len() {
+      for j in 0..=right_lower.len() {
+        if i + j == 0 {
+          continue;
+        }
+        let premise = premise(&left_lower, i, &right_lower, j);
+        if i + j < modulo {
+          let mut clause = premise;
+          clause.extend(carry);
+          clause.push(lower[i + j - 1]);
+          self.clauses.push(clause);
+          continue;
+        }
+        let mut clause = premise.clone();
+        clause.push(carry.unwrap());
+        self.clauses.push(clause);
+        if i + j > modulo {
+          let mut clause = premise;
+          clause.push(lower[i + j - modulo - 1]);
+          self.clauses.push(clause);
+        }
+      }
+    }
+
+    for i in 0..=left_upper.len() {
+      for j in 0..=right_upper.len() {
+        let premise = premise(&left_upper, i, &right_upper, j);
+        if i + j >= 1 && i + j <= upper.len() {
+          let mut clause = premise.clone();
+          clause.push(upper[i + j - 1]);
+          self.clauses.push(clause);
+        }
+        if let Some(carry) = carry {
+          if i + j < upper.len() {
+            let mut clause = premise;
+            clause.push(!carry);
+            clause.push(upper[i + j]);
+            self.clauses.push(clause);
+          }
+        }
+      }
+    }
+
+    (upper, lower)
+  }
+
+  fn sorting_network(&mut self, literals: &[Literal], k: usize) {
+    // 定数偽(None)で長さを2の冪にそろえる
+    let mut values = literals.iter().copied().map(Some).collect::<Vec<_>>();
+    values.resize(literals.len().next_power_of_two(), None);
+    self.sort(&mut values);
+    if let Some(literal) = values[k] {
+      self.clauses.push(vec![!literal]);
+    }
+  }
+
+  /// 真のものが前に来るように並べ替える(奇偶マージソート)
+  fn sort(&mut self, values: &mut [Option<Literal>]) {
+    if values.len() <= 1 {
+      return;
+    }
+    let half = values.len() / 2;
+    self.sort(&mut values[..half]);
+    self.sort(&mut values[half..]);
+    self.merge(values, 1);
+  }
+
+  /// 前半と後半がそれぞれ並んでいるとき，間隔stepの要素を併合する
+  fn merge(&mut self, values: &mut [Option<Literal>], step: usize) {
+    let double = 2 * step;
+    if double >= values.len() {
+      self.compare(values, 0, step);
+      return;
+    }
+    self.merge_strided(values, 0, double);
+    self.merge_strided(values, step, double);
+    let mut i = step;
+    while i + step < values.len() {
+      self.compare(values, i, i + step);
+      i += double;
+    }
+  }
+
+  /// offsetから間隔strideで取り出した部分列を併合する
+  fn merge_strided(&mut self, values: &mut [Option<Literal>], offset: usize, stride: usize) {
+    let mut strided = values[offset..]
+      .iter()
+      .step_by(stride)
+      .copied()
+      .collect::<Vec<_>>();
+    self.merge(&mut strided, 1);
+    for (k, value) in strided.into_iter().enumerate() {
+      values[offset + k * stride] = value;
+    }
+  }
+
+  /// values[i]を2つのうち大きい方(or)，values[j]を小さい方(and)にする
+  /// 真になる向きの含意だけを節にする
+  fn compare(&mut self, values: &mut [Option<Literal>], i: usize, j: usize) {
+    let (a, b) = match (values[i], values[j]) {
+      (Some(a), Some(b)) => (a, b),
+      (a, b) => {
+        values[i] = a.or(b);
+        values[j] = None;
+        return;
+      }
+    };
+    let (high, low) = (self.fresh(), self.fresh());
+    self.clauses.push(vec![!a, high]);
+    self.clauses.push(vec![!b, high]);
+    self.clauses.push(vec![!a, !b, low]);
+    values[i] = Some(high);
+    values[j] = Some(low);
+  }
+}
+
+/// 左の個数がi以上かつ右の個数がj以上であることの否定(1進数の表現で)
+fn premise(left: &[Literal], i: usize, right: &[Literal], j: usize) -> Vec<Literal> {
+  let mut clause = vec![];
+  if i > 0 {
+    clause.push(!left[i - 1]);
+  }
+  if j > 0 {
+    clause.push(!right[j - 1]);
+  }
+  clause
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing;
+
+  type Encode = fn(&[Literal], usize, CardinalityEncoding, &mut usize) -> Vec<Vec<Literal>>;
+
+  /// 全ての割り当てで，補助変数を含めて節を満たせることと個数の条件が一致する
+  fn check(encode: Encode, holds: fn(usize, usize) -> bool) {
+    for encoding in CardinalityEncoding::ALL {
+      for n in 1..=7 {
+        // 否定のリテラルも含める
+        let literals = (0..n)
+          .map(|variable| Literal::new(variable, variable % 3 != 1))
+          .collect::<Vec<_>>();
+        for k in 0..=n + 1 {
+          let mut next_variable = n;
+          let clauses = encode(&literals, k, encoding, &mut next_variable);
+          for assignment in 0..1 << n {
+            let count = literals
+              .iter()
+              .filter(|&&literal| testing::holds(assignment, literal))
+              .count();
+            assert_eq!(
+              testing::extendable(n, next_variable, &clauses, assignment),
+              holds(count, k),
+              "{} n={} k={} assignment={:b}",
+              encoding,
+              n,
+              k,
+              assignment
+            );
+          }
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn encodes_at_most_k() {
+    check(at_most_k, |count, k| count <= k);
+  }
+
+  #[test]
+  fn encodes_at_least_k() {
+    check(at_least_k, |count, k| count >= k);
+  }
+
+  #[test]
+  fn encodes_exactly_k() {
+    check(exactly_k, |count, k| count == k);
+  }
+
+  #[test]
+  fn parses_names() {
+    for encoding in CardinalityEncoding::ALL {
+      assert_eq!(
+        encoding.name().parse::<CardinalityEncoding>().unwrap(),
+        encoding
+      );
+    }
+    assert!("ladder".parse::<CardinalityEncoding>().is_err());
+  }
+
+  /// 節と補助変数の数が各方法の式どおりになる
+  #[test]
+  fn matches_known_sizes() {
+    let size = |n: usize, k: usize, encoding: CardinalityEncoding| {
+      let literals = (0..n)
+        .map(|variable| Literal::new(variable, true))
+        .collect::<Vec<_>>();
+      let mut next_variable = n;
+      let clauses = at_most_k(&literals, k, encoding, &mut next_variable);
+      (clauses.len(), next_variable - n)
+    };
+    for (n, k) in [(6, 1), (12, 1), (12, 3), (30, 5)] {
+      // 組み合わせの数 C(n, k+1)
+      let combinations = (0..=k).fold(1, |acc, i| acc * (n - i) / (i + 1));
+      assert_eq!(size(n, k, CardinalityEncoding::Pairwise), (combinations, 0));
+      // Sinzの逐次カウンタは2nk+n-3k-1個の節と(n-1)k個の補助変数
+      let sequential = size(n, k, CardinalityEncoding::Sequential);
+      assert_eq!(sequential, (2 * n * k + n - 3 * k - 1, (n - 1) * k));
+      if k > 1 {
+        for encoding in [
+          CardinalityEncoding::Commander,
+          CardinalityEncoding::Product,
+          CardinalityEncoding::Bimander,
+        ] {
+          assert_eq!(size(n, k, encoding), sequential, "{}", encoding);
+        }
+      }
+    }
+    // Bimanderの補助変数は2個ずつのグループの番号のビット数
+    assert_eq!(size(12, 1, CardinalityEncoding::Bimander).1, 3);
+    assert_eq!(size(30, 1, CardinalityEncoding::Bimander).1, 4);
+    // kが大きいと部分和を数える方が節が少ない
+    let totalizer = size(30, 5, CardinalityEncoding::Totalizer).0;
+    assert!(totalizer < size(30, 5, CardinalityEncoding::Sequential).0);
+    assert!(size(30, 5, CardinalityEncoding::ModuloTotalizer).0 < totalizer);
+  }
+
+  #[test]
+  fn reports_unknown_names() {
+    let error = "ladder".parse::<CardinalityEncoding>().unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "unknown encoding: ladder (expected one of pairwise, sequential, commander, product, \
+       bimander, totalizer, modulo-totalizer, sorting-network, native)"
+    );
+  }
+}
@@ -0,0 +1,611 @@
+use std::{collections::HashMap, error::Error, fmt, mem, ops::Not, str::FromStr};
+
+use crate::expressions::{formula::Formula, literal::Literal};
+
+/// 論理式を節に変換する方法
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FormulaEncoding {
+  /// 部分式ごとの補助変数と部分式の値が等しくなる節を加える
+  Tseitin,
+  /// 部分式が現れる極性に必要な向きの含意だけを加える(Plaisted-Greenbaum)
+  /// 補助変数は部分式の値で決まらないので，補助変数を含めた解の数は変わる
+  #[default]
+  PlaistedGreenbaum,
+}
+
+impl FormulaEncoding {
+  pub const ALL: [FormulaEncoding; 2] =
+    [FormulaEncoding::Tseitin, FormulaEncoding::PlaistedGreenbaum];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      FormulaEncoding::Tseitin => "tseitin",
+      FormulaEncoding::PlaistedGreenbaum => "plaisted-greenbaum",
+    }
+  }
+}
+
+impl fmt::Display for FormulaEncoding {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl FromStr for FormulaEncoding {
+  type Err = UnknownFormulaEncodingError;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    FormulaEncoding::ALL
+      .into_iter()
+      .find(|encoding| encoding.name() == name)
+      .ok_or_else(|| UnknownFormulaEncodingError(name.to_string()))
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct UnknownFormulaEncodingError(pub String);
+
+impl fmt::Display for UnknownFormulaEncodingError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let names = FormulaEncoding::ALL
+      .map(|encoding| encoding.name())
+      .join(", ");
+    write!(
+      f,
+      "unknown encoding: {} (expected one of {})",
+      self.0, names
+    )
+  }
+}
+
+impl Error for UnknownFormulaEncodingError {}
+
+/// 部分式の値を表すリテラル(定数に簡約できたものはそのまま持つ)
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Node {
+  Constant(bool),
+  Literal(Literal),
+}
+
+impl Not for Node {
+  type Output = Node;
+  fn not(self) -> Self::Output {
+    match self {
+      Node::Constant(constant) => Node::Constant(!constant),
+      Node::Literal(literal) => Node::Literal(!literal),
+    }
+  }
+}
+
+/// 正規化した論理ゲート(OrとImpliesはAnd，IffはXorの否定として表す)
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Gate {
+  /// 整列して重複を除いた2つ以上のリテラル
+  And(Vec<Literal>),
+  /// 正のリテラル2つ(小さい順)
+  Xor(Literal, Literal),
+  /// 条件・真の場合とも正のリテラル
+  Ite(Literal, Literal, Literal),
+}
+
+/// 部分式が真・偽のどちらの値で使われるか
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+struct Polarity {
+  /// 部分式が真であることを使う(出力から部分式への含意が必要)
+  positive: bool,
+  /// 部分式が偽であることを使う(部分式から出力への含意が必要)
+  negative: bool,
+}
+
+impl Polarity {
+  const POSITIVE: Polarity = Polarity {
+    positive: true,
+    negative: false,
+  };
+  const BOTH: Polarity = Polarity {
+    positive: true,
+    negative: true,
+  };
+
+  fn flip(self) -> Self {
+    Polarity {
+      positive: self.negative,
+      negative: self.positive,
+    }
+  }
+
+  fn union(self, other: Polarity) -> Self {
+    Polarity {
+      positive: self.positive || other.positive,
+      negative: self.negative || other.negative,
+    }
+  }
+}
+
+/// 論理式を節に変換する
+/// 同じ部分式(正規化して同じゲートになるもの)には同じ補助変数を使う
+/// ゲートは変換をまたいで覚えておくので，同じSATSolverに加える論理式の間でも共有する
+#[derive(Clone, Default)]
+pub struct FormulaEncoder {
+  encoding: FormulaEncoding,
+  /// ゲートの出力リテラルと，節を加え済みの極性
+  gates: HashMap<Gate, (Literal, Polarity)>,
+  /// 変換中に加える節と補助変数の次の番号
+  clauses: Vec<Vec<Literal>>,
+  next_variable: usize,
+}
+
+impl FormulaEncoder {
+  pub fn new(encoding: FormulaEncoding) -> Self {
+    Self {
+      encoding,
+      ..Self::default()
+    }
+  }
+
+  pub fn encoding(&self) -> FormulaEncoding {
+    self.encoding
+  }
+
+  /// 以後の変換の方法を変える(加え済みのゲートはそのまま使い，足りない向きの節を加える)
+  pub fn set_encoding(&mut self, encoding: FormulaEncoding) {
+    self.encoding = encoding;
+  }
+
+  /// formulaが真になる制約を節に変換する
+  /// 補助変数にはnext_variableから順に番号を振る
+  pub fn encode(&mut self, formula: &Formula, next_variable: &mut usize) -> Vec<Vec<Literal>> {
+    self.next_variable = *next_variable;
+    self.assert(formula, true);
+    *next_variable = self.next_variable;
+    mem::take(&mut self.clauses)
+  }
+
+  /// formulaの値がsignになる節を加える
+  /// 一番外側のAnd・Orには補助変数を使わず，節を分けるか1つの節にする
+  fn assert(&mut self, formula: &Formula, sign: bool) {
+    let polarity = if sign {
+      Polarity::POSITIVE
+    } else {
+      Polarity::POSITIVE.flip()
+    };
+    match formula {
+      Formula::Not(formula) => self.assert(formula, !sign),
+      Formula::And(formulas) if sign => {
+        for formula in formulas {
+          self.assert(formula, true);
+        }
+      }
+      Formula::Or(formulas) if !sign => {
+        for formula in formulas {
+          self.assert(formula, false);
+        }
+      }
+      Formula::Implies(lhs, rhs) if !sign => {
+        self.assert(lhs, true);
+        self.assert(rhs, false);
+      }
+      Formula::Or(formulas) | Formula::And(formulas) => {
+        let nodes = formulas
+          .iter()
+          .map(|formula| self.node(formula, polarity))
+          .collect::<Vec<_>>();
+        self.add_clause(
+          nodes
+            .into_iter()
+            .map(|node| if sign { node } else { !node }),
+        );
+      }
+      Formula::Implies(lhs, rhs) => {
+        let lhs = self.node(lhs, polarity.flip());
+        let rhs = self.node(rhs, polarity);
+        self.add_clause([!lhs, rhs]);
+      }
+      formula => {
+        let node = self.node(formula, polarity);
+        self.add_clause([if sign { node } else { !node }]);
+      }
+    }
+  }
+
+  /// nodesのいずれかが真になる節を加える
+  fn add_clause(&mut self, nodes: impl IntoIterator<Item = Node>) {
+    let mut clause = vec![];
+    for node in nodes {
+      match node {
+        Node::Constant(true) => return,
+        Node::Constant(false) => {}
+        Node::Literal(literal) => clause.push(literal),
+      }
+    }
+    self.clauses.push(clause);
+  }
+
+  /// formulaの値を表すリテラルを作る
+  /// polarityはformulaの値がどちらの向きで使われるか(Tseitin変換では常に両方)
+  fn node(&mut self, formula: &Formula, polarity: Polarity) -> Node {
+    let polarity = match self.encoding {
+      FormulaEncoding::Tseitin => Polarity::BOTH,
+      FormulaEncoding::PlaistedGreenbaum => polarity,
+    };
+    match formula {
+      Formula::Constant(constant) => Node::Constant(*constant),
+      Formula::Variable(variable) => Node::Literal(variable.literal()),
+      Formula::Not(formula) => !self.node(formula, polarity.flip()),
+      Formula::And(formulas) => {
+        let nodes = formulas
+          .iter()
+          .map(|formula| self.node(formula, polarity))
+          .collect();
+        self.and(nodes, polarity)
+      }
+      // a | b = !(!a & !b)
+      Formula::Or(formulas) => {
+        let nodes = formulas
+          .iter()
+          .map(|formula| !self.node(formula, polarity))
+          .collect();
+        !self.and(nodes, polarity.flip())
+      }
+      // a -> b = !(a & !b)
+      Formula::Implies(lhs, rhs) => {
+        let lhs = self.node(lhs, polarity.flip());
+        let rhs = self.node(rhs, polarity);
+        !self.and(vec![lhs, !rhs], polarity.flip())
+      }
+      // a <-> b = !(a ^ b)
+      Formula::Iff(lhs, rhs) => {
+        let lhs = self.node(lhs, Polarity::BOTH);
+        let rhs = self.node(rhs, Polarity::BOTH);
+        !self.xor(lhs, rhs, polarity.flip())
+      }
+      Formula::Xor(lhs, rhs) => {
+        let lhs = self.node(lhs, Polarity::BOTH);
+        let rhs = self.node(rhs, Polarity::BOTH);
+        self.xor(lhs, rhs, polarity)
+      }
+      Formula::Ite(condition, then, otherwise) => {
+        let condition = self.node(condition, Polarity::BOTH);
+        let then = self.node(then, polarity);
+        let otherwise = self.node(otherwise, polarity);
+        self.ite(condition, then, otherwise, polarity)
+      }
+    }
+  }
+
+  fn and(&mut self, nodes: Vec<Node>, polarity: Polarity) -> Node {
+    let mut literals = vec![];
+    for node in nodes {
+      match node {
+        Node::Constant(false) => return Node::Constant(false),
+        Node::Constant(true) => {}
+        Node::Literal(literal) => literals.push(literal),
+      }
+    }
+    literals.sort();
+    literals.dedup();
+    // 整列すると同じ変数の正負のリテラルは隣り合う
+    if literals.windows(2).any(|pair| pair[0] == !pair[1]) {
+      return Node::Constant(false);
+    }
+    match literals[..] {
+      [] => Node::Constant(true),
+      [literal] => Node::Literal(literal),
+      _ => self.gate(Gate::And(literals), polarity),
+    }
+  }
+
+  fn xor(&mut self, lhs: Node, rhs: Node, polarity: Polarity) -> Node {
+    let (lhs, rhs) = match (lhs, rhs) {
+      (Node::Constant(constant), node) | (node, Node::Constant(constant)) => {
+        return if constant { !node } else { node };
+      }
+      (Node::Literal(lhs), Node::Literal(rhs)) => (lhs, rhs),
+    };
+    // 負のリテラルは出力の否定として外に出す
+    let negated = lhs.sign() != rhs.sign();
+    let (lhs, rhs) = (positive(lhs), positive(rhs));
+    let node = if lhs == rhs {
+      Node::Constant(false)
+    } else {
+      let polarity = if negated { polarity.flip() } else { polarity };
+      self.gate(Gate::Xor(lhs.min(rhs), lhs.max(rhs)), polarity)
+    };
+    if negated {
+      !node
+    } else {
+      node
+    }
+  }
+
+  fn ite(&mut self, condition: Node, then: Node, otherwise: Node, polarity: Polarity) -> Node {
+    let condition = match condition {
+      Node::Constant(true) => return then,
+      Node::Constant(false) => return otherwise,
+      Node::Literal(literal) => literal,
+    };
+    // 条件を正のリテラルにする
+    let (condition, then, otherwise) = if condition.sign() {
+      (condition, then, otherwise)
+    } else {
+      (!condition, otherwise, then)
+    };
+    let (then, otherwise) = match (then, otherwise) {
+      (Node::Constant(true), otherwise) => {
+        return !self.and(vec![Node::Literal(!condition), !otherwise], polarity.flip());
+      }
+      (Node::Constant(false), otherwise) => {
+        return self.and(vec![Node::Literal(!condition), otherwise], polarity);
+      }
+      (then, Node::Constant(true)) => {
+        return !self.and(vec![Node::Literal(condition), !then], polarity.flip());
+      }
+      (then, Node::Constant(false)) => {
+        return self.and(vec![Node::Literal(condition), then], polarity);
+      }
+      (Node::Literal(then), Node::Literal(otherwise)) => (then, otherwise),
+    };
+    if then == otherwise {
+      return Node::Literal(then);
+    }
+    // c ? t : !t = !(c ^ t)
+    if then == !otherwise {
+      return !self.xor(
+        Node::Literal(condition),
+        Node::Literal(then),
+        polarity.flip(),
+      );
+    }
+    // 真の場合を正のリテラルにして，負なら出力の否定として外に出す
+    if then.sign() {
+      self.gate(Gate::Ite(condition, then, otherwise), polarity)
+    } else {
+      !self.gate(Gate::Ite(condition, !then, !otherwise), polarity.flip())
+    }
+  }
+
+  /// gateの出力リテラルを返す
+  /// 同じゲートがあればそれを使い，まだ加えていない極性の節だけを加える
+  fn gate(&mut self, gate: Gate, polarity: Polarity) -> Node {
+    let (output, added) = match self.gates.get(&gate) {
+      Some(&(output, added)) => (output, added),
+      None => {
+        let output = Literal::new(self.next_variable, true);
+        self.next_variable += 1;
+        (output, Polarity::default())
+      }
+    };
+    if polarity.positive && !added.positive {
+      self.clauses.extend(gate_clauses(&gate, output));
+    }
+    if polarity.negative && !added.negative {
+      self.clauses.extend(gate_clauses(&gate, !output));
+    }
+    self.gates.insert(gate, (output, added.union(polarity)));
+    Node::Literal(output)
+  }
+}
+
+fn positive(literal: Literal) -> Literal {
+  if literal.sign() {
+    literal
+  } else {
+    !literal
+  }
+}
+
+/// outputが真ならgateの値がoutputの符号と一致することを表す節
+/// (outputに出力リテラルを渡すと出力からゲートへの含意，否定を渡すと逆向きの含意になる)
+fn gate_clauses(gate: &Gate, output: Literal) -> Vec<Vec<Literal>> {
+  // 出力の符号に合わせて入力の値を読み替える
+  let sign = output.sign();
+  match *gate {
+    Gate::And(ref literals) => {
+      if sign {
+        literals
+          .iter()
+          .map(|&literal| vec![!output, literal])
+          .collect()
+      } else {
+        let mut clause = vec![!output];
+        clause.extend(literals.iter().map(|&literal| !literal));
+        vec![clause]
+      }
+    }
+    Gate::Xor(lhs, rhs) => {
+      let rhs = if sign { rhs } else { !rhs };
+      vec![vec![!output, lhs, rhs], vec![!output, !lhs, !rhs]]
+    }
+    Gate::Ite(condition, then, otherwise) => {
+      let (then, otherwise) = if sign {
+        (then, otherwise)
+      } else {
+        (!then, !otherwise)
+      };
+      vec![
+        vec![!output, !condition, then],
+        vec![!output, condition, otherwise],
+      ]
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    random::Random,
+    sat_solver::{SATSolver, Variable},
+    testing,
+  };
+
+  /// 深さdepthまでのランダムな論理式(定数や同じ部分式も含める)
+  fn random_formula(random: &mut Random, variables: &[Variable], depth: usize) -> Formula {
+    if depth == 0 || random.below(4) == 0 {
+      return match random.below(8) {
+        0 => Formula::Constant(random.below(2) == 0),
+        _ => Formula::Variable(variables[random.below(variables.len())]),
+      };
+    }
+    let child = |random: &mut Random| random_formula(random, variables, depth - 1);
+    match random.below(7) {
+      0 => !child(random),
+      1 => Formula::And((0..random.below(4)).map(|_| child(random)).collect()),
+      2 => Formula::Or((0..random.below(4)).map(|_| child(random)).collect()),
+      3 => child(random).implies(child(random)),
+      4 => child(random).iff(child(random)),
+      5 => Formula::Xor(Box::new(child(random)), Box::new(child(random))),
+      _ => Formula::ite(child(random), child(random), child(random)),
+    }
+  }
+
+  fn variables(num_variables: usize) -> Vec<Variable> {
+    let mut solver = SATSolver::new();
+    (0..num_variables).map(|i| solver.variable(i)).collect()
+  }
+
+  fn value(assignment: u64) -> impl Fn(Variable) -> bool {
+    move |variable| testing::holds(assignment, variable.literal())
+  }
+
+  #[test]
+  fn encodes_formulas() {
+    let mut random = Random::new(12);
+    let num_variables = 5;
+    let variables = variables(num_variables);
+    for encoding in FormulaEncoding::ALL {
+      for _ in 0..150 {
+        let formula = random_formula(&mut random, &variables, 4);
+        let mut next_variable = num_variables;
+        let clauses = FormulaEncoder::new(encoding).encode(&formula, &mut next_variable);
+        for assignment in 0..1 << num_variables {
+          assert_eq!(
+            testing::extendable(num_variables, next_variable, &clauses, assignment),
+            formula.evaluate(&value(assignment)),
+            "{} {:?} {:b}",
+            encoding,
+            formula,
+            assignment
+          );
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn tseitin_defines_auxiliary_variables() {
+    // 補助変数は入力の値で決まるので，補助変数を含めた解の数は論理式の解の数に等しい
+    let mut random = Random::new(13);
+    let num_variables = 4;
+    let variables = variables(num_variables);
+    let mut checked = 0;
+    for _ in 0..100 {
+      let formula = random_formula(&mut random, &variables, 3);
+      let mut next_variable = num_variables;
+      let clauses =
+        FormulaEncoder::new(FormulaEncoding::Tseitin).encode(&formula, &mut next_variable);
+      if next_variable > 16 {
+        continue;
+      }
+      let expected = (0..1 << num_variables)
+        .filter(|&assignment| formula.evaluate(&value(assignment)))
+        .count();
+      assert_eq!(
+        testing::models(next_variable, &clauses).len(),
+        expected,
+        "{:?}",
+        formula
+      );
+      checked += 1;
+    }
+    assert!(checked > 50);
+  }
+
+  #[test]
+  fn plaisted_greenbaum_encodes_used_polarities_only() {
+    let [a, b, c, d, e] = variables(5)[..] else {
+      unreachable!()
+    };
+    let size = |formula: &Formula, encoding: FormulaEncoding| {
+      let mut next_variable = 5;
+      let clauses = FormulaEncoder::new(encoding).encode(formula, &mut next_variable);
+      (clauses, next_variable)
+    };
+    // (a ∧ b) ∨ (c ∧ d) ではAndは真の向きだけ，否定してeとのOrにすると偽の向きだけで使われる
+    let formula = (a & b) | (c & d);
+    for formula in [formula.clone(), !formula | e] {
+      let (tseitin, tseitin_variables) = size(&formula, FormulaEncoding::Tseitin);
+      let (pg, pg_variables) = size(&formula, FormulaEncoding::PlaistedGreenbaum);
+      assert_eq!(pg_variables, tseitin_variables);
+      assert!(pg.len() < tseitin.len(), "{:?}", formula);
+      // 片方の向きの含意しかないので，補助変数の値は入力で決まらない
+      let expected = (0..1 << 5)
+        .filter(|&assignment| formula.evaluate(&value(assignment)))
+        .count();
+      assert_eq!(testing::models(tseitin_variables, &tseitin).len(), expected);
+      assert!(testing::models(pg_variables, &pg).len() > expected);
+    }
+  }
+
+  #[test]
+  fn reuses_gates_of_equal_subformulas() {
+    // 順序や重複の違うAndは同じゲートになり，否定からの含意はOrになる
+    let [a, b, c, d] = variables(4)[..] else {
+      unreachable!()
+    };
+    let formula = Formula::and([(a & b & a) | c, (!(b & a)).implies(c)]);
+    let mut encoder = FormulaEncoder::new(FormulaEncoding::Tseitin);
+    let mut next_variable = 4;
+    encoder.encode(&formula, &mut next_variable);
+    assert_eq!(next_variable, 5);
+    // 次の変換でもAndのゲートを使い，Xorのゲートだけを加える
+    encoder.encode(&(d | ((b & a) ^ c)), &mut next_variable);
+    assert_eq!(next_variable, 6);
+  }
+
+  #[test]
+  fn shares_gates_between_formulas() {
+    // 変換をまたいで同じゲートを使い，途中で変換方法を変えても足りない向きの節を加える
+    let mut random = Random::new(14);
+    let num_variables = 5;
+    let variables = variables(num_variables);
+    for _ in 0..100 {
+      let formula = random_formula(&mut random, &variables, 3);
+      let other = random_formula(&mut random, &variables, 3);
+      let mut encoder = FormulaEncoder::new(FormulaEncoding::PlaistedGreenbaum);
+      let mut next_variable = num_variables;
+      let mut clauses = encoder.encode(&formula, &mut next_variable);
+      encoder.set_encoding(FormulaEncoding::Tseitin);
+      assert_eq!(encoder.encoding(), FormulaEncoding::Tseitin);
+      let negated = !Formula::and([formula.clone(), other.clone()]);
+      clauses.extend(encoder.encode(&negated, &mut next_variable));
+      for assignment in 0..1 << num_variables {
+        let value = value(assignment);
+        assert_eq!(
+          testing::extendable(num_variables, next_variable, &clauses, assignment),
+          formula.evaluate(&value) && !other.evaluate(&value),
+          "{:?} {:?} {:b}",
+          formula,
+          other,
+          assignment
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn parses_names() {
+    for encoding in FormulaEncoding::ALL {
+      assert_eq!(
+        encoding.name().parse::<FormulaEncoding>().unwrap(),
+        encoding
+      );
+    }
+    let error = "cnf".parse::<FormulaEncoding>().unwrap_err();
+    assert_eq!(error.0, "cnf");
+    assert_eq!(
+      error.to_string(),
+      "unknown encoding: cnf (expected one of tseitin, plaisted-greenbaum)"
+    );
+  }
+}
@@ -0,0 +1,3 @@
+pub mod cardinality;
+pub mod formula;
+pub mod xor;
@@ -0,0 +1,117 @@
+use crate::expressions::literal::Literal;
+
+/// 1つの部分に含める変数の数(補助変数を含む)
+const CUT_SIZE: usize = 4;
+
+/// literalsの排他的論理和がrhsになる制約を節に変換する
+/// 長い制約は補助変数で最大CUT_SIZE個ずつの部分に分け，各部分を2^(k-1)個の節で表す
+/// 補助変数にはnext_variableから順に番号を振る
+pub fn encode_xor(literals: &[Literal], rhs: bool, next_variable: &mut usize) -> Vec<Vec<Literal>> {
+  let (mut variables, rhs) = normalize(literals, rhs);
+  if variables.is_empty() {
+    return if rhs { vec![vec![]] } else { vec![] };
+  }
+
+  let mut clauses = vec![];
+  // 先頭のCUT_SIZE-1個の和を補助変数に置き換えることを繰り返す
+  while variables.len() > CUT_SIZE {
+    let auxiliary = *next_variable;
+    *next_variable += 1;
+    let mut part = variables.drain(..CUT_SIZE - 1).collect::<Vec<_>>();
+    part.push(auxiliary);
+    clauses.extend(encode_part(&part, false));
+    variables.insert(0, auxiliary);
+  }
+  clauses.extend(encode_part(&variables, rhs));
+  clauses
+}
+
+/// encode_xorが使う補助変数の数
+pub fn auxiliary_variables(len: usize) -> usize {
+  len.saturating_sub(CUT_SIZE).div_ceil(CUT_SIZE - 2)
+}
+
+/// 負のリテラルを右辺に移し，同じ変数の組を打ち消して変数の和にする
+pub fn normalize(literals: &[Literal], rhs: bool) -> (Vec<usize>, bool) {
+  let mut rhs = rhs;
+  let mut variables = literals
+    .iter()
+    .map(|literal| {
+      rhs ^= !literal.sign();
+      literal.variable()
+    })
+    .collect::<Vec<_>>();
+  variables.sort_unstable();
+
+  let mut reduced: Vec<usize> = vec![];
+  for variable in variables {
+    if reduced.last() == Some(&variable) {
+      reduced.pop();
+    } else {
+      reduced.push(variable);
+    }
+  }
+  (reduced, rhs)
+}
+
+/// 和の偶奇がrhsと異なる割り当てを1つずつ禁止する
+fn encode_part(variables: &[usize], rhs: bool) -> Vec<Vec<Literal>> {
+  (0..1usize << variables.len())
+    .filter(|assignment| (assignment.count_ones() % 2 == 1) != rhs)
+    .map(|assignment| {
+      variables
+        .iter()
+        .enumerate()
+        .map(|(k, &variable)| Literal::new(variable, assignment >> k & 1 == 0))
+        .collect()
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{random::Random, testing};
+
+  #[test]
+  fn encodes_parity() {
+    let mut random = Random::new(10);
+    let num_variables = 8;
+    for _ in 0..60 {
+      // 同じ変数や否定のリテラルも含める
+      let literals = (0..random.below(13))
+        .map(|_| Literal::new(random.below(num_variables), random.below(2) == 0))
+        .collect::<Vec<_>>();
+      let rhs = random.below(2) == 0;
+      let mut next_variable = num_variables;
+      let clauses = encode_xor(&literals, rhs, &mut next_variable);
+      assert!(next_variable - num_variables <= auxiliary_variables(literals.len()));
+      for assignment in 0..1 << num_variables {
+        let parity = literals
+          .iter()
+          .filter(|&&literal| testing::holds(assignment, literal))
+          .count()
+          % 2
+          == 1;
+        assert_eq!(
+          testing::extendable(num_variables, next_variable, &clauses, assignment),
+          parity == rhs,
+          "{:?} {}",
+          literals,
+          rhs
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn normalizes_negations_and_duplicates() {
+    let x = |variable: usize, sign: bool| Literal::new(variable, sign);
+    assert_eq!(
+      normalize(&[x(2, true), x(0, false)], true),
+      (vec![0, 2], false)
+    );
+    assert_eq!(normalize(&[x(1, true), x(1, false)], false), (vec![], true));
+    assert_eq!(normalize(&[], true), (vec![], true));
+  }
+}
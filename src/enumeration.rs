@@ -0,0 +1,196 @@
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{
+  cdcl::solver::{Cdcl, SolveResult},
+  expressions::literal::Literal,
+  statistics::Statistics,
+};
+
+/// 解を1つずつ求めて返すイテレータ(AllSAT)
+/// 見つけた解の射影の否定を節(blocking clause)として加えて次の解を探すので，
+/// 射影した変数の値が同じ解は2度返さない
+pub struct Models<T> {
+  solver: Cdcl,
+  /// 射影先の変数の番号と名前
+  projection: Vec<(usize, T)>,
+  done: bool,
+  complete: bool,
+}
+
+impl<T: Clone + Eq + Hash> Models<T> {
+  /// solverには元の問題の節を全て追加しておく
+  pub(crate) fn new(solver: Cdcl, projection: Vec<(usize, T)>) -> Self {
+    Self {
+      solver,
+      projection,
+      done: false,
+      complete: false,
+    }
+  }
+
+  /// 全ての解を返し終えたか
+  /// 探索の上限に達して途中で終わった場合はfalse
+  pub fn is_complete(&self) -> bool {
+    self.complete
+  }
+
+  pub fn statistics(&self) -> &Statistics {
+    self.solver.statistics()
+  }
+}
+
+impl<T: Clone + Eq + Hash> Iterator for Models<T> {
+  type Item = HashMap<T, bool>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done {
+      return None;
+    }
+
+    let model = match self.solver.solve() {
+      SolveResult::Satisfiable(model) => model,
+      SolveResult::Unsatisfiable => {
+        self.done = true;
+        self.complete = true;
+        return None;
+      }
+      SolveResult::Unknown => {
+        self.done = true;
+        return None;
+      }
+    };
+
+    let values = self
+      .projection
+      .iter()
+      .map(|&(id, _)| model.sign(id).unwrap_or(false))
+      .collect::<Vec<_>>();
+    let blocking = self
+      .projection
+      .iter()
+      .zip(&values)
+      .map(|(&(id, _), &value)| Literal::new(id, !value))
+      .collect::<Vec<_>>();
+    if !self.solver.add_clause(&blocking) {
+      // 射影先の変数の値の組み合わせが尽きた
+      self.done = true;
+      self.complete = true;
+    }
+
+    Some(
+      self
+        .projection
+        .iter()
+        .zip(values)
+        .map(|((_, name), value)| (name.clone(), value))
+        .collect(),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::BTreeSet;
+
+  use super::*;
+  use crate::{
+    cdcl::options::Limits,
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  /// projectionの変数の解を総当たりで求めた解と比べる
+  fn check(num_variables: usize, clauses: &[Vec<Literal>], projection: &[usize]) {
+    let mut solver = Cdcl::new(num_variables);
+    for clause in clauses {
+      solver.add_clause(clause);
+    }
+    let projection = projection
+      .iter()
+      .map(|&variable| (variable, variable))
+      .collect::<Vec<_>>();
+    let mut models = Models::new(solver, projection.clone());
+    let mut found = vec![];
+    for model in models.by_ref() {
+      assert_eq!(model.len(), projection.len());
+      let assignment = model
+        .iter()
+        .filter(|&(_, &value)| value)
+        .fold(0, |assignment, (&variable, _)| assignment | 1 << variable);
+      found.push(assignment);
+    }
+    assert!(models.is_complete());
+
+    let mask = projection
+      .iter()
+      .fold(0u64, |mask, &(variable, _)| mask | 1 << variable);
+    let expected = testing::models(num_variables, clauses)
+      .into_iter()
+      .map(|assignment| assignment & mask)
+      .collect::<BTreeSet<_>>();
+    // 同じ解は2度返さない
+    assert_eq!(found.len(), expected.len());
+    assert_eq!(found.into_iter().collect::<BTreeSet<_>>(), expected);
+  }
+
+  #[test]
+  fn enumerates_all_models() {
+    let mut random = Random::new(4);
+    for _ in 0..40 {
+      let num_variables = 4 + random.below(6);
+      let num_clauses = random.below(3 * num_variables);
+      let clauses = random_clauses(&mut random, num_variables, num_clauses, 3);
+      check(
+        num_variables,
+        &clauses,
+        &(0..num_variables).collect::<Vec<_>>(),
+      );
+    }
+  }
+
+  #[test]
+  fn enumerates_projected_models() {
+    let mut random = Random::new(5);
+    for _ in 0..40 {
+      let num_variables = 4 + random.below(6);
+      let num_clauses = random.below(3 * num_variables);
+      let clauses = random_clauses(&mut random, num_variables, num_clauses, 3);
+      let projection = (0..num_variables)
+        .filter(|_| random.below(2) == 0)
+        .collect::<Vec<_>>();
+      check(num_variables, &clauses, &projection);
+    }
+  }
+
+  #[test]
+  fn stops_at_the_search_limit() {
+    // 7羽を6つの巣に入れる鳩の巣原理は衝突1回では充足不能と分からない
+    let (pigeons, holes) = (7, 6);
+    let variable = |pigeon: usize, hole: usize| pigeon * holes + hole;
+    let mut solver = Cdcl::new(pigeons * holes);
+    solver.set_limits(Limits {
+      conflicts: Some(1),
+      ..Limits::default()
+    });
+    for pigeon in 0..pigeons {
+      let clause = (0..holes)
+        .map(|hole| Literal::new(variable(pigeon, hole), true))
+        .collect::<Vec<_>>();
+      solver.add_clause(&clause);
+    }
+    for hole in 0..holes {
+      for first in 0..pigeons {
+        for second in first + 1..pigeons {
+          solver.add_clause(&[
+            Literal::new(variable(first, hole), false),
+            Literal::new(variable(second, hole), false),
+          ]);
+        }
+      }
+    }
+    let mut models = Models::new(solver, vec![(0, 0)]);
+    assert_eq!(models.next(), None);
+    assert!(!models.is_complete());
+    assert_eq!(models.next(), None);
+  }
+}
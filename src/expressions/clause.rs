@@ -46,6 +46,14 @@ impl Clause {
     self.literals.len() == 1
   }
 
+  pub fn literals(&self) -> &[Literal] {
+    &self.literals
+  }
+
+  pub fn iter(&self) -> std::slice::Iter<'_, Literal> {
+    self.literals.iter()
+  }
+
   pub fn assign(&mut self, variable: usize, sign: bool) {
     if self.has_deleted || !self.is_valid {
       return;
@@ -76,6 +84,12 @@ impl Clause {
   }
 }
 
+impl Default for Clause {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl From<&Clause> for Clause {
   fn from(clause: &Clause) -> Self {
     clause.clone()
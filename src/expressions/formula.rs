@@ -0,0 +1,204 @@
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+use crate::sat_solver::Variable;
+
+/// 変数を論理演算で組み合わせた命題論理式
+/// SATSolver::assertで節に変換して加える
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Formula {
+  Constant(bool),
+  Variable(Variable),
+  Not(Box<Formula>),
+  /// 空なら真
+  And(Vec<Formula>),
+  /// 空なら偽
+  Or(Vec<Formula>),
+  Implies(Box<Formula>, Box<Formula>),
+  Iff(Box<Formula>, Box<Formula>),
+  Xor(Box<Formula>, Box<Formula>),
+  /// 1つ目が真なら2つ目，偽なら3つ目の値になる
+  Ite(Box<Formula>, Box<Formula>, Box<Formula>),
+}
+
+impl Formula {
+  pub fn and(formulas: impl IntoIterator<Item = impl Into<Formula>>) -> Self {
+    Formula::And(formulas.into_iter().map(Into::into).collect())
+  }
+
+  pub fn or(formulas: impl IntoIterator<Item = impl Into<Formula>>) -> Self {
+    Formula::Or(formulas.into_iter().map(Into::into).collect())
+  }
+
+  pub fn implies(self, other: impl Into<Formula>) -> Self {
+    Formula::Implies(Box::new(self), Box::new(other.into()))
+  }
+
+  pub fn iff(self, other: impl Into<Formula>) -> Self {
+    Formula::Iff(Box::new(self), Box::new(other.into()))
+  }
+
+  pub fn ite(
+    condition: impl Into<Formula>,
+    then: impl Into<Formula>,
+    otherwise: impl Into<Formula>,
+  ) -> Self {
+    Formula::Ite(
+      Box::new(condition.into()),
+      Box::new(then.into()),
+      Box::new(otherwise.into()),
+    )
+  }
+
+  /// 各変数の値をvalueで与えたときの値
+  pub fn evaluate(&self, value: &impl Fn(Variable) -> bool) -> bool {
+    match self {
+      Formula::Constant(constant) => *constant,
+      Formula::Variable(variable) => value(*variable),
+      Formula::Not(formula) => !formula.evaluate(value),
+      Formula::And(formulas) => formulas.iter().all(|formula| formula.evaluate(value)),
+      Formula::Or(formulas) => formulas.iter().any(|formula| formula.evaluate(value)),
+      Formula::Implies(lhs, rhs) => !lhs.evaluate(value) || rhs.evaluate(value),
+      Formula::Iff(lhs, rhs) => lhs.evaluate(value) == rhs.evaluate(value),
+      Formula::Xor(lhs, rhs) => lhs.evaluate(value) != rhs.evaluate(value),
+      Formula::Ite(condition, then, otherwise) => {
+        if condition.evaluate(value) {
+          then.evaluate(value)
+        } else {
+          otherwise.evaluate(value)
+        }
+      }
+    }
+  }
+}
+
+impl Variable {
+  pub fn implies(self, other: impl Into<Formula>) -> Formula {
+    Formula::from(self).implies(other)
+  }
+
+  pub fn iff(self, other: impl Into<Formula>) -> Formula {
+    Formula::from(self).iff(other)
+  }
+}
+
+impl From<Variable> for Formula {
+  fn from(variable: Variable) -> Self {
+    Formula::Variable(variable)
+  }
+}
+
+impl From<bool> for Formula {
+  fn from(constant: bool) -> Self {
+    Formula::Constant(constant)
+  }
+}
+
+impl Not for Formula {
+  type Output = Formula;
+  fn not(self) -> Self::Output {
+    match self {
+      Formula::Not(formula) => *formula,
+      formula => Formula::Not(Box::new(formula)),
+    }
+  }
+}
+
+/// a & b & cが1つのAndになるように左辺のAndに右辺を追加する
+impl<T: Into<Formula>> BitAnd<T> for Formula {
+  type Output = Formula;
+  fn bitand(self, rhs: T) -> Self::Output {
+    match self {
+      Formula::And(mut formulas) => {
+        formulas.push(rhs.into());
+        Formula::And(formulas)
+      }
+      formula => Formula::And(vec![formula, rhs.into()]),
+    }
+  }
+}
+
+impl<T: Into<Formula>> BitOr<T> for Formula {
+  type Output = Formula;
+  fn bitor(self, rhs: T) -> Self::Output {
+    match self {
+      Formula::Or(mut formulas) => {
+        formulas.push(rhs.into());
+        Formula::Or(formulas)
+      }
+      formula => Formula::Or(vec![formula, rhs.into()]),
+    }
+  }
+}
+
+impl<T: Into<Formula>> BitXor<T> for Formula {
+  type Output = Formula;
+  fn bitxor(self, rhs: T) -> Self::Output {
+    Formula::Xor(Box::new(self), Box::new(rhs.into()))
+  }
+}
+
+impl<T: Into<Formula>> BitAnd<T> for Variable {
+  type Output = Formula;
+  fn bitand(self, rhs: T) -> Self::Output {
+    Formula::from(self) & rhs
+  }
+}
+
+impl<T: Into<Formula>> BitOr<T> for Variable {
+  type Output = Formula;
+  fn bitor(self, rhs: T) -> Self::Output {
+    Formula::from(self) | rhs
+  }
+}
+
+impl<T: Into<Formula>> BitXor<T> for Variable {
+  type Output = Formula;
+  fn bitxor(self, rhs: T) -> Self::Output {
+    Formula::from(self) ^ rhs
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{sat_solver::SATSolver, testing};
+
+  #[test]
+  fn flattens_operators() {
+    let mut solver = SATSolver::new();
+    let [a, b, c] = ["a", "b", "c"].map(|name| solver.variable(name));
+    assert_eq!(a & b & c, Formula::and([a, b, c]));
+    assert_eq!(a | b | c, Formula::or([a, b, c]));
+    // 括弧の中のAndは別の部分式になる
+    assert_eq!(
+      a & (b & c),
+      Formula::And(vec![a.into(), Formula::and([b, c])])
+    );
+    assert_eq!(!!Formula::from(a), Formula::from(a));
+    assert_eq!(a ^ b, Formula::Xor(Box::new(a.into()), Box::new(b.into())));
+  }
+
+  #[test]
+  fn evaluates_every_connective() {
+    let mut solver = SATSolver::new();
+    let [a, b, c] = ["a", "b", "c"].map(|name| solver.variable(name));
+    for assignment in 0..8 {
+      // 否定した変数も受け取る
+      let value = |variable: Variable| testing::holds(assignment, variable.literal());
+      let [x, y, z] = [a, b, c].map(value);
+      assert_eq!((a & b & c).evaluate(&value), x && y && z);
+      assert_eq!((a | b | c).evaluate(&value), x || y || z);
+      assert_eq!((!(a & b)).evaluate(&value), !(x && y));
+      assert_eq!(a.implies(b).evaluate(&value), !x || y);
+      assert_eq!(a.iff(b).evaluate(&value), x == y);
+      assert_eq!((a ^ b ^ c).evaluate(&value), x ^ y ^ z);
+      assert_eq!(
+        Formula::ite(a, b, c).evaluate(&value),
+        if x { y } else { z }
+      );
+      assert_eq!((!a).implies(false).evaluate(&value), x);
+    }
+    assert!(Formula::and(Vec::<Formula>::new()).evaluate(&|_| false));
+    assert!(!Formula::or(Vec::<Formula>::new()).evaluate(&|_| true));
+  }
+}
@@ -1,31 +1,42 @@
 use std::ops::Not;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct Literal {
-  variable: usize,
-  sign: bool,
-}
+/// 変数番号と符号を2*variable+signの32ビット整数で表したリテラル
+/// 節のアリーナではu32の語の並びをそのままリテラルの並びとして読むので，u32と同じ配置にする
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Literal(u32);
 
 impl Literal {
   pub fn new(variable: usize, sign: bool) -> Self {
-    Self { variable, sign }
+    assert!(
+      variable < (u32::MAX / 2) as usize,
+      "too many variables: {}",
+      variable
+    );
+    Self((2 * variable + sign as usize) as u32)
   }
 
   pub fn variable(&self) -> usize {
-    self.variable
+    (self.0 >> 1) as usize
   }
 
   pub fn sign(&self) -> bool {
-    self.sign
+    self.0 & 1 == 1
+  }
+
+  /// リテラルを0..2*num_variablesの整数に対応させる
+  pub fn index(&self) -> usize {
+    self.0 as usize
+  }
+
+  pub fn from_index(index: usize) -> Self {
+    Self(index as u32)
   }
 }
 
 impl Not for Literal {
   type Output = Self;
   fn not(self) -> Self::Output {
-    Self {
-      variable: self.variable,
-      sign: !self.sign,
-    }
+    Self(self.0 ^ 1)
   }
 }
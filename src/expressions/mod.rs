@@ -1,3 +1,5 @@
 pub mod clause;
+pub mod formula;
 pub mod literal;
 pub mod model;
+pub mod parser;
@@ -0,0 +1,399 @@
+use std::{error::Error, fmt, iter::Peekable, str::CharIndices};
+
+use crate::{expressions::formula::Formula, sat_solver::Variable};
+
+/// 論理式の字句
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+  Name(String),
+  True,
+  False,
+  /// `!`, `~`
+  Not,
+  /// `&`, `&&`
+  And,
+  /// `|`, `||`
+  Or,
+  /// `^`
+  Xor,
+  /// `->`, `=>`
+  Implies,
+  /// `<->`, `<=>`
+  Iff,
+  /// `?`
+  Question,
+  /// `:`
+  Colon,
+  LeftParen,
+  RightParen,
+}
+
+impl fmt::Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Token::Name(name) => write!(f, "{}", name),
+      Token::True => write!(f, "true"),
+      Token::False => write!(f, "false"),
+      Token::Not => write!(f, "!"),
+      Token::And => write!(f, "&"),
+      Token::Or => write!(f, "|"),
+      Token::Xor => write!(f, "^"),
+      Token::Implies => write!(f, "->"),
+      Token::Iff => write!(f, "<->"),
+      Token::Question => write!(f, "?"),
+      Token::Colon => write!(f, ":"),
+      Token::LeftParen => write!(f, "("),
+      Token::RightParen => write!(f, ")"),
+    }
+  }
+}
+
+/// `(a | !b) & (c -> d)`のような論理式を読む
+/// 結合の弱い順に `c ? t : e`, `<->`, `->`(右結合), `|`, `^`, `&`, `!`
+/// 変数名は英字か`_`で始まり英数字・`_`・`.`・`[`・`]`が続くもので，variableで変数に変換する
+/// `true`と`false`は定数
+pub fn parse_formula<F: FnMut(&str) -> Variable>(
+  input: &str,
+  variable: F,
+) -> Result<Formula, FormulaParseError> {
+  let tokens = tokenize(input)?;
+  let mut parser = Parser {
+    tokens,
+    position: 0,
+    end: input.chars().count() + 1,
+    variable,
+  };
+  let formula = parser.ternary()?;
+  match parser.peek() {
+    None => Ok(formula),
+    Some(token) => Err(parser.unexpected(token.clone())),
+  }
+}
+
+/// 字句と，その先頭の列番号(1から数える)の組に分ける
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, FormulaParseError> {
+  let mut tokens = vec![];
+  let mut chars = input.char_indices().peekable();
+  let mut column = 0;
+  let mut next = |chars: &mut Peekable<CharIndices>| {
+    column += 1;
+    chars.next().map(|(_, c)| (c, column))
+  };
+  while let Some((c, start)) = next(&mut chars) {
+    let token = match c {
+      c if c.is_whitespace() => continue,
+      '!' | '~' => Token::Not,
+      '&' | '|' => {
+        if chars.peek().map(|&(_, next)| next) == Some(c) {
+          next(&mut chars);
+        }
+        if c == '&' {
+          Token::And
+        } else {
+          Token::Or
+        }
+      }
+      '^' => Token::Xor,
+      '?' => Token::Question,
+      ':' => Token::Colon,
+      '(' => Token::LeftParen,
+      ')' => Token::RightParen,
+      '-' | '=' => match next(&mut chars) {
+        Some(('>', _)) => Token::Implies,
+        _ => return Err(expected("->", start)),
+      },
+      '<' => match (next(&mut chars), next(&mut chars)) {
+        (Some(('-' | '=', _)), Some(('>', _))) => Token::Iff,
+        _ => return Err(expected("<->", start)),
+      },
+      c if c.is_ascii_alphabetic() || c == '_' => {
+        let mut name = c.to_string();
+        while let Some(&(_, c)) = chars.peek() {
+          if !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '[' | ']')) {
+            break;
+          }
+          name.push(c);
+          next(&mut chars);
+        }
+        match name.as_str() {
+          "true" => Token::True,
+          "false" => Token::False,
+          _ => Token::Name(name),
+        }
+      }
+      c => {
+        return Err(FormulaParseError(format!(
+          "unexpected character '{}' at column {}",
+          c, start
+        )));
+      }
+    };
+    tokens.push((token, start));
+  }
+  Ok(tokens)
+}
+
+fn expected(token: &str, column: usize) -> FormulaParseError {
+  FormulaParseError(format!("expected '{}' at column {}", token, column))
+}
+
+/// 再帰下降で構文木を作る
+struct Parser<F> {
+  tokens: Vec<(Token, usize)>,
+  position: usize,
+  /// 入力の終わりの列番号
+  end: usize,
+  variable: F,
+}
+
+impl<F: FnMut(&str) -> Variable> Parser<F> {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.position).map(|(token, _)| token)
+  }
+
+  fn column(&self) -> usize {
+    self
+      .tokens
+      .get(self.position)
+      .map_or(self.end, |&(_, column)| column)
+  }
+
+  /// 次の字句がtokenなら読み進める
+  fn consume(&mut self, token: &Token) -> bool {
+    if self.peek() == Some(token) {
+      self.position += 1;
+      true
+    } else {
+      false
+    }
+  }
+
+  fn expect(&mut self, token: &Token) -> Result<(), FormulaParseError> {
+    if self.consume(token) {
+      Ok(())
+    } else {
+      Err(expected(&token.to_string(), self.column()))
+    }
+  }
+
+  fn unexpected(&self, token: Token) -> FormulaParseError {
+    FormulaParseError(format!(
+      "unexpected '{}' at column {}",
+      token,
+      self.column()
+    ))
+  }
+
+  /// `c ? t : e`(右結合)
+  fn ternary(&mut self) -> Result<Formula, FormulaParseError> {
+    let condition = self.iff()?;
+    if !self.consume(&Token::Question) {
+      return Ok(condition);
+    }
+    let then = self.ternary()?;
+    self.expect(&Token::Colon)?;
+    let otherwise = self.ternary()?;
+    Ok(Formula::ite(condition, then, otherwise))
+  }
+
+  fn iff(&mut self) -> Result<Formula, FormulaParseError> {
+    let mut formula = self.implies()?;
+    while self.consume(&Token::Iff) {
+      formula = formula.iff(self.implies()?);
+    }
+    Ok(formula)
+  }
+
+  /// `a -> b -> c`は`a -> (b -> c)`
+  fn implies(&mut self) -> Result<Formula, FormulaParseError> {
+    let formula = self.or()?;
+    if self.consume(&Token::Implies) {
+      Ok(formula.implies(self.implies()?))
+    } else {
+      Ok(formula)
+    }
+  }
+
+  fn or(&mut self) -> Result<Formula, FormulaParseError> {
+    let mut formulas = vec![self.xor()?];
+    while self.consume(&Token::Or) {
+      formulas.push(self.xor()?);
+    }
+    Ok(if formulas.len() == 1 {
+      formulas.pop().unwrap()
+    } else {
+      Formula::Or(formulas)
+    })
+  }
+
+  fn xor(&mut self) -> Result<Formula, FormulaParseError> {
+    let mut formula = self.and()?;
+    while self.consume(&Token::Xor) {
+      formula = formula ^ self.and()?;
+    }
+    Ok(formula)
+  }
+
+  fn and(&mut self) -> Result<Formula, FormulaParseError> {
+    let mut formulas = vec![self.not()?];
+    while self.consume(&Token::And) {
+      formulas.push(self.not()?);
+    }
+    Ok(if formulas.len() == 1 {
+      formulas.pop().unwrap()
+    } else {
+      Formula::And(formulas)
+    })
+  }
+
+  fn not(&mut self) -> Result<Formula, FormulaParseError> {
+    if self.consume(&Token::Not) {
+      Ok(Formula::Not(Box::new(self.not()?)))
+    } else {
+      self.atom()
+    }
+  }
+
+  fn atom(&mut self) -> Result<Formula, FormulaParseError> {
+    let Some(token) = self.peek().cloned() else {
+      return Err(FormulaParseError(format!(
+        "unexpected end of formula at column {}",
+        self.end
+      )));
+    };
+    let formula = match token {
+      Token::Name(ref name) => Formula::Variable((self.variable)(name)),
+      Token::True => Formula::Constant(true),
+      Token::False => Formula::Constant(false),
+      Token::LeftParen => {
+        self.position += 1;
+        let formula = self.ternary()?;
+        self.expect(&Token::RightParen)?;
+        return Ok(formula);
+      }
+      token => return Err(self.unexpected(token)),
+    };
+    self.position += 1;
+    Ok(formula)
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct FormulaParseError(pub String);
+
+impl fmt::Display for FormulaParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "failed to parse formula: {}", self.0)
+  }
+}
+
+impl Error for FormulaParseError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::sat_solver::SATSolver;
+
+  fn parse(solver: &mut SATSolver<String>, input: &str) -> Result<Formula, String> {
+    parse_formula(input, |name| solver.variable(name.to_string())).map_err(|error| error.0)
+  }
+
+  fn not(formula: impl Into<Formula>) -> Formula {
+    Formula::Not(Box::new(formula.into()))
+  }
+
+  #[test]
+  fn parses_precedence() {
+    let mut solver = SATSolver::new();
+    let [a, b, c, d] = ["a", "b", "c", "d"].map(|name| Formula::from(solver.variable(name.into())));
+    let cases = [
+      ("a", a.clone()),
+      ("!a", not(a.clone())),
+      ("~~a", not(not(a.clone()))),
+      (
+        "a & b && c",
+        Formula::and([a.clone(), b.clone(), c.clone()]),
+      ),
+      ("a | b || c", Formula::or([a.clone(), b.clone(), c.clone()])),
+      (
+        "a | b & c",
+        Formula::or([a.clone(), Formula::and([b.clone(), c.clone()])]),
+      ),
+      ("!a & b", Formula::and([not(a.clone()), b.clone()])),
+      (
+        "a ^ b & c ^ d",
+        (a.clone() ^ Formula::and([b.clone(), c.clone()])) ^ d.clone(),
+      ),
+      ("a | b ^ c", Formula::or([a.clone(), b.clone() ^ c.clone()])),
+      (
+        "a -> b -> c",
+        a.clone().implies(b.clone().implies(c.clone())),
+      ),
+      ("a => b | c", a.clone().implies(b.clone() | c.clone())),
+      ("a <-> b <=> c", a.clone().iff(b.clone()).iff(c.clone())),
+      ("a -> b <-> c", a.clone().implies(b.clone()).iff(c.clone())),
+      (
+        "a ? b : c ? d : a",
+        Formula::ite(
+          a.clone(),
+          b.clone(),
+          Formula::ite(c.clone(), d.clone(), a.clone()),
+        ),
+      ),
+      (
+        "a <-> b ? c : d",
+        Formula::ite(a.clone().iff(b.clone()), c.clone(), d.clone()),
+      ),
+      (
+        "(a | b) & c",
+        Formula::and([a.clone() | b.clone(), c.clone()]),
+      ),
+      ("!(a -> b)", not(a.clone().implies(b.clone()))),
+      (
+        "true & !false",
+        Formula::and([Formula::Constant(true), not(false)]),
+      ),
+    ];
+    for (input, expected) in cases {
+      assert_eq!(parse(&mut solver, input).unwrap(), expected, "{}", input);
+    }
+  }
+
+  #[test]
+  fn parses_names() {
+    let mut solver = SATSolver::new();
+    let formula = parse(&mut solver, "x[0].y_1 & _tmp | x[0].y_1 & trueish").unwrap();
+    let names = ["x[0].y_1", "_tmp", "trueish"];
+    let [x, tmp, trueish] = names.map(|name| Formula::from(solver.variable(name.into())));
+    assert_eq!(
+      formula,
+      Formula::or([Formula::and([x.clone(), tmp]), Formula::and([x, trueish])])
+    );
+  }
+
+  #[test]
+  fn rejects_malformed_formulas() {
+    let mut solver = SATSolver::new();
+    let cases = [
+      ("", "unexpected end of formula at column 1"),
+      ("a &", "unexpected end of formula at column 4"),
+      ("a & $", "unexpected character '$' at column 5"),
+      ("1a", "unexpected character '1' at column 1"),
+      ("a - b", "expected '->' at column 3"),
+      ("a <- b", "expected '<->' at column 3"),
+      ("(a | b", "expected ')' at column 7"),
+      ("a ? b", "expected ':' at column 6"),
+      ("a b", "unexpected 'b' at column 3"),
+      ("a & )", "unexpected ')' at column 5"),
+      ("(a))", "unexpected ')' at column 4"),
+    ];
+    for (input, expected) in cases {
+      assert_eq!(
+        parse(&mut solver, input).unwrap_err(),
+        expected,
+        "{}",
+        input
+      );
+    }
+  }
+}
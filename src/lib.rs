@@ -1,5 +1,21 @@
+pub mod cdcl;
+pub mod config;
+pub mod count;
+pub mod cube_and_conquer;
 pub mod dimacs;
 pub mod dpll;
+pub mod encodings;
+pub mod enumeration;
 pub mod expressions;
 pub mod io;
+pub mod local_search;
+pub mod logic;
+pub mod maxsat;
+pub mod pb;
+pub mod portfolio;
+pub mod preprocess;
+pub mod random;
 pub mod sat_solver;
+pub mod statistics;
+#[cfg(test)]
+mod testing;
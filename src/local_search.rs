@@ -0,0 +1,305 @@
+use std::sync::{
+  atomic::{AtomicBool, Ordering},
+  Arc,
+};
+
+use crate::{
+  expressions::{clause::Clause, literal::Literal, model::Model},
+  random::Random,
+};
+
+/// ProbSATの確率分布 (EPSILON + break)^(-CB)
+const PROBSAT_CB: f64 = 2.38;
+const PROBSAT_EPSILON: f64 = 1.0;
+/// WalkSATで無作為に変数を選ぶ確率
+const WALKSAT_NOISE: f64 = 0.567;
+/// 中断の指示を確認する間隔(反転回数)
+const STOP_CHECK_INTERVAL: u64 = 1024;
+
+/// 局所探索のアルゴリズム
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+  ProbSat,
+  WalkSat,
+}
+
+/// 確率的局所探索
+/// 解を見つけられなくても充足不能とは限らない
+#[derive(Clone, Debug)]
+pub struct LocalSearch {
+  algorithm: Algorithm,
+  clauses: Vec<Vec<Literal>>,
+  occurrences: Vec<Vec<usize>>,
+  assignment: Vec<bool>,
+  /// 各節で真になっているリテラルの数
+  true_counts: Vec<usize>,
+  /// 偽の節の一覧と，各節の一覧内での位置
+  unsatisfied: Vec<usize>,
+  positions: Vec<Option<usize>>,
+  best_assignment: Vec<bool>,
+  best_unsatisfied: usize,
+  flips: u64,
+  random: Random,
+  stop: Option<Arc<AtomicBool>>,
+}
+
+impl LocalSearch {
+  pub fn new(num_variables: usize, clauses: &[Clause], algorithm: Algorithm, seed: u64) -> Self {
+    let clauses = clauses
+      .iter()
+      .map(|clause| clause.iter().copied().collect::<Vec<_>>())
+      .collect::<Vec<_>>();
+
+    let mut occurrences = vec![vec![]; 2 * num_variables];
+    for (id, clause) in clauses.iter().enumerate() {
+      for literal in clause {
+        occurrences[literal.index()].push(id);
+      }
+    }
+
+    let mut random = Random::new(seed);
+    let assignment = (0..num_variables)
+      .map(|_| random.below(2) == 1)
+      .collect::<Vec<_>>();
+
+    let mut local_search = Self {
+      algorithm,
+      true_counts: vec![0; clauses.len()],
+      positions: vec![None; clauses.len()],
+      clauses,
+      occurrences,
+      assignment: vec![],
+      unsatisfied: vec![],
+      best_assignment: vec![],
+      best_unsatisfied: usize::MAX,
+      flips: 0,
+      random,
+      stop: None,
+    };
+    local_search.set_assignment(&assignment);
+    local_search
+  }
+
+  /// 探索の初期割り当てを設定する
+  pub fn set_assignment(&mut self, assignment: &[bool]) {
+    self.assignment = assignment.to_vec();
+    self.unsatisfied.clear();
+    for id in 0..self.clauses.len() {
+      self.true_counts[id] = self.clauses[id]
+        .iter()
+        .filter(|literal| self.is_true(**literal))
+        .count();
+      self.positions[id] = None;
+      if self.true_counts[id] == 0 {
+        self.positions[id] = Some(self.unsatisfied.len());
+        self.unsatisfied.push(id);
+      }
+    }
+    self.best_assignment = self.assignment.clone();
+    self.best_unsatisfied = self.unsatisfied.len();
+  }
+
+  /// 最大max_flips回変数を反転して解を探す
+  /// 全ての節を充足すればtrueを返す
+  pub fn run(&mut self, max_flips: u64) -> bool {
+    // 空節があれば充足できない
+    if self.clauses.iter().any(|clause| clause.is_empty()) {
+      return false;
+    }
+
+    for i in 0..max_flips {
+      if self.unsatisfied.is_empty() {
+        return true;
+      }
+      if i % STOP_CHECK_INTERVAL == 0 && self.is_stopped() {
+        return false;
+      }
+
+      let clause = self.unsatisfied[self.random.below(self.unsatisfied.len())];
+      let variable = match self.algorithm {
+        Algorithm::ProbSat => self.pick_probsat(clause),
+        Algorithm::WalkSat => self.pick_walksat(clause),
+      };
+      self.flip(variable);
+
+      if self.unsatisfied.len() < self.best_unsatisfied {
+        self.best_unsatisfied = self.unsatisfied.len();
+        self.best_assignment.clone_from(&self.assignment);
+      }
+    }
+
+    self.unsatisfied.is_empty()
+  }
+
+  /// 他のスレッドからtrueにされたら探索を中断する
+  pub fn set_stop_flag(&mut self, stop: Arc<AtomicBool>) {
+    self.stop = Some(stop);
+  }
+
+  pub fn is_stopped(&self) -> bool {
+    self
+      .stop
+      .as_ref()
+      .is_some_and(|stop| stop.load(Ordering::Relaxed))
+  }
+
+  pub fn flips(&self) -> u64 {
+    self.flips
+  }
+
+  /// これまでで偽の節が最も少なかった割り当て
+  pub fn best_assignment(&self) -> &[bool] {
+    &self.best_assignment
+  }
+
+  pub fn best_unsatisfied(&self) -> usize {
+    self.best_unsatisfied
+  }
+
+  pub fn model(&self) -> Model {
+    let mut model = Model::new(self.best_assignment.len());
+    for (variable, &sign) in self.best_assignment.iter().enumerate() {
+      model.assign(variable, sign);
+    }
+    model
+  }
+
+  fn is_true(&self, literal: Literal) -> bool {
+    self.assignment[literal.variable()] == literal.sign()
+  }
+
+  /// 変数を反転したときに偽になる節の数
+  fn break_count(&self, variable: usize) -> usize {
+    let literal = Literal::new(variable, self.assignment[variable]);
+    self.occurrences[literal.index()]
+      .iter()
+      .filter(|&&id| self.true_counts[id] == 1)
+      .count()
+  }
+
+  fn pick_probsat(&mut self, clause: usize) -> usize {
+    let weights = self.clauses[clause]
+      .iter()
+      .map(|literal| {
+        let breaks = self.break_count(literal.variable()) as f64;
+        (PROBSAT_EPSILON + breaks).powf(-PROBSAT_CB)
+      })
+      .collect::<Vec<_>>();
+
+    let mut threshold = self.random.next_f64() * weights.iter().sum::<f64>();
+    for (literal, weight) in self.clauses[clause].iter().zip(&weights) {
+      if threshold < *weight {
+        return literal.variable();
+      }
+      threshold -= weight;
+    }
+    self.clauses[clause].last().unwrap().variable()
+  }
+
+  fn pick_walksat(&mut self, clause: usize) -> usize {
+    let breaks = self.clauses[clause]
+      .iter()
+      .map(|literal| (self.break_count(literal.variable()), literal.variable()))
+      .collect::<Vec<_>>();
+
+    let &(min_break, variable) = breaks.iter().min().unwrap();
+    if min_break == 0 || self.random.next_f64() >= WALKSAT_NOISE {
+      variable
+    } else {
+      breaks[self.random.below(breaks.len())].1
+    }
+  }
+
+  fn flip(&mut self, variable: usize) {
+    self.flips += 1;
+    let falsified = Literal::new(variable, self.assignment[variable]);
+    self.assignment[variable] = !self.assignment[variable];
+
+    for i in 0..self.occurrences[falsified.index()].len() {
+      let id = self.occurrences[falsified.index()][i];
+      self.true_counts[id] -= 1;
+      if self.true_counts[id] == 0 {
+        self.positions[id] = Some(self.unsatisfied.len());
+        self.unsatisfied.push(id);
+      }
+    }
+
+    for i in 0..self.occurrences[(!falsified).index()].len() {
+      let id = self.occurrences[(!falsified).index()][i];
+      self.true_counts[id] += 1;
+      if self.true_counts[id] == 1 {
+        let position = self.positions[id].take().unwrap();
+        self.unsatisfied.swap_remove(position);
+        if let Some(&moved) = self.unsatisfied.get(position) {
+          self.positions[moved] = Some(position);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing;
+
+  fn to_clauses(clauses: &[Vec<Literal>]) -> Vec<Clause> {
+    clauses.iter().map(Clause::from).collect()
+  }
+
+  #[test]
+  fn finds_planted_solutions() {
+    let mut random = Random::new(1);
+    let num_variables = 60;
+    for algorithm in [Algorithm::ProbSat, Algorithm::WalkSat] {
+      for seed in 0..10 {
+        let hidden = random.next_u64() & ((1 << num_variables) - 1);
+        let clauses =
+          testing::planted_clauses(&mut random, num_variables, 4 * num_variables, 3, hidden);
+        let mut local_search =
+          LocalSearch::new(num_variables, &to_clauses(&clauses), algorithm, seed);
+        assert!(local_search.run(1_000_000), "{:?} {}", algorithm, seed);
+        assert_eq!(local_search.best_unsatisfied(), 0);
+        let assignment = testing::assignment(&local_search.model(), num_variables);
+        assert!(clauses
+          .iter()
+          .all(|clause| testing::satisfies(assignment, clause)));
+      }
+    }
+  }
+
+  #[test]
+  fn starts_from_the_given_assignment() {
+    let mut random = Random::new(2);
+    let num_variables = 30;
+    let hidden = random.next_u64() & ((1 << num_variables) - 1);
+    let clauses = testing::planted_clauses(&mut random, num_variables, 120, 3, hidden);
+    let mut local_search =
+      LocalSearch::new(num_variables, &to_clauses(&clauses), Algorithm::ProbSat, 0);
+    let assignment = (0..num_variables)
+      .map(|variable| hidden >> variable & 1 == 1)
+      .collect::<Vec<_>>();
+    local_search.set_assignment(&assignment);
+    assert!(local_search.run(0));
+    assert_eq!(local_search.flips(), 0);
+    assert_eq!(local_search.best_assignment(), assignment);
+  }
+
+  #[test]
+  fn keeps_the_best_assignment_of_unsatisfiable_formulas() {
+    // xの全ての組み合わせを禁止すると必ず1つの節が偽になる
+    let clauses = (0..8)
+      .map(|assignment: u64| {
+        (0..3)
+          .map(|variable| Literal::new(variable, assignment >> variable & 1 == 0))
+          .collect::<Vec<_>>()
+      })
+      .collect::<Vec<_>>();
+    for algorithm in [Algorithm::ProbSat, Algorithm::WalkSat] {
+      let mut local_search = LocalSearch::new(3, &to_clauses(&clauses), algorithm, 3);
+      assert!(!local_search.run(1000));
+      assert_eq!(local_search.flips(), 1000);
+      assert_eq!(local_search.best_unsatisfied(), 1);
+    }
+  }
+}
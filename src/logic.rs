@@ -0,0 +1,164 @@
+use std::{collections::HashMap, error::Error, fmt, path::Path, str::FromStr};
+
+use crate::{
+  expressions::{formula::Formula, parser},
+  io,
+  sat_solver::SATSolver,
+};
+
+/// 1行に1つずつ論理式を書いた問題(全ての論理式が真になる解を求める)
+/// `#`から行末まではコメント
+#[derive(Clone, Debug, Default)]
+pub struct Logic {
+  /// 現れた順の変数名(i番目の名前の変数の番号がi)
+  names: Vec<String>,
+  formulas: Vec<Formula>,
+}
+
+impl Logic {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// parse logic file
+  pub fn from<P: AsRef<Path>>(logic_file: P) -> Result<Logic, Box<dyn Error>> {
+    Ok(io::read_file(logic_file)?.join("\n").parse()?)
+  }
+
+  pub fn names(&self) -> &[String] {
+    &self.names
+  }
+
+  pub fn formulas(&self) -> &[Formula] {
+    &self.formulas
+  }
+
+  pub fn solve(&self) -> Option<Option<Vec<(String, bool)>>> {
+    self.solve_with(|_| {})
+  }
+
+  /// 全ての論理式が真になる解を変数名と値の組で返す(変数は現れた順)
+  /// 充足不能ならSome(None)，上限に達して分からなければNoneを返す
+  /// 解く前にconfigureでソルバの設定を変更する
+  pub fn solve_with<F: FnOnce(&mut SATSolver<String>)>(
+    &self,
+    configure: F,
+  ) -> Option<Option<Vec<(String, bool)>>> {
+    let mut solver = SATSolver::new();
+    configure(&mut solver);
+    // 読んだときと同じ順に変数を作り，論理式の変数の番号と揃える
+    for name in &self.names {
+      let _ = solver.variable(name.clone());
+    }
+    for formula in &self.formulas {
+      solver.assert(formula);
+    }
+
+    if !solver.solve_limited()? {
+      return Some(None);
+    }
+    Some(Some(
+      self
+        .names
+        .iter()
+        .map(|name| {
+          let value = solver.get_model_value_from_name(name).unwrap_or(false);
+          (name.clone(), value)
+        })
+        .collect(),
+    ))
+  }
+}
+
+impl FromStr for Logic {
+  type Err = LogicParseError;
+
+  fn from_str(input: &str) -> Result<Self, Self::Err> {
+    let mut solver = SATSolver::new();
+    let mut names = vec![];
+    let mut variables = HashMap::new();
+    let mut formulas = vec![];
+    for (i, line) in input.lines().enumerate() {
+      let line = line.split('#').next().unwrap();
+      if line.trim().is_empty() {
+        continue;
+      }
+      let formula = parser::parse_formula(line, |name| {
+        *variables.entry(name.to_string()).or_insert_with(|| {
+          names.push(name.to_string());
+          solver.variable(name.to_string())
+        })
+      })
+      .map_err(|error| LogicParseError(format!("line {}: {}", i + 1, error.0)))?;
+      formulas.push(formula);
+    }
+    Ok(Logic { names, formulas })
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct LogicParseError(pub String);
+
+impl fmt::Display for LogicParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "failed to parse logic file: {}", self.0)
+  }
+}
+
+impl Error for LogicParseError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// 解で全ての論理式が真になるか
+  fn satisfied(logic: &Logic, model: &[(String, bool)]) -> bool {
+    logic.formulas().iter().all(|formula| {
+      formula.evaluate(&|variable| {
+        let literal = variable.literal();
+        model[literal.variable()].1 == literal.sign()
+      })
+    })
+  }
+
+  #[test]
+  fn parses_and_solves() {
+    let input = "# 3人のうち2人以上が来る\n\
+                 (alice & bob) | (bob & carol) | (carol & alice)\n\
+                 \n\
+                 alice -> !bob  # aliceが来ればbobは来ない\n\
+                 carol ? dave : false\n";
+    let logic = input.parse::<Logic>().unwrap();
+    assert_eq!(logic.names(), ["alice", "bob", "carol", "dave"]);
+    assert_eq!(logic.formulas().len(), 3);
+    let model = logic.solve().unwrap().unwrap();
+    assert_eq!(
+      model
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>(),
+      logic.names()
+    );
+    assert!(satisfied(&logic, &model));
+    // carolは必ず来るのでdaveも来る
+    assert!(model[2].1 && model[3].1);
+  }
+
+  #[test]
+  fn detects_unsatisfiable() {
+    let logic = "a ^ b\na <-> b\n".parse::<Logic>().unwrap();
+    assert_eq!(logic.solve(), Some(None));
+    let logic = "false".parse::<Logic>().unwrap();
+    assert_eq!(logic.solve(), Some(None));
+  }
+
+  #[test]
+  fn reports_the_line() {
+    let error = "a | b\n# comment\na & (b | c\n"
+      .parse::<Logic>()
+      .unwrap_err();
+    assert_eq!(error.0, "line 3: expected ')' at column 11");
+    let error = "a | b\n  -> c".parse::<Logic>().unwrap_err();
+    assert_eq!(error.0, "line 2: unexpected '->' at column 3");
+  }
+}
@@ -0,0 +1,128 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::{cdcl::solver::Cdcl, expressions::literal::Literal};
+
+/// 重み付き部分MaxSATの解き方
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MaxSatAlgorithm {
+  /// 充足不能コアから下限を上げていく(OLL)
+  #[default]
+  Oll,
+  /// 見つけた解のコストより小さくなる制約を加えて解き直す(SAT-UNSAT)
+  Linear,
+}
+
+impl MaxSatAlgorithm {
+  pub const ALL: [MaxSatAlgorithm; 2] = [MaxSatAlgorithm::Oll, MaxSatAlgorithm::Linear];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      MaxSatAlgorithm::Oll => "oll",
+      MaxSatAlgorithm::Linear => "linear",
+    }
+  }
+}
+
+impl fmt::Display for MaxSatAlgorithm {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl FromStr for MaxSatAlgorithm {
+  type Err = UnknownMaxSatAlgorithmError;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    MaxSatAlgorithm::ALL
+      .into_iter()
+      .find(|algorithm| algorithm.name() == name)
+      .ok_or_else(|| UnknownMaxSatAlgorithmError(name.to_string()))
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct UnknownMaxSatAlgorithmError(pub String);
+
+impl fmt::Display for UnknownMaxSatAlgorithmError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "unknown maxsat algorithm: {} (expected oll or linear)",
+      self.0
+    )
+  }
+}
+
+impl Error for UnknownMaxSatAlgorithmError {}
+
+/// 軟らかい節を，満たさない節の重みの和を表す目的関数にする
+/// 節ごとに節を満たさないときだけ真になるリテラルを用意して重みを付ける
+/// 単位節はリテラルの否定をそのまま使う
+pub fn relax(solver: &mut Cdcl, soft_clauses: &[(Vec<Literal>, u64)]) -> Vec<(i64, Literal)> {
+  let num_relaxed = soft_clauses
+    .iter()
+    .filter(|(clause, _)| clause.len() != 1)
+    .count();
+  let mut next_variable = solver.add_variables(num_relaxed);
+
+  let mut objective = vec![];
+  for (clause, weight) in soft_clauses {
+    let weight = *weight as i64;
+    if let [literal] = clause[..] {
+      objective.push((weight, !literal));
+      continue;
+    }
+    let violated = Literal::new(next_variable, true);
+    next_variable += 1;
+    let mut relaxed = clause.clone();
+    relaxed.push(violated);
+    solver.add_clause(&relaxed);
+    for &literal in clause {
+      solver.add_clause(&[!violated, !literal]);
+    }
+    objective.push((weight, violated));
+  }
+  objective
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cdcl::solver::SolveResult;
+
+  #[test]
+  fn relaxes_only_non_unit_clauses() {
+    let x = |variable: usize, sign: bool| Literal::new(variable, sign);
+    let soft = [(vec![x(0, false)], 2), (vec![x(0, true), x(1, true)], 3)];
+    let mut solver = Cdcl::new(2);
+    let objective = relax(&mut solver, &soft);
+    assert_eq!(solver.num_variables(), 3);
+    assert_eq!(objective, [(2, x(0, true)), (3, x(2, true))]);
+
+    // 緩和のリテラルは節を満たさないときに限り真になる
+    for (assumptions, violated) in [
+      ([x(0, true), x(1, false)], false),
+      ([x(0, false), x(1, false)], true),
+    ] {
+      let SolveResult::Satisfiable(model) = solver.solve_with_assumptions(&assumptions) else {
+        panic!("satisfiable");
+      };
+      assert_eq!(model.sign(2), Some(violated));
+    }
+  }
+
+  #[test]
+  fn parses_names() {
+    for algorithm in MaxSatAlgorithm::ALL {
+      assert_eq!(
+        algorithm.name().parse::<MaxSatAlgorithm>().unwrap(),
+        algorithm
+      );
+    }
+    let error = "wpm".parse::<MaxSatAlgorithm>().unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "unknown maxsat algorithm: wpm (expected oll or linear)"
+    );
+  }
+}
@@ -0,0 +1,415 @@
+use std::time::Instant;
+
+use crate::{
+  expressions::{clause::Clause, literal::Literal, model::Model},
+  random::Random,
+};
+
+/// 改善できる変数がないときに，選んだ節の無作為な変数を反転する確率
+const NOISE: f64 = 0.1;
+/// 期限を確認する間隔(反転回数)
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+/// 硬い節を全て満たし，満たさない軟らかい節の重みの和が小さい割り当てを探す局所探索
+/// 偽の節を1つ選び，その節の変数のうち反転したときに偽の節の動的な重みの和が最も減るものを反転する
+/// 減らせる変数がなければ偽の節の動的な重みを増やす(軟らかい節は元の重みまで)
+#[derive(Clone, Debug)]
+pub struct MaxSatLocalSearch {
+  /// 硬い節の後に軟らかい節を並べる
+  clauses: Vec<Vec<Literal>>,
+  num_hard: usize,
+  /// 軟らかい節の重み
+  costs: Vec<u64>,
+  /// 動的な重み
+  weights: Vec<u64>,
+  /// 常に満たさない空の軟らかい節の重みの和
+  constant: u64,
+  occurrences: Vec<Vec<usize>>,
+  assignment: Vec<bool>,
+  true_counts: Vec<usize>,
+  /// 偽の節の一覧と，各節の一覧内での位置
+  unsatisfied: Vec<usize>,
+  positions: Vec<Option<usize>>,
+  unsatisfied_hard: usize,
+  /// 偽の軟らかい節の重みの和
+  cost: u64,
+  /// 硬い節を全て満たした割り当てのうち，コストが最小のもの
+  best: Option<(u64, Vec<bool>)>,
+  random: Random,
+  flips: u64,
+}
+
+impl MaxSatLocalSearch {
+  pub fn new(
+    num_variables: usize,
+    hard_clauses: &[Clause],
+    soft_clauses: &[(Vec<Literal>, u64)],
+    seed: u64,
+  ) -> Self {
+    // 常に真の節は除き，同じリテラルは1つにまとめる(偽の節を数えるときに重複して数えない)
+    let mut clauses = hard_clauses
+      .iter()
+      .filter_map(|clause| normalize(clause.iter().copied()))
+      .collect::<Vec<_>>();
+    let num_hard = clauses.len();
+    let mut costs = vec![0; num_hard];
+    let mut constant = 0;
+    for (clause, weight) in soft_clauses {
+      if clause.is_empty() {
+        constant += weight;
+      } else if *weight > 0 {
+        if let Some(clause) = normalize(clause.iter().copied()) {
+          clauses.push(clause);
+          costs.push(*weight);
+        }
+      }
+    }
+
+    let mut occurrences = vec![vec![]; 2 * num_variables];
+    for (id, clause) in clauses.iter().enumerate() {
+      for literal in clause {
+        occurrences[literal.index()].push(id);
+      }
+    }
+
+    Self {
+      num_hard,
+      weights: vec![1; clauses.len()],
+      costs,
+      constant,
+      occurrences,
+      assignment: vec![false; num_variables],
+      true_counts: vec![0; clauses.len()],
+      unsatisfied: vec![],
+      positions: vec![None; clauses.len()],
+      unsatisfied_hard: 0,
+      cost: 0,
+      best: None,
+      random: Random::new(seed),
+      flips: 0,
+      clauses,
+    }
+  }
+
+  pub fn num_variables(&self) -> usize {
+    self.assignment.len()
+  }
+
+  /// 探索の初期割り当てを設定する(これまでの最良の割り当ては忘れる)
+  pub fn set_assignment(&mut self, assignment: &[bool]) {
+    self.assignment = assignment.to_vec();
+    self.unsatisfied.clear();
+    self.unsatisfied_hard = 0;
+    self.cost = self.constant;
+    for id in 0..self.clauses.len() {
+      self.true_counts[id] = self.clauses[id]
+        .iter()
+        .filter(|literal| self.is_true(**literal))
+        .count();
+      self.positions[id] = None;
+      if self.true_counts[id] == 0 {
+        self.falsify(id);
+      }
+    }
+    self.best = None;
+    self.record();
+  }
+
+  /// 最大max_flips回変数を反転して，コストの小さい割り当てを探す
+  /// 最良の割り当てが更新されたらtrueを返す
+  pub fn run(&mut self, max_flips: u64, deadline: Option<Instant>) -> bool {
+    let initial = self.best_cost();
+    for i in 0..max_flips {
+      if self.unsatisfied.is_empty() {
+        break;
+      }
+      if i % DEADLINE_CHECK_INTERVAL == 0
+        && deadline.is_some_and(|deadline| Instant::now() >= deadline)
+      {
+        break;
+      }
+
+      let clause = self.unsatisfied[self.random.below(self.unsatisfied.len())];
+      let (score, mut variable) = self.clauses[clause]
+        .iter()
+        .map(|literal| (self.score(literal.variable()), literal.variable()))
+        .max_by_key(|&(score, _)| score)
+        .unwrap();
+      if score <= 0 {
+        self.update_weights();
+        if self.random.next_f64() < NOISE {
+          let literals = &self.clauses[clause];
+          variable = literals[self.random.below(literals.len())].variable();
+        }
+      }
+      self.flip(variable);
+      self.record();
+    }
+    self.best_cost() < initial
+  }
+
+  pub fn flips(&self) -> u64 {
+    self.flips
+  }
+
+  /// 硬い節を全て満たした割り当てのうち最小のコスト(なければu64::MAX)
+  pub fn best_cost(&self) -> u64 {
+    self.best.as_ref().map_or(u64::MAX, |(cost, _)| *cost)
+  }
+
+  pub fn best_assignment(&self) -> Option<&[bool]> {
+    self.best.as_ref().map(|(_, assignment)| &assignment[..])
+  }
+
+  fn is_true(&self, literal: Literal) -> bool {
+    self.assignment[literal.variable()] == literal.sign()
+  }
+
+  /// 今の割り当てが硬い節を全て満たし，これまでよりコストが小さければ記録する
+  fn record(&mut self) {
+    if self.unsatisfied_hard == 0 && self.cost < self.best_cost() {
+      self.best = Some((self.cost, self.assignment.clone()));
+    }
+  }
+
+  /// 変数を反転したときに減る偽の節の動的な重みの和
+  fn score(&self, variable: usize) -> i64 {
+    let falsified = Literal::new(variable, self.assignment[variable]);
+    let breaks = self.occurrences[falsified.index()]
+      .iter()
+      .filter(|&&id| self.true_counts[id] == 1)
+      .map(|&id| self.weights[id] as i64)
+      .sum::<i64>();
+    let makes = self.occurrences[(!falsified).index()]
+      .iter()
+      .filter(|&&id| self.true_counts[id] == 0)
+      .map(|&id| self.weights[id] as i64)
+      .sum::<i64>();
+    makes - breaks
+  }
+
+  /// 偽の節の動的な重みを増やす
+  fn update_weights(&mut self) {
+    for &id in &self.unsatisfied {
+      if id < self.num_hard || self.weights[id] < self.costs[id] {
+        self.weights[id] += 1;
+      }
+    }
+  }
+
+  fn falsify(&mut self, id: usize) {
+    self.positions[id] = Some(self.unsatisfied.len());
+    self.unsatisfied.push(id);
+    if id < self.num_hard {
+      self.unsatisfied_hard += 1;
+    } else {
+      self.cost += self.costs[id];
+    }
+  }
+
+  fn satisfy(&mut self, id: usize) {
+    let position = self.positions[id].take().unwrap();
+    self.unsatisfied.swap_remove(position);
+    if let Some(&moved) = self.unsatisfied.get(position) {
+      self.positions[moved] = Some(position);
+    }
+    if id < self.num_hard {
+      self.unsatisfied_hard -= 1;
+    } else {
+      self.cost -= self.costs[id];
+    }
+  }
+
+  fn flip(&mut self, variable: usize) {
+    self.flips += 1;
+    let falsified = Literal::new(variable, self.assignment[variable]);
+    self.assignment[variable] = !self.assignment[variable];
+
+    for i in 0..self.occurrences[falsified.index()].len() {
+      let id = self.occurrences[falsified.index()][i];
+      self.true_counts[id] -= 1;
+      if self.true_counts[id] == 0 {
+        self.falsify(id);
+      }
+    }
+    for i in 0..self.occurrences[(!falsified).index()].len() {
+      let id = self.occurrences[(!falsified).index()][i];
+      self.true_counts[id] += 1;
+      if self.true_counts[id] == 1 {
+        self.satisfy(id);
+      }
+    }
+  }
+}
+
+/// CDCLで見つけた解から局所探索を始めて，コストの小さい解を探す
+/// 局所探索は元の問題の変数だけを扱い，軟らかい節を満たさないことを表すリテラルの値は節から決める
+#[derive(Clone, Debug)]
+pub struct Improver {
+  local_search: MaxSatLocalSearch,
+  /// 軟らかい節と，それを満たさないときに真になるリテラル
+  relaxations: Vec<(Vec<Literal>, Literal)>,
+  max_flips: u64,
+}
+
+impl Improver {
+  /// objectiveはsoft_clausesを緩和した目的関数(algorithm::relaxの結果)
+  pub fn new(
+    local_search: MaxSatLocalSearch,
+    soft_clauses: &[(Vec<Literal>, u64)],
+    objective: &[(i64, Literal)],
+    max_flips: u64,
+  ) -> Self {
+    let relaxations = soft_clauses
+      .iter()
+      .zip(objective)
+      .map(|((clause, _), &(_, literal))| (clause.clone(), literal))
+      .collect();
+    Self {
+      local_search,
+      relaxations,
+      max_flips,
+    }
+  }
+
+  pub fn flips(&self) -> u64 {
+    self.local_search.flips()
+  }
+
+  /// modelよりコストの小さい解が見つかれば，modelの値を書き換えた解を返す
+  pub fn improve(&mut self, model: &Model, deadline: Option<Instant>) -> Option<Model> {
+    let assignment = (0..self.local_search.num_variables())
+      .map(|variable| model.sign(variable).unwrap_or(false))
+      .collect::<Vec<_>>();
+    self.local_search.set_assignment(&assignment);
+    if !self.local_search.run(self.max_flips, deadline) {
+      return None;
+    }
+
+    let mut improved = model.clone();
+    let assignment = self.local_search.best_assignment()?;
+    for (variable, &value) in assignment.iter().enumerate() {
+      improved.assign(variable, value);
+    }
+    for (clause, literal) in &self.relaxations {
+      let violated = !clause
+        .iter()
+        .any(|literal| assignment[literal.variable()] == literal.sign());
+      improved.assign(literal.variable(), literal.sign() == violated);
+    }
+    Some(improved)
+  }
+}
+
+/// リテラルを整列して重複を除く(xと!xを両方含めばNone)
+fn normalize(literals: impl Iterator<Item = Literal>) -> Option<Vec<Literal>> {
+  let mut literals = literals.collect::<Vec<_>>();
+  literals.sort_unstable();
+  literals.dedup();
+  if literals.windows(2).any(|pair| pair[0] == !pair[1]) {
+    return None;
+  }
+  Some(literals)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{self, random_clauses};
+
+  fn cost(soft: &[(Vec<Literal>, u64)], assignment: u64) -> u64 {
+    soft
+      .iter()
+      .filter(|(clause, _)| !testing::satisfies(assignment, clause))
+      .map(|&(_, weight)| weight)
+      .sum()
+  }
+
+  #[test]
+  fn improves_models_with_relaxation_literals() {
+    // x0, x1のどちらかを真にしたいが，両方は真にできない
+    let x = |variable: usize| Literal::new(variable, true);
+    let hard = [Clause::from(&vec![!x(0), !x(1)])];
+    let soft = [(vec![x(0)], 1), (vec![x(1), x(2)], 3)];
+    // x3は2番目の軟らかい節を満たさないときに真になるリテラル
+    let objective = [(1, !x(0)), (3, x(3))];
+    let local_search = MaxSatLocalSearch::new(3, &hard, &soft, 0);
+    let mut improver = Improver::new(local_search, &soft, &objective, 1000);
+    let mut model = Model::new(4);
+    for variable in 0..4 {
+      model.assign(variable, variable == 3);
+    }
+    let improved = improver.improve(&model, None).unwrap();
+    assert_eq!(improved.sign(0), Some(true));
+    assert_eq!(improved.sign(1), Some(false));
+    assert_eq!(improved.sign(2), Some(true));
+    assert_eq!(improved.sign(3), Some(false));
+    assert!(improver.flips() > 0);
+    // これ以上良い解はない
+    assert!(improver.improve(&improved, None).is_none());
+  }
+
+  #[test]
+  fn finds_assignments_satisfying_hard_clauses() {
+    let mut random = Random::new(22);
+    let num_variables = 10;
+    let mut optimal = 0;
+    for seed in 0..20 {
+      // 硬い節は充足可能な問題にする
+      let hard = loop {
+        let hard = random_clauses(&mut random, num_variables, 3 * num_variables, 3);
+        if !testing::models(num_variables, &hard).is_empty() {
+          break hard;
+        }
+      };
+      let soft = random_clauses(&mut random, num_variables, 2 * num_variables, 2)
+        .into_iter()
+        .map(|clause| (clause, 1 + random.below(5) as u64))
+        .collect::<Vec<_>>();
+      let minimum = testing::models(num_variables, &hard)
+        .into_iter()
+        .map(|assignment| cost(&soft, assignment))
+        .min()
+        .unwrap();
+
+      let clauses = hard.iter().map(Clause::from).collect::<Vec<_>>();
+      let mut local_search = MaxSatLocalSearch::new(num_variables, &clauses, &soft, seed);
+      local_search.set_assignment(&vec![false; num_variables]);
+      local_search.run(10_000, None);
+      let best = local_search.best_assignment().unwrap();
+      let assignment = best
+        .iter()
+        .enumerate()
+        .fold(0, |assignment, (variable, &value)| {
+          assignment | (value as u64) << variable
+        });
+      assert!(hard
+        .iter()
+        .all(|clause| testing::satisfies(assignment, clause)));
+      assert_eq!(cost(&soft, assignment), local_search.best_cost());
+      assert!(local_search.best_cost() >= minimum);
+      if local_search.best_cost() == minimum {
+        optimal += 1;
+      }
+    }
+    assert!(optimal >= 15, "{}", optimal);
+  }
+
+  #[test]
+  fn ignores_repeated_literals_and_tautologies() {
+    let x = |variable: usize| Literal::new(variable, true);
+    let hard = [
+      Clause::from(&vec![x(0), x(0)]),
+      Clause::from(&vec![x(1), !x(1)]),
+    ];
+    let soft = [(vec![!x(0), !x(0)], 2), (vec![x(1), x(2), !x(1)], 5)];
+    let mut local_search = MaxSatLocalSearch::new(3, &hard, &soft, 0);
+    // 常に真の節は除き，重複したリテラルは1つにする
+    assert_eq!(local_search.clauses, [vec![x(0)], vec![!x(0)]]);
+    assert_eq!(local_search.num_hard, 1);
+    local_search.set_assignment(&[false; 3]);
+    local_search.run(100, None);
+    assert_eq!(local_search.best_cost(), 2);
+    assert!(local_search.best_assignment().unwrap()[0]);
+  }
+}
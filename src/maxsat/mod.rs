@@ -0,0 +1,5 @@
+pub mod algorithm;
+pub mod local_search;
+pub mod oll;
+pub mod options;
+pub mod wcnf;
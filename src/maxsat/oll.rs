@@ -0,0 +1,458 @@
+use std::{collections::BTreeMap, time::Instant};
+
+use crate::{
+  cdcl::solver::{Cdcl, SolveResult},
+  encodings::cardinality::unary_count,
+  expressions::{literal::Literal, model::Model},
+  maxsat::{local_search::Improver, options::MaxSatOptions},
+  pb::optimize::{cost, normalize_objective, MinimizeResult, Optimum},
+  statistics::Statistics,
+};
+
+/// 充足不能コアに基づいて目的関数sum(a_i * l_i)を最小にする(OLL)
+/// 重みのあるリテラルを全て偽と仮定して解き，充足不能なら原因の仮定(コア)の最小の重みだけ下限を上げる
+/// コアのリテラルの重みはその分減らし，コアのうち2個以上, 3個以上, ...が真であることを表すリテラルに移す
+pub struct Oll {
+  solver: Cdcl,
+  objective: Vec<(i64, Literal)>,
+  options: MaxSatOptions,
+  /// 解を見つけるたびに改善を試みる局所探索
+  improver: Option<Improver>,
+  /// 偽と仮定するリテラルの重み
+  weights: BTreeMap<Literal, i64>,
+  /// コストの下限
+  lower: i64,
+  /// これまでで最良の解とそのコスト
+  best: Option<(i64, Model)>,
+  deadline: Option<Instant>,
+}
+
+impl Oll {
+  /// solverには元の問題の制約を全て追加しておく
+  pub fn new(solver: Cdcl, objective: Vec<(i64, Literal)>, options: MaxSatOptions) -> Self {
+    Self {
+      solver,
+      objective,
+      options,
+      improver: None,
+      weights: BTreeMap::new(),
+      lower: 0,
+      best: None,
+      deadline: options.time_limit.map(|time| Instant::now() + time),
+    }
+  }
+
+  /// 探索を打ち切る時刻を設定する(newではoptionsのtime_limitから決める)
+  pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+    self.deadline = deadline;
+  }
+
+  pub fn set_improver(&mut self, improver: Improver) {
+    self.improver = Some(improver);
+  }
+
+  pub fn statistics(&self) -> &Statistics {
+    self.solver.statistics()
+  }
+
+  /// 局所探索で反転した回数
+  pub fn local_search_flips(&self) -> u64 {
+    self
+      .improver
+      .as_ref()
+      .map_or(0, |improver| improver.flips())
+  }
+
+  /// 最小のコストの解を求める
+  /// コストが小さくなる解を見つけるたびにon_improveを(コスト, 解)で呼ぶ
+  /// 期限や探索の上限に達したら，それまでで最良の解を最適とは限らないものとして返す
+  pub fn minimize<F: FnMut(i64, &Model)>(&mut self, mut on_improve: F) -> MinimizeResult<Model> {
+    // 仮定なしで解いて，充足可能かどうかと最初の解を求める
+    match self.solver.solve_with_budget(&[], None, self.deadline) {
+      SolveResult::Satisfiable(model) => self.update(model, &mut on_improve),
+      SolveResult::Unsatisfiable => return MinimizeResult::Unsatisfiable,
+      SolveResult::Unknown => return MinimizeResult::Unknown,
+    }
+
+    let (lower, terms) = normalize_objective(&self.objective);
+    self.lower = lower;
+    for (weight, literal) in terms {
+      *self.weights.entry(literal).or_default() += weight;
+    }
+
+    // この重み以上のリテラルだけを仮定する
+    let mut threshold = if self.options.stratification {
+      self.next_threshold(i64::MAX)
+    } else {
+      1
+    };
+    let optimal = loop {
+      if self.best_cost() <= self.lower {
+        break true;
+      }
+      // コアの変換に時間がかかっても期限を守る
+      if self
+        .deadline
+        .is_some_and(|deadline| Instant::now() >= deadline)
+      {
+        break false;
+      }
+      let assumptions = self
+        .weights
+        .iter()
+        .filter(|&(_, &weight)| weight > 0 && weight >= threshold)
+        .map(|(&literal, _)| !literal)
+        .collect::<Vec<_>>();
+      match self
+        .solver
+        .solve_with_budget(&assumptions, None, self.deadline)
+      {
+        SolveResult::Satisfiable(model) => {
+          self.update(model, &mut on_improve);
+          // 全てのリテラルを仮定して充足可能なら，コストは下限に等しい
+          if !self
+            .weights
+            .values()
+            .any(|&weight| 0 < weight && weight < threshold)
+          {
+            break true;
+          }
+          threshold = self.next_threshold(threshold);
+        }
+        SolveResult::Unsatisfiable => {
+          let core = self
+            .solver
+            .failed_assumptions()
+            .iter()
+            .map(|&assumption| !assumption)
+            .collect::<Vec<_>>();
+          if core.is_empty() {
+            break true;
+          }
+          let core = if self.options.core_minimization {
+            self.minimize_core(core, &mut on_improve)
+          } else {
+            core
+          };
+          self.relax_core(&core);
+        }
+        SolveResult::Unknown => break false,
+      }
+    };
+
+    let (cost, model) = self.best.take().unwrap();
+    MinimizeResult::Found(Optimum {
+      cost,
+      model,
+      optimal,
+    })
+  }
+
+  fn best_cost(&self) -> i64 {
+    self.best.as_ref().map_or(i64::MAX, |(cost, _)| *cost)
+  }
+
+  /// 見つけた解がこれまでより良ければ，局所探索で改善して記録する
+  fn update<F: FnMut(i64, &Model)>(&mut self, model: Model, on_improve: &mut F) {
+    if cost(&self.objective, &model) >= self.best_cost() {
+      return;
+    }
+    let model = match &mut self.improver {
+      Some(improver) => improver.improve(&model, self.deadline).unwrap_or(model),
+      None => model,
+    };
+    let cost = cost(&self.objective, &model);
+    on_improve(cost, &model);
+    self.best = Some((cost, model));
+  }
+
+  /// threshold未満の重みのうち最大のもの(なければ1)
+  fn next_threshold(&self, threshold: i64) -> i64 {
+    self
+      .weights
+      .values()
+      .copied()
+      .filter(|&weight| weight > 0 && weight < threshold)
+      .max()
+      .unwrap_or(1)
+  }
+
+  /// コアのリテラルを1つずつ除いて解き直し，除いても充足不能ならより小さいコアに置き換える
+  /// 除いて充足可能なときの解も暫定解として使う
+  fn minimize_core<F: FnMut(i64, &Model)>(
+    &mut self,
+    mut core: Vec<Literal>,
+    on_improve: &mut F,
+  ) -> Vec<Literal> {
+    let mut k = 0;
+    while k < core.len() {
+      let assumptions = core
+        .iter()
+        .enumerate()
+        .filter(|&(j, _)| j != k)
+        .map(|(_, &literal)| !literal)
+        .collect::<Vec<_>>();
+      let conflicts = Some(self.options.core_conflicts);
+      match self
+        .solver
+        .solve_with_budget(&assumptions, conflicts, self.deadline)
+      {
+        SolveResult::Unsatisfiable if !self.solver.failed_assumptions().is_empty() => {
+          core = self
+            .solver
+            .failed_assumptions()
+            .iter()
+            .map(|&assumption| !assumption)
+            .collect();
+        }
+        SolveResult::Satisfiable(model) => {
+          self.update(model, on_improve);
+          k += 1;
+        }
+        _ => k += 1,
+      }
+    }
+    core
+  }
+
+  /// コアの最小の重みだけ下限を上げ，コアのうち真になる個数を表すリテラルに重みを移す
+  fn relax_core(&mut self, core: &[Literal]) {
+    let weight = core
+      .iter()
+      .map(|literal| self.weights[literal])
+      .min()
+      .unwrap();
+    self.lower += weight;
+    for literal in core {
+      *self.weights.get_mut(literal).unwrap() -= weight;
+    }
+    if core.len() == 1 {
+      self.solver.add_clause(core);
+      return;
+    }
+
+    // コアのどれか1つは真なので，1個以上を表すリテラルは使わない
+    let first = self.solver.num_variables();
+    let mut next_variable = first;
+    let (counts, clauses) = unary_count(core, core.len(), &mut next_variable);
+    self.solver.add_variables(next_variable - first);
+    for clause in clauses {
+      self.solver.add_clause(&clause);
+    }
+    let mut counts = &counts[1..];
+
+    // 仮定なしでも真になる個数のリテラルは，その分だけ下限を上げて重みを付けない(core exhaustion)
+    if self.options.core_exhaustion {
+      let conflicts = Some(self.options.core_conflicts);
+      while let Some(&count) = counts.first() {
+        match self
+          .solver
+          .solve_with_budget(&[!count], conflicts, self.deadline)
+        {
+          SolveResult::Unsatisfiable => {
+            self.solver.add_clause(&[count]);
+            self.lower += weight;
+            counts = &counts[1..];
+          }
+          _ => break,
+        }
+      }
+    }
+    for &count in counts {
+      *self.weights.entry(count).or_default() += weight;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::*;
+  use crate::{
+    expressions::clause::Clause,
+    maxsat::{algorithm, local_search::MaxSatLocalSearch},
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  /// 硬い節と重み付きの軟らかい節の問題
+  struct Instance {
+    num_variables: usize,
+    hard: Vec<Vec<Literal>>,
+    soft: Vec<(Vec<Literal>, u64)>,
+  }
+
+  impl Instance {
+    fn random(random: &mut Random) -> Self {
+      let num_variables = 8;
+      let hard = random_clauses(random, num_variables, 2 * num_variables, 3);
+      let length = 1 + random.below(2);
+      let soft = random_clauses(random, num_variables, 3 * num_variables, length)
+        .into_iter()
+        .map(|clause| (clause, 1 + random.below(8) as u64))
+        .collect();
+      Self {
+        num_variables,
+        hard,
+        soft,
+      }
+    }
+
+    /// n個の変数を全て真にしたいが，高々1つしか真にできない問題
+    fn at_most_one(n: usize) -> Self {
+      let x = |variable: usize| Literal::new(variable, true);
+      let mut hard = vec![];
+      for i in 0..n {
+        for j in i + 1..n {
+          hard.push(vec![!x(i), !x(j)]);
+        }
+      }
+      Self {
+        num_variables: n,
+        hard,
+        soft: (0..n).map(|variable| (vec![x(variable)], 1)).collect(),
+      }
+    }
+
+    /// 総当たりで求めた最小のコスト
+    fn minimum(&self) -> Option<i64> {
+      testing::models(self.num_variables, &self.hard)
+        .into_iter()
+        .map(|assignment| self.cost(assignment))
+        .min()
+    }
+
+    fn cost(&self, assignment: u64) -> i64 {
+      self
+        .soft
+        .iter()
+        .filter(|(clause, _)| !testing::satisfies(assignment, clause))
+        .map(|&(_, weight)| weight as i64)
+        .sum()
+    }
+
+    fn oll(&self, options: MaxSatOptions) -> Oll {
+      let mut solver = Cdcl::new(self.num_variables);
+      for clause in &self.hard {
+        solver.add_clause(clause);
+      }
+      let objective = algorithm::relax(&mut solver, &self.soft);
+      let mut oll = Oll::new(solver, objective.clone(), options);
+      if options.local_search_flips > 0 {
+        let hard = self.hard.iter().map(Clause::from).collect::<Vec<_>>();
+        let local_search = MaxSatLocalSearch::new(self.num_variables, &hard, &self.soft, 0);
+        let improver = Improver::new(
+          local_search,
+          &self.soft,
+          &objective,
+          options.local_search_flips,
+        );
+        oll.set_improver(improver);
+      }
+      oll
+    }
+  }
+
+  #[test]
+  fn finds_minimum_with_every_option() {
+    let mut random = Random::new(20);
+    let instances = (0..20)
+      .map(|_| Instance::random(&mut random))
+      .collect::<Vec<_>>();
+    for options in 0..16 {
+      let options = MaxSatOptions {
+        stratification: options & 1 != 0,
+        core_minimization: options & 2 != 0,
+        core_exhaustion: options & 4 != 0,
+        local_search_flips: if options & 8 != 0 { 1000 } else { 0 },
+        ..MaxSatOptions::default()
+      };
+      for instance in &instances {
+        let mut improvements = vec![];
+        let result = instance.oll(options).minimize(|cost, model| {
+          let assignment = testing::assignment(model, instance.num_variables);
+          assert_eq!(instance.cost(assignment), cost);
+          improvements.push(cost);
+        });
+        match (result, instance.minimum()) {
+          (MinimizeResult::Found(optimum), Some(minimum)) => {
+            assert!(optimum.optimal);
+            assert_eq!(optimum.cost, minimum, "{:?}", options);
+            assert!(improvements.windows(2).all(|pair| pair[0] > pair[1]));
+            assert_eq!(improvements.last(), Some(&minimum));
+          }
+          (MinimizeResult::Unsatisfiable, None) => assert!(improvements.is_empty()),
+          (result, minimum) => panic!("{:?} {:?} {:?}", options, result, minimum),
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn stratifies_by_weight() {
+    // 重みの大きい層から順に仮定に加える
+    let x = |variable: usize| Literal::new(variable, true);
+    let mut oll = Instance::at_most_one(5).oll(MaxSatOptions::default());
+    oll.weights = BTreeMap::from([(x(0), 10), (x(1), 1), (x(2), 5), (x(3), 10), (x(4), 0)]);
+    assert_eq!(oll.next_threshold(i64::MAX), 10);
+    assert_eq!(oll.next_threshold(10), 5);
+    assert_eq!(oll.next_threshold(5), 1);
+    assert_eq!(oll.next_threshold(1), 1);
+  }
+
+  #[test]
+  fn minimizes_cores() {
+    // x0 ∨ x1 が硬い節なら，コア{x0, x1, x2}からx2を除ける
+    let x = |variable: usize| Literal::new(variable, true);
+    let instance = Instance {
+      num_variables: 3,
+      hard: vec![vec![x(0), x(1)]],
+      soft: vec![],
+    };
+    let mut oll = instance.oll(MaxSatOptions::default());
+    let mut core = oll.minimize_core(vec![x(0), x(1), x(2)], &mut |_, _| {});
+    core.sort();
+    assert_eq!(core, [x(0), x(1)]);
+  }
+
+  #[test]
+  fn exhausts_cores() {
+    // 3個のうち2個以上が真になる硬い節があれば，2個以上を表すリテラルは必ず真
+    let x = |variable: usize| Literal::new(variable, true);
+    for core_exhaustion in [false, true] {
+      let instance = Instance {
+        num_variables: 3,
+        hard: vec![vec![x(0), x(1)], vec![x(0), x(2)], vec![x(1), x(2)]],
+        soft: vec![],
+      };
+      let mut oll = instance.oll(MaxSatOptions {
+        core_exhaustion,
+        ..MaxSatOptions::default()
+      });
+      oll.weights = (0..3).map(|variable| (x(variable), 1)).collect();
+      oll.relax_core(&[x(0), x(1), x(2)]);
+      assert_eq!(oll.lower, if core_exhaustion { 2 } else { 1 });
+      // 3個以上を表すリテラルには常に重みを移す
+      let relaxed = oll.weights.values().filter(|&&weight| weight > 0).count();
+      assert_eq!(relaxed, if core_exhaustion { 1 } else { 2 });
+    }
+  }
+
+  #[test]
+  fn stops_at_the_time_limit() {
+    // 時間を過ぎていれば最初の解も探さない
+    let instance = Instance::at_most_one(6);
+    let options = MaxSatOptions {
+      time_limit: Some(Duration::ZERO),
+      ..MaxSatOptions::default()
+    };
+    let mut oll = instance.oll(options);
+    assert!(matches!(oll.minimize(|_, _| {}), MinimizeResult::Unknown));
+    oll.set_deadline(None);
+    match oll.minimize(|_, _| {}) {
+      MinimizeResult::Found(optimum) => assert_eq!(optimum.cost, 5),
+      result => panic!("{:?}", result),
+    }
+  }
+}
@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+/// MaxSATの探索の各手法とパラメータ
+/// OLLで使い，time_limitは線形探索でも使う
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxSatOptions {
+  /// 重みの大きいリテラルから順に仮定に加える(stratification)
+  /// 途中の層で見つかる解が暫定解になる
+  pub stratification: bool,
+  /// コアから1つずつリテラルを除いて解き直し，小さなコアにする
+  pub core_minimization: bool,
+  /// コアから作った個数のリテラルのうち，必ず真になるものを求めて下限に加える(core exhaustion)
+  pub core_exhaustion: bool,
+  /// コアの最小化・core exhaustionでの1回の探索の衝突回数
+  pub core_conflicts: u64,
+  /// 解を見つけるたびに局所探索で改善を試みる反転回数(0なら行わない)
+  pub local_search_flips: u64,
+  /// 全体の実行時間(超えたらそれまでで最良の解を返す)
+  /// 節の変換や最初の解の探索も含めて数え，解が見つからなければ結果はUnknownになる
+  pub time_limit: Option<Duration>,
+}
+
+impl Default for MaxSatOptions {
+  fn default() -> Self {
+    Self {
+      stratification: true,
+      core_minimization: true,
+      core_exhaustion: true,
+      core_conflicts: 1000,
+      local_search_flips: 100_000,
+      time_limit: None,
+    }
+  }
+}
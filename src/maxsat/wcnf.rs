@@ -0,0 +1,335 @@
+use std::{collections::HashMap, error::Error, fmt, path::Path, str::FromStr};
+
+use crate::{
+  expressions::literal::Literal,
+  io,
+  pb::optimize::{MinimizeResult, Optimum},
+  sat_solver::{SATSolver, Variable},
+};
+
+/// 重み付き部分MaxSATの問題(WCNF形式)
+/// 変数1, 2, ...は0, 1, ...番のリテラルで表す
+#[derive(Clone, Debug, Default)]
+pub struct Wcnf {
+  num_variables: usize,
+  /// 必ず満たす節
+  hard_clauses: Vec<Vec<Literal>>,
+  /// 満たさないと重みの分だけコストがかかる節
+  soft_clauses: Vec<(Vec<Literal>, u64)>,
+}
+
+impl Wcnf {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// parse wcnf file
+  /// `p wcnf`の行で始まり，重みが`top`以上の節を硬い節とする従来の形式と，
+  /// 硬い節を`h`で始める2022年以降の形式を読む
+  pub fn from<P: AsRef<Path>>(wcnf_file: P) -> Result<Wcnf, Box<dyn Error>> {
+    Ok(io::read_file(wcnf_file)?.join("\n").parse()?)
+  }
+
+  pub fn num_variables(&self) -> usize {
+    self.num_variables
+  }
+
+  pub fn hard_clauses(&self) -> &[Vec<Literal>] {
+    &self.hard_clauses
+  }
+
+  pub fn soft_clauses(&self) -> &[(Vec<Literal>, u64)] {
+    &self.soft_clauses
+  }
+
+  pub fn add_hard_clause(&mut self, clause: Vec<Literal>) {
+    self.num_variables = self.num_variables.max(max_variable(&clause));
+    self.hard_clauses.push(clause);
+  }
+
+  pub fn add_soft_clause(&mut self, clause: Vec<Literal>, weight: u64) {
+    self.num_variables = self.num_variables.max(max_variable(&clause));
+    self.soft_clauses.push((clause, weight));
+  }
+
+  pub fn solve(&mut self) -> MinimizeResult<Vec<i64>> {
+    self.solve_with(|_| {}, |_| {})
+  }
+
+  /// 満たさない軟らかい節の重みの和が最小の解を求める
+  /// 解く前にconfigureでソルバの設定を変更し，コストが小さくなる解を見つけるたびにon_improveをコストで呼ぶ
+  pub fn solve_with<F: FnOnce(&mut SATSolver<usize>), G: FnMut(i64)>(
+    &mut self,
+    configure: F,
+    mut on_improve: G,
+  ) -> MinimizeResult<Vec<i64>> {
+    let mut solver = SATSolver::new();
+    configure(&mut solver);
+    for i in 1..=self.num_variables {
+      let _ = solver.variable(i);
+    }
+
+    for clause in &self.hard_clauses {
+      let clause = variables(&mut solver, clause);
+      solver.add_clause(&clause);
+    }
+    for (clause, weight) in &self.soft_clauses {
+      let clause = variables(&mut solver, clause);
+      solver.add_soft_clause(&clause, *weight);
+    }
+
+    solver
+      .solve_maxsat_with(|cost, _| on_improve(cost))
+      .map(|optimum| Optimum {
+        cost: optimum.cost,
+        model: self.solution(&optimum.model),
+        optimal: optimum.optimal,
+      })
+  }
+
+  /// 変数の値から，真ならi，偽なら-iを並べた解を作る
+  fn solution(&self, model: &HashMap<usize, bool>) -> Vec<i64> {
+    (1..=self.num_variables)
+      .map(|i| if model[&i] { i as i64 } else { -(i as i64) })
+      .collect()
+  }
+}
+
+fn max_variable(clause: &[Literal]) -> usize {
+  clause
+    .iter()
+    .map(|literal| literal.variable() + 1)
+    .max()
+    .unwrap_or(0)
+}
+
+/// 節のリテラルに対応するソルバの変数
+fn variables(solver: &mut SATSolver<usize>, clause: &[Literal]) -> Vec<Variable> {
+  clause
+    .iter()
+    .map(|literal| {
+      let variable = solver.variable(literal.variable() + 1);
+      if literal.sign() {
+        variable
+      } else {
+        !variable
+      }
+    })
+    .collect()
+}
+
+impl FromStr for Wcnf {
+  type Err = WcnfParseError;
+
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let mut wcnf = Wcnf::new();
+    // 従来の形式の(変数の数, 節の数, 硬い節の重み)
+    let mut header: Option<(usize, usize, Option<u64>)> = None;
+    for line in text.lines() {
+      let words = line.split_whitespace().collect::<Vec<_>>();
+      if words.is_empty() || words[0].starts_with('c') {
+        continue;
+      }
+
+      if words[0] == "p" {
+        if header.is_some() || wcnf.hard_clauses.len() + wcnf.soft_clauses.len() > 0 {
+          return Err(WcnfParseError(format!("unexpected header: {}", line)));
+        }
+        header = Some(parse_header(&words)?);
+        continue;
+      }
+
+      let clause = parse_clause(&words[1..])?;
+      match header {
+        Some((_, _, top)) => {
+          let weight = parse_weight(words[0])?;
+          if top.is_some_and(|top| weight >= top) {
+            wcnf.add_hard_clause(clause);
+          } else {
+            wcnf.add_soft_clause(clause, weight);
+          }
+        }
+        None if words[0] == "h" => wcnf.add_hard_clause(clause),
+        None => wcnf.add_soft_clause(clause, parse_weight(words[0])?),
+      }
+    }
+
+    if let Some((num_variables, num_clauses, _)) = header {
+      let len = wcnf.hard_clauses.len() + wcnf.soft_clauses.len();
+      if num_clauses != len || num_variables < wcnf.num_variables {
+        return Err(WcnfParseError(
+          "the header does not match the problem".to_string(),
+        ));
+      }
+      wcnf.num_variables = num_variables;
+    }
+    Ok(wcnf)
+  }
+}
+
+/// `p wcnf 変数の数 節の数 [硬い節の重み]` を読む
+fn parse_header(words: &[&str]) -> Result<(usize, usize, Option<u64>), WcnfParseError> {
+  let error = || WcnfParseError(format!("invalid header: {}", words.join(" ")));
+  if !(4..=5).contains(&words.len()) || words[1] != "wcnf" {
+    return Err(error());
+  }
+  let num_variables = words[2].parse().map_err(|_| error())?;
+  let num_clauses = words[3].parse().map_err(|_| error())?;
+  let top = match words.get(4) {
+    Some(top) => Some(top.parse().map_err(|_| error())?),
+    None => None,
+  };
+  Ok((num_variables, num_clauses, top))
+}
+
+fn parse_weight(word: &str) -> Result<u64, WcnfParseError> {
+  word
+    .parse::<u64>()
+    .ok()
+    .filter(|&weight| weight > 0)
+    .ok_or_else(|| WcnfParseError(format!("invalid weight: {}", word)))
+}
+
+/// 0で終わるリテラルの並びを読む
+fn parse_clause(words: &[&str]) -> Result<Vec<Literal>, WcnfParseError> {
+  let error = || WcnfParseError(format!("invalid clause: {}", words.join(" ")));
+  let (&last, words) = words.split_last().ok_or_else(error)?;
+  if last != "0" {
+    return Err(error());
+  }
+  words
+    .iter()
+    .map(|word| match word.parse::<i64>() {
+      Ok(value) if value != 0 => Ok(Literal::new(value.unsigned_abs() as usize - 1, value > 0)),
+      _ => Err(error()),
+    })
+    .collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct WcnfParseError(pub String);
+
+impl fmt::Display for WcnfParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "failed to parse wcnf file: {}", self.0)
+  }
+}
+
+impl Error for WcnfParseError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    maxsat::{algorithm::MaxSatAlgorithm, options::MaxSatOptions},
+    pb::encoding::PbEncoding,
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  fn x(index: i64) -> Literal {
+    Literal::new(index.unsigned_abs() as usize - 1, index > 0)
+  }
+
+  #[test]
+  fn parses_both_formats() {
+    let new: Wcnf = "c new\nh 1 -2 0\n3 2 0\n5 -1 3 0\n".parse().unwrap();
+    let old: Wcnf = "c old\np wcnf 3 3 10\n10 1 -2 0\n3 2 0\n5 -1 3 0\n"
+      .parse()
+      .unwrap();
+    for wcnf in [new, old] {
+      assert_eq!(wcnf.num_variables(), 3);
+      assert_eq!(wcnf.hard_clauses(), &[vec![x(1), x(-2)]]);
+      assert_eq!(
+        wcnf.soft_clauses(),
+        &[(vec![x(2)], 3), (vec![x(-1), x(3)], 5)]
+      );
+    }
+
+    // 硬い節の重みがなければ全て軟らかい節
+    let wcnf: Wcnf = "p wcnf 4 2\n4 1 -3 0\n2 2 0\n".parse().unwrap();
+    assert_eq!(wcnf.num_variables(), 4);
+    assert!(wcnf.hard_clauses().is_empty());
+    assert_eq!(wcnf.soft_clauses().len(), 2);
+  }
+
+  #[test]
+  fn rejects_malformed_input() {
+    let inputs = [
+      "p wcnf 2 1 10\n3 1 2\n",
+      "p wcnf 2 2 10\n3 1 2 0\n",
+      "p wcnf 1 1\n1 2 0\n",
+      "p cnf 2 1\n1 1 0\n",
+      "p wcnf 2 1 10\nh 1 0\n",
+      "h 1 0\np wcnf 1 1\n",
+      "0 1 0\n",
+      "-1 1 0\n",
+      "1 x 0\n",
+      "1 1 0 2 0\n",
+    ];
+    for input in inputs {
+      assert!(input.parse::<Wcnf>().is_err(), "{}", input);
+    }
+  }
+
+  #[test]
+  fn solves_like_brute_force() {
+    let mut random = Random::new(19);
+    let num_variables = 8;
+    for _ in 0..30 {
+      let hard = random_clauses(&mut random, num_variables, 2 * num_variables, 3);
+      let mut wcnf = Wcnf::new();
+      for clause in &hard {
+        wcnf.add_hard_clause(clause.clone());
+      }
+      for clause in random_clauses(&mut random, num_variables, 2 * num_variables, 2) {
+        wcnf.add_soft_clause(clause, 1 + random.below(20) as u64);
+      }
+      let minimum = testing::models(num_variables, &hard)
+        .into_iter()
+        .map(|assignment| {
+          wcnf
+            .soft_clauses()
+            .iter()
+            .filter(|(clause, _)| !testing::satisfies(assignment, clause))
+            .map(|&(_, weight)| weight as i64)
+            .sum::<i64>()
+        })
+        .min();
+
+      // 符号化は線形探索の目的関数で使う
+      let configurations = MaxSatAlgorithm::ALL
+        .into_iter()
+        .flat_map(|algorithm| PbEncoding::ALL.map(|encoding| (algorithm, encoding)));
+      for (algorithm, encoding) in configurations {
+        let result = wcnf.clone().solve_with(
+          |solver| {
+            solver.set_maxsat_algorithm(algorithm);
+            solver.set_maxsat_options(MaxSatOptions {
+              local_search_flips: 1000,
+              ..MaxSatOptions::default()
+            });
+            solver.set_pb_encoding(encoding);
+          },
+          |_| {},
+        );
+        match (result, minimum) {
+          (MinimizeResult::Found(optimum), Some(minimum)) => {
+            assert!(optimum.optimal);
+            assert_eq!(optimum.cost, minimum, "{} {}", algorithm, encoding);
+            let assignment = optimum
+              .model
+              .iter()
+              .filter(|&&value| value > 0)
+              .fold(0, |assignment, &value| assignment | 1 << (value - 1));
+            assert!(hard
+              .iter()
+              .all(|clause| testing::satisfies(assignment, clause)));
+          }
+          (MinimizeResult::Unsatisfiable, None) => {}
+          (result, minimum) => panic!("{} {} {:?} {:?}", algorithm, encoding, result, minimum),
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,270 @@
+use std::{collections::BTreeMap, error::Error, fmt, str::FromStr};
+
+use crate::expressions::literal::Literal;
+
+/// 線形制約の比較演算子
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Comparison {
+  /// >=
+  AtLeast,
+  /// <=
+  AtMost,
+  /// =
+  Equal,
+}
+
+impl Comparison {
+  pub const ALL: [Comparison; 3] = [Comparison::AtLeast, Comparison::AtMost, Comparison::Equal];
+
+  pub fn symbol(&self) -> &'static str {
+    match self {
+      Comparison::AtLeast => ">=",
+      Comparison::AtMost => "<=",
+      Comparison::Equal => "=",
+    }
+  }
+}
+
+impl fmt::Display for Comparison {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.symbol())
+  }
+}
+
+impl FromStr for Comparison {
+  type Err = UnknownComparisonError;
+
+  fn from_str(symbol: &str) -> Result<Self, Self::Err> {
+    Comparison::ALL
+      .into_iter()
+      .find(|comparison| comparison.symbol() == symbol)
+      .ok_or_else(|| UnknownComparisonError(symbol.to_string()))
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct UnknownComparisonError(pub String);
+
+impl fmt::Display for UnknownComparisonError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "unknown comparison: {} (expected >=, <= or =)", self.0)
+  }
+}
+
+impl Error for UnknownComparisonError {}
+
+/// sum(a_i * l_i) (>=, <=, =) bound の線形制約(係数は整数)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PbConstraint {
+  pub terms: Vec<(i64, Literal)>,
+  pub comparison: Comparison,
+  pub bound: i64,
+}
+
+/// 正の係数をもつリテラルの和が上限以下になる制約
+/// 各係数は上限以下で，係数の和は上限より大きい(自明に満たされない)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AtMost {
+  pub terms: Vec<(u64, Literal)>,
+  pub bound: u64,
+}
+
+/// 正規化した結果
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Normalized {
+  /// 常に満たされる
+  Satisfied,
+  /// 満たせない
+  Unsatisfiable,
+  /// 値が決まるリテラルと，残りの制約
+  Constraint {
+    units: Vec<Literal>,
+    at_most: Option<AtMost>,
+  },
+}
+
+impl PbConstraint {
+  pub fn new(terms: Vec<(i64, Literal)>, comparison: Comparison, bound: i64) -> Self {
+    Self {
+      terms,
+      comparison,
+      bound,
+    }
+  }
+
+  /// 上限の形(sum(a_i * l_i) <= k, a_i > 0)の制約に変換する
+  /// =は<=と>=の2つになる
+  pub fn normalize(&self) -> Vec<Normalized> {
+    let negated = || {
+      let terms = self
+        .terms
+        .iter()
+        .map(|&(coefficient, literal)| (-coefficient, literal))
+        .collect::<Vec<_>>();
+      normalize_at_most(&terms, -(self.bound as i128))
+    };
+    match self.comparison {
+      Comparison::AtMost => vec![normalize_at_most(&self.terms, self.bound as i128)],
+      Comparison::AtLeast => vec![negated()],
+      Comparison::Equal => vec![
+        normalize_at_most(&self.terms, self.bound as i128),
+        negated(),
+      ],
+    }
+  }
+}
+
+/// sum(a_i * l_i) <= bound を正の係数の形にする
+/// 同じ変数の項はまとめ，負の係数は否定のリテラルに移す(a*l = a - a*!l)
+fn normalize_at_most(terms: &[(i64, Literal)], bound: i128) -> Normalized {
+  let mut bound = bound;
+  let mut coefficients: BTreeMap<usize, i128> = BTreeMap::new();
+  for &(coefficient, literal) in terms {
+    let coefficient = coefficient as i128;
+    let entry = coefficients.entry(literal.variable()).or_default();
+    if literal.sign() {
+      *entry += coefficient;
+    } else {
+      *entry -= coefficient;
+      bound -= coefficient;
+    }
+  }
+
+  let mut positive = vec![];
+  for (variable, coefficient) in coefficients {
+    if coefficient > 0 {
+      positive.push((coefficient, Literal::new(variable, true)));
+    } else if coefficient < 0 {
+      positive.push((-coefficient, Literal::new(variable, false)));
+      bound -= coefficient;
+    }
+  }
+  if bound < 0 {
+    return Normalized::Unsatisfiable;
+  }
+
+  // 上限を超える係数のリテラルは偽
+  let (units, terms): (Vec<_>, Vec<_>) = positive
+    .into_iter()
+    .partition(|&(coefficient, _)| coefficient > bound);
+  let units = units
+    .into_iter()
+    .map(|(_, literal)| !literal)
+    .collect::<Vec<_>>();
+  let sum = terms
+    .iter()
+    .map(|&(coefficient, _)| coefficient)
+    .sum::<i128>();
+  if units.is_empty() && sum <= bound {
+    return Normalized::Satisfied;
+  }
+  let at_most = (sum > bound).then(|| AtMost {
+    terms: terms
+      .into_iter()
+      .map(|(coefficient, literal)| (coefficient as u64, literal))
+      .collect(),
+    bound: bound as u64,
+  });
+  Normalized::Constraint { units, at_most }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn x(variable: usize) -> Literal {
+    Literal::new(variable, true)
+  }
+
+  #[test]
+  fn merges_terms_and_moves_negative_coefficients() {
+    // 3x0 - 2x1 + x0 <= 1 は 4x0 + 2!x1 <= 3 になり，x0は偽に決まる
+    let constraint = PbConstraint::new(
+      vec![(3, x(0)), (-2, x(1)), (1, x(0))],
+      Comparison::AtMost,
+      1,
+    );
+    assert_eq!(
+      constraint.normalize(),
+      [Normalized::Constraint {
+        units: vec![!x(0)],
+        at_most: None,
+      }]
+    );
+
+    // 2!x0 + 3x1 <= 3 は否定のリテラルのまま残る
+    let constraint = PbConstraint::new(vec![(2, !x(0)), (3, x(1))], Comparison::AtMost, 3);
+    assert_eq!(
+      constraint.normalize(),
+      [Normalized::Constraint {
+        units: vec![],
+        at_most: Some(AtMost {
+          terms: vec![(2, !x(0)), (3, x(1))],
+          bound: 3,
+        }),
+      }]
+    );
+  }
+
+  #[test]
+  fn detects_trivial_constraints() {
+    let terms = vec![(1, x(0)), (1, x(1))];
+    let at_least = |bound| PbConstraint::new(terms.clone(), Comparison::AtLeast, bound).normalize();
+    assert_eq!(at_least(3), [Normalized::Unsatisfiable]);
+    assert_eq!(at_least(0), [Normalized::Satisfied]);
+    // x0 + x0 <= 1 は x0を偽にする
+    let constraint = PbConstraint::new(vec![(1, x(0)), (1, x(0))], Comparison::AtMost, 1);
+    assert_eq!(
+      constraint.normalize(),
+      [Normalized::Constraint {
+        units: vec![!x(0)],
+        at_most: None,
+      }]
+    );
+  }
+
+  #[test]
+  fn splits_equalities() {
+    let terms = vec![(1, x(0)), (1, x(1)), (1, x(2))];
+    let constraint = PbConstraint::new(terms.clone(), Comparison::Equal, 1);
+    let negated = terms
+      .iter()
+      .map(|&(coefficient, literal)| (coefficient as u64, !literal))
+      .collect();
+    let terms = terms
+      .into_iter()
+      .map(|(coefficient, literal)| (coefficient as u64, literal))
+      .collect();
+    assert_eq!(
+      constraint.normalize(),
+      [
+        Normalized::Constraint {
+          units: vec![],
+          at_most: Some(AtMost { terms, bound: 1 }),
+        },
+        Normalized::Constraint {
+          units: vec![],
+          at_most: Some(AtMost {
+            terms: negated,
+            bound: 2,
+          }),
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn parses_comparisons() {
+    for comparison in Comparison::ALL {
+      assert_eq!(
+        comparison.to_string().parse::<Comparison>().unwrap(),
+        comparison
+      );
+    }
+    let error = ">".parse::<Comparison>().unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "unknown comparison: > (expected >=, <= or =)"
+    );
+  }
+}
@@ -0,0 +1,687 @@
+use std::{
+  cmp::Reverse,
+  collections::{BTreeMap, BTreeSet, VecDeque},
+  error::Error,
+  fmt, mem,
+  str::FromStr,
+};
+
+use crate::{
+  encodings::cardinality::unary_count,
+  expressions::literal::Literal,
+  pb::constraint::{AtMost, Normalized, PbConstraint},
+};
+
+/// 線形制約を節に変換する方法
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PbEncoding {
+  /// 係数の大きい順にリテラルの値で場合分けする二分決定図(上限の区間が同じ節点は共有する)
+  #[default]
+  Bdd,
+  /// 全加算器で和を2進数で求めて上限と比べる
+  Adder,
+  /// 二分木の各節点で部分和の取りうる値ごとにリテラルを置く(generalized totalizer)
+  GeneralizedTotalizer,
+  /// 係数のビットごとに1進数で数えて繰り上がりを次の桁に渡し，最上位からの繰り上がりを禁止する
+  /// (polynomial watchdog)
+  Watchdog,
+}
+
+impl PbEncoding {
+  pub const ALL: [PbEncoding; 4] = [
+    PbEncoding::Bdd,
+    PbEncoding::Adder,
+    PbEncoding::GeneralizedTotalizer,
+    PbEncoding::Watchdog,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      PbEncoding::Bdd => "bdd",
+      PbEncoding::Adder => "adder",
+      PbEncoding::GeneralizedTotalizer => "generalized-totalizer",
+      PbEncoding::Watchdog => "watchdog",
+    }
+  }
+}
+
+impl fmt::Display for PbEncoding {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl FromStr for PbEncoding {
+  type Err = UnknownPbEncodingError;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    PbEncoding::ALL
+      .into_iter()
+      .find(|encoding| encoding.name() == name)
+      .ok_or_else(|| UnknownPbEncodingError(name.to_string()))
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct UnknownPbEncodingError(pub String);
+
+impl fmt::Display for UnknownPbEncodingError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let names = PbEncoding::ALL.map(|encoding| encoding.name()).join(", ");
+    write!(
+      f,
+      "unknown encoding: {} (expected one of {})",
+      self.0, names
+    )
+  }
+}
+
+impl Error for UnknownPbEncodingError {}
+
+/// 線形制約を節に変換する
+/// 補助変数にはnext_variableから順に番号を振る
+pub fn encode(
+  constraint: &PbConstraint,
+  encoding: PbEncoding,
+  next_variable: &mut usize,
+) -> Vec<Vec<Literal>> {
+  let mut clauses = vec![];
+  for normalized in constraint.normalize() {
+    match normalized {
+      Normalized::Satisfied => {}
+      Normalized::Unsatisfiable => return vec![vec![]],
+      Normalized::Constraint { units, at_most } => {
+        clauses.extend(units.into_iter().map(|literal| vec![literal]));
+        if let Some(at_most) = at_most {
+          clauses.extend(encode_at_most(&at_most, encoding, next_variable));
+        }
+      }
+    }
+  }
+  clauses
+}
+
+/// 正の係数の和が上限以下になる制約を節に変換する
+pub fn encode_at_most(
+  constraint: &AtMost,
+  encoding: PbEncoding,
+  next_variable: &mut usize,
+) -> Vec<Vec<Literal>> {
+  let mut encoder = Encoder {
+    next_variable,
+    clauses: vec![],
+  };
+  match encoding {
+    PbEncoding::Bdd => encoder.bdd(constraint),
+    PbEncoding::Adder => encoder.adder(constraint),
+    PbEncoding::GeneralizedTotalizer => encoder.generalized_totalizer(constraint),
+    PbEncoding::Watchdog => encoder.watchdog(constraint),
+  }
+  encoder.clauses
+}
+
+/// 正の係数の項の和を表す出力のリテラル
+/// 和を一度だけ節に変換しておき，上限ごとにat_mostで出力への短い節を加える
+#[derive(Clone, Debug)]
+pub enum SumOutputs {
+  /// 和の取りうる値ごとの，和がその値以上であることを表すリテラル(generalized totalizer)
+  /// limit以上の値はlimitにまとめる
+  Unary(BTreeMap<u64, Literal>),
+  /// 和の2進数の各桁(Noneは0)
+  Binary(Vec<Option<Literal>>),
+}
+
+impl SumOutputs {
+  /// 和がbound以下になる節(boundはlimitより小さくなければならない)
+  pub fn at_most(&self, bound: u64) -> Vec<Vec<Literal>> {
+    match self {
+      SumOutputs::Unary(sums) => sums
+        .range(bound + 1..)
+        .next()
+        .map(|(_, &literal)| vec![vec![!literal]])
+        .unwrap_or_default(),
+      SumOutputs::Binary(bits) => compare_at_most(bits, bound),
+    }
+  }
+}
+
+/// 値ごとの出力で和を表すときの節の数の上限(超えるなら2進数で表す)
+const MAX_UNARY_CLAUSES: usize = 1 << 18;
+
+/// 正の係数の項の和を節に変換し，上限を加えるための出力と節を返す
+/// BddとGeneralizedTotalizerは値ごとの出力(limit以上の値は区別しない)，
+/// AdderとWatchdogは全加算器で求めた2進数の桁を出力にする
+/// 値ごとの出力は重みの種類が多いと値の数だけ大きくなるので，節がMAX_UNARY_CLAUSESを超えるなら2進数にする
+pub fn encode_sum(
+  terms: &[(u64, Literal)],
+  limit: u64,
+  encoding: PbEncoding,
+  next_variable: &mut usize,
+) -> (SumOutputs, Vec<Vec<Literal>>) {
+  let mut encoder = Encoder {
+    next_variable,
+    clauses: vec![],
+  };
+  let unary = match encoding {
+    PbEncoding::Bdd | PbEncoding::GeneralizedTotalizer => {
+      let mut clauses = 0;
+      unary_sums(terms, limit, &mut clauses).is_some()
+    }
+    PbEncoding::Adder | PbEncoding::Watchdog => false,
+  };
+  let outputs = if terms.is_empty() {
+    SumOutputs::Binary(vec![])
+  } else if unary {
+    let sums = encoder.partial_sums(terms, limit);
+    // 大きい値以上なら小さい値以上でもあるようにして，1つの出力で上限を表せるようにする
+    let literals = sums.values().copied().collect::<Vec<_>>();
+    for pair in literals.windows(2) {
+      encoder.clauses.push(vec![!pair[1], pair[0]]);
+    }
+    SumOutputs::Unary(sums)
+  } else {
+    SumOutputs::Binary(encoder.binary_sum(terms))
+  };
+  (outputs, encoder.clauses)
+}
+
+/// partial_sumsで作る部分和の値と，節の数をclausesに足したもの
+/// 節の数がMAX_UNARY_CLAUSESを超えたらNoneを返す
+fn unary_sums(terms: &[(u64, Literal)], limit: u64, clauses: &mut usize) -> Option<BTreeSet<u64>> {
+  if terms.len() <= 1 {
+    return Some(
+      terms
+        .iter()
+        .map(|&(coefficient, _)| coefficient.min(limit))
+        .collect(),
+    );
+  }
+  let (left, right) = terms.split_at(terms.len() / 2);
+  let left = unary_sums(left, limit, clauses)?;
+  let right = unary_sums(right, limit, clauses)?;
+  *clauses += (left.len() + 1) * (right.len() + 1) - 1;
+  if *clauses > MAX_UNARY_CLAUSES {
+    return None;
+  }
+  let mut sums = left.clone();
+  sums.extend(&right);
+  for a in &left {
+    sums.extend(right.iter().map(|b| (a + b).min(limit)));
+  }
+  Some(sums)
+}
+
+/// 二分決定図の節点
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Node {
+  False,
+  True,
+  Literal(Literal),
+}
+
+/// 上限の区間[lo, hi]と，その区間で共通の節点
+type Interval = (i128, i128, Node);
+
+struct Encoder<'a> {
+  next_variable: &'a mut usize,
+  clauses: Vec<Vec<Literal>>,
+}
+
+impl Encoder<'_> {
+  fn fresh(&mut self) -> Literal {
+    let literal = Literal::new(*self.next_variable, true);
+    *self.next_variable += 1;
+    literal
+  }
+
+  fn bdd(&mut self, constraint: &AtMost) {
+    let mut terms = constraint.terms.clone();
+    terms.sort_by_key(|&(coefficient, _)| Reverse(coefficient));
+    let mut rest = vec![0; terms.len() + 1];
+    for i in (0..terms.len()).rev() {
+      rest[i] = rest[i + 1] + terms[i].0 as i128;
+    }
+
+    let mut memo = vec![vec![]; terms.len()];
+    let (_, _, root) = self.bdd_node(&terms, &rest, 0, constraint.bound as i128, &mut memo);
+    match root {
+      Node::False => self.clauses.push(vec![]),
+      Node::True => {}
+      Node::Literal(literal) => self.clauses.push(vec![literal]),
+    }
+  }
+
+  /// terms[i..]の和がbound以下であることを表す節点と，同じ節点になる上限の区間
+  /// 節点のリテラルが真なら制約が満たされるように節を加える
+  fn bdd_node(
+    &mut self,
+    terms: &[(u64, Literal)],
+    rest: &[i128],
+    i: usize,
+    bound: i128,
+    memo: &mut [Vec<Interval>],
+  ) -> Interval {
+    if bound < 0 {
+      return (i128::MIN, -1, Node::False);
+    }
+    if rest[i] <= bound {
+      return (rest[i], i128::MAX, Node::True);
+    }
+    if let Some(&interval) = memo[i]
+      .iter()
+      .find(|&&(lo, hi, _)| lo <= bound && bound <= hi)
+    {
+      return interval;
+    }
+
+    let (coefficient, literal) = terms[i];
+    let coefficient = coefficient as i128;
+    let (lo1, hi1, high) = self.bdd_node(terms, rest, i + 1, bound - coefficient, memo);
+    let (lo0, hi0, low) = self.bdd_node(terms, rest, i + 1, bound, memo);
+    let lo = lo1.saturating_add(coefficient).max(lo0);
+    let hi = hi1.saturating_add(coefficient).min(hi0);
+
+    let node = if high == low {
+      low
+    } else {
+      let node = self.fresh();
+      match high {
+        Node::False => self.clauses.push(vec![!node, !literal]),
+        Node::True => {}
+        Node::Literal(high) => self.clauses.push(vec![!node, !literal, high]),
+      }
+      match low {
+        Node::False => self.clauses.push(vec![!node]),
+        Node::True => {}
+        Node::Literal(low) => self.clauses.push(vec![!node, low]),
+      }
+      Node::Literal(node)
+    };
+    memo[i].push((lo, hi, node));
+    (lo, hi, node)
+  }
+
+  fn adder(&mut self, constraint: &AtMost) {
+    let bits = self.binary_sum(&constraint.terms);
+    self
+      .clauses
+      .extend(compare_at_most(&bits, constraint.bound));
+  }
+
+  /// 全加算器で求めた和の2進数の各桁(Noneは0)
+  fn binary_sum(&mut self, terms: &[(u64, Literal)]) -> Vec<Option<Literal>> {
+    // 桁ごとに，その桁の重みで数えるリテラルを集める
+    let mut buckets: Vec<VecDeque<Literal>> = vec![];
+    for &(coefficient, literal) in terms {
+      for bit in 0..u64::BITS as usize {
+        if coefficient >> bit & 1 == 1 {
+          if buckets.len() <= bit {
+            buckets.resize(bit + 1, VecDeque::new());
+          }
+          buckets[bit].push_back(literal);
+        }
+      }
+    }
+
+    // 和の各桁(Noneは0)
+    let mut bits = vec![];
+    let mut bit = 0;
+    while bit < buckets.len() {
+      let mut carries = vec![];
+      let mut bucket = mem::take(&mut buckets[bit]);
+      while bucket.len() >= 3 {
+        let (x, y, z) = (
+          bucket.pop_front().unwrap(),
+          bucket.pop_front().unwrap(),
+          bucket.pop_front().unwrap(),
+        );
+        let (sum, carry) = self.full_adder(x, y, z);
+        bucket.push_back(sum);
+        carries.push(carry);
+      }
+      if bucket.len() == 2 {
+        let (x, y) = (bucket.pop_front().unwrap(), bucket.pop_front().unwrap());
+        let (sum, carry) = self.half_adder(x, y);
+        bits.push(Some(sum));
+        carries.push(carry);
+      } else {
+        bits.push(bucket.pop_front());
+      }
+      if !carries.is_empty() {
+        if buckets.len() <= bit + 1 {
+          buckets.push(VecDeque::new());
+        }
+        buckets[bit + 1].extend(carries);
+      }
+      bit += 1;
+    }
+    bits
+  }
+
+  /// x + y + z = sum + 2 * carry
+  fn full_adder(&mut self, x: Literal, y: Literal, z: Literal) -> (Literal, Literal) {
+    let (sum, carry) = (self.fresh(), self.fresh());
+    for signs in 0..8 {
+      let (a, b, c) = (signs & 1 == 1, signs & 2 == 2, signs & 4 == 4);
+      // x = a, y = b, z = c のとき sum = a ^ b ^ c
+      self.clauses.push(vec![
+        Literal::new(x.variable(), x.sign() ^ a),
+        Literal::new(y.variable(), y.sign() ^ b),
+        Literal::new(z.variable(), z.sign() ^ c),
+        Literal::new(sum.variable(), a ^ b ^ c),
+      ]);
+    }
+    for (p, q) in [(x, y), (x, z), (y, z)] {
+      self.clauses.push(vec![!p, !q, carry]);
+      self.clauses.push(vec![p, q, !carry]);
+    }
+    (sum, carry)
+  }
+
+  /// x + y = sum + 2 * carry
+  fn half_adder(&mut self, x: Literal, y: Literal) -> (Literal, Literal) {
+    let (sum, carry) = (self.fresh(), self.fresh());
+    self.clauses.push(vec![!x, !y, !sum]);
+    self.clauses.push(vec![x, y, !sum]);
+    self.clauses.push(vec![!x, y, sum]);
+    self.clauses.push(vec![x, !y, sum]);
+    self.clauses.push(vec![!x, !y, carry]);
+    self.clauses.push(vec![x, !carry]);
+    self.clauses.push(vec![y, !carry]);
+    (sum, carry)
+  }
+
+  fn generalized_totalizer(&mut self, constraint: &AtMost) {
+    let limit = constraint.bound + 1;
+    let sums = self.partial_sums(&constraint.terms, limit);
+    if let Some(&literal) = sums.get(&limit) {
+      self.clauses.push(vec![!literal]);
+    }
+  }
+
+  /// 部分和の取りうる値(limit以上はlimitにまとめる)ごとの，その値以上であることを表すリテラル
+  fn partial_sums(&mut self, terms: &[(u64, Literal)], limit: u64) -> BTreeMap<u64, Literal> {
+    if terms.len() == 1 {
+      let (coefficient, literal) = terms[0];
+      return BTreeMap::from([(coefficient.min(limit), literal)]);
+    }
+    let (left, right) = terms.split_at(terms.len() / 2);
+    let left = self.partial_sums(left, limit);
+    let right = self.partial_sums(right, limit);
+
+    let with_zero = |sums: &BTreeMap<u64, Literal>| {
+      let mut values = vec![(0, None)];
+      values.extend(sums.iter().map(|(&value, &literal)| (value, Some(literal))));
+      values
+    };
+    let (left, right) = (with_zero(&left), with_zero(&right));
+    let values = left
+      .iter()
+      .flat_map(|&(a, _)| right.iter().map(move |&(b, _)| (a + b).min(limit)))
+      .filter(|&value| value > 0)
+      .collect::<BTreeSet<_>>();
+    let sums = values
+      .into_iter()
+      .map(|value| (value, self.fresh()))
+      .collect::<BTreeMap<_, _>>();
+
+    for &(a, left_literal) in &left {
+      for &(b, right_literal) in &right {
+        if a + b == 0 {
+          continue;
+        }
+        let mut clause = vec![];
+        clause.extend(left_literal.map(|literal| !literal));
+        clause.extend(right_literal.map(|literal| !literal));
+        clause.push(sums[&(a + b).min(limit)]);
+        self.clauses.push(clause);
+      }
+    }
+    sums
+  }
+
+  fn watchdog(&mut self, constraint: &AtMost) {
+    // 2^width > bound となる桁数で，和 + offset >= 2^width と 和 > bound が同値になる
+    let width = (u64::BITS - constraint.bound.leading_zeros()) as usize;
+    let offset = (1u128 << width) - (constraint.bound as u128 + 1);
+
+    let mut buckets = vec![vec![]; width];
+    for &(coefficient, literal) in &constraint.terms {
+      for (bit, bucket) in buckets.iter_mut().enumerate() {
+        if coefficient >> bit & 1 == 1 {
+          bucket.push(literal);
+        }
+      }
+    }
+    if offset > 0 {
+      let constant = self.fresh();
+      self.clauses.push(vec![constant]);
+      for (bit, bucket) in buckets.iter_mut().enumerate() {
+        if offset >> bit & 1 == 1 {
+          bucket.push(constant);
+        }
+      }
+    }
+
+    // 下の桁から，その桁の重みのリテラルと繰り上がりを1進数で数える
+    let mut carries = vec![];
+    for (bit, bucket) in buckets.into_iter().enumerate() {
+      let mut inputs = bucket;
+      inputs.extend(carries);
+      let limit = if bit == width - 1 { 2 } else { inputs.len() };
+      let (counts, clauses) = unary_count(&inputs, limit, self.next_variable);
+      self.clauses.extend(clauses);
+      carries = counts.into_iter().skip(1).step_by(2).collect();
+    }
+    if let Some(&carry) = carries.first() {
+      self.clauses.push(vec![!carry]);
+    }
+  }
+}
+
+/// 2進数の和bitsがbound以下になる節
+/// boundが0の桁jでbitsが1になるとき，jより上でboundが1の桁のどれかはbitsが0でなければならない
+fn compare_at_most(bits: &[Option<Literal>], bound: u64) -> Vec<Vec<Literal>> {
+  let width = bits.len().max((u64::BITS - bound.leading_zeros()) as usize);
+  let mut clauses = vec![];
+  'bits: for j in 0..bits.len() {
+    let Some(bit) = bits[j] else {
+      continue;
+    };
+    if bound >> j & 1 == 1 {
+      continue;
+    }
+    let mut clause = vec![!bit];
+    for i in j + 1..width {
+      if i >= u64::BITS as usize || bound >> i & 1 == 0 {
+        continue;
+      }
+      match bits.get(i).copied().flatten() {
+        Some(higher) => clause.push(!higher),
+        // 上の桁が0に決まっていればboundを超えない
+        None => continue 'bits,
+      }
+    }
+    clauses.push(clause);
+  }
+  clauses
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{pb::constraint::Comparison, random::Random, testing};
+
+  /// 割り当てでの項の和
+  fn sum(terms: &[(i64, Literal)], assignment: u64) -> i64 {
+    terms
+      .iter()
+      .filter(|&&(_, literal)| testing::holds(assignment, literal))
+      .map(|&(coefficient, _)| coefficient)
+      .sum()
+  }
+
+  /// 負の係数・否定のリテラル・同じ変数の項も含む項
+  fn random_terms(
+    random: &mut Random,
+    num_variables: usize,
+    max_coefficient: usize,
+  ) -> Vec<(i64, Literal)> {
+    (0..1 + random.below(num_variables + 1))
+      .map(|_| {
+        let coefficient = random.below(2 * max_coefficient + 1) as i64 - max_coefficient as i64;
+        let literal = Literal::new(random.below(num_variables), random.below(2) == 0);
+        (coefficient, literal)
+      })
+      .collect()
+  }
+
+  #[test]
+  fn encodes_constraints() {
+    let num_variables = 6;
+    for encoding in PbEncoding::ALL {
+      let mut random = Random::new(14);
+      for _ in 0..40 {
+        let max_coefficient = 1 + random.below(20);
+        let terms = random_terms(&mut random, num_variables, max_coefficient);
+        let comparison = Comparison::ALL[random.below(3)];
+        let bound = random.below(4 * max_coefficient + 1) as i64 - 2 * max_coefficient as i64;
+        let constraint = PbConstraint::new(terms, comparison, bound);
+        let mut next_variable = num_variables;
+        let clauses = encode(&constraint, encoding, &mut next_variable);
+        for assignment in 0..1 << num_variables {
+          let sum = sum(&constraint.terms, assignment);
+          let holds = match comparison {
+            Comparison::AtLeast => sum >= bound,
+            Comparison::AtMost => sum <= bound,
+            Comparison::Equal => sum == bound,
+          };
+          assert_eq!(
+            testing::extendable(num_variables, next_variable, &clauses, assignment),
+            holds,
+            "{} {:?}",
+            encoding,
+            constraint
+          );
+        }
+      }
+    }
+  }
+
+  /// 係数の形に合わせて補助変数が少なくなる
+  #[test]
+  fn bounds_sum_outputs() {
+    let num_variables = 6;
+    for encoding in PbEncoding::ALL {
+      let mut random = Random::new(16);
+      for _ in 0..10 {
+        // 目的関数の項と同じく変数は重複しない
+        let mut terms = vec![];
+        for variable in 0..num_variables {
+          if random.below(3) != 0 {
+            let literal = Literal::new(variable, random.below(2) == 0);
+            terms.push((1 + random.below(6) as u64, literal));
+          }
+        }
+        let total = terms
+          .iter()
+          .map(|&(coefficient, _)| coefficient)
+          .sum::<u64>();
+        let limit = 1 + random.below(total as usize + 1) as u64;
+        let mut next_variable = num_variables;
+        let (outputs, clauses) = encode_sum(&terms, limit, encoding, &mut next_variable);
+        let terms = terms
+          .iter()
+          .map(|&(coefficient, literal)| (coefficient as i64, literal))
+          .collect::<Vec<_>>();
+        for bound in 0..limit {
+          let mut bounded = clauses.clone();
+          bounded.extend(outputs.at_most(bound));
+          for assignment in 0..1 << num_variables {
+            assert_eq!(
+              testing::extendable(num_variables, next_variable, &bounded, assignment),
+              sum(&terms, assignment) <= bound as i64,
+              "{} {:?} limit {} bound {}",
+              encoding,
+              terms,
+              limit,
+              bound
+            );
+          }
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn falls_back_to_binary_sum() {
+    let literals = (0..64).map(|variable| Literal::new(variable, true));
+    let small = literals
+      .clone()
+      .map(|literal| (1, literal))
+      .collect::<Vec<_>>();
+    // 重みが全て異なると部分和の値が多くなる
+    let large = literals
+      .zip(1..)
+      .map(|(literal, i)| (i * 1_000_003, literal))
+      .collect::<Vec<_>>();
+    for encoding in [PbEncoding::Bdd, PbEncoding::GeneralizedTotalizer] {
+      let mut next_variable = 64;
+      let (outputs, _) = encode_sum(&small, 64, encoding, &mut next_variable);
+      assert!(matches!(outputs, SumOutputs::Unary(_)));
+      let limit = large.iter().map(|&(coefficient, _)| coefficient).sum();
+      let (outputs, clauses) = encode_sum(&large, limit, encoding, &mut next_variable);
+      assert!(matches!(outputs, SumOutputs::Binary(_)));
+      assert!(clauses.len() <= MAX_UNARY_CLAUSES);
+    }
+  }
+
+  #[test]
+  fn exploits_coefficient_structure() {
+    let size = |terms: Vec<(u64, Literal)>, bound: u64, encoding: PbEncoding| {
+      let mut next_variable = terms.len();
+      let clauses = encode_at_most(&AtMost { terms, bound }, encoding, &mut next_variable);
+      (clauses.len(), next_variable)
+    };
+    let literals = (0..30)
+      .map(|variable| Literal::new(variable, true))
+      .collect::<Vec<_>>();
+
+    // 係数が全て1なら個数制約と同じで，上限の区間を共有する節点や部分和はk+1通りに収まる
+    let units = literals
+      .iter()
+      .map(|&literal| (1, literal))
+      .collect::<Vec<_>>();
+    for encoding in [PbEncoding::Bdd, PbEncoding::GeneralizedTotalizer] {
+      let (_, next_variable) = size(units.clone(), 5, encoding);
+      assert!(next_variable - 30 <= 30 * 6, "{}", encoding);
+    }
+
+    // 係数が異なる2のべき乗ならリテラルがそのまま和の各桁になる
+    let powers = literals[..16]
+      .iter()
+      .zip(0..)
+      .map(|(&literal, bit)| (1 << bit, literal))
+      .collect::<Vec<_>>();
+    let (clauses, next_variable) = size(powers, 30000, PbEncoding::Adder);
+    assert_eq!(next_variable, 16);
+    assert!(clauses <= 16);
+  }
+
+  #[test]
+  fn parses_names() {
+    for encoding in PbEncoding::ALL {
+      assert_eq!(encoding.name().parse::<PbEncoding>().unwrap(), encoding);
+    }
+    assert!("sorter".parse::<PbEncoding>().is_err());
+  }
+
+  #[test]
+  fn reports_unknown_names() {
+    let error = "sorter".parse::<PbEncoding>().unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "unknown encoding: sorter (expected one of bdd, adder, generalized-totalizer, watchdog)"
+    );
+  }
+}
@@ -0,0 +1,4 @@
+pub mod constraint;
+pub mod encoding;
+pub mod opb;
+pub mod optimize;
@@ -0,0 +1,483 @@
+use std::{error::Error, fmt, path::Path, str::FromStr};
+
+use crate::{
+  expressions::literal::Literal,
+  io,
+  pb::{
+    constraint::{Comparison, PbConstraint},
+    optimize::{MinimizeResult, Optimum},
+  },
+  sat_solver::{SATSolver, Variable},
+};
+
+/// PB競技会のOPB形式の問題
+/// 変数x1, x2, ...は0, 1, ...番のリテラルで表す
+#[derive(Clone, Debug, Default)]
+pub struct Opb {
+  num_variables: usize,
+  /// 最小化する目的関数(min: の行)
+  objective: Option<Vec<(i64, Literal)>>,
+  constraints: Vec<PbConstraint>,
+}
+
+impl Opb {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// parse opb file
+  /// `+2 x1 -1 ~x2 >= 1 ;` のような線形制約と，`min: +1 x1 ;` の目的関数を読む
+  pub fn from<P: AsRef<Path>>(opb_file: P) -> Result<Opb, Box<dyn Error>> {
+    Ok(io::read_file(opb_file)?.join("\n").parse()?)
+  }
+
+  pub fn num_variables(&self) -> usize {
+    self.num_variables
+  }
+
+  pub fn objective(&self) -> Option<&[(i64, Literal)]> {
+    self.objective.as_deref()
+  }
+
+  pub fn constraints(&self) -> &[PbConstraint] {
+    &self.constraints
+  }
+
+  pub fn set_objective(&mut self, objective: Vec<(i64, Literal)>) {
+    self.num_variables = self.num_variables.max(max_variable(&objective));
+    self.objective = Some(objective);
+  }
+
+  pub fn add_constraint(&mut self, constraint: PbConstraint) {
+    self.num_variables = self.num_variables.max(max_variable(&constraint.terms));
+    self.constraints.push(constraint);
+  }
+
+  pub fn solve(&mut self) -> Option<Option<Vec<i64>>> {
+    self.solve_with(|_| {})
+  }
+
+  /// 制約を満たす解を求める(目的関数は使わない)
+  /// 解があればSome(Some(解))，充足不能ならSome(None)，上限に達して分からなければNoneを返す
+  /// 解く前にconfigureでソルバの設定を変更する
+  pub fn solve_with<F: FnOnce(&mut SATSolver<usize>)>(
+    &mut self,
+    configure: F,
+  ) -> Option<Option<Vec<i64>>> {
+    let mut solver = self.solver(configure);
+    if !solver.solve_limited()? {
+      return Some(None);
+    }
+    Some(Some(
+      self.solution(|i| solver.get_model_value_from_name(&i).unwrap()),
+    ))
+  }
+
+  /// 目的関数を最小にする解を求める(目的関数がなければ制約を満たす解を1つ求める)
+  /// コストが小さくなる解を見つけるたびにon_improveをコストで呼ぶ
+  pub fn minimize_with<F: FnOnce(&mut SATSolver<usize>), G: FnMut(i64)>(
+    &mut self,
+    configure: F,
+    mut on_improve: G,
+  ) -> MinimizeResult<Vec<i64>> {
+    let mut solver = self.solver(configure);
+    let objective = self
+      .objective
+      .iter()
+      .flatten()
+      .map(|&(coefficient, literal)| (coefficient, variable(&mut solver, literal)))
+      .collect::<Vec<_>>();
+    solver
+      .minimize_with(&objective, |cost, _| on_improve(cost))
+      .map(|optimum| Optimum {
+        cost: optimum.cost,
+        model: self.solution(|i| optimum.model[&i]),
+        optimal: optimum.optimal,
+      })
+  }
+
+  /// 全ての制約を加えたソルバ
+  fn solver<F: FnOnce(&mut SATSolver<usize>)>(&self, configure: F) -> SATSolver<usize> {
+    let mut solver = SATSolver::new();
+    configure(&mut solver);
+    for i in 1..=self.num_variables {
+      let _ = solver.variable(i);
+    }
+
+    for constraint in &self.constraints {
+      let terms = constraint
+        .terms
+        .iter()
+        .map(|&(coefficient, literal)| (coefficient, variable(&mut solver, literal)))
+        .collect::<Vec<_>>();
+      solver.add_pb_constraint(&terms, constraint.comparison, constraint.bound);
+    }
+    solver
+  }
+
+  /// 変数の値から，真ならi，偽なら-iを並べた解を作る
+  fn solution<F: Fn(usize) -> bool>(&self, value: F) -> Vec<i64> {
+    (1..=self.num_variables)
+      .map(|i| if value(i) { i as i64 } else { -(i as i64) })
+      .collect()
+  }
+}
+
+/// リテラルに対応するソルバの変数
+fn variable(solver: &mut SATSolver<usize>, literal: Literal) -> Variable {
+  let variable = solver.variable(literal.variable() + 1);
+  if literal.sign() {
+    variable
+  } else {
+    !variable
+  }
+}
+
+fn max_variable(terms: &[(i64, Literal)]) -> usize {
+  terms
+    .iter()
+    .map(|(_, literal)| literal.variable() + 1)
+    .max()
+    .unwrap_or(0)
+}
+
+impl FromStr for Opb {
+  type Err = OpbParseError;
+
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let mut opb = Opb::new();
+    let mut header = None;
+    let mut statements = String::new();
+    for line in text.lines() {
+      let line = line.trim();
+      if let Some(comment) = line.strip_prefix('*') {
+        if header.is_none() {
+          header = parse_header(comment);
+        }
+        continue;
+      }
+      statements.push_str(line);
+      statements.push('\n');
+    }
+
+    let mut statements = statements.split(';').collect::<Vec<_>>();
+    // 最後の;の後には何も書かない
+    let last = statements.pop().unwrap_or_default();
+    if !last.trim().is_empty() {
+      return Err(OpbParseError(format!("missing ';': {}", last.trim())));
+    }
+
+    for statement in statements {
+      let words = statement.split_whitespace().collect::<Vec<_>>();
+      if words.is_empty() {
+        continue;
+      }
+      if let Some(rest) = words[0].strip_prefix("min:") {
+        if opb.objective.is_some() {
+          return Err(OpbParseError("multiple objectives".to_string()));
+        }
+        let mut words = words[1..].to_vec();
+        if !rest.is_empty() {
+          words.insert(0, rest);
+        }
+        opb.set_objective(parse_terms(&words)?);
+        continue;
+      }
+
+      let position = words
+        .iter()
+        .position(|word| word.parse::<Comparison>().is_ok())
+        .ok_or_else(|| OpbParseError(format!("missing comparison: {}", statement.trim())))?;
+      let comparison = words[position].parse::<Comparison>().unwrap();
+      let terms = parse_terms(&words[..position])?;
+      let bound = match words[position + 1..] {
+        [bound] => parse_integer(bound)?,
+        _ => {
+          return Err(OpbParseError(format!(
+            "invalid bound: {}",
+            statement.trim()
+          )))
+        }
+      };
+      opb.add_constraint(PbConstraint::new(terms, comparison, bound));
+    }
+
+    if let Some((num_variables, num_constraints)) = header {
+      if num_constraints != opb.constraints.len() || num_variables < opb.num_variables {
+        return Err(OpbParseError(
+          "the header does not match the problem".to_string(),
+        ));
+      }
+      opb.num_variables = num_variables;
+    }
+    Ok(opb)
+  }
+}
+
+/// `#variable= 5 #constraint= 4` から(変数の数, 制約の数)を読む
+fn parse_header(comment: &str) -> Option<(usize, usize)> {
+  let words = comment.split_whitespace().collect::<Vec<_>>();
+  let value = |key: &str| {
+    let position = words.iter().position(|&word| word == key)?;
+    words.get(position + 1)?.parse::<usize>().ok()
+  };
+  Some((value("#variable=")?, value("#constraint=")?))
+}
+
+/// 係数とリテラルが交互に並んだ項を読む
+fn parse_terms(words: &[&str]) -> Result<Vec<(i64, Literal)>, OpbParseError> {
+  if !words.len().is_multiple_of(2) {
+    return Err(OpbParseError(format!(
+      "non-linear or incomplete terms are not supported: {}",
+      words.join(" ")
+    )));
+  }
+  words
+    .chunks(2)
+    .map(|term| Ok((parse_integer(term[0])?, parse_literal(term[1])?)))
+    .collect()
+}
+
+fn parse_integer(word: &str) -> Result<i64, OpbParseError> {
+  word
+    .strip_prefix('+')
+    .unwrap_or(word)
+    .parse::<i64>()
+    .map_err(|_| OpbParseError(format!("invalid integer: {}", word)))
+}
+
+/// x3 や否定の ~x3 を読む
+fn parse_literal(word: &str) -> Result<Literal, OpbParseError> {
+  let (sign, name) = match word.strip_prefix('~') {
+    Some(name) => (false, name),
+    None => (true, word),
+  };
+  let index = name
+    .strip_prefix('x')
+    .and_then(|index| index.parse::<usize>().ok())
+    .filter(|&index| index > 0)
+    .ok_or_else(|| OpbParseError(format!("invalid literal: {}", word)))?;
+  Ok(Literal::new(index - 1, sign))
+}
+
+/// OPB形式で書き出す(<=の制約は両辺の符号を変えて>=にする)
+impl fmt::Display for Opb {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(
+      f,
+      "* #variable= {} #constraint= {}",
+      self.num_variables,
+      self.constraints.len()
+    )?;
+    if let Some(objective) = &self.objective {
+      let mut words = vec!["min:".to_string()];
+      words.extend(format_terms(objective, 1));
+      words.push(";".to_string());
+      writeln!(f, "{}", words.join(" "))?;
+    }
+    for constraint in &self.constraints {
+      let (comparison, sign) = match constraint.comparison {
+        Comparison::AtMost => (Comparison::AtLeast, -1),
+        comparison => (comparison, 1),
+      };
+      let mut words = format_terms(&constraint.terms, sign);
+      words.push(comparison.to_string());
+      words.push((sign * constraint.bound).to_string());
+      words.push(";".to_string());
+      writeln!(f, "{}", words.join(" "))?;
+    }
+    Ok(())
+  }
+}
+
+/// 各項を "+2 x1" や "-1 ~x3" の形にする
+fn format_terms(terms: &[(i64, Literal)], sign: i64) -> Vec<String> {
+  terms
+    .iter()
+    .map(|&(coefficient, literal)| {
+      let negation = if literal.sign() { "" } else { "~" };
+      format!(
+        "{:+} {}x{}",
+        sign * coefficient,
+        negation,
+        literal.variable() + 1
+      )
+    })
+    .collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct OpbParseError(pub String);
+
+impl fmt::Display for OpbParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "failed to parse opb file: {}", self.0)
+  }
+}
+
+impl Error for OpbParseError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    pb::{encoding::PbEncoding, optimize::Search},
+    random::Random,
+  };
+
+  fn x(index: usize) -> Literal {
+    Literal::new(index - 1, true)
+  }
+
+  #[test]
+  fn parses_constraints_and_objective() {
+    let text = "* #variable= 4 #constraint= 2\n\
+                min: +1 x1 -2 ~x3 ;\n\
+                +2 x1 +1 x2\n>= 2 ;\n\
+                -1 x2 +1 ~x3 = 0 ;\n";
+    let opb: Opb = text.parse().unwrap();
+    assert_eq!(opb.num_variables(), 4);
+    assert_eq!(opb.objective(), Some(&[(1, x(1)), (-2, !x(3))][..]));
+    assert_eq!(
+      opb.constraints(),
+      &[
+        PbConstraint::new(vec![(2, x(1)), (1, x(2))], Comparison::AtLeast, 2),
+        PbConstraint::new(vec![(-1, x(2)), (1, !x(3))], Comparison::Equal, 0),
+      ]
+    );
+
+    // 書き出したものを読み直すと同じ問題になる
+    let reparsed: Opb = opb.to_string().parse().unwrap();
+    assert_eq!(reparsed.objective(), opb.objective());
+    assert_eq!(reparsed.constraints(), opb.constraints());
+    assert_eq!(reparsed.num_variables(), opb.num_variables());
+  }
+
+  #[test]
+  fn rejects_malformed_input() {
+    let inputs = [
+      "+1 x1 >= 1",
+      "+1 x1 1 ;",
+      "+1 x1 >= 1 2 ;",
+      "+1 x1 x2 >= 1 ;",
+      "+a x1 >= 1 ;",
+      "+1 y1 >= 1 ;",
+      "+1 x0 >= 1 ;",
+      "+1 x1 > 1 ;",
+      "min: +1 x1 ;\nmin: +1 x2 ;",
+      "* #variable= 1 #constraint= 1\n+1 x2 >= 1 ;",
+      "* #variable= 2 #constraint= 2\n+1 x2 >= 1 ;",
+    ];
+    for input in inputs {
+      assert!(input.parse::<Opb>().is_err(), "{}", input);
+    }
+  }
+
+  #[test]
+  fn solves_like_brute_force() {
+    let mut random = Random::new(15);
+    let num_variables = 6;
+    for encoding in PbEncoding::ALL {
+      for _ in 0..20 {
+        let mut opb = Opb::new();
+        for _ in 0..1 + random.below(4) {
+          let terms = (0..1 + random.below(num_variables))
+            .map(|_| {
+              let coefficient = random.below(9) as i64 - 4;
+              let literal = Literal::new(random.below(num_variables), random.below(2) == 0);
+              (coefficient, literal)
+            })
+            .collect::<Vec<_>>();
+          let comparison = Comparison::ALL[random.below(3)];
+          let bound = random.below(9) as i64 - 4;
+          opb.add_constraint(PbConstraint::new(terms, comparison, bound));
+        }
+        let satisfies = |value: &dyn Fn(usize) -> bool| {
+          opb.constraints().iter().all(|constraint| {
+            let sum = constraint
+              .terms
+              .iter()
+              .filter(|(_, literal)| value(literal.variable()) == literal.sign())
+              .map(|&(coefficient, _)| coefficient)
+              .sum::<i64>();
+            match constraint.comparison {
+              Comparison::AtLeast => sum >= constraint.bound,
+              Comparison::AtMost => sum <= constraint.bound,
+              Comparison::Equal => sum == constraint.bound,
+            }
+          })
+        };
+        let satisfiable = (0..1u64 << opb.num_variables())
+          .any(|assignment| satisfies(&|variable| assignment >> variable & 1 == 1));
+
+        let solution = opb
+          .clone()
+          .solve_with(|solver| solver.set_pb_encoding(encoding))
+          .unwrap();
+        assert_eq!(solution.is_some(), satisfiable, "{}", opb);
+        if let Some(solution) = solution {
+          assert!(satisfies(&|variable| solution[variable] > 0), "{}", opb);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn minimizes_like_brute_force() {
+    let mut random = Random::new(19);
+    let num_variables = 6;
+    for search in Search::ALL {
+      for _ in 0..20 {
+        let mut opb = Opb::new();
+        let random_terms = |random: &mut Random| {
+          (0..1 + random.below(num_variables))
+            .map(|_| {
+              let coefficient = random.below(9) as i64 - 4;
+              let literal = Literal::new(random.below(num_variables), random.below(2) == 0);
+              (coefficient, literal)
+            })
+            .collect::<Vec<_>>()
+        };
+        opb.set_objective(random_terms(&mut random));
+        let terms = random_terms(&mut random);
+        opb.add_constraint(PbConstraint::new(terms, Comparison::AtLeast, 1));
+        let value = |terms: &[(i64, Literal)], assignment: u64| {
+          terms
+            .iter()
+            .filter(|(_, literal)| (assignment >> literal.variable() & 1 == 1) == literal.sign())
+            .map(|&(coefficient, _)| coefficient)
+            .sum::<i64>()
+        };
+        let constraint = &opb.constraints()[0].terms;
+        let objective = opb.objective().unwrap();
+        let minimum = (0..1u64 << opb.num_variables())
+          .filter(|&assignment| value(constraint, assignment) >= 1)
+          .map(|assignment| value(objective, assignment))
+          .min();
+
+        let mut improvements = vec![];
+        let optimum = opb.clone().minimize_with(
+          |solver| solver.set_search(search),
+          |cost| improvements.push(cost),
+        );
+        let optimum = optimum.optimum();
+        assert_eq!(
+          optimum.as_ref().map(|optimum| optimum.cost),
+          minimum,
+          "{}",
+          opb
+        );
+        if let Some(optimum) = optimum {
+          assert!(optimum.optimal);
+          let assignment = optimum
+            .model
+            .iter()
+            .filter(|&&value| value > 0)
+            .fold(0, |assignment, &value| assignment | 1 << (value - 1));
+          assert_eq!(value(objective, assignment), optimum.cost);
+          assert_eq!(improvements.last(), Some(&optimum.cost));
+        }
+      }
+    }
+  }
+}
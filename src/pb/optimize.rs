@@ -0,0 +1,453 @@
+use std::{collections::BTreeMap, error::Error, fmt, str::FromStr, time::Instant};
+
+use crate::{
+  cdcl::solver::{Cdcl, SolveResult},
+  expressions::{literal::Literal, model::Model},
+  pb::encoding::{self, PbEncoding, SumOutputs},
+  statistics::Statistics,
+};
+
+/// 目的関数の上限の探し方
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Search {
+  /// 見つけた解のコストより小さくなる制約を加えて，充足不能になるまで解き直す(SAT-UNSAT)
+  #[default]
+  Linear,
+  /// 下限と見つけた解のコストの中央を上限として仮定し，範囲を半分にしていく
+  Binary,
+}
+
+impl Search {
+  pub const ALL: [Search; 2] = [Search::Linear, Search::Binary];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Search::Linear => "linear",
+      Search::Binary => "binary",
+    }
+  }
+}
+
+impl fmt::Display for Search {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl FromStr for Search {
+  type Err = UnknownSearchError;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    Search::ALL
+      .into_iter()
+      .find(|search| search.name() == name)
+      .ok_or_else(|| UnknownSearchError(name.to_string()))
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct UnknownSearchError(pub String);
+
+impl fmt::Display for UnknownSearchError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "unknown search: {} (expected linear or binary)", self.0)
+  }
+}
+
+impl Error for UnknownSearchError {}
+
+/// 最小化の結果
+#[derive(Clone, Debug)]
+pub struct Optimum<M> {
+  pub cost: i64,
+  pub model: M,
+  /// 最適であることを示せたか(探索の上限に達したらfalse)
+  pub optimal: bool,
+}
+
+/// 最小化の結果
+#[derive(Clone, Debug)]
+pub enum MinimizeResult<M> {
+  /// 解が見つかった(最適とは限らない)
+  Found(Optimum<M>),
+  /// 制約を満たす解がない
+  Unsatisfiable,
+  /// 期限や探索の上限までに解が見つからなかった
+  Unknown,
+}
+
+impl<M> MinimizeResult<M> {
+  /// 見つかった解(なければNone)
+  pub fn optimum(self) -> Option<Optimum<M>> {
+    match self {
+      MinimizeResult::Found(optimum) => Some(optimum),
+      MinimizeResult::Unsatisfiable | MinimizeResult::Unknown => None,
+    }
+  }
+
+  /// 見つかった解をfで変換する
+  pub fn map<N, F: FnOnce(Optimum<M>) -> Optimum<N>>(self, f: F) -> MinimizeResult<N> {
+    match self {
+      MinimizeResult::Found(optimum) => MinimizeResult::Found(f(optimum)),
+      MinimizeResult::Unsatisfiable => MinimizeResult::Unsatisfiable,
+      MinimizeResult::Unknown => MinimizeResult::Unknown,
+    }
+  }
+}
+
+/// 目的関数sum(a_i * l_i)を最小にする解を求める
+/// 1つのCDCLソルバに目的関数の上限の制約を加えながら解き直すので，学習節は次の探索でも使われる
+/// 目的関数の和は最初の解が見つかったときに一度だけ節に変換し，上限はその出力への短い節で加える
+pub struct Optimizer {
+  solver: Cdcl,
+  objective: Vec<(i64, Literal)>,
+  /// 目的関数を正規化した定数と正の係数の項
+  constant: i64,
+  terms: Vec<(u64, Literal)>,
+  search: Search,
+  encoding: PbEncoding,
+  /// 項の和の出力(最初の上限を加えるときに作る)
+  outputs: Option<SumOutputs>,
+  /// 探索を打ち切る時刻
+  deadline: Option<Instant>,
+}
+
+impl Optimizer {
+  /// solverには元の問題の制約を全て追加しておく
+  pub fn new(
+    solver: Cdcl,
+    objective: Vec<(i64, Literal)>,
+    search: Search,
+    encoding: PbEncoding,
+  ) -> Self {
+    let (constant, terms) = normalize_objective(&objective);
+    let terms = terms
+      .into_iter()
+      .map(|(coefficient, literal)| (coefficient as u64, literal))
+      .collect();
+    Self {
+      solver,
+      objective,
+      constant,
+      terms,
+      search,
+      encoding,
+      outputs: None,
+      deadline: None,
+    }
+  }
+
+  /// 探索を打ち切る時刻を設定する(過ぎたらそれまでで最良の解を返す)
+  /// 目的関数の変換の前にも確かめる
+  pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+    self.deadline = deadline;
+  }
+
+  pub fn statistics(&self) -> &Statistics {
+    self.solver.statistics()
+  }
+
+  /// 最小のコストの解を求める
+  /// コストが小さくなる解を見つけるたびにon_improveを(コスト, 解)で呼ぶ
+  pub fn minimize<F: FnMut(i64, &Model)>(&mut self, mut on_improve: F) -> MinimizeResult<Model> {
+    let deadline = self.deadline;
+    // 下限より小さいコストの解はない
+    let mut lower = self.constant;
+    let mut best: Option<(i64, Model)> = None;
+    let optimal = loop {
+      // 今回の探索で仮定する上限(二分探索のとき)
+      let mut assumption = None;
+      if let Some((cost, _)) = &best {
+        if *cost <= lower {
+          break true;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+          break false;
+        }
+        if self.outputs.is_none() && !self.encode_objective(*cost) {
+          break true;
+        }
+        let bound = match self.search {
+          Search::Linear => cost - 1,
+          Search::Binary => lower + (cost - 1 - lower) / 2,
+        };
+        let activation = match self.search {
+          Search::Linear => None,
+          Search::Binary => Some(Literal::new(self.solver.add_variables(1), true)),
+        };
+        if !self.add_bound(bound, activation) {
+          break true;
+        }
+        assumption = activation.map(|activation| (activation, bound));
+      }
+
+      let assumptions = assumption
+        .iter()
+        .map(|&(activation, _)| activation)
+        .collect::<Vec<_>>();
+      match self.solver.solve_with_budget(&assumptions, None, deadline) {
+        SolveResult::Satisfiable(model) => {
+          let cost = cost(&self.objective, &model);
+          on_improve(cost, &model);
+          if let Some((activation, _)) = assumption {
+            self.solver.add_clause(&[activation]);
+          }
+          best = Some((cost, model));
+        }
+        SolveResult::Unsatisfiable => match assumption {
+          Some((activation, bound)) => {
+            lower = bound + 1;
+            self.solver.add_clause(&[!activation]);
+          }
+          None if best.is_none() => return MinimizeResult::Unsatisfiable,
+          None => break true,
+        },
+        SolveResult::Unknown => break false,
+      }
+    };
+
+    match best {
+      Some((cost, model)) => MinimizeResult::Found(Optimum {
+        cost,
+        model,
+        optimal,
+      }),
+      None => MinimizeResult::Unknown,
+    }
+  }
+
+  /// 目的関数の項の和を節に変換して加える
+  /// 以後の上限は全てcostより小さいので，cost以上の和は区別しない
+  fn encode_objective(&mut self, cost: i64) -> bool {
+    let limit = (cost - self.constant) as u64;
+    let first = self.solver.num_variables();
+    let mut next_variable = first;
+    let (outputs, clauses) =
+      encoding::encode_sum(&self.terms, limit, self.encoding, &mut next_variable);
+    self.solver.add_variables(next_variable - first);
+    self.outputs = Some(outputs);
+    clauses.iter().all(|clause| self.solver.add_clause(clause))
+  }
+
+  /// 目的関数がbound以下になる制約を加える
+  /// activationがあれば，それが真のときだけ制約が有効になる
+  fn add_bound(&mut self, bound: i64, activation: Option<Literal>) -> bool {
+    let clauses = match &self.outputs {
+      Some(outputs) if bound >= self.constant => outputs.at_most((bound - self.constant) as u64),
+      _ => vec![vec![]],
+    };
+    for mut clause in clauses {
+      if let Some(activation) = activation {
+        clause.push(!activation);
+      }
+      if !self.solver.add_clause(&clause) {
+        return false;
+      }
+    }
+    true
+  }
+}
+
+/// 解での目的関数の値
+pub fn cost(objective: &[(i64, Literal)], model: &Model) -> i64 {
+  objective
+    .iter()
+    .filter(|(_, literal)| model.sign(literal.variable()).unwrap_or(false) == literal.sign())
+    .map(|&(coefficient, _)| coefficient)
+    .sum()
+}
+
+/// 目的関数を定数と正の係数の項の和にする
+/// 同じ変数の項はまとめ，否定のリテラルの項は定数と変数の項に分ける(a*!x = a - a*x)
+/// 定数は目的関数の取りうる最小の値になる
+pub(crate) fn normalize_objective(objective: &[(i64, Literal)]) -> (i64, Vec<(i64, Literal)>) {
+  let mut constant = 0;
+  let mut coefficients: BTreeMap<usize, i64> = BTreeMap::new();
+  for &(coefficient, literal) in objective {
+    let entry = coefficients.entry(literal.variable()).or_default();
+    if literal.sign() {
+      *entry += coefficient;
+    } else {
+      *entry -= coefficient;
+      constant += coefficient;
+    }
+  }
+
+  let mut terms = vec![];
+  for (variable, coefficient) in coefficients {
+    if coefficient > 0 {
+      terms.push((coefficient, Literal::new(variable, true)));
+    } else if coefficient < 0 {
+      constant += coefficient;
+      terms.push((-coefficient, Literal::new(variable, false)));
+    }
+  }
+  (constant, terms)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    pb::encoding::encode_sum,
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  /// 負の係数・否定のリテラル・同じ変数の項も含む目的関数
+  fn random_objective(random: &mut Random, num_variables: usize) -> Vec<(i64, Literal)> {
+    (0..1 + random.below(2 * num_variables))
+      .map(|_| {
+        let coefficient = random.below(21) as i64 - 10;
+        (
+          coefficient,
+          Literal::new(random.below(num_variables), random.below(2) == 0),
+        )
+      })
+      .collect()
+  }
+
+  fn assignment_cost(objective: &[(i64, Literal)], assignment: u64) -> i64 {
+    objective
+      .iter()
+      .filter(|&&(_, literal)| testing::holds(assignment, literal))
+      .map(|&(coefficient, _)| coefficient)
+      .sum()
+  }
+
+  #[test]
+  fn finds_minimum() {
+    let num_variables = 8;
+    for search in Search::ALL {
+      for encoding in PbEncoding::ALL {
+        let mut random = Random::new(17);
+        for _ in 0..25 {
+          let clauses = random_clauses(&mut random, num_variables, 3 * num_variables, 3);
+          let objective = random_objective(&mut random, num_variables);
+          let minimum = testing::models(num_variables, &clauses)
+            .into_iter()
+            .map(|assignment| assignment_cost(&objective, assignment))
+            .min();
+
+          let mut solver = Cdcl::new(num_variables);
+          for clause in &clauses {
+            solver.add_clause(clause);
+          }
+          let mut optimizer = Optimizer::new(solver, objective.clone(), search, encoding);
+          let mut improvements = vec![];
+          let result = optimizer.minimize(|cost, model| {
+            let assignment = testing::assignment(model, num_variables);
+            assert!(clauses
+              .iter()
+              .all(|clause| testing::satisfies(assignment, clause)));
+            assert_eq!(assignment_cost(&objective, assignment), cost);
+            improvements.push(cost);
+          });
+          match (result, minimum) {
+            (MinimizeResult::Found(optimum), Some(minimum)) => {
+              assert!(optimum.optimal);
+              assert_eq!(optimum.cost, minimum, "{} {}", search, encoding);
+              assert_eq!(cost(&objective, &optimum.model), minimum);
+              assert!(improvements.windows(2).all(|pair| pair[0] > pair[1]));
+              assert_eq!(improvements.last(), Some(&minimum));
+            }
+            (MinimizeResult::Unsatisfiable, None) => assert!(improvements.is_empty()),
+            (result, minimum) => panic!("{:?} {:?}", result, minimum),
+          }
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn normalizes_objective() {
+    let x = |variable: usize, sign: bool| Literal::new(variable, sign);
+    // 3*x0 - 2*!x0 + 4*!x1 = 2 + 5*x0 - 4*x1 = -2 + 5*x0 + 4*!x1
+    let objective = [(3, x(0, true)), (-2, x(0, false)), (4, x(1, false))];
+    let (constant, terms) = normalize_objective(&objective);
+    assert_eq!(constant, -2);
+    assert_eq!(terms, vec![(5, x(0, true)), (4, x(1, false))]);
+  }
+
+  #[test]
+  fn stops_at_the_deadline() {
+    let mut random = Random::new(18);
+    let num_variables = 8;
+    let clauses = random_clauses(&mut random, num_variables, num_variables, 3);
+    let mut solver = Cdcl::new(num_variables);
+    for clause in &clauses {
+      solver.add_clause(clause);
+    }
+    let objective = random_objective(&mut random, num_variables);
+    let mut optimizer = Optimizer::new(solver, objective, Search::Linear, PbEncoding::Bdd);
+    // 期限を過ぎていれば最初の解も探さない
+    optimizer.set_deadline(Some(Instant::now()));
+    assert!(matches!(
+      optimizer.minimize(|_, _| {}),
+      MinimizeResult::Unknown
+    ));
+  }
+
+  #[test]
+  fn stops_at_the_lower_bound() {
+    // 最初の解が下限に達していれば上限の制約を加えずに最適と分かる
+    for search in Search::ALL {
+      let solver = Cdcl::new(3);
+      let objective = (0..3)
+        .map(|variable| (1, Literal::new(variable, true)))
+        .collect::<Vec<_>>();
+      let mut optimizer = Optimizer::new(solver, objective, search, PbEncoding::Bdd);
+      let optimum = optimizer.minimize(|_, _| {}).optimum().unwrap();
+      assert!(optimum.optimal);
+      assert_eq!(optimum.cost, 0);
+      assert_eq!(optimizer.solver.num_variables(), 3, "{}", search);
+    }
+  }
+
+  #[test]
+  fn parses_names() {
+    for search in Search::ALL {
+      assert_eq!(search.name().parse::<Search>().unwrap(), search);
+    }
+    let error = "ternary".parse::<Search>().unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "unknown search: ternary (expected linear or binary)"
+    );
+  }
+
+  #[test]
+  fn encodes_objective_once() {
+    // 上限を何度下げても，目的関数の和の補助変数は最初の解のコストで一度だけ作る
+    let num_variables = 10;
+    let objective = (0..num_variables)
+      .map(|variable| (1 + variable as i64 % 3, Literal::new(variable, false)))
+      .collect::<Vec<_>>();
+    for encoding in PbEncoding::ALL {
+      let mut solver = Cdcl::new(num_variables);
+      // 真にできるのは高々5個
+      for i in 0..num_variables / 2 {
+        solver.add_clause(&[Literal::new(2 * i, false), Literal::new(2 * i + 1, false)]);
+      }
+      let mut optimizer = Optimizer::new(solver, objective.clone(), Search::Linear, encoding);
+      let mut improvements = vec![];
+      let optimum = optimizer
+        .minimize(|cost, _| improvements.push(cost))
+        .optimum()
+        .unwrap();
+      assert!(optimum.optimal);
+      assert_eq!(optimum.cost, 6);
+      assert!(improvements.len() > 1);
+
+      let mut next_variable = num_variables;
+      let limit = (improvements[0] - optimizer.constant) as u64;
+      encode_sum(&optimizer.terms, limit, encoding, &mut next_variable);
+      assert_eq!(
+        optimizer.solver.num_variables(),
+        next_variable,
+        "{}",
+        encoding
+      );
+    }
+  }
+}
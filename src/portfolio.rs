@@ -0,0 +1,337 @@
+use std::{
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc, Arc,
+  },
+  thread,
+};
+
+use crate::{
+  cdcl::{options::CdclOptions, sharing::ClauseExchange, solver::SolveResult},
+  config::SolverConfig,
+  dpll::Dpll,
+  expressions::clause::Clause,
+  local_search::{Algorithm, LocalSearch},
+  sat_solver::Engine,
+  statistics::Statistics,
+};
+
+/// ポートフォリオの各ワーカの設定
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Worker {
+  pub engine: Engine,
+  /// 設定のシードに足し，ワーカの番号と混ぜて使う
+  pub seed: u64,
+  /// CDCLで局所探索による極性の設定を行うか
+  pub rephasing: bool,
+}
+
+/// 設定の異なる複数のソルバを別々のスレッドで動かし，最初に得られた答えを返す
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Portfolio {
+  workers: Vec<Worker>,
+  sharing: bool,
+}
+
+impl Portfolio {
+  /// num_threads個のワーカに既定の設定を割り振る
+  pub fn new(num_threads: usize) -> Self {
+    let workers = (0..num_threads.max(1))
+      .map(|i| {
+        let seed = i as u64;
+        match i % 4 {
+          0 => Worker {
+            engine: Engine::Cdcl,
+            seed,
+            rephasing: true,
+          },
+          1 => Worker {
+            engine: Engine::Cdcl,
+            seed,
+            rephasing: false,
+          },
+          2 => Worker {
+            engine: Engine::LocalSearch(Algorithm::ProbSat),
+            seed,
+            rephasing: true,
+          },
+          _ => Worker {
+            engine: Engine::LocalSearch(Algorithm::WalkSat),
+            seed,
+            rephasing: false,
+          },
+        }
+      })
+      .collect();
+
+    Self::with_workers(workers)
+  }
+
+  pub fn with_workers(workers: Vec<Worker>) -> Self {
+    Self {
+      workers,
+      sharing: true,
+    }
+  }
+
+  pub fn workers(&self) -> &[Worker] {
+    &self.workers
+  }
+
+  /// CDCLのワーカ間で短い学習節と単位節を共有するか
+  pub fn set_sharing(&mut self, sharing: bool) {
+    self.sharing = sharing;
+  }
+
+  /// 全てのワーカで同じ節集合を解き，最初に得られた答えを返す
+  /// エンジン・シード・rephasing以外はconfigの設定を使う
+  /// statisticsには答えを出したワーカの統計情報を加える
+  /// DPLLのワーカは途中で中断できないので，他のワーカが先に答えても終了を待つ
+  pub fn solve(
+    &self,
+    num_variables: usize,
+    clauses: &[Clause],
+    config: &SolverConfig,
+    statistics: &mut Statistics,
+  ) -> SolveResult {
+    let stop = Arc::new(AtomicBool::new(false));
+    let exchange = Arc::new(ClauseExchange::new(self.workers.len()));
+
+    thread::scope(|scope| {
+      let (sender, receiver) = mpsc::channel();
+
+      for (id, worker) in self.workers.iter().enumerate() {
+        let sender = sender.clone();
+        let stop = stop.clone();
+        let exchange = self.sharing.then(|| (exchange.clone(), id));
+        let mut statistics = statistics.clone();
+        let config = SolverConfig {
+          engine: worker.engine,
+          seed: worker_seed(config.seed, worker, id),
+          cdcl: CdclOptions {
+            rephasing: worker.rephasing,
+            ..config.cdcl
+          },
+          ..config.clone()
+        };
+        scope.spawn(move || {
+          let result = solve_worker(
+            num_variables,
+            clauses,
+            &config,
+            &mut statistics,
+            stop,
+            exchange,
+          );
+          let _ = sender.send((result, statistics));
+        });
+      }
+      drop(sender);
+
+      let mut answer = SolveResult::Unknown;
+      for (result, worker_statistics) in receiver {
+        if !matches!(result, SolveResult::Unknown) {
+          stop.store(true, Ordering::Relaxed);
+          answer = result;
+          *statistics = worker_statistics;
+          break;
+        }
+      }
+      stop.store(true, Ordering::Relaxed);
+      answer
+    })
+  }
+}
+
+/// ワーカの番号も混ぜて，同じ設定のワーカどうしでも別の乱数列を使わせる
+fn worker_seed(seed: u64, worker: &Worker, id: usize) -> u64 {
+  seed.wrapping_add(worker.seed) ^ (id as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+}
+
+/// ポートフォリオの1つのワーカとしてconfig.engineで解く
+fn solve_worker(
+  num_variables: usize,
+  clauses: &[Clause],
+  config: &SolverConfig,
+  statistics: &mut Statistics,
+  stop: Arc<AtomicBool>,
+  exchange: Option<(Arc<ClauseExchange>, usize)>,
+) -> SolveResult {
+  match config.engine {
+    Engine::Cdcl => solve_with_cdcl(num_variables, clauses, config, statistics, stop, exchange),
+    Engine::Dpll => match Dpll::new().solve(num_variables, clauses) {
+      Some(model) => SolveResult::Satisfiable(model),
+      None => SolveResult::Unsatisfiable,
+    },
+    Engine::CubeAndConquer(cube_and_conquer) => {
+      cube_and_conquer.solve_until(num_variables, clauses, config, statistics, stop)
+    }
+    Engine::LocalSearch(algorithm) => {
+      let mut local_search = LocalSearch::new(num_variables, clauses, algorithm, config.seed);
+      local_search.set_stop_flag(stop.clone());
+      let found = local_search.run(config.local_search_flips);
+      statistics.local_search_flips += local_search.flips();
+      if found {
+        SolveResult::Satisfiable(local_search.model())
+      } else if local_search.is_stopped() {
+        SolveResult::Unknown
+      } else {
+        solve_with_cdcl(num_variables, clauses, config, statistics, stop, exchange)
+      }
+    }
+  }
+}
+
+fn solve_with_cdcl(
+  num_variables: usize,
+  clauses: &[Clause],
+  config: &SolverConfig,
+  statistics: &mut Statistics,
+  stop: Arc<AtomicBool>,
+  exchange: Option<(Arc<ClauseExchange>, usize)>,
+) -> SolveResult {
+  let mut solver = config.cdcl_solver(num_variables);
+  *solver.statistics_mut() = statistics.clone();
+  solver.set_stop_flag(stop);
+  if let Some((exchange, id)) = exchange {
+    solver.set_exchange(exchange, id);
+  }
+
+  for clause in clauses {
+    solver.add_clause(&clause.iter().copied().collect::<Vec<_>>());
+  }
+  let result = solver.solve();
+  *statistics = solver.statistics().clone();
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    expressions::literal::Literal,
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  fn to_clauses(clauses: &[Vec<Literal>]) -> Vec<Clause> {
+    clauses.iter().map(Clause::from).collect()
+  }
+
+  /// 共有の有無にかかわらず総当たりと同じ答えを返す
+  fn check(sharing: bool, seed: u64) {
+    let mut random = Random::new(seed);
+    let mut portfolio = Portfolio::new(4);
+    portfolio.set_sharing(sharing);
+    let config = SolverConfig::default().with_seed(seed);
+    for _ in 0..20 {
+      let num_variables = 10 + random.below(5);
+      let num_clauses = num_variables * 4 + random.below(num_variables);
+      let clauses = random_clauses(&mut random, num_variables, num_clauses, 3);
+      let models = testing::models(num_variables, &clauses);
+      let mut statistics = Statistics::default();
+      match portfolio.solve(
+        num_variables,
+        &to_clauses(&clauses),
+        &config,
+        &mut statistics,
+      ) {
+        SolveResult::Satisfiable(model) => {
+          assert!(models.contains(&testing::assignment(&model, num_variables)));
+        }
+        SolveResult::Unsatisfiable => assert!(models.is_empty()),
+        SolveResult::Unknown => panic!("every worker runs to the end"),
+      }
+      if !sharing {
+        assert_eq!(statistics.exported_clauses, 0);
+        assert_eq!(statistics.imported_clauses, 0);
+      }
+    }
+  }
+
+  #[test]
+  fn solves_with_sharing() {
+    check(true, 1);
+  }
+
+  #[test]
+  fn solves_without_sharing() {
+    check(false, 2);
+  }
+
+  #[test]
+  fn exports_learned_clauses_only_when_sharing() {
+    // 鳩の巣原理(7羽を6つの巣に入れる)はCDCLのワーカが衝突を重ねて解く
+    let (pigeons, holes) = (7, 6);
+    let variable = |pigeon: usize, hole: usize| pigeon * holes + hole;
+    let mut clauses = (0..pigeons)
+      .map(|pigeon| {
+        (0..holes)
+          .map(|hole| Literal::new(variable(pigeon, hole), true))
+          .collect::<Vec<_>>()
+      })
+      .collect::<Vec<_>>();
+    for hole in 0..holes {
+      for first in 0..pigeons {
+        for second in first + 1..pigeons {
+          clauses.push(vec![
+            Literal::new(variable(first, hole), false),
+            Literal::new(variable(second, hole), false),
+          ]);
+        }
+      }
+    }
+    let workers = (1..=2)
+      .map(|seed| Worker {
+        engine: Engine::Cdcl,
+        seed,
+        rephasing: false,
+      })
+      .collect::<Vec<_>>();
+    for sharing in [true, false] {
+      let mut portfolio = Portfolio::with_workers(workers.clone());
+      portfolio.set_sharing(sharing);
+      let mut statistics = Statistics::default();
+      let result = portfolio.solve(
+        pigeons * holes,
+        &to_clauses(&clauses),
+        &SolverConfig::default(),
+        &mut statistics,
+      );
+      assert!(matches!(result, SolveResult::Unsatisfiable));
+      assert_eq!(statistics.exported_clauses > 0, sharing);
+    }
+  }
+
+  #[test]
+  fn assigns_different_seeds() {
+    let portfolio = Portfolio::new(8);
+    let workers = portfolio.workers();
+    assert_eq!(workers.len(), 8);
+    for (i, worker) in workers.iter().enumerate() {
+      assert!(workers[i + 1..]
+        .iter()
+        .all(|other| other.seed != worker.seed));
+    }
+  }
+
+  #[test]
+  fn mixes_the_worker_index_into_the_seed() {
+    let worker = Worker {
+      engine: Engine::Cdcl,
+      seed: 0,
+      rephasing: true,
+    };
+    let mut seeds = (0..8)
+      .map(|id| worker_seed(5, &worker, id))
+      .collect::<Vec<_>>();
+    // 1つ目のワーカは設定のシードをそのまま使う
+    assert_eq!(seeds[0], 5);
+    seeds.sort();
+    seeds.dedup();
+    assert_eq!(seeds.len(), 8);
+    // シードと番号を入れ替えたワーカも別の列を使う
+    let shifted = Worker { seed: 1, ..worker };
+    assert_ne!(worker_seed(5, &shifted, 0), worker_seed(5, &worker, 1));
+  }
+}
@@ -0,0 +1,370 @@
+use std::mem;
+
+use crate::{
+  expressions::literal::Literal,
+  preprocess::reconstruction::{Reconstruction, ReconstructionStep},
+};
+
+/// 1回の消去で調べるリテラル数の上限
+const MAX_STEPS: usize = 10_000_000;
+
+/// blocked clause elimination
+/// 節Cのあるリテラルlについて，!lを含むすべての節との導出節が恒真ならCを取り除く
+/// frozenの変数(節以外の制約に現れる変数)のリテラルはlに使わない
+/// 取り除いた節の数を返す
+pub fn eliminate_blocked_clauses(
+  num_variables: usize,
+  clauses: &mut Vec<Vec<Literal>>,
+  frozen: &[bool],
+  reconstruction: &mut Reconstruction,
+) -> usize {
+  let mut eliminator = Eliminator::new(num_variables, mem::take(clauses), frozen);
+
+  let mut count = 0;
+  let mut changed = true;
+  while changed && eliminator.steps < MAX_STEPS {
+    changed = false;
+    for id in 0..eliminator.clauses.len() {
+      if eliminator.removed[id] {
+        continue;
+      }
+      if let Some(pivot) = eliminator.find_blocking_literal(id) {
+        eliminator.removed[id] = true;
+        reconstruction.push(ReconstructionStep::Blocked {
+          clause: eliminator.clauses[id].clone(),
+          pivot,
+        });
+        count += 1;
+        changed = true;
+      }
+    }
+  }
+
+  *clauses = eliminator.into_clauses();
+  count
+}
+
+/// covered clause elimination
+/// covered literal additionで節を拡張し，拡張した節がblockedになればもとの節を取り除く
+/// 取り除いた節の数を返す
+pub fn eliminate_covered_clauses(
+  num_variables: usize,
+  clauses: &mut Vec<Vec<Literal>>,
+  frozen: &[bool],
+  reconstruction: &mut Reconstruction,
+) -> usize {
+  let mut eliminator = Eliminator::new(num_variables, mem::take(clauses), frozen);
+
+  let mut count = 0;
+  for id in 0..eliminator.clauses.len() {
+    if eliminator.steps >= MAX_STEPS {
+      break;
+    }
+    if let Some(steps) = eliminator.cover(id) {
+      eliminator.removed[id] = true;
+      // 復元時は拡張の逆順に処理する必要があるので，拡張した順に積む
+      for (clause, pivot) in steps {
+        reconstruction.push(ReconstructionStep::Blocked { clause, pivot });
+      }
+      count += 1;
+    }
+  }
+
+  *clauses = eliminator.into_clauses();
+  count
+}
+
+struct Eliminator<'a> {
+  clauses: Vec<Vec<Literal>>,
+  frozen: &'a [bool],
+  occurrences: Vec<Vec<usize>>,
+  removed: Vec<bool>,
+  marks: Vec<bool>,
+  steps: usize,
+}
+
+impl<'a> Eliminator<'a> {
+  fn new(num_variables: usize, clauses: Vec<Vec<Literal>>, frozen: &'a [bool]) -> Self {
+    let mut occurrences = vec![vec![]; 2 * num_variables];
+    for (id, clause) in clauses.iter().enumerate() {
+      for literal in clause {
+        occurrences[literal.index()].push(id);
+      }
+    }
+
+    Self {
+      removed: vec![false; clauses.len()],
+      clauses,
+      frozen,
+      occurrences,
+      marks: vec![false; 2 * num_variables],
+      steps: 0,
+    }
+  }
+
+  /// 取り除かれていない節を返す
+  fn into_clauses(self) -> Vec<Vec<Literal>> {
+    self
+      .clauses
+      .into_iter()
+      .zip(self.removed)
+      .filter(|(_, removed)| !removed)
+      .map(|(clause, _)| clause)
+      .collect()
+  }
+
+  fn mark(&mut self, literals: &[Literal], value: bool) {
+    for literal in literals {
+      self.marks[literal.index()] = value;
+    }
+  }
+
+  /// 印を付けた節と節otherとの!pivotによる導出節が恒真かどうか
+  fn is_tautological_resolvent(&mut self, other: usize, pivot: Literal) -> bool {
+    self.steps += self.clauses[other].len();
+    self.clauses[other]
+      .iter()
+      .any(|&literal| literal != !pivot && self.marks[(!literal).index()])
+  }
+
+  /// 節idがblockedであればその根拠となるリテラルを返す
+  fn find_blocking_literal(&mut self, id: usize) -> Option<Literal> {
+    let clause = self.clauses[id].clone();
+    self.mark(&clause, true);
+
+    let mut result = None;
+    'pivot: for &pivot in &clause {
+      if self.frozen[pivot.variable()] {
+        continue;
+      }
+      for i in 0..self.occurrences[(!pivot).index()].len() {
+        let other = self.occurrences[(!pivot).index()][i];
+        if other != id && !self.removed[other] && !self.is_tautological_resolvent(other, pivot) {
+          continue 'pivot;
+        }
+      }
+      result = Some(pivot);
+      break;
+    }
+
+    self.mark(&clause, false);
+    result
+  }
+
+  /// 節idにcovered literal additionを繰り返し，blockedになれば
+  /// 拡張の過程(拡張前の節と拡張に使ったリテラル)を返す
+  fn cover(&mut self, id: usize) -> Option<Vec<(Vec<Literal>, Literal)>> {
+    let mut extended = self.clauses[id].clone();
+    self.mark(&extended, true);
+
+    let mut steps = vec![];
+    let mut progress = true;
+    let mut result = None;
+
+    'extend: while progress && self.steps < MAX_STEPS {
+      progress = false;
+
+      for pivot in extended.clone() {
+        if self.frozen[pivot.variable()] {
+          continue;
+        }
+        // !pivotを含む節との導出節が恒真でないものについて，共通に含まれるリテラルを求める
+        let mut covered: Option<Vec<Literal>> = None;
+        for i in 0..self.occurrences[(!pivot).index()].len() {
+          let other = self.occurrences[(!pivot).index()][i];
+          if other == id || self.removed[other] || self.is_tautological_resolvent(other, pivot) {
+            continue;
+          }
+          let literals = self.clauses[other]
+            .iter()
+            .copied()
+            .filter(|&literal| literal != !pivot)
+            .collect::<Vec<_>>();
+          covered = Some(match covered {
+            None => literals,
+            Some(mut covered) => {
+              covered.retain(|literal| literals.contains(literal));
+              covered
+            }
+          });
+          if covered.as_ref().is_some_and(|covered| covered.is_empty()) {
+            break;
+          }
+        }
+
+        match covered {
+          None => {
+            steps.push((extended.clone(), pivot));
+            result = Some(steps);
+            break 'extend;
+          }
+          Some(covered) => {
+            let covered = covered
+              .into_iter()
+              .filter(|literal| !self.marks[literal.index()])
+              .collect::<Vec<_>>();
+            if covered.is_empty() {
+              continue;
+            }
+            steps.push((extended.clone(), pivot));
+            self.mark(&covered, true);
+            extended.extend(covered);
+            progress = true;
+          }
+        }
+      }
+    }
+
+    self.mark(&extended, false);
+    result
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    expressions::model::Model,
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  fn literal(variable: usize, sign: bool) -> Literal {
+    Literal::new(variable, sign)
+  }
+
+  /// 残った節の解を全て復元し，元の節集合の解になることを確かめる
+  fn check_models(
+    num_variables: usize,
+    original: &[Vec<Literal>],
+    remaining: &[Vec<Literal>],
+    reconstruction: &Reconstruction,
+  ) {
+    for assignment in testing::models(num_variables, remaining) {
+      let mut model = Model::new(num_variables);
+      for variable in 0..num_variables {
+        model.assign(variable, assignment >> variable & 1 == 1);
+      }
+      reconstruction.reconstruct(&mut model);
+      let value = |literal: &Literal| model.sign(literal.variable()) == Some(literal.sign());
+      assert!(
+        original.iter().all(|clause| clause.iter().any(value)),
+        "{:?} {:?}",
+        original,
+        remaining
+      );
+    }
+  }
+
+  #[test]
+  fn eliminates_blocked_clause() {
+    // (0 | 1)は0について(!0 | !1)との導出節が恒真なのでblocked
+    // 取り除くと(!0 | !1)も0についてblockedになる
+    let original = vec![
+      vec![literal(0, true), literal(1, true)],
+      vec![literal(0, false), literal(1, false)],
+      vec![literal(1, true), literal(2, true)],
+      vec![literal(1, false), literal(2, true)],
+      vec![literal(2, false), literal(3, true)],
+      vec![literal(2, false), literal(3, false)],
+    ];
+    let mut clauses = original.clone();
+    let mut reconstruction = Reconstruction::new();
+    let count = eliminate_blocked_clauses(4, &mut clauses, &[false; 4], &mut reconstruction);
+    assert_eq!(count, original.len() - clauses.len());
+    assert!(!clauses.contains(&original[0]));
+    assert_eq!(reconstruction.len(), count);
+    // 残った節も充足不能のまま
+    assert!(testing::models(4, &original).is_empty());
+    assert!(testing::models(4, &clauses).is_empty());
+
+    // XOR制約に現れる変数のリテラルでは取り除かない
+    let mut clauses = original.clone();
+    let frozen = [true, true, false, false];
+    let count = eliminate_blocked_clauses(4, &mut clauses, &frozen, &mut Reconstruction::new());
+    assert!(clauses.contains(&original[0]) && clauses.contains(&original[1]));
+    assert_eq!(count, original.len() - clauses.len());
+  }
+
+  #[test]
+  fn blocked_clause_elimination_preserves_models() {
+    let mut random = Random::new(8);
+    let num_variables = 8;
+    let mut total = 0;
+    for _ in 0..300 {
+      let num_clauses = 4 + random.below(20);
+      let length = 2 + random.below(2);
+      let original = random_clauses(&mut random, num_variables, num_clauses, length);
+      let mut clauses = original.clone();
+      let mut reconstruction = Reconstruction::new();
+      total += eliminate_blocked_clauses(
+        num_variables,
+        &mut clauses,
+        &[false; 8],
+        &mut reconstruction,
+      );
+      // 取り除いても充足可能性は変わらない
+      assert_eq!(
+        testing::models(num_variables, &original).is_empty(),
+        testing::models(num_variables, &clauses).is_empty()
+      );
+      check_models(num_variables, &original, &clauses, &reconstruction);
+    }
+    assert!(total > 0);
+  }
+
+  #[test]
+  fn eliminates_covered_clause() {
+    // (0 | 1)はblockedではないが，!0を含む節が全て2を含むので(0 | 1 | 2)に拡張でき，
+    // 拡張した節は1について(!1 | !2)との導出節が恒真になる
+    let original = vec![
+      vec![literal(0, true), literal(1, true)],
+      vec![literal(0, false), literal(2, true), literal(3, true)],
+      vec![literal(0, false), literal(2, true), literal(3, false)],
+      vec![literal(1, false), literal(2, false)],
+      vec![literal(2, false), literal(3, true)],
+      vec![literal(3, false), literal(0, true)],
+    ];
+    let mut clauses = original.clone();
+    let mut reconstruction = Reconstruction::new();
+    assert_eq!(
+      eliminate_blocked_clauses(
+        4,
+        &mut clauses.clone(),
+        &[false; 4],
+        &mut Reconstruction::new()
+      ),
+      0
+    );
+    let count = eliminate_covered_clauses(4, &mut clauses, &[false; 4], &mut reconstruction);
+    assert!(count > 0);
+    assert!(!clauses.contains(&original[0]));
+    check_models(4, &original, &clauses, &reconstruction);
+  }
+
+  #[test]
+  fn covered_clause_elimination_preserves_models() {
+    let mut random = Random::new(9);
+    let num_variables = 8;
+    let mut total = 0;
+    for _ in 0..300 {
+      let num_clauses = 4 + random.below(20);
+      let length = 2 + random.below(2);
+      let original = random_clauses(&mut random, num_variables, num_clauses, length);
+      let mut clauses = original.clone();
+      let mut reconstruction = Reconstruction::new();
+      total += eliminate_covered_clauses(
+        num_variables,
+        &mut clauses,
+        &[false; 8],
+        &mut reconstruction,
+      );
+      assert_eq!(
+        testing::models(num_variables, &original).is_empty(),
+        testing::models(num_variables, &clauses).is_empty()
+      );
+      check_models(num_variables, &original, &clauses, &reconstruction);
+    }
+    assert!(total > 0);
+  }
+}
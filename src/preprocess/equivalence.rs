@@ -0,0 +1,236 @@
+use crate::{expressions::literal::Literal, preprocess::implication::ImplicationGraph};
+
+/// 二項節からなる含意グラフの強連結成分を求め，各リテラルの代表リテラルを返す
+/// (同じ強連結成分に属するリテラルは互いに同値)
+/// リテラルとその否定が同じ成分に属する場合は充足不能なのでNoneを返す
+pub fn find_representatives(
+  num_variables: usize,
+  clauses: &[Vec<Literal>],
+) -> Option<Vec<Literal>> {
+  let num_literals = 2 * num_variables;
+
+  let implications = ImplicationGraph::new(num_variables, clauses);
+  let graph = (0..num_literals)
+    .map(|index| {
+      implications
+        .successors(Literal::from_index(index))
+        .iter()
+        .map(|literal| literal.index())
+        .collect()
+    })
+    .collect::<Vec<Vec<_>>>();
+
+  let components = strongly_connected_components(&graph);
+
+  let mut component_of = vec![0; num_literals];
+  for (id, component) in components.iter().enumerate() {
+    for &index in component {
+      component_of[index] = id;
+    }
+  }
+
+  if (0..num_variables).any(|v| component_of[2 * v] == component_of[2 * v + 1]) {
+    return None;
+  }
+
+  let mut representatives = (0..num_literals)
+    .map(Literal::from_index)
+    .collect::<Vec<_>>();
+
+  for component in &components {
+    if component.len() < 2 {
+      continue;
+    }
+
+    // 成分内で添字最小のリテラルを代表とする
+    // 否定側の成分では同じ変数の否定が代表になるので，代表同士の関係も否定で保たれる
+    let representative = *component.iter().min().unwrap();
+    for &index in component {
+      representatives[index] = Literal::from_index(representative);
+    }
+  }
+
+  Some(representatives)
+}
+
+/// 各リテラルを代表リテラルに置き換える
+/// 恒真になった節は取り除き，重複したリテラルはまとめる
+pub fn substitute(clauses: &[Vec<Literal>], representatives: &[Literal]) -> Vec<Vec<Literal>> {
+  let mut result = vec![];
+
+  'clause: for clause in clauses {
+    let mut substituted: Vec<Literal> = vec![];
+    for literal in clause {
+      let literal = representatives[literal.index()];
+      if substituted.contains(&!literal) {
+        continue 'clause;
+      }
+      if !substituted.contains(&literal) {
+        substituted.push(literal);
+      }
+    }
+    result.push(substituted);
+  }
+
+  result
+}
+
+/// Tarjanのアルゴリズム(再帰を使わない版)
+fn strongly_connected_components(graph: &[Vec<usize>]) -> Vec<Vec<usize>> {
+  const UNVISITED: usize = usize::MAX;
+
+  let n = graph.len();
+  let mut order = vec![UNVISITED; n];
+  let mut low = vec![0; n];
+  let mut on_stack = vec![false; n];
+  let mut stack = vec![];
+  let mut components = vec![];
+  let mut counter = 0;
+
+  for root in 0..n {
+    if order[root] != UNVISITED {
+      continue;
+    }
+
+    // (頂点, 次に調べる辺の番号)
+    let mut call_stack = vec![(root, 0)];
+    order[root] = counter;
+    low[root] = counter;
+    counter += 1;
+    stack.push(root);
+    on_stack[root] = true;
+
+    while let Some(&mut (v, ref mut edge)) = call_stack.last_mut() {
+      if let Some(&w) = graph[v].get(*edge) {
+        *edge += 1;
+        if order[w] == UNVISITED {
+          order[w] = counter;
+          low[w] = counter;
+          counter += 1;
+          stack.push(w);
+          on_stack[w] = true;
+          call_stack.push((w, 0));
+        } else if on_stack[w] {
+          low[v] = low[v].min(order[w]);
+        }
+        continue;
+      }
+
+      call_stack.pop();
+      if let Some(&(parent, _)) = call_stack.last() {
+        low[parent] = low[parent].min(low[v]);
+      }
+
+      if low[v] == order[v] {
+        let mut component = vec![];
+        while let Some(w) = stack.pop() {
+          on_stack[w] = false;
+          component.push(w);
+          if w == v {
+            break;
+          }
+        }
+        components.push(component);
+      }
+    }
+  }
+
+  components
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  fn literal(variable: usize, sign: bool) -> Literal {
+    Literal::new(variable, sign)
+  }
+
+  #[test]
+  fn merges_implication_cycles() {
+    // 0 -> 1 -> !2 -> 0 と 3 -> 0
+    let clauses = vec![
+      vec![literal(0, false), literal(1, true)],
+      vec![literal(1, false), literal(2, false)],
+      vec![literal(2, true), literal(0, true)],
+      vec![literal(3, false), literal(0, true)],
+    ];
+    let representatives = find_representatives(4, &clauses).unwrap();
+    let representative = |literal: Literal| representatives[literal.index()];
+    // 成分内で添字最小の!0が代表になり，否定側の成分は0が代表になる
+    assert_eq!(representative(literal(1, true)), literal(0, true));
+    assert_eq!(representative(literal(2, false)), literal(0, true));
+    assert_eq!(representative(literal(1, false)), literal(0, false));
+    assert_eq!(representative(literal(2, true)), literal(0, false));
+    assert_eq!(representative(literal(3, true)), literal(3, true));
+  }
+
+  #[test]
+  fn detects_contradictory_cycles() {
+    // 0 -> 1 -> !0 -> 2 -> 0
+    let clauses = vec![
+      vec![literal(0, false), literal(1, true)],
+      vec![literal(1, false), literal(0, false)],
+      vec![literal(0, true), literal(2, true)],
+      vec![literal(2, false), literal(0, true)],
+    ];
+    assert!(find_representatives(3, &clauses).is_none());
+  }
+
+  #[test]
+  fn substitutes_representatives() {
+    // 添字2v + signの順
+    let representatives = vec![
+      literal(0, false),
+      literal(0, true),
+      literal(0, false),
+      literal(0, true),
+      literal(2, false),
+      literal(2, true),
+    ];
+    let clauses = vec![
+      vec![literal(0, true), literal(1, true), literal(2, true)],
+      vec![literal(0, true), literal(1, false)],
+    ];
+    // 1を0に置き換えると1つ目の節は重複，2つ目の節は恒真になる
+    assert_eq!(
+      substitute(&clauses, &representatives),
+      vec![vec![literal(0, true), literal(2, true)]]
+    );
+  }
+
+  #[test]
+  fn preserves_models_up_to_equivalence() {
+    // 同値なリテラルは全ての解で等しく，置き換えた節集合の解のうちそれを満たすものが元の解になる
+    let mut random = Random::new(2);
+    let num_variables = 8;
+    for _ in 0..300 {
+      let num_binaries = 4 + random.below(12);
+      let mut clauses = random_clauses(&mut random, num_variables, num_binaries, 2);
+      let num_clauses = random.below(8);
+      clauses.extend(random_clauses(&mut random, num_variables, num_clauses, 3));
+      let models = testing::models(num_variables, &clauses);
+      let Some(representatives) = find_representatives(num_variables, &clauses) else {
+        assert!(models.is_empty(), "{:?}", clauses);
+        continue;
+      };
+      let substituted = substitute(&clauses, &representatives);
+      let consistent = |assignment: u64| {
+        (0..2 * num_variables).all(|index| {
+          let literal = Literal::from_index(index);
+          testing::holds(assignment, literal) == testing::holds(assignment, representatives[index])
+        })
+      };
+      assert!(models.iter().all(|&assignment| consistent(assignment)));
+      let found = testing::models(num_variables, &substituted)
+        .into_iter()
+        .filter(|&assignment| consistent(assignment))
+        .collect::<Vec<_>>();
+      assert_eq!(found, models, "{:?}", clauses);
+    }
+  }
+}
@@ -0,0 +1,51 @@
+use crate::expressions::literal::Literal;
+
+/// 二項節 (a || b) を !a -> b, !b -> a の辺として表した含意グラフ
+#[derive(Clone, Debug)]
+pub struct ImplicationGraph {
+  successors: Vec<Vec<Literal>>,
+}
+
+impl ImplicationGraph {
+  /// clausesのうち二項節だけから作る
+  pub fn new(num_variables: usize, clauses: &[Vec<Literal>]) -> Self {
+    let mut successors = vec![vec![]; 2 * num_variables];
+    for clause in clauses.iter().filter(|clause| clause.len() == 2) {
+      successors[(!clause[0]).index()].push(clause[1]);
+      successors[(!clause[1]).index()].push(clause[0]);
+    }
+    Self { successors }
+  }
+
+  /// literalが真なら真になるリテラル
+  pub fn successors(&self, literal: Literal) -> &[Literal] {
+    &self.successors[literal.index()]
+  }
+
+  /// 変数が二項節に現れるか
+  pub fn contains(&self, variable: usize) -> bool {
+    let positive = Literal::new(variable, true);
+    !self.successors(positive).is_empty() || !self.successors(!positive).is_empty()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn links_both_literals_of_binary_clauses() {
+    let literal = |variable, sign| Literal::new(variable, sign);
+    // (x0 | !x1)と3リテラルの節
+    let clauses = vec![
+      vec![literal(0, true), literal(1, false)],
+      vec![literal(1, true), literal(2, true), literal(3, true)],
+    ];
+    let graph = ImplicationGraph::new(4, &clauses);
+    assert_eq!(graph.successors(literal(0, false)), [literal(1, false)]);
+    assert_eq!(graph.successors(literal(1, true)), [literal(0, true)]);
+    assert!(graph.successors(literal(0, true)).is_empty());
+    assert!(graph.contains(0) && graph.contains(1));
+    assert!(!graph.contains(2) && !graph.contains(3));
+  }
+}
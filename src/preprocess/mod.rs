@@ -0,0 +1,7 @@
+pub mod blocked;
+pub mod equivalence;
+pub mod implication;
+pub mod options;
+pub mod preprocessor;
+pub mod probing;
+pub mod reconstruction;
@@ -0,0 +1,35 @@
+/// 前処理の各手法を個別に有効化・無効化する
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PreprocessOptions {
+  /// 失敗リテラル探索
+  pub probing: bool,
+  /// 同値リテラルの置き換え
+  pub equivalent_literal_substitution: bool,
+  /// blocked clause elimination
+  pub blocked_clause_elimination: bool,
+  /// covered clause elimination
+  pub covered_clause_elimination: bool,
+}
+
+impl PreprocessOptions {
+  /// 前処理を行わない
+  pub fn none() -> Self {
+    Self {
+      probing: false,
+      equivalent_literal_substitution: false,
+      blocked_clause_elimination: false,
+      covered_clause_elimination: false,
+    }
+  }
+}
+
+impl Default for PreprocessOptions {
+  fn default() -> Self {
+    Self {
+      probing: true,
+      equivalent_literal_substitution: true,
+      blocked_clause_elimination: true,
+      covered_clause_elimination: false,
+    }
+  }
+}
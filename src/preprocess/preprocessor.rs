@@ -0,0 +1,486 @@
+use std::mem;
+
+use crate::{
+  encodings::xor::normalize,
+  expressions::{clause::Clause, literal::Literal, model::Model},
+  preprocess::{
+    blocked, equivalence,
+    options::PreprocessOptions,
+    probing,
+    reconstruction::{Reconstruction, ReconstructionStep},
+  },
+  statistics::Statistics,
+};
+
+/// 前処理を繰り返す最大回数
+const MAX_ROUNDS: usize = 4;
+
+/// 探索前に節集合を簡単化する
+/// 消去した変数の値はreconstructでモデルに復元する
+/// XOR制約・個数制約は値の決まった変数と同値な変数を置き換えるだけで，その変数の節は取り除かない
+#[derive(Clone, Debug)]
+pub struct Preprocessor {
+  num_variables: usize,
+  clauses: Vec<Vec<Literal>>,
+  xors: Vec<(Vec<Literal>, bool)>,
+  /// 真のリテラルの個数が上限以下になる個数制約
+  cardinalities: Vec<(Vec<Literal>, usize)>,
+  fixed: Vec<Option<bool>>,
+  reconstruction: Reconstruction,
+  options: PreprocessOptions,
+}
+
+impl Preprocessor {
+  pub fn new(num_variables: usize, clauses: &[Clause], options: PreprocessOptions) -> Self {
+    Self {
+      num_variables,
+      clauses: clauses
+        .iter()
+        .map(|clause| clause.iter().copied().collect())
+        .collect(),
+      xors: vec![],
+      cardinalities: vec![],
+      fixed: vec![None; num_variables],
+      reconstruction: Reconstruction::new(),
+      options,
+    }
+  }
+
+  /// リテラルの排他的論理和が右辺になるXOR制約を加える
+  pub fn add_xors(&mut self, xors: &[(Vec<Literal>, bool)]) {
+    self.xors.extend_from_slice(xors);
+  }
+
+  /// 前処理後のXOR制約
+  pub fn xors(&self) -> &[(Vec<Literal>, bool)] {
+    &self.xors
+  }
+
+  /// リテラルのうち真のものが上限以下になる個数制約を加える
+  pub fn add_cardinalities(&mut self, cardinalities: &[(Vec<Literal>, usize)]) {
+    self.cardinalities.extend_from_slice(cardinalities);
+  }
+
+  /// 前処理後の個数制約
+  pub fn cardinalities(&self) -> &[(Vec<Literal>, usize)] {
+    &self.cardinalities
+  }
+
+  /// 前処理を行い，簡単化した節集合を返す
+  /// 充足不能であればNoneを返す
+  pub fn preprocess(&mut self, statistics: &mut Statistics) -> Option<Vec<Clause>> {
+    self.clauses = equivalence::substitute(&self.clauses, &self.identity());
+    self.propagate_units()?;
+
+    for _ in 0..MAX_ROUNDS {
+      let mut changed = false;
+
+      if self.options.equivalent_literal_substitution {
+        changed |= self.substitute_equivalent_literals(statistics)?;
+      }
+
+      if self.options.probing {
+        changed |= self.probe(statistics)?;
+      }
+
+      if !changed {
+        break;
+      }
+    }
+
+    let mut frozen = vec![false; self.num_variables];
+    for (literals, _) in &self.xors {
+      for literal in literals {
+        frozen[literal.variable()] = true;
+      }
+    }
+    for (literals, _) in &self.cardinalities {
+      for literal in literals {
+        frozen[literal.variable()] = true;
+      }
+    }
+
+    if self.options.blocked_clause_elimination {
+      statistics.blocked_clauses += blocked::eliminate_blocked_clauses(
+        self.num_variables,
+        &mut self.clauses,
+        &frozen,
+        &mut self.reconstruction,
+      ) as u64;
+    }
+
+    if self.options.covered_clause_elimination {
+      statistics.covered_clauses += blocked::eliminate_covered_clauses(
+        self.num_variables,
+        &mut self.clauses,
+        &frozen,
+        &mut self.reconstruction,
+      ) as u64;
+    }
+
+    // 復元には節を使わないので，写しを残さず渡す
+    Some(
+      mem::take(&mut self.clauses)
+        .into_iter()
+        .map(|clause| Clause::from(&clause))
+        .collect(),
+    )
+  }
+
+  /// 前処理後の節集合のモデルを元の節集合のモデルに変換する
+  pub fn reconstruct(&self, model: &mut Model) {
+    self.reconstruction.reconstruct(model);
+  }
+
+  /// 同値なリテラルを代表リテラルに置き換える
+  /// 置き換えた変数があればtrueを返す
+  fn substitute_equivalent_literals(&mut self, statistics: &mut Statistics) -> Option<bool> {
+    let representatives = equivalence::find_representatives(self.num_variables, &self.clauses)?;
+
+    let mut changed = false;
+    for variable in 0..self.num_variables {
+      let literal = Literal::new(variable, true);
+      let representative = representatives[literal.index()];
+      if representative != literal {
+        self.reconstruction.push(ReconstructionStep::Equivalence {
+          literal,
+          representative,
+        });
+        statistics.equivalent_literals += 1;
+        changed = true;
+      }
+    }
+
+    if changed {
+      self.clauses = equivalence::substitute(&self.clauses, &representatives);
+      for (literals, _) in self.xors.iter_mut() {
+        for literal in literals.iter_mut() {
+          *literal = representatives[literal.index()];
+        }
+      }
+      for (literals, _) in self.cardinalities.iter_mut() {
+        for literal in literals.iter_mut() {
+          *literal = representatives[literal.index()];
+        }
+      }
+      self.propagate_units()?;
+    }
+
+    Some(changed)
+  }
+
+  /// 失敗リテラル探索で得られた単位節と二項節を追加する
+  /// 節を追加すればtrueを返す
+  fn probe(&mut self, statistics: &mut Statistics) -> Option<bool> {
+    let result = probing::probe(self.num_variables, &self.clauses, statistics)?;
+    let changed = !result.units.is_empty() || !result.binaries.is_empty();
+
+    self
+      .clauses
+      .extend(result.units.into_iter().map(|literal| vec![literal]));
+    self
+      .clauses
+      .extend(result.binaries.into_iter().map(|binary| binary.to_vec()));
+    self.clauses = equivalence::substitute(&self.clauses, &self.identity());
+    self.propagate_units()?;
+
+    Some(changed)
+  }
+
+  fn identity(&self) -> Vec<Literal> {
+    (0..2 * self.num_variables)
+      .map(Literal::from_index)
+      .collect()
+  }
+
+  /// 単位節を繰り返し適用して節集合から取り除く
+  /// 空節が生まれた場合はNoneを返す
+  fn propagate_units(&mut self) -> Option<()> {
+    loop {
+      self.simplify_xors()?;
+      self.simplify_cardinalities()?;
+      let units = self
+        .clauses
+        .iter()
+        .filter(|clause| clause.len() == 1)
+        .map(|clause| clause[0])
+        .collect::<Vec<_>>();
+
+      if units.is_empty() {
+        return if self.clauses.iter().any(|clause| clause.is_empty()) {
+          None
+        } else {
+          Some(())
+        };
+      }
+
+      for literal in units {
+        match self.fixed[literal.variable()] {
+          Some(sign) if sign != literal.sign() => return None,
+          Some(_) => {}
+          None => {
+            self.fixed[literal.variable()] = Some(literal.sign());
+            self.reconstruction.push(ReconstructionStep::Unit(literal));
+          }
+        }
+      }
+
+      let fixed = &self.fixed;
+      let mut clauses = vec![];
+      for clause in &self.clauses {
+        if clause
+          .iter()
+          .any(|literal| fixed[literal.variable()] == Some(literal.sign()))
+        {
+          continue;
+        }
+        let clause = clause
+          .iter()
+          .filter(|literal| fixed[literal.variable()].is_none())
+          .copied()
+          .collect::<Vec<_>>();
+        if clause.is_empty() {
+          return None;
+        }
+        clauses.push(clause);
+      }
+      self.clauses = clauses;
+    }
+  }
+
+  /// XOR制約から値の決まった変数を除き，変数が1つになった制約を単位節にする
+  /// 満たせない制約があればNoneを返す
+  fn simplify_xors(&mut self) -> Option<()> {
+    let fixed = &self.fixed;
+    let mut xors = vec![];
+    for (literals, rhs) in mem::take(&mut self.xors) {
+      let mut rhs = rhs;
+      let literals = literals
+        .into_iter()
+        .filter(|literal| match fixed[literal.variable()] {
+          Some(sign) => {
+            rhs ^= sign == literal.sign();
+            false
+          }
+          None => true,
+        })
+        .collect::<Vec<_>>();
+      let (variables, rhs) = normalize(&literals, rhs);
+      match variables.len() {
+        0 if rhs => return None,
+        0 => {}
+        1 => self.clauses.push(vec![Literal::new(variables[0], rhs)]),
+        _ => xors.push((
+          variables
+            .into_iter()
+            .map(|variable| Literal::new(variable, true))
+            .collect(),
+          rhs,
+        )),
+      }
+    }
+    self.xors = xors;
+    Some(())
+  }
+
+  /// 個数制約から値の決まったリテラルを除き，上限が0になった制約を単位節にする
+  /// 上限を超えて真のリテラルがあればNoneを返す
+  fn simplify_cardinalities(&mut self) -> Option<()> {
+    let fixed = &self.fixed;
+    let mut cardinalities = vec![];
+    for (literals, bound) in mem::take(&mut self.cardinalities) {
+      let mut bound = bound;
+      let mut remaining = vec![];
+      for literal in literals {
+        match fixed[literal.variable()] {
+          Some(sign) if sign == literal.sign() => bound = bound.checked_sub(1)?,
+          Some(_) => {}
+          None => remaining.push(literal),
+        }
+      }
+      if bound >= remaining.len() {
+        continue;
+      }
+      if bound == 0 {
+        self
+          .clauses
+          .extend(remaining.into_iter().map(|literal| vec![!literal]));
+        continue;
+      }
+      cardinalities.push((remaining, bound));
+    }
+    self.cardinalities = cardinalities;
+    Some(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  /// 前処理後の節集合のどの解を復元しても元の節集合の解になることを確かめ，統計情報を返す
+  fn check_reconstruction(options: PreprocessOptions, seed: u64) -> Statistics {
+    let mut random = Random::new(seed);
+    let num_variables = 9;
+    let mut statistics = Statistics::default();
+    for _ in 0..300 {
+      let num_binaries = 6 + random.below(10);
+      let mut clauses = random_clauses(&mut random, num_variables, num_binaries, 2);
+      let num_clauses = random.below(12);
+      clauses.extend(random_clauses(&mut random, num_variables, num_clauses, 3));
+      let original = clauses.iter().map(Clause::from).collect::<Vec<_>>();
+      let satisfiable = !testing::models(num_variables, &clauses).is_empty();
+
+      let mut preprocessor = Preprocessor::new(num_variables, &original, options);
+      let Some(simplified) = preprocessor.preprocess(&mut statistics) else {
+        assert!(!satisfiable, "{:?}", clauses);
+        continue;
+      };
+      let simplified = simplified
+        .iter()
+        .map(|clause| clause.iter().copied().collect())
+        .collect::<Vec<_>>();
+      let models = testing::models(num_variables, &simplified);
+      assert_eq!(!models.is_empty(), satisfiable, "{:?}", clauses);
+      for assignment in models {
+        let mut model = Model::new(num_variables);
+        for variable in 0..num_variables {
+          model.assign(variable, assignment >> variable & 1 == 1);
+        }
+        preprocessor.reconstruct(&mut model);
+        let value = |literal: &Literal| model.sign(literal.variable()) == Some(literal.sign());
+        assert!(
+          clauses.iter().all(|clause| clause.iter().any(value)),
+          "{:?} {:?}",
+          options,
+          clauses
+        );
+      }
+    }
+    statistics
+  }
+
+  #[test]
+  fn reconstructs_after_probing() {
+    let options = PreprocessOptions {
+      probing: true,
+      ..PreprocessOptions::none()
+    };
+    let statistics = check_reconstruction(options, 3);
+    assert!(statistics.failed_literals > 0);
+    assert_eq!(statistics.equivalent_literals, 0);
+  }
+
+  #[test]
+  fn reconstructs_after_equivalent_literal_substitution() {
+    let options = PreprocessOptions {
+      equivalent_literal_substitution: true,
+      ..PreprocessOptions::none()
+    };
+    let statistics = check_reconstruction(options, 4);
+    assert!(statistics.equivalent_literals > 0);
+    assert_eq!(statistics.failed_literals, 0);
+  }
+
+  #[test]
+  fn reconstructs_after_blocked_clause_elimination() {
+    let options = PreprocessOptions {
+      blocked_clause_elimination: true,
+      ..PreprocessOptions::none()
+    };
+    assert!(check_reconstruction(options, 5).blocked_clauses > 0);
+  }
+
+  #[test]
+  fn reconstructs_after_covered_clause_elimination() {
+    let options = PreprocessOptions {
+      covered_clause_elimination: true,
+      ..PreprocessOptions::none()
+    };
+    assert!(check_reconstruction(options, 6).covered_clauses > 0);
+  }
+
+  #[test]
+  fn reconstructs_after_all_techniques() {
+    let options = PreprocessOptions {
+      covered_clause_elimination: true,
+      ..PreprocessOptions::default()
+    };
+    let statistics = check_reconstruction(options, 7);
+    assert!(statistics.equivalent_literals > 0);
+    assert!(statistics.blocked_clauses + statistics.covered_clauses > 0);
+  }
+
+  #[test]
+  fn reconstructs_with_xor_constraints() {
+    let satisfies_xors = |assignment: u64, xors: &[(Vec<Literal>, bool)]| {
+      xors.iter().all(|(literals, rhs)| {
+        let parity = literals
+          .iter()
+          .filter(|&&literal| testing::holds(assignment, literal))
+          .count()
+          % 2
+          == 1;
+        parity == *rhs
+      })
+    };
+    let options = PreprocessOptions {
+      covered_clause_elimination: true,
+      ..PreprocessOptions::default()
+    };
+    let mut random = Random::new(8);
+    let num_variables = 9;
+    for _ in 0..300 {
+      let mut clauses = random_clauses(&mut random, num_variables, 8, 2);
+      clauses.extend(random_clauses(&mut random, num_variables, 4, 3));
+      let xors = (0..1 + random.below(2))
+        .map(|_| {
+          let length = 1 + random.below(4);
+          let literals = random_clauses(&mut random, num_variables, 1, length).remove(0);
+          (literals, random.below(2) == 0)
+        })
+        .collect::<Vec<_>>();
+      let satisfiable = testing::models(num_variables, &clauses)
+        .into_iter()
+        .any(|assignment| satisfies_xors(assignment, &xors));
+
+      let original = clauses.iter().map(Clause::from).collect::<Vec<_>>();
+      let mut preprocessor = Preprocessor::new(num_variables, &original, options);
+      preprocessor.add_xors(&xors);
+      let Some(simplified) = preprocessor.preprocess(&mut Statistics::default()) else {
+        assert!(!satisfiable, "{:?} {:?}", clauses, xors);
+        continue;
+      };
+      let simplified = simplified
+        .iter()
+        .map(|clause| clause.iter().copied().collect())
+        .collect::<Vec<_>>();
+      let models = testing::models(num_variables, &simplified)
+        .into_iter()
+        .filter(|&assignment| satisfies_xors(assignment, preprocessor.xors()))
+        .collect::<Vec<_>>();
+      assert_eq!(!models.is_empty(), satisfiable, "{:?} {:?}", clauses, xors);
+      for assignment in models {
+        let mut model = Model::new(num_variables);
+        for variable in 0..num_variables {
+          model.assign(variable, assignment >> variable & 1 == 1);
+        }
+        preprocessor.reconstruct(&mut model);
+        let reconstructed = testing::assignment(&model, num_variables);
+        assert!(clauses
+          .iter()
+          .all(|clause| testing::satisfies(reconstructed, clause)));
+        assert!(
+          satisfies_xors(reconstructed, &xors),
+          "{:?} {:?}",
+          clauses,
+          xors
+        );
+      }
+    }
+  }
+}
@@ -0,0 +1,416 @@
+use std::mem;
+
+use crate::{
+  expressions::literal::Literal, preprocess::implication::ImplicationGraph, statistics::Statistics,
+};
+
+/// 失敗リテラル探索の結果
+#[derive(Clone, Debug, Default)]
+pub struct ProbingResult {
+  /// 値が確定したリテラル
+  pub units: Vec<Literal>,
+  /// hyper binary resolution で得られた二項節(既存の二項節と重複しない)
+  pub binaries: Vec<[Literal; 2]>,
+}
+
+/// 二項節に現れるリテラルを仮に割り当てて単位伝播し，
+/// 失敗リテラル・必要割り当て・hyper binary resolventを探す
+/// hyper binary resolventは長い節が伝播したリテラルuについて，
+/// 含意木での偽リテラルの支配リテラルdから (!d || u) を作る
+/// 充足不能であればNoneを返す
+pub fn probe(
+  num_variables: usize,
+  clauses: &[Vec<Literal>],
+  statistics: &mut Statistics,
+) -> Option<ProbingResult> {
+  let mut prober = Prober::new(num_variables, clauses);
+
+  if !prober.propagate() {
+    return None;
+  }
+
+  let mut implied_by_positive = vec![false; 2 * num_variables];
+
+  let candidates = (0..num_variables)
+    .filter(|&variable| prober.implications.contains(variable))
+    .collect::<Vec<_>>();
+
+  for variable in candidates {
+    if prober.value(Literal::new(variable, true)).is_some() {
+      continue;
+    }
+
+    let positive = Literal::new(variable, true);
+    let Some(implied_positive) = prober.probe(positive) else {
+      statistics.failed_literals += 1;
+      if !prober.assign_root(!positive) {
+        return None;
+      }
+      continue;
+    };
+
+    let Some(implied_negative) = prober.probe(!positive) else {
+      statistics.failed_literals += 1;
+      if !prober.assign_root(positive) {
+        return None;
+      }
+      continue;
+    };
+
+    // どちらの割り当てでも導かれるリテラルは必ず成り立つ
+    for &literal in &implied_positive {
+      implied_by_positive[literal.index()] = true;
+    }
+    let necessary = implied_negative
+      .iter()
+      .copied()
+      .filter(|literal| implied_by_positive[literal.index()])
+      .collect::<Vec<_>>();
+    for &literal in &implied_positive {
+      implied_by_positive[literal.index()] = false;
+    }
+
+    for literal in necessary {
+      if prober.value(literal).is_none() {
+        statistics.necessary_assignments += 1;
+        if !prober.assign_root(literal) {
+          return None;
+        }
+      }
+    }
+  }
+
+  let mut binaries = mem::take(&mut prober.resolvents);
+  for binary in &mut binaries {
+    binary.sort();
+  }
+  binaries.sort();
+  binaries.dedup();
+  binaries.truncate(clauses.len());
+  statistics.hyper_binary_resolvents += binaries.len() as u64;
+
+  Some(ProbingResult {
+    units: prober.trail.clone(),
+    binaries,
+  })
+}
+
+/// 探索用の簡易的な単位伝播器
+/// 二項節は含意グラフで，3リテラル以上の節は2-watched literalsで伝播する
+struct Prober {
+  implications: ImplicationGraph,
+  clauses: Vec<Vec<Literal>>,
+  watches: Vec<Vec<usize>>,
+  values: Vec<Option<bool>>,
+  /// 探索中の含意木での親(二項節で導いたリテラルか，長い節の偽リテラルの支配リテラル)
+  parents: Vec<Option<Literal>>,
+  /// 含意木での深さ(探索の外で確定した変数はNone)
+  depths: Vec<Option<usize>>,
+  /// 探索中に見つけたhyper binary resolvent
+  resolvents: Vec<[Literal; 2]>,
+  trail: Vec<Literal>,
+  head: usize,
+  inconsistent: bool,
+}
+
+impl Prober {
+  fn new(num_variables: usize, clauses: &[Vec<Literal>]) -> Self {
+    let mut prober = Self {
+      implications: ImplicationGraph::new(num_variables, clauses),
+      clauses: vec![],
+      watches: vec![vec![]; 2 * num_variables],
+      values: vec![None; num_variables],
+      parents: vec![None; num_variables],
+      depths: vec![None; num_variables],
+      resolvents: vec![],
+      trail: vec![],
+      head: 0,
+      inconsistent: false,
+    };
+
+    for clause in clauses {
+      if clause.len() == 1 {
+        match prober.value(clause[0]) {
+          Some(false) => prober.inconsistent = true,
+          Some(true) => {}
+          None => prober.assign(clause[0], None),
+        }
+        continue;
+      }
+      if clause.len() == 2 {
+        continue;
+      }
+      let id = prober.clauses.len();
+      prober.watches[clause[0].index()].push(id);
+      prober.watches[clause[1].index()].push(id);
+      prober.clauses.push(clause.clone());
+    }
+
+    prober
+  }
+
+  fn value(&self, literal: Literal) -> Option<bool> {
+    self.values[literal.variable()].map(|sign| sign == literal.sign())
+  }
+
+  /// literalをparentの子として割り当てる
+  fn assign(&mut self, literal: Literal, parent: Option<Literal>) {
+    self.values[literal.variable()] = Some(literal.sign());
+    self.parents[literal.variable()] = parent;
+    self.depths[literal.variable()] =
+      parent.and_then(|parent| self.depths[parent.variable()].map(|depth| depth + 1));
+    self.trail.push(literal);
+  }
+
+  /// 値を確定させて伝播する
+  /// 矛盾した場合falseを返す
+  fn assign_root(&mut self, literal: Literal) -> bool {
+    match self.value(literal) {
+      Some(true) => true,
+      Some(false) => false,
+      None => {
+        self.assign(literal, None);
+        self.propagate()
+      }
+    }
+  }
+
+  /// literalを仮に割り当てて伝播し，導かれたリテラルを返す
+  /// 矛盾した場合はNone(その探索で見つけたresolventも捨てる)
+  fn probe(&mut self, literal: Literal) -> Option<Vec<Literal>> {
+    let mark = self.trail.len();
+    let num_resolvents = self.resolvents.len();
+    self.assign(literal, None);
+    self.depths[literal.variable()] = Some(0);
+    let result = if self.propagate() {
+      Some(self.trail[mark + 1..].to_vec())
+    } else {
+      self.resolvents.truncate(num_resolvents);
+      None
+    };
+    self.backtrack(mark);
+    result
+  }
+
+  fn backtrack(&mut self, mark: usize) {
+    for literal in self.trail.drain(mark..) {
+      self.values[literal.variable()] = None;
+      self.parents[literal.variable()] = None;
+      self.depths[literal.variable()] = None;
+    }
+    self.head = self.head.min(mark);
+  }
+
+  /// 節idの先頭以外の偽リテラルの否定の，含意木での支配リテラル
+  /// 全て探索の外で確定していればNone
+  fn dominator(&self, id: usize) -> Option<Literal> {
+    let mut dominator: Option<Literal> = None;
+    for &literal in &self.clauses[id][1..] {
+      let literal = !literal;
+      if self.depths[literal.variable()].is_none() {
+        continue;
+      }
+      dominator = Some(match dominator {
+        Some(dominator) => self.common_ancestor(dominator, literal),
+        None => literal,
+      });
+    }
+    dominator
+  }
+
+  /// 含意木での共通の祖先のうち最も深いもの
+  fn common_ancestor(&self, mut first: Literal, mut second: Literal) -> Literal {
+    let depth = |literal: Literal| self.depths[literal.variable()].unwrap();
+    while first != second {
+      if depth(first) >= depth(second) {
+        first = self.parents[first.variable()].unwrap();
+      } else {
+        second = self.parents[second.variable()].unwrap();
+      }
+    }
+    first
+  }
+
+  /// 単位伝播を行う
+  /// 矛盾した場合falseを返す
+  fn propagate(&mut self) -> bool {
+    if self.inconsistent {
+      return false;
+    }
+
+    while self.head < self.trail.len() {
+      let literal = self.trail[self.head];
+      let falsified = !literal;
+      self.head += 1;
+
+      // 二項節による含意を長い節より先に伝播する
+      for k in 0..self.implications.successors(literal).len() {
+        let implied = self.implications.successors(literal)[k];
+        match self.value(implied) {
+          Some(true) => {}
+          Some(false) => return false,
+          None => self.assign(implied, Some(literal)),
+        }
+      }
+
+      let mut watches = mem::take(&mut self.watches[falsified.index()]);
+      let mut i = 0;
+      let mut conflict = false;
+
+      while i < watches.len() {
+        let id = watches[i];
+        if self.clauses[id][0] == falsified {
+          self.clauses[id].swap(0, 1);
+        }
+
+        let first = self.clauses[id][0];
+        if self.value(first) == Some(true) {
+          i += 1;
+          continue;
+        }
+
+        // 偽でない別のリテラルを見張りにする
+        if let Some(k) =
+          (2..self.clauses[id].len()).find(|&k| self.value(self.clauses[id][k]) != Some(false))
+        {
+          self.clauses[id].swap(1, k);
+          self.watches[self.clauses[id][1].index()].push(id);
+          watches.swap_remove(i);
+          continue;
+        }
+
+        if self.value(first) == Some(false) {
+          conflict = true;
+          break;
+        }
+
+        // 支配リテラルから直接導けるように二項節を加え，含意木でもその子にする
+        let dominator = self.dominator(id);
+        if let Some(dominator) = dominator {
+          if !self.implications.successors(dominator).contains(&first) {
+            self.resolvents.push([!dominator, first]);
+          }
+        }
+        self.assign(first, dominator);
+        i += 1;
+      }
+
+      self.watches[falsified.index()] = watches;
+
+      if conflict {
+        return false;
+      }
+    }
+
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  fn literal(variable: usize, sign: bool) -> Literal {
+    Literal::new(variable, sign)
+  }
+
+  #[test]
+  fn finds_failed_literals() {
+    // 0 -> 1, 0 -> 2, (1 & 2) -> !0 なので0は偽
+    let clauses = vec![
+      vec![literal(0, false), literal(1, true)],
+      vec![literal(0, false), literal(2, true)],
+      vec![literal(1, false), literal(2, false), literal(0, false)],
+    ];
+    let mut statistics = Statistics::default();
+    let result = probe(3, &clauses, &mut statistics).unwrap();
+    assert!(result.units.contains(&literal(0, false)));
+    assert_eq!(statistics.failed_literals, 1);
+  }
+
+  #[test]
+  fn finds_necessary_assignments() {
+    // 0でも!0でも1が導かれる
+    let clauses = vec![
+      vec![literal(0, false), literal(2, true)],
+      vec![literal(2, false), literal(1, true)],
+      vec![literal(0, true), literal(1, true)],
+    ];
+    let mut statistics = Statistics::default();
+    let result = probe(3, &clauses, &mut statistics).unwrap();
+    assert!(result.units.contains(&literal(1, true)));
+    assert!(statistics.necessary_assignments >= 1);
+  }
+
+  #[test]
+  fn detects_unsatisfiable_binaries() {
+    // 0 <-> 1 かつ 0 <-> !1
+    let clauses = vec![
+      vec![literal(0, false), literal(1, true)],
+      vec![literal(1, false), literal(0, true)],
+      vec![literal(0, false), literal(1, false)],
+      vec![literal(0, true), literal(1, true)],
+    ];
+    assert!(probe(2, &clauses, &mut Statistics::default()).is_none());
+  }
+
+  #[test]
+  fn resolves_with_the_dominator() {
+    // 0 -> 1, 1 -> 2, 1 -> 3, (2 & 3) -> 4 では4を導く支配リテラルは1
+    let clauses = vec![
+      vec![literal(0, false), literal(1, true)],
+      vec![literal(1, false), literal(2, true)],
+      vec![literal(1, false), literal(3, true)],
+      vec![literal(2, false), literal(3, false), literal(4, true)],
+    ];
+    let mut statistics = Statistics::default();
+    let result = probe(5, &clauses, &mut statistics).unwrap();
+    assert_eq!(result.binaries, [[literal(1, false), literal(4, true)]]);
+    assert_eq!(statistics.hyper_binary_resolvents, 1);
+
+    // 加えた二項節から導けるので，もう一度探索しても二項節は増えない
+    let mut clauses = clauses;
+    clauses.extend(result.binaries.iter().map(|binary| binary.to_vec()));
+    let result = probe(5, &clauses, &mut statistics).unwrap();
+    assert!(result.binaries.is_empty());
+  }
+
+  #[test]
+  fn derives_implied_clauses() {
+    // 見つけた単位節と二項節は全ての解で成り立ち，充足不能なら解はない
+    let mut random = Random::new(1);
+    let num_variables = 9;
+    for _ in 0..300 {
+      let mut clauses = random_clauses(&mut random, num_variables, 6, 2);
+      let num_clauses = 4 + random.below(16);
+      clauses.extend(random_clauses(&mut random, num_variables, num_clauses, 3));
+      let models = testing::models(num_variables, &clauses);
+      let mut statistics = Statistics::default();
+      let Some(result) = probe(num_variables, &clauses, &mut statistics) else {
+        assert!(models.is_empty(), "{:?}", clauses);
+        continue;
+      };
+      assert_eq!(
+        statistics.hyper_binary_resolvents,
+        result.binaries.len() as u64
+      );
+      for &assignment in &models {
+        for &unit in &result.units {
+          assert!(testing::holds(assignment, unit), "{:?} {:?}", clauses, unit);
+        }
+        for binary in &result.binaries {
+          assert!(
+            testing::satisfies(assignment, binary),
+            "{:?} {:?}",
+            clauses,
+            binary
+          );
+        }
+      }
+    }
+  }
+}
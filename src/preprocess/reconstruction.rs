@@ -0,0 +1,73 @@
+use crate::expressions::{literal::Literal, model::Model};
+
+/// 前処理で消去した変数の値を復元するための情報
+#[derive(Clone, Debug)]
+pub enum ReconstructionStep {
+  /// 前処理で値が確定したリテラル
+  Unit(Literal),
+  /// literalは代表リテラルrepresentativeと同値
+  Equivalence {
+    literal: Literal,
+    representative: Literal,
+  },
+  /// 取り除いたclauseはpivotについてblocked
+  Blocked {
+    clause: Vec<Literal>,
+    pivot: Literal,
+  },
+}
+
+/// 前処理の逆順にモデルを修正するためのスタック
+#[derive(Clone, Debug, Default)]
+pub struct Reconstruction {
+  stack: Vec<ReconstructionStep>,
+}
+
+impl Reconstruction {
+  pub fn new() -> Self {
+    Self { stack: vec![] }
+  }
+
+  pub fn push(&mut self, step: ReconstructionStep) {
+    self.stack.push(step);
+  }
+
+  pub fn len(&self) -> usize {
+    self.stack.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.stack.is_empty()
+  }
+
+  /// 前処理後の節集合のモデルを元の節集合のモデルに変換する
+  pub fn reconstruct(&self, model: &mut Model) {
+    for step in self.stack.iter().rev() {
+      match step {
+        &ReconstructionStep::Unit(literal) => {
+          model.assign(literal.variable(), literal.sign());
+        }
+        &ReconstructionStep::Equivalence {
+          literal,
+          representative,
+        } => {
+          if let Some(sign) = model.sign(representative.variable()) {
+            model.assign(
+              literal.variable(),
+              (sign == representative.sign()) == literal.sign(),
+            );
+          }
+        }
+        ReconstructionStep::Blocked { clause, pivot } => {
+          // 節が偽になっていればpivotを反転して充足させる
+          if !clause
+            .iter()
+            .any(|literal| model.sign(literal.variable()) == Some(literal.sign()))
+          {
+            model.assign(pivot.variable(), pivot.sign());
+          }
+        }
+      }
+    }
+  }
+}
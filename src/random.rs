@@ -0,0 +1,83 @@
+/// 乱数生成器(xorshift64*)
+/// 同じシードからは常に同じ列を生成する
+#[derive(Clone, Debug)]
+pub struct Random {
+  state: u64,
+}
+
+impl Random {
+  /// シードをsplitmix64で混ぜて状態にする(近いシードからも無関係な列を作る)
+  pub fn new(seed: u64) -> Self {
+    let mut state = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    state = (state ^ state >> 30).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    state = (state ^ state >> 27).wrapping_mul(0x94d0_49bb_1331_11eb);
+    state ^= state >> 31;
+    // 状態が0になると以降ずっと0になるので避ける
+    if state == 0 {
+      state = 0x9e37_79b9_7f4a_7c15;
+    }
+    Self { state }
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    self.state ^= self.state >> 12;
+    self.state ^= self.state << 25;
+    self.state ^= self.state >> 27;
+    self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+  }
+
+  /// 0..nの一様乱数
+  pub fn below(&mut self, n: usize) -> usize {
+    (self.next_u64() % n as u64) as usize
+  }
+
+  /// [0, 1)の一様乱数
+  pub fn next_f64(&mut self) -> f64 {
+    (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn repeats_the_stream_of_a_seed() {
+    let stream = |seed| {
+      let mut random = Random::new(seed);
+      (0..8).map(|_| random.next_u64()).collect::<Vec<_>>()
+    };
+    assert_eq!(stream(7), stream(7));
+    assert_ne!(stream(7), stream(8));
+  }
+
+  #[test]
+  fn separates_adjacent_seeds() {
+    // 下位ビットだけが異なるシードも別の列になる
+    let mut firsts = (0..64)
+      .map(|seed| Random::new(seed).next_u64())
+      .collect::<Vec<_>>();
+    firsts.sort();
+    firsts.dedup();
+    assert_eq!(firsts.len(), 64);
+    let mut first = Random::new(6);
+    let mut second = Random::new(7);
+    let same = (0..64)
+      .filter(|_| first.next_u64() == second.next_u64())
+      .count();
+    assert_eq!(same, 0);
+  }
+
+  #[test]
+  fn stays_in_range() {
+    let mut random = Random::new(1);
+    let mut counts = [0; 3];
+    for _ in 0..3000 {
+      counts[random.below(3)] += 1;
+      let value = random.next_f64();
+      assert!((0.0..1.0).contains(&value));
+    }
+    // おおよそ一様
+    assert!(counts.iter().all(|&count| (800..1200).contains(&count)));
+  }
+}
@@ -1,16 +1,70 @@
-use std::{collections::HashMap, hash::Hash, ops::Not};
+use std::{borrow::Cow, collections::HashMap, hash::Hash, mem, ops::Not, time::Instant};
 
 use crate::{
+  cdcl::solver::{Cdcl, SolveResult},
+  config::SolverConfig,
+  count::{
+    approximate::{ApproxMc, Problem},
+    counter::ModelCounter,
+    natural::Natural,
+  },
+  cube_and_conquer::conquer::CubeAndConquer,
   dpll::Dpll,
-  expressions::{clause::Clause, literal::Literal, model::Model},
+  encodings::{
+    cardinality::{self, CardinalityEncoding},
+    formula::{FormulaEncoder, FormulaEncoding},
+    xor::encode_xor,
+  },
+  enumeration::Models,
+  expressions::{
+    clause::Clause,
+    formula::Formula,
+    literal::Literal,
+    model::Model,
+    parser::{self, FormulaParseError},
+  },
+  local_search::{self, LocalSearch},
+  maxsat::{
+    algorithm::{self, MaxSatAlgorithm},
+    local_search::{Improver, MaxSatLocalSearch},
+    oll::Oll,
+    options::MaxSatOptions,
+  },
+  pb::{
+    self,
+    constraint::{Comparison, PbConstraint},
+    encoding::PbEncoding,
+    optimize::{MinimizeResult, Optimizer, Optimum, Search},
+  },
+  portfolio::Portfolio,
+  preprocess::{options::PreprocessOptions, preprocessor::Preprocessor},
+  statistics::Statistics,
 };
 
-#[derive(Clone, Copy)]
+/// 前処理後の節集合を解くエンジン
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Engine {
+  #[default]
+  Cdcl,
+  Dpll,
+  /// 局所探索で解が見つからなければCDCLで解く
+  LocalSearch(local_search::Algorithm),
+  /// 先読みでキューブに分割してCDCLで並列に解く
+  CubeAndConquer(CubeAndConquer),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Variable {
   id: usize,
   sign: bool,
 }
 
+impl Variable {
+  pub(crate) fn literal(&self) -> Literal {
+    Literal::new(self.id, self.sign)
+  }
+}
+
 impl Not for Variable {
   type Output = Self;
   fn not(self) -> Self::Output {
@@ -26,37 +80,513 @@ pub struct SATSolver<T> {
   id_to_name: HashMap<usize, T>,
   num_variables: usize,
   clauses: Vec<Clause>,
+  /// リテラルの排他的論理和が右辺になるXOR制約
+  xors: Vec<(Vec<Literal>, bool)>,
+  /// 真のリテラルの個数が上限以下になる個数制約(探索中に直接扱うもの)
+  cardinalities: Vec<(Vec<Literal>, usize)>,
   model: Model,
+  statistics: Statistics,
+  config: SolverConfig,
+  /// 設定されていればconfigのengineの代わりにこのポートフォリオで解く
+  portfolio: Option<Portfolio>,
+  /// 個数制約を節に変換する方法
+  cardinality_encoding: CardinalityEncoding,
+  /// 線形制約を節に変換する方法
+  pb_encoding: PbEncoding,
+  /// 最小化で目的関数の上限を探す方法
+  search: Search,
+  /// 満たさないと重みの分だけコストがかかる節(MaxSATで使う)
+  soft_clauses: Vec<(Vec<Literal>, u64)>,
+  maxsat_algorithm: MaxSatAlgorithm,
+  maxsat_options: MaxSatOptions,
+  /// assertで論理式を節に変換する(部分式の補助変数を覚えておく)
+  formula_encoder: FormulaEncoder,
+}
+
+impl<T: Clone + Eq + Hash> Default for SATSolver<T> {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl<T: Clone + Eq + Hash> SATSolver<T> {
   pub fn new() -> Self {
+    Self::with_config(SolverConfig::default())
+  }
+
+  pub fn with_config(config: SolverConfig) -> Self {
     Self {
       num_variables: 0,
       clauses: vec![],
+      xors: vec![],
+      cardinalities: vec![],
       name_to_id: HashMap::new(),
       id_to_name: HashMap::new(),
       model: Model::new(0),
+      statistics: Statistics::default(),
+      config,
+      portfolio: None,
+      cardinality_encoding: CardinalityEncoding::default(),
+      pb_encoding: PbEncoding::default(),
+      search: Search::default(),
+      soft_clauses: vec![],
+      maxsat_algorithm: MaxSatAlgorithm::default(),
+      maxsat_options: MaxSatOptions::default(),
+      formula_encoder: FormulaEncoder::default(),
     }
   }
 
+  /// 解があればtrueを返す
+  /// 設定した上限に達した場合もfalseになるので，充足不能と区別するにはsolve_limitedを使う
   pub fn solve(&mut self) -> bool {
-    let mut solver = Dpll::new();
-    if let Some(model) = solver.solve(self.num_variables, &self.clauses) {
-      self.model = model;
-      true
+    self.solve_limited() == Some(true)
+  }
+
+  /// 設定した上限に達して答えが得られなければNoneを返す
+  pub fn solve_limited(&mut self) -> Option<bool> {
+    let mut statistics = Statistics::default();
+    let (num_variables, clauses, xors, cardinalities) = if self.handles_native_constraints() {
+      (
+        self.num_variables,
+        Cow::Borrowed(&self.clauses),
+        &self.xors[..],
+        &self.cardinalities[..],
+      )
     } else {
-      false
+      let (num_variables, clauses) = self.encoded_clauses();
+      (num_variables, Cow::Owned(clauses), &[][..], &[][..])
+    };
+    let mut preprocessor = Preprocessor::new(num_variables, &clauses, self.config.preprocess);
+    // 前処理器が節を写したので，変換した節集合はここで解放する
+    drop(clauses);
+    preprocessor.add_xors(xors);
+    preprocessor.add_cardinalities(cardinalities);
+
+    let result = match preprocessor.preprocess(&mut statistics) {
+      Some(clauses) => self.solve_preprocessed(
+        num_variables,
+        clauses,
+        preprocessor.xors(),
+        preprocessor.cardinalities(),
+        &mut statistics,
+      ),
+      None => SolveResult::Unsatisfiable,
+    };
+    self.statistics = statistics;
+
+    match result {
+      SolveResult::Satisfiable(mut model) => {
+        preprocessor.reconstruct(&mut model);
+        self.model = model;
+        Some(true)
+      }
+      SolveResult::Unsatisfiable => Some(false),
+      SolveResult::Unknown => None,
+    }
+  }
+
+  /// XOR制約・個数制約は1スレッドのCDCLエンジンだけが直接扱い，それ以外では節に変換して解く
+  fn handles_native_constraints(&self) -> bool {
+    self.portfolio.is_none() && self.config.num_threads <= 1 && self.config.engine == Engine::Cdcl
+  }
+
+  /// XOR制約・個数制約を補助変数を使って節に変換し，(補助変数を含む変数の数, 節集合)を返す
+  fn encoded_clauses(&self) -> (usize, Vec<Clause>) {
+    let mut next_variable = self.num_variables;
+    let mut clauses = self.clauses.clone();
+    for (literals, rhs) in &self.xors {
+      for clause in encode_xor(literals, *rhs, &mut next_variable) {
+        clauses.push(Clause::from(&clause));
+      }
+    }
+    for (literals, bound) in &self.cardinalities {
+      let encoding = CardinalityEncoding::Sequential;
+      for clause in cardinality::at_most_k(literals, *bound, encoding, &mut next_variable) {
+        clauses.push(Clause::from(&clause));
+      }
+    }
+    (next_variable, clauses)
+  }
+
+  fn solve_preprocessed(
+    &self,
+    num_variables: usize,
+    clauses: Vec<Clause>,
+    xors: &[(Vec<Literal>, bool)],
+    cardinalities: &[(Vec<Literal>, usize)],
+    statistics: &mut Statistics,
+  ) -> SolveResult {
+    let config = &self.config;
+    if let Some(portfolio) = &self.portfolio {
+      return portfolio.solve(num_variables, &clauses, config, statistics);
+    }
+    if config.num_threads > 1 {
+      return Portfolio::new(config.num_threads).solve(num_variables, &clauses, config, statistics);
+    }
+
+    match config.engine {
+      Engine::Cdcl => self.solve_with_cdcl(num_variables, clauses, xors, cardinalities, statistics),
+      Engine::Dpll => match Dpll::new().solve(num_variables, &clauses) {
+        Some(model) => SolveResult::Satisfiable(model),
+        None => SolveResult::Unsatisfiable,
+      },
+      Engine::CubeAndConquer(cube_and_conquer) => {
+        cube_and_conquer.solve(num_variables, &clauses, config, statistics)
+      }
+      Engine::LocalSearch(algorithm) => {
+        let mut local_search = LocalSearch::new(num_variables, &clauses, algorithm, config.seed);
+        let found = local_search.run(config.local_search_flips);
+        statistics.local_search_flips += local_search.flips();
+        if found {
+          SolveResult::Satisfiable(local_search.model())
+        } else {
+          self.solve_with_cdcl(num_variables, clauses, xors, cardinalities, statistics)
+        }
+      }
+    }
+  }
+
+  /// 節はCDCLエンジンに加えたものから解放する
+  fn solve_with_cdcl(
+    &self,
+    num_variables: usize,
+    clauses: Vec<Clause>,
+    xors: &[(Vec<Literal>, bool)],
+    cardinalities: &[(Vec<Literal>, usize)],
+    statistics: &mut Statistics,
+  ) -> SolveResult {
+    let mut solver = self.config.cdcl_solver(num_variables);
+    *solver.statistics_mut() = mem::take(statistics);
+    for clause in clauses {
+      solver.add_clause(clause.literals());
+    }
+    for (literals, rhs) in xors {
+      solver.add_xor(literals, *rhs);
+    }
+    for (literals, bound) in cardinalities {
+      solver.add_at_most(literals, *bound);
+    }
+    let result = solver.solve();
+    *statistics = solver.statistics().clone();
+    result
+  }
+
+  pub fn statistics(&self) -> &Statistics {
+    &self.statistics
+  }
+
+  pub fn config(&self) -> &SolverConfig {
+    &self.config
+  }
+
+  pub fn set_config(&mut self, config: SolverConfig) {
+    self.config = config;
+  }
+
+  pub fn preprocess_options(&self) -> &PreprocessOptions {
+    &self.config.preprocess
+  }
+
+  pub fn engine(&self) -> Engine {
+    self.config.engine
+  }
+
+  pub fn set_engine(&mut self, engine: Engine) {
+    self.config.engine = engine;
+  }
+
+  pub fn portfolio(&self) -> Option<&Portfolio> {
+    self.portfolio.as_ref()
+  }
+
+  /// ワーカを指定したポートフォリオで並列に解くように設定する
+  /// Noneを渡すと設定のengine・num_threadsで解くのに戻す
+  pub fn set_portfolio(&mut self, portfolio: Option<Portfolio>) {
+    self.portfolio = portfolio;
+  }
+
+  /// num_threads個のスレッドで既定のポートフォリオを使う
+  /// 1以下なら並列化しない
+  pub fn set_num_threads(&mut self, num_threads: usize) {
+    self.config.num_threads = num_threads;
+    self.portfolio = None;
+  }
+
+  /// 前処理の各手法の有効・無効を設定する
+  pub fn set_preprocess_options(&mut self, options: PreprocessOptions) {
+    self.config.preprocess = options;
+  }
+
+  pub fn cardinality_encoding(&self) -> CardinalityEncoding {
+    self.cardinality_encoding
+  }
+
+  /// 以降に加える個数制約を節に変換する方法を設定する
+  pub fn set_cardinality_encoding(&mut self, encoding: CardinalityEncoding) {
+    self.cardinality_encoding = encoding;
+  }
+
+  pub fn pb_encoding(&self) -> PbEncoding {
+    self.pb_encoding
+  }
+
+  /// 以降に加える線形制約を節に変換する方法を設定する
+  pub fn set_pb_encoding(&mut self, encoding: PbEncoding) {
+    self.pb_encoding = encoding;
+  }
+
+  pub fn search(&self) -> Search {
+    self.search
+  }
+
+  /// 最小化で目的関数の上限を探す方法を設定する
+  pub fn set_search(&mut self, search: Search) {
+    self.search = search;
+  }
+
+  pub fn maxsat_algorithm(&self) -> MaxSatAlgorithm {
+    self.maxsat_algorithm
+  }
+
+  pub fn set_maxsat_algorithm(&mut self, algorithm: MaxSatAlgorithm) {
+    self.maxsat_algorithm = algorithm;
+  }
+
+  pub fn maxsat_options(&self) -> &MaxSatOptions {
+    &self.maxsat_options
+  }
+
+  pub fn formula_encoding(&self) -> FormulaEncoding {
+    self.formula_encoder.encoding()
+  }
+
+  /// 以後にassertする論理式の変換方法を変える
+  pub fn set_formula_encoding(&mut self, encoding: FormulaEncoding) {
+    self.formula_encoder.set_encoding(encoding);
+  }
+
+  pub fn set_maxsat_options(&mut self, options: MaxSatOptions) {
+    self.maxsat_options = options;
+  }
+
+  /// 全ての解を1つずつ返すイテレータ
+  /// 前処理・並列化は行わず，設定のパラメータ・上限をもつ1つのCDCLで順に解く
+  pub fn models(&self) -> Models<T> {
+    let projection = self.named_variables();
+    self.enumerate(&projection)
+  }
+
+  /// projectionの変数の値の組み合わせが異なる解を1つずつ返すイテレータ
+  /// 返す解にはprojectionの変数の値だけが含まれる
+  pub fn projected_models(&self, projection: &[Variable]) -> Models<T> {
+    let mut projection = projection.iter().map(|v| v.id).collect::<Vec<_>>();
+    projection.sort_unstable();
+    projection.dedup();
+    self.enumerate(&projection)
+  }
+
+  /// 解の個数を数える
+  pub fn count_models(&mut self) -> Natural {
+    let projection = self.named_variables();
+    self.count(&projection)
+  }
+
+  /// projectionの変数の値の組み合わせのうち，解に拡張できるものの個数を数える
+  pub fn count_projected_models(&mut self, projection: &[Variable]) -> Natural {
+    let projection = projection.iter().map(|v| v.id).collect::<Vec<_>>();
+    self.count(&projection)
+  }
+
+  /// 解の個数を推定する(確率1-delta以上で誤差が(1+epsilon)倍以内)
+  /// 正確に数えるには大きすぎる問題に使う
+  /// 設定の上限に達して答えが得られなければNoneを返す
+  pub fn approximate_count_models(&mut self, epsilon: f64, delta: f64) -> Option<Natural> {
+    let projection = self.named_variables();
+    self.approximate_count(&projection, epsilon, delta)
+  }
+
+  /// projectionの変数の値の組み合わせのうち，解に拡張できるものの個数を推定する
+  pub fn approximate_count_projected_models(
+    &mut self,
+    projection: &[Variable],
+    epsilon: f64,
+    delta: f64,
+  ) -> Option<Natural> {
+    let mut projection = projection.iter().map(|v| v.id).collect::<Vec<_>>();
+    projection.sort_unstable();
+    projection.dedup();
+    self.approximate_count(&projection, epsilon, delta)
+  }
+
+  /// 名前のある変数(個数制約の補助変数を除く)
+  fn named_variables(&self) -> Vec<usize> {
+    let mut variables = self.id_to_name.keys().copied().collect::<Vec<_>>();
+    variables.sort_unstable();
+    variables
+  }
+
+  fn approximate_count(
+    &mut self,
+    projection: &[usize],
+    epsilon: f64,
+    delta: f64,
+  ) -> Option<Natural> {
+    let mut statistics = Statistics::default();
+    let problem = Problem {
+      num_variables: self.num_variables,
+      clauses: &self.clauses,
+      xors: &self.xors,
+      cardinalities: &self.cardinalities,
+    };
+    let count =
+      ApproxMc::new(epsilon, delta).count(&problem, projection, &self.config, &mut statistics);
+    self.statistics = statistics;
+    count
+  }
+
+  fn count(&mut self, projection: &[usize]) -> Natural {
+    let mut statistics = Statistics::default();
+    // 補助変数の値はXOR制約の変数の値で決まるので，節に変換しても解の個数は変わらない
+    let (num_variables, clauses) = self.encoded_clauses();
+    let mut counter = ModelCounter::new(num_variables, &clauses);
+    counter.set_projection(projection);
+    let count = counter.count(&mut statistics);
+    self.statistics = statistics;
+    count
+  }
+
+  fn enumerate(&self, projection: &[usize]) -> Models<T> {
+    let solver = self.cdcl_solver();
+    let projection = projection
+      .iter()
+      .map(|&id| (id, self.id_to_name[&id].clone()))
+      .collect();
+    Models::new(solver, projection)
+  }
+
+  /// 全ての制約を加えたCDCLソルバ(前処理はしない)
+  fn cdcl_solver(&self) -> Cdcl {
+    let mut solver = self.config.cdcl_solver(self.num_variables);
+    for clause in &self.clauses {
+      solver.add_clause(&clause.iter().copied().collect::<Vec<_>>());
+    }
+    for (literals, rhs) in &self.xors {
+      solver.add_xor(literals, *rhs);
+    }
+    for (literals, bound) in &self.cardinalities {
+      solver.add_at_most(literals, *bound);
+    }
+    solver
+  }
+
+  /// 目的関数sum(a_i * v_i)を最小にする解を求める
+  /// 否定した変数vは1 - vとして数える
+  /// 求めた解はget_model_valueでも得られる
+  pub fn minimize(&mut self, objective: &[(i64, Variable)]) -> MinimizeResult<HashMap<T, bool>> {
+    self.minimize_with(objective, |_, _| {})
+  }
+
+  /// コストが小さくなる解を見つけるたびにon_improveを(コスト, 名前のある変数の値)で呼ぶ
+  /// 探索の上限に達したら，それまでで最良の解を最適とは限らないものとして返す
+  pub fn minimize_with<F: FnMut(i64, &HashMap<T, bool>)>(
+    &mut self,
+    objective: &[(i64, Variable)],
+    mut on_improve: F,
+  ) -> MinimizeResult<HashMap<T, bool>> {
+    let objective = objective
+      .iter()
+      .map(|&(coefficient, v)| (coefficient, Literal::new(v.id, v.sign)))
+      .collect::<Vec<_>>();
+    let mut optimizer =
+      Optimizer::new(self.cdcl_solver(), objective, self.search, self.pb_encoding);
+    let optimum = optimizer.minimize(|cost, model| on_improve(cost, &self.named_values(model)));
+    self.statistics = optimizer.statistics().clone();
+    optimum.map(|optimum| self.set_optimum(optimum))
+  }
+
+  /// 満たさない軟らかい節の重みの和(コスト)が最小で，硬い節(add_clauseなどで加えた制約)を全て満たす解を求める
+  /// 求めた解はget_model_valueでも得られる
+  pub fn solve_maxsat(&mut self) -> MinimizeResult<HashMap<T, bool>> {
+    self.solve_maxsat_with(|_, _| {})
+  }
+
+  /// これまでで最良の解が更新されるたびにon_improveを(コスト, 名前のある変数の値)で呼ぶ
+  /// maxsat_optionsの実行時間や探索の上限に達したら，それまでで最良の解を最適とは限らないものとして返す
+  /// 実行時間は節の変換も含めた全体で数える
+  pub fn solve_maxsat_with<F: FnMut(i64, &HashMap<T, bool>)>(
+    &mut self,
+    mut on_improve: F,
+  ) -> MinimizeResult<HashMap<T, bool>> {
+    let options = self.maxsat_options;
+    let deadline = options.time_limit.map(|time| Instant::now() + time);
+    let mut solver = self.cdcl_solver();
+    let objective = algorithm::relax(&mut solver, &self.soft_clauses);
+    let on_improve = |cost, model: &Model| on_improve(cost, &self.named_values(model));
+    let (optimum, statistics) = match self.maxsat_algorithm {
+      MaxSatAlgorithm::Oll => {
+        let mut oll = Oll::new(solver, objective.clone(), options);
+        oll.set_deadline(deadline);
+        // 局所探索は節だけの問題で行う
+        if options.local_search_flips > 0 && self.xors.is_empty() && self.cardinalities.is_empty() {
+          let local_search = MaxSatLocalSearch::new(
+            self.num_variables,
+            &self.clauses,
+            &self.soft_clauses,
+            self.config.seed,
+          );
+          let improver = Improver::new(
+            local_search,
+            &self.soft_clauses,
+            &objective,
+            options.local_search_flips,
+          );
+          oll.set_improver(improver);
+        }
+        let optimum = oll.minimize(on_improve);
+        let mut statistics = oll.statistics().clone();
+        statistics.local_search_flips += oll.local_search_flips();
+        (optimum, statistics)
+      }
+      MaxSatAlgorithm::Linear => {
+        let mut optimizer = Optimizer::new(solver, objective, Search::Linear, self.pb_encoding);
+        optimizer.set_deadline(deadline);
+        (
+          optimizer.minimize(on_improve),
+          optimizer.statistics().clone(),
+        )
+      }
+    };
+    self.statistics = statistics;
+    optimum.map(|optimum| self.set_optimum(optimum))
+  }
+
+  /// 求めた解を保存し，名前のある変数の値の解にする
+  fn set_optimum(&mut self, optimum: Optimum<Model>) -> Optimum<HashMap<T, bool>> {
+    let values = self.named_values(&optimum.model);
+    self.model = optimum.model;
+    Optimum {
+      cost: optimum.cost,
+      model: values,
+      optimal: optimum.optimal,
     }
   }
 
+  /// 名前のある変数の解での値
+  fn named_values(&self, model: &Model) -> HashMap<T, bool> {
+    self
+      .id_to_name
+      .iter()
+      .map(|(&id, name)| (name.clone(), model.sign(id).unwrap_or(false)))
+      .collect()
+  }
+
   pub fn get_model_value(&self, variable: &Variable) -> Option<bool> {
     self.get_model_value_from_id(&variable.id)
   }
 
   pub fn get_model_value_from_name(&self, name: &T) -> Option<bool> {
     if let Some(id) = self.name_to_id.get(name) {
-      self.get_model_value_from_id(&id)
+      self.get_model_value_from_id(id)
     } else {
       None
     }
@@ -101,4 +631,425 @@ impl<T: Clone + Eq + Hash> SATSolver<T> {
       self.add_clause(clause);
     }
   }
+
+  /// variablesの排他的論理和がrhsになる制約を加える
+  /// 否定した変数は右辺を反転したものとして扱う
+  pub fn add_xor(&mut self, variables: &[Variable], rhs: bool) {
+    let literals = variables
+      .iter()
+      .map(|v| Literal::new(v.id, v.sign))
+      .collect();
+    self.xors.push((literals, rhs));
+  }
+
+  /// variablesのうち真になるものが1つ以下になる制約を加える
+  pub fn at_most_one(&mut self, variables: &[Variable]) {
+    self.at_most_k(variables, 1);
+  }
+
+  /// variablesのうち真になるものがちょうど1つになる制約を加える
+  pub fn exactly_one(&mut self, variables: &[Variable]) {
+    self.exactly_k(variables, 1);
+  }
+
+  /// variablesのうち真になるものがk個以下になる制約を加える
+  /// 補助変数には名前を付けないので，解の列挙・数え上げでは区別しない
+  pub fn at_most_k(&mut self, variables: &[Variable], k: usize) {
+    let literals = literals(variables);
+    if self.cardinality_encoding == CardinalityEncoding::Native && k < literals.len() {
+      self.cardinalities.push((literals, k));
+      return;
+    }
+    let encoding = self.cardinality_encoding;
+    self.add_encoded(|next_variable| cardinality::at_most_k(&literals, k, encoding, next_variable));
+  }
+
+  /// variablesのうち真になるものがk個以上になる制約を加える
+  pub fn at_least_k(&mut self, variables: &[Variable], k: usize) {
+    let literals = literals(variables);
+    // 偽になるものがn-k個以下になる制約として扱う(k <= 1は節1つで済む)
+    if self.cardinality_encoding == CardinalityEncoding::Native && 1 < k && k <= literals.len() {
+      let negated = literals.iter().map(|&literal| !literal).collect();
+      self.cardinalities.push((negated, literals.len() - k));
+      return;
+    }
+    let encoding = self.cardinality_encoding;
+    self
+      .add_encoded(|next_variable| cardinality::at_least_k(&literals, k, encoding, next_variable));
+  }
+
+  /// variablesのうち真になるものがちょうどk個になる制約を加える
+  pub fn exactly_k(&mut self, variables: &[Variable], k: usize) {
+    self.at_most_k(variables, k);
+    self.at_least_k(variables, k);
+  }
+
+  /// 満たさないと重みの分だけコストがかかる節を加える(solve_maxsatで使う)
+  /// solveなどでは使わない
+  pub fn add_soft_clause(&mut self, clause: &[Variable], weight: u64) {
+    self.soft_clauses.push((literals(clause), weight));
+  }
+
+  /// sum(a_i * v_i) (>=, <=, =) bound の線形制約を加える
+  /// 否定した変数vは1 - vとして数える
+  pub fn add_pb_constraint(
+    &mut self,
+    terms: &[(i64, Variable)],
+    comparison: Comparison,
+    bound: i64,
+  ) {
+    let terms = terms
+      .iter()
+      .map(|&(coefficient, v)| (coefficient, Literal::new(v.id, v.sign)))
+      .collect();
+    let constraint = PbConstraint::new(terms, comparison, bound);
+    let encoding = self.pb_encoding;
+    self.add_encoded(|next_variable| pb::encoding::encode(&constraint, encoding, next_variable));
+  }
+
+  /// formulaが真になる制約を加える
+  /// 部分式には名前のない補助変数を使うので，解の列挙・数え上げでは区別しない
+  pub fn assert(&mut self, formula: &Formula) {
+    let mut encoder = mem::take(&mut self.formula_encoder);
+    self.add_encoded(|next_variable| encoder.encode(formula, next_variable));
+    self.formula_encoder = encoder;
+  }
+
+  /// 補助変数を使って変換した節を加える
+  fn add_encoded(&mut self, encode: impl FnOnce(&mut usize) -> Vec<Vec<Literal>>) {
+    let mut next_variable = self.num_variables;
+    let clauses = encode(&mut next_variable);
+    self.num_variables = next_variable;
+    // 満たせない制約は空節になる
+    self.clauses.extend(clauses.iter().map(Clause::from));
+  }
+}
+
+impl SATSolver<String> {
+  /// `(a | !b) & (c -> d)`のような論理式を読む(変数は名前で作る)
+  /// 書き方はparser::parse_formulaを参照
+  pub fn parse_formula(&mut self, input: &str) -> Result<Formula, FormulaParseError> {
+    parser::parse_formula(input, |name| self.variable(name.to_string()))
+  }
+}
+
+fn literals(variables: &[Variable]) -> Vec<Literal> {
+  variables
+    .iter()
+    .map(|v| Literal::new(v.id, v.sign))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::*;
+  use crate::{
+    cdcl::options::CdclOptions,
+    config::Preset,
+    random::Random,
+    testing::{self, random_clauses},
+  };
+
+  #[test]
+  fn reads_substituted_variables_by_name() {
+    // a <-> b <-> !c は1つの変数にまとめられ，dは失敗リテラル探索で偽に決まる
+    let mut solver = SATSolver::new();
+    let [a, b, c, d, e] = ["a", "b", "c", "d", "e"].map(|name| solver.variable(name));
+    solver.add_clauses(&[
+      vec![!a, b],
+      vec![!b, !c],
+      vec![c, a],
+      vec![!d, a],
+      vec![!d, c],
+      vec![a, e],
+      vec![!e, b, d],
+    ]);
+    assert!(solver.solve());
+    assert!(solver.statistics().equivalent_literals >= 2);
+    let value = |name| solver.get_model_value_from_name(&name).unwrap();
+    assert_eq!(value("a"), value("b"));
+    assert_eq!(value("a"), !value("c"));
+    assert!(!value("d"));
+    assert!(value("a") || value("e"));
+    assert!(!value("e") || value("b"));
+  }
+
+  /// 変数iの名前をiとしてclausesを加えたソルバ
+  fn solver(num_variables: usize, clauses: &[Vec<Literal>]) -> SATSolver<usize> {
+    let mut solver = SATSolver::new();
+    for variable in 0..num_variables {
+      solver.variable(variable);
+    }
+    for clause in clauses {
+      let clause = clause
+        .iter()
+        .map(|literal| {
+          let variable = solver.variable(literal.variable());
+          if literal.sign() {
+            variable
+          } else {
+            !variable
+          }
+        })
+        .collect::<Vec<_>>();
+      solver.add_clause(&clause);
+    }
+    solver
+  }
+
+  /// 名前から読んだ解で全ての節が充足されるか
+  fn satisfied(solver: &SATSolver<usize>, clauses: &[Vec<Literal>]) -> bool {
+    let value = |literal: &Literal| {
+      solver.get_model_value_from_name(&literal.variable()) == Some(literal.sign())
+    };
+    clauses.iter().all(|clause| clause.iter().any(value))
+  }
+
+  #[test]
+  fn reconstructs_models_for_every_name() {
+    let mut random = Random::new(4);
+    let num_variables = 8;
+    for _ in 0..200 {
+      let num_binaries = 6 + random.below(8);
+      let mut clauses = random_clauses(&mut random, num_variables, num_binaries, 2);
+      let num_clauses = random.below(10);
+      clauses.extend(random_clauses(&mut random, num_variables, num_clauses, 3));
+
+      let mut solver = solver(num_variables, &clauses);
+      let satisfiable = !testing::models(num_variables, &clauses).is_empty();
+      assert_eq!(solver.solve(), satisfiable, "{:?}", clauses);
+      assert!(
+        !satisfiable || satisfied(&solver, &clauses),
+        "{:?}",
+        clauses
+      );
+    }
+  }
+
+  #[test]
+  fn solves_with_every_engine() {
+    let engines = [
+      Engine::Cdcl,
+      Engine::Dpll,
+      Engine::LocalSearch(local_search::Algorithm::ProbSat),
+      Engine::LocalSearch(local_search::Algorithm::WalkSat),
+      Engine::CubeAndConquer(CubeAndConquer::new(2)),
+    ];
+    let mut random = Random::new(5);
+    let num_variables = 40;
+    for _ in 0..5 {
+      let hidden = random.next_u64();
+      let clauses = testing::planted_clauses(&mut random, num_variables, 160, 3, hidden);
+      for engine in engines {
+        let mut solver = solver(num_variables, &clauses);
+        solver.set_engine(engine);
+        assert_eq!(solver.engine(), engine);
+        assert!(solver.solve(), "{:?}", engine);
+        assert!(satisfied(&solver, &clauses), "{:?}", engine);
+      }
+    }
+  }
+
+  #[test]
+  fn reproduces_the_search_of_a_seed() {
+    let mut random = Random::new(6);
+    let num_variables = 60;
+    let clauses = random_clauses(&mut random, num_variables, 250, 3);
+    let config = SolverConfig::default()
+      .with_seed(11)
+      .with_cdcl(CdclOptions {
+        restart_interval: 10,
+        reduce_interval: 50,
+        ..CdclOptions::default()
+      });
+    let run = |config: &SolverConfig| {
+      let mut solver = solver(num_variables, &clauses);
+      solver.set_config(config.clone());
+      let result = solver.solve_limited();
+      let values = (0..num_variables)
+        .map(|name| solver.get_model_value_from_name(&name))
+        .collect::<Vec<_>>();
+      (result, values, solver.statistics().to_string())
+    };
+    let first = run(&config);
+    assert!(first.0.is_some());
+    assert!(first.2.contains("conflicts"));
+    // 同じ設定・同じシードなら解も統計情報も一致する
+    assert_eq!(run(&config), first);
+  }
+
+  #[test]
+  fn stops_at_the_search_limits() {
+    let mut random = Random::new(7);
+    let num_variables = 80;
+    let clauses = random_clauses(&mut random, num_variables, 480, 3);
+    let configs = [
+      SolverConfig::default().with_conflict_limit(10),
+      SolverConfig::default().with_propagation_limit(100),
+      SolverConfig::default().with_time_limit(Duration::ZERO),
+    ];
+    for config in configs {
+      let mut solver = solver(num_variables, &clauses);
+      solver.set_config(config.clone());
+      assert_eq!(solver.solve_limited(), None, "{:?}", config.limits);
+      assert!(!solver.solve());
+    }
+    // 上限がなければ充足不能と分かる
+    let mut solver = solver(num_variables, &clauses);
+    assert_eq!(solver.solve_limited(), Some(false));
+  }
+
+  #[test]
+  fn solves_with_every_preset() {
+    let mut random = Random::new(8);
+    for preset in Preset::ALL {
+      for _ in 0..30 {
+        let num_variables = 10;
+        let clauses = random_clauses(&mut random, num_variables, 42, 3);
+        let mut solver = solver(num_variables, &clauses);
+        solver.set_config(SolverConfig::preset(preset));
+        let satisfiable = !testing::models(num_variables, &clauses).is_empty();
+        assert_eq!(solver.solve_limited(), Some(satisfiable), "{}", preset);
+        assert!(!satisfiable || satisfied(&solver, &clauses), "{}", preset);
+      }
+    }
+  }
+
+  #[test]
+  fn enumerates_models_projected_onto_names() {
+    // 3つの名前のうちちょうど1つが真で，補助変数tは x -> t を満たせばよい
+    let mut solver = SATSolver::new();
+    let [x, y, z, t] = ["x", "y", "z", "t"].map(|name| solver.variable(name));
+    solver.add_clauses(&[
+      vec![x, y, z],
+      vec![!x, !y],
+      vec![!x, !z],
+      vec![!y, !z],
+      vec![!x, t],
+    ]);
+    assert_eq!(solver.models().count(), 5);
+
+    let mut models = solver.projected_models(&[x, y, z, x]);
+    let mut trues = models
+      .by_ref()
+      .map(|model| {
+        assert_eq!(model.len(), 3);
+        let trues = model
+          .into_iter()
+          .filter(|&(_, value)| value)
+          .map(|(name, _)| name)
+          .collect::<Vec<_>>();
+        assert_eq!(trues.len(), 1);
+        trues[0]
+      })
+      .collect::<Vec<_>>();
+    assert!(models.is_complete());
+    trues.sort();
+    assert_eq!(trues, ["x", "y", "z"]);
+  }
+
+  #[test]
+  fn counts_models_projected_onto_names() {
+    let mut solver = SATSolver::new();
+    let [x, y, z, t] = ["x", "y", "z", "t"].map(|name| solver.variable(name));
+    solver.add_clauses(&[
+      vec![x, y, z],
+      vec![!x, !y],
+      vec![!x, !z],
+      vec![!y, !z],
+      vec![!x, t],
+    ]);
+    assert_eq!(solver.count_models().to_u64(), Some(5));
+    assert_eq!(solver.count_projected_models(&[x, y, z]).to_u64(), Some(3));
+    assert_eq!(solver.count_projected_models(&[t]).to_u64(), Some(2));
+    assert_eq!(solver.count_projected_models(&[]).to_u64(), Some(1));
+    solver.add_clause(&[!t]);
+    assert_eq!(solver.count_models().to_u64(), Some(2));
+  }
+
+  #[test]
+  fn approximates_counts_of_many_models() {
+    // 解が少なければ正確に数え，多ければ誤差の範囲で推定する
+    let mut solver = SATSolver::new();
+    let variables = (0..10)
+      .map(|name| solver.variable(name))
+      .collect::<Vec<_>>();
+    solver.add_clause(&[variables[0], variables[1]]);
+    let count = |models: Option<Natural>| models.unwrap().to_u64().unwrap() as f64;
+    let exact = count(solver.approximate_count_projected_models(&variables[..4], 0.8, 0.5));
+    assert_eq!(exact, 12.0);
+    let estimate = count(solver.approximate_count_models(0.8, 0.5));
+    let expected = (3 << 8) as f64;
+    assert!(expected / 1.8 <= estimate && estimate <= expected * 1.8);
+    assert!(solver.statistics().cells > 0);
+  }
+
+  #[test]
+  fn ignores_auxiliary_variables_of_cardinality_constraints() {
+    // 5個から2個を選ぶ組み合わせは補助変数の値によらず10通り
+    for encoding in CardinalityEncoding::ALL {
+      let mut solver = SATSolver::new();
+      solver.set_cardinality_encoding(encoding);
+      let variables = (0..5).map(|name| solver.variable(name)).collect::<Vec<_>>();
+      solver.exactly_k(&variables, 2);
+      assert_eq!(solver.count_models().to_u64(), Some(10), "{}", encoding);
+      let models = solver.models().collect::<Vec<_>>();
+      assert_eq!(models.len(), 10, "{}", encoding);
+      for model in models {
+        assert_eq!(model.len(), 5);
+        assert_eq!(model.values().filter(|&&value| value).count(), 2);
+      }
+      solver.at_most_one(&variables[..2]);
+      solver.at_least_k(&variables[2..], 2);
+      assert_eq!(solver.count_models().to_u64(), Some(3), "{}", encoding);
+    }
+  }
+
+  #[test]
+  fn counts_solutions_of_pb_constraints() {
+    // 2a + 3b + 4c <= 5 を満たすのは {}, {a}, {b}, {c}, {a, b}
+    for encoding in PbEncoding::ALL {
+      let mut solver = SATSolver::new();
+      solver.set_pb_encoding(encoding);
+      let [a, b, c] = ["a", "b", "c"].map(|name| solver.variable(name));
+      solver.add_pb_constraint(&[(2, a), (3, b), (4, c)], Comparison::AtMost, 5);
+      assert_eq!(solver.count_models().to_u64(), Some(5), "{}", encoding);
+      // 否定した変数は1 - vとして数える: 2a + 3!b >= 5 はaが真でbが偽
+      solver.add_pb_constraint(&[(2, a), (3, !b)], Comparison::AtLeast, 5);
+      assert_eq!(solver.count_models().to_u64(), Some(1), "{}", encoding);
+    }
+  }
+
+  #[test]
+  fn counts_models_of_asserted_formulas() {
+    // 部分式の補助変数は数えない
+    for encoding in FormulaEncoding::ALL {
+      let mut solver = SATSolver::new();
+      solver.set_formula_encoding(encoding);
+      let [a, b, c] = ["a", "b", "c"].map(|name| solver.variable(name));
+      solver.assert(&((a & b) | c));
+      assert_eq!(solver.count_models().to_u64(), Some(5), "{}", encoding);
+      solver.assert(&a.iff(c));
+      assert_eq!(solver.count_models().to_u64(), Some(2), "{}", encoding);
+      assert!(solver.solve());
+      assert_eq!(solver.get_model_value_from_name(&"a"), Some(true));
+      assert_eq!(solver.get_model_value_from_name(&"c"), Some(true));
+    }
+  }
+
+  #[test]
+  fn parses_formulas_with_named_variables() {
+    let mut solver = SATSolver::new();
+    let formula = solver.parse_formula("(a | !b) & (b -> c)").unwrap();
+    solver.assert(&formula);
+    // 論理式に現れた名前の変数だけを数える
+    assert_eq!(solver.count_models().to_u64(), Some(5));
+    let error = solver.parse_formula("a &").unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "failed to parse formula: unexpected end of formula at column 4"
+    );
+  }
 }
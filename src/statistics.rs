@@ -0,0 +1,183 @@
+use std::{fmt, ops::AddAssign};
+
+/// ソルバの統計情報
+#[derive(Clone, Debug, Default)]
+pub struct Statistics {
+  /// 失敗リテラルの数
+  pub failed_literals: u64,
+  /// 失敗リテラル探索で見つかった必要割り当ての数
+  pub necessary_assignments: u64,
+  /// 追加したhyper binary resolventの数
+  pub hyper_binary_resolvents: u64,
+  /// 代表リテラルに置き換えた変数の数
+  pub equivalent_literals: u64,
+  /// blocked clause eliminationで取り除いた節の数
+  pub blocked_clauses: u64,
+  /// covered clause eliminationで取り除いた節の数
+  pub covered_clauses: u64,
+  pub conflicts: u64,
+  pub decisions: u64,
+  pub propagations: u64,
+  /// XOR制約から導いたリテラルの数
+  pub xor_propagations: u64,
+  /// XOR制約で起きた衝突の数
+  pub xor_conflicts: u64,
+  /// 節から見つけたXOR制約の数
+  pub recovered_xors: u64,
+  /// 個数制約から導いたリテラルの数
+  pub cardinality_propagations: u64,
+  /// 個数制約で起きた衝突の数
+  pub cardinality_conflicts: u64,
+  pub restarts: u64,
+  /// 衝突後に直前のレベルまでしか戻らなかった回数
+  pub chronological_backtracks: u64,
+  pub learned_clauses: u64,
+  /// 再帰的な最小化で学習節から取り除いたリテラルの数
+  pub minimized_literals: u64,
+  /// 二項節による最小化で学習節から取り除いたリテラルの数
+  pub binary_minimized_literals: u64,
+  /// shrinkingで学習節から取り除いたリテラルの数
+  pub shrunk_literals: u64,
+  /// 学習節の削減で削除した節の数
+  pub deleted_clauses: u64,
+  /// 節の配列を詰め直した回数
+  pub garbage_collections: u64,
+  /// 単位伝播で見張りリストを調べた回数(仕事量の目安)
+  pub ticks: u64,
+  pub inprocessings: u64,
+  /// ticksのうちインプロセッシングに使った分
+  pub inprocessing_ticks: u64,
+  /// vivificationで短くなった節の数
+  pub vivified_clauses: u64,
+  /// vivificationで取り除いたリテラルの数
+  pub vivified_literals: u64,
+  /// 包摂されて削除した節の数
+  pub subsumed_clauses: u64,
+  /// 自己包摂導出で強化した節の数
+  pub strengthened_clauses: u64,
+  /// 局所探索で極性を設定した回数
+  pub rephases: u64,
+  /// 局所探索で変数を反転した回数
+  pub local_search_flips: u64,
+  /// 他のソルバに送った学習節の数
+  pub exported_clauses: u64,
+  /// 他のソルバから受け取った学習節の数
+  pub imported_clauses: u64,
+  /// cube-and-conquerで生成したキューブの数
+  pub cubes: u64,
+  /// 先読みで見つけた失敗リテラルの数
+  pub lookahead_failed_literals: u64,
+  /// モデル計数で数えた連結成分の数
+  pub components: u64,
+  /// モデル計数で連結成分の解の個数をキャッシュから得た回数
+  pub component_cache_hits: u64,
+  /// 近似モデル計数で解を数えたセルの数
+  pub cells: u64,
+}
+
+impl fmt::Display for Statistics {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "failed literals: {}", self.failed_literals)?;
+    writeln!(f, "necessary assignments: {}", self.necessary_assignments)?;
+    writeln!(
+      f,
+      "hyper binary resolvents: {}",
+      self.hyper_binary_resolvents
+    )?;
+    writeln!(f, "equivalent literals: {}", self.equivalent_literals)?;
+    writeln!(f, "blocked clauses: {}", self.blocked_clauses)?;
+    writeln!(f, "covered clauses: {}", self.covered_clauses)?;
+    writeln!(f, "conflicts: {}", self.conflicts)?;
+    writeln!(f, "decisions: {}", self.decisions)?;
+    writeln!(f, "propagations: {}", self.propagations)?;
+    writeln!(f, "xor propagations: {}", self.xor_propagations)?;
+    writeln!(f, "xor conflicts: {}", self.xor_conflicts)?;
+    writeln!(f, "recovered xors: {}", self.recovered_xors)?;
+    writeln!(
+      f,
+      "cardinality propagations: {}",
+      self.cardinality_propagations
+    )?;
+    writeln!(f, "cardinality conflicts: {}", self.cardinality_conflicts)?;
+    writeln!(f, "restarts: {}", self.restarts)?;
+    writeln!(
+      f,
+      "chronological backtracks: {}",
+      self.chronological_backtracks
+    )?;
+    writeln!(f, "learned clauses: {}", self.learned_clauses)?;
+    writeln!(f, "minimized literals: {}", self.minimized_literals)?;
+    writeln!(
+      f,
+      "binary minimized literals: {}",
+      self.binary_minimized_literals
+    )?;
+    writeln!(f, "shrunk literals: {}", self.shrunk_literals)?;
+    writeln!(f, "deleted clauses: {}", self.deleted_clauses)?;
+    writeln!(f, "garbage collections: {}", self.garbage_collections)?;
+    writeln!(f, "ticks: {}", self.ticks)?;
+    writeln!(f, "inprocessings: {}", self.inprocessings)?;
+    writeln!(f, "inprocessing ticks: {}", self.inprocessing_ticks)?;
+    writeln!(f, "vivified clauses: {}", self.vivified_clauses)?;
+    writeln!(f, "vivified literals: {}", self.vivified_literals)?;
+    writeln!(f, "subsumed clauses: {}", self.subsumed_clauses)?;
+    writeln!(f, "strengthened clauses: {}", self.strengthened_clauses)?;
+    writeln!(f, "rephases: {}", self.rephases)?;
+    writeln!(f, "local search flips: {}", self.local_search_flips)?;
+    writeln!(f, "exported clauses: {}", self.exported_clauses)?;
+    writeln!(f, "imported clauses: {}", self.imported_clauses)?;
+    writeln!(f, "cubes: {}", self.cubes)?;
+    writeln!(
+      f,
+      "lookahead failed literals: {}",
+      self.lookahead_failed_literals
+    )?;
+    writeln!(f, "components: {}", self.components)?;
+    writeln!(f, "component cache hits: {}", self.component_cache_hits)?;
+    write!(f, "cells: {}", self.cells)
+  }
+}
+
+/// 複数のソルバの統計情報を合計する
+impl AddAssign<&Statistics> for Statistics {
+  fn add_assign(&mut self, other: &Statistics) {
+    self.failed_literals += other.failed_literals;
+    self.necessary_assignments += other.necessary_assignments;
+    self.hyper_binary_resolvents += other.hyper_binary_resolvents;
+    self.equivalent_literals += other.equivalent_literals;
+    self.blocked_clauses += other.blocked_clauses;
+    self.covered_clauses += other.covered_clauses;
+    self.conflicts += other.conflicts;
+    self.decisions += other.decisions;
+    self.propagations += other.propagations;
+    self.xor_propagations += other.xor_propagations;
+    self.xor_conflicts += other.xor_conflicts;
+    self.recovered_xors += other.recovered_xors;
+    self.cardinality_propagations += other.cardinality_propagations;
+    self.cardinality_conflicts += other.cardinality_conflicts;
+    self.restarts += other.restarts;
+    self.chronological_backtracks += other.chronological_backtracks;
+    self.learned_clauses += other.learned_clauses;
+    self.minimized_literals += other.minimized_literals;
+    self.binary_minimized_literals += other.binary_minimized_literals;
+    self.shrunk_literals += other.shrunk_literals;
+    self.deleted_clauses += other.deleted_clauses;
+    self.garbage_collections += other.garbage_collections;
+    self.ticks += other.ticks;
+    self.inprocessings += other.inprocessings;
+    self.inprocessing_ticks += other.inprocessing_ticks;
+    self.vivified_clauses += other.vivified_clauses;
+    self.vivified_literals += other.vivified_literals;
+    self.subsumed_clauses += other.subsumed_clauses;
+    self.strengthened_clauses += other.strengthened_clauses;
+    self.rephases += other.rephases;
+    self.local_search_flips += other.local_search_flips;
+    self.exported_clauses += other.exported_clauses;
+    self.imported_clauses += other.imported_clauses;
+    self.cubes += other.cubes;
+    self.lookahead_failed_literals += other.lookahead_failed_literals;
+    self.components += other.components;
+    self.component_cache_hits += other.component_cache_hits;
+    self.cells += other.cells;
+  }
+}
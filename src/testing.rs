@@ -0,0 +1,87 @@
+use crate::{
+  cdcl::solver::{Cdcl, SolveResult},
+  expressions::{literal::Literal, model::Model},
+  random::Random,
+};
+
+/// 変数0..num_variablesからなる長さlengthのランダムな節をnum_clauses個作る
+pub fn random_clauses(
+  random: &mut Random,
+  num_variables: usize,
+  num_clauses: usize,
+  length: usize,
+) -> Vec<Vec<Literal>> {
+  (0..num_clauses)
+    .map(|_| {
+      (0..length)
+        .map(|_| Literal::new(random.below(num_variables), random.below(2) == 0))
+        .collect()
+    })
+    .collect()
+}
+
+/// 割り当てhiddenで真になる長さlengthのランダムな節をnum_clauses個作る(充足可能な問題)
+pub fn planted_clauses(
+  random: &mut Random,
+  num_variables: usize,
+  num_clauses: usize,
+  length: usize,
+  hidden: u64,
+) -> Vec<Vec<Literal>> {
+  let mut clauses = vec![];
+  while clauses.len() < num_clauses {
+    let clause = random_clauses(random, num_variables, 1, length)
+      .pop()
+      .unwrap();
+    if satisfies(hidden, &clause) {
+      clauses.push(clause);
+    }
+  }
+  clauses
+}
+
+/// 割り当て(iビット目が変数iの値)でliteralが真か
+pub fn holds(assignment: u64, literal: Literal) -> bool {
+  (assignment >> literal.variable() & 1 == 1) == literal.sign()
+}
+
+/// 割り当てでclauseのどれかのリテラルが真か
+pub fn satisfies(assignment: u64, clause: &[Literal]) -> bool {
+  clause.iter().any(|&literal| holds(assignment, literal))
+}
+
+/// 変数0..num_variablesの割り当てのうち，節を全て満たすもの(総当たり)
+pub fn models(num_variables: usize, clauses: &[Vec<Literal>]) -> Vec<u64> {
+  (0..1 << num_variables)
+    .filter(|&assignment| clauses.iter().all(|clause| satisfies(assignment, clause)))
+    .collect()
+}
+
+/// 解の変数0..num_variablesの値を割り当てにする
+pub fn assignment(model: &Model, num_variables: usize) -> u64 {
+  (0..num_variables)
+    .filter(|&variable| model.sign(variable) == Some(true))
+    .fold(0, |assignment, variable| assignment | 1 << variable)
+}
+
+/// 変数0..num_variablesを割り当ての値に固定したとき，補助変数を含めて節を全て満たせるか
+/// 補助変数はnum_variables..total_variablesで，CDCLで探す
+pub fn extendable(
+  num_variables: usize,
+  total_variables: usize,
+  clauses: &[Vec<Literal>],
+  assignment: u64,
+) -> bool {
+  let mut solver = Cdcl::new(total_variables);
+  if !clauses.iter().all(|clause| solver.add_clause(clause)) {
+    return false;
+  }
+  let assumptions = (0..num_variables)
+    .map(|variable| Literal::new(variable, assignment >> variable & 1 == 1))
+    .collect::<Vec<_>>();
+  match solver.solve_with_assumptions(&assumptions) {
+    SolveResult::Satisfiable(_) => true,
+    SolveResult::Unsatisfiable => false,
+    SolveResult::Unknown => panic!("no limit is set"),
+  }
+}